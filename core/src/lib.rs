@@ -0,0 +1,40 @@
+//! Decoding, verification, encoding and secret resolution of JSON Web Tokens, as used by
+//! [JWT UI](https://github.com/jwt-rs/jwt-ui).
+//!
+//! Secrets are strings read the same way as the secret input of JWT UI:
+//!
+//! - plain text HMAC secrets, or base64 encoded ones with the `b64:` prefix
+//! - key files with the `@` prefix: `.pem` and `.der` keys, `.json` JWKS
+//! - inline JWKS (e.g. the output of `curl https://auth.example.com/jwks.json`)
+//!
+//! ```
+//! use jsonwebtoken::{Algorithm, Header, Validation};
+//! use serde_json::{json, Value};
+//!
+//! let token = jwt_ui_core::encode(&Header::default(), &json!({"sub": "alice"}), "my-secret")?;
+//!
+//! let decoded = jwt_ui_core::decode_unverified::<Value>(&token)?;
+//! assert_eq!(decoded.header.alg, Algorithm::HS256);
+//! assert_eq!(decoded.claims["sub"], "alice");
+//!
+//! let mut validation = Validation::new(Algorithm::HS256);
+//! validation.required_spec_claims.clear();
+//! let (verified, _) = jwt_ui_core::verify::<Value>(&token, "my-secret", &validation)?;
+//! assert_eq!(verified.claims["sub"], "alice");
+//! # Ok::<(), jwt_ui_core::JWTError>(())
+//! ```
+
+mod error;
+pub mod secret;
+pub mod token;
+
+pub use error::{JWTError, JWTResult};
+pub use secret::{
+  decoding_key_from_jwks_secret, decoding_key_from_secret, decoding_keys_from_jwks_secret,
+  encoding_key_from_bytes, encoding_key_from_secret, get_secret_from_file_or_input, run_command,
+  shell_command, slurp_file, split_secrets, strip_leading_symbol, SecretType, COMMAND_PREFIX,
+};
+pub use token::{
+  decode_segment, decode_unverified, encode, encode_json, signature_matched, validation, verify,
+  VerifiedToken,
+};
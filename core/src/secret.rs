@@ -0,0 +1,455 @@
+use std::{fs, io, process::Command, str::from_utf8};
+
+use jsonwebtoken::{
+  errors::Error,
+  jwk::{self, AlgorithmParameters},
+  Algorithm, DecodingKey, EncodingKey, Header,
+};
+
+use crate::error::{JWTError, JWTResult};
+
+/// prefix of secrets printed by a command (e.g. `cmd:op read op://vault/jwt/secret`)
+pub const COMMAND_PREFIX: &str = "cmd:";
+
+/// how the bytes of a secret are read, from the prefix or the file extension of the secret
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretType {
+  Pem,
+  Der,
+  Jwks,
+  B64,
+  Plain,
+}
+
+/// split the secret input into candidate secrets, separated by commas. Inline JWKS (JSON) is
+/// always a single secret and a `cmd:` secret command takes the rest of the input
+pub fn split_secrets(secret: &str) -> Vec<&str> {
+  let mut rest = secret.trim();
+  if rest.starts_with('{') || rest.starts_with('[') {
+    return vec![rest];
+  }
+  let mut secrets = vec![];
+  while !rest.is_empty() {
+    if rest.starts_with(COMMAND_PREFIX) {
+      secrets.push(rest);
+      break;
+    }
+    let (secret, next) = rest.split_once(',').unwrap_or((rest, ""));
+    if !secret.trim().is_empty() {
+      secrets.push(secret.trim());
+    }
+    rest = next.trim_start();
+  }
+  secrets
+}
+
+/// the bytes of the secret and how to read them. `@` reads a file, `b64:` is a base64 encoded
+/// HMAC secret and other asymmetric secrets are inline JWKS
+pub fn get_secret_from_file_or_input(
+  alg: &Algorithm,
+  secret_string: &str,
+) -> (JWTResult<Vec<u8>>, SecretType) {
+  match alg {
+    Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+      if secret_string.starts_with('@') {
+        (
+          slurp_file(strip_leading_symbol(secret_string)).map_err(JWTError::from),
+          if secret_string.ends_with(".json") {
+            SecretType::Jwks
+          } else {
+            SecretType::Plain
+          },
+        )
+      } else if secret_string.starts_with("b64:") {
+        (
+          Ok(
+            secret_string
+              .chars()
+              .skip(4)
+              .collect::<String>()
+              .as_bytes()
+              .to_owned(),
+          ),
+          SecretType::B64,
+        )
+      } else {
+        (Ok(secret_string.as_bytes().to_owned()), SecretType::Plain)
+      }
+    }
+    _ => {
+      if secret_string.starts_with('@') {
+        (
+          slurp_file(strip_leading_symbol(secret_string)).map_err(JWTError::from),
+          get_secret_file_type(secret_string),
+        )
+      } else {
+        // allows to read JWKS from argument (e.g. output of 'curl https://auth.domain.com/jwks.json')
+        (Ok(secret_string.as_bytes().to_vec()), SecretType::Jwks)
+      }
+    }
+  }
+}
+
+/// the secret without its `@` prefix
+pub fn strip_leading_symbol(secret_string: &str) -> String {
+  secret_string.chars().skip(1).collect::<String>()
+}
+
+/// the key verifying tokens signed with the algorithm. The `kid` of the header selects the key
+/// of a JWKS secret
+pub fn decoding_key_from_secret(
+  alg: &Algorithm,
+  secret_string: &str,
+  header: Option<Header>,
+) -> JWTResult<DecodingKey> {
+  let (secret, file_type) = get_secret_from_file_or_input(alg, secret_string);
+  let secret = secret?;
+  match alg {
+    Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => match file_type {
+      SecretType::Plain => Ok(DecodingKey::from_secret(&secret)),
+      SecretType::Jwks => decoding_key_from_jwks_secret(&secret, header),
+      SecretType::B64 => DecodingKey::from_base64_secret(from_utf8(&secret)?).map_err(Error::into),
+      _ => Err(JWTError::Internal(format!(
+        "Invalid secret file type for {alg:?}"
+      ))),
+    },
+    Algorithm::RS256
+    | Algorithm::RS384
+    | Algorithm::RS512
+    | Algorithm::PS256
+    | Algorithm::PS384
+    | Algorithm::PS512 => match file_type {
+      SecretType::Pem => DecodingKey::from_rsa_pem(&secret).map_err(Error::into),
+      SecretType::Der => Ok(DecodingKey::from_rsa_der(&secret)),
+      SecretType::Jwks => decoding_key_from_jwks_secret(&secret, header),
+      _ => Err(JWTError::Internal(format!(
+        "Invalid secret file type for {alg:?}"
+      ))),
+    },
+    Algorithm::ES256 | Algorithm::ES384 => match file_type {
+      SecretType::Pem => DecodingKey::from_ec_pem(&secret).map_err(Error::into),
+      SecretType::Der => Ok(DecodingKey::from_ec_der(&secret)),
+      SecretType::Jwks => decoding_key_from_jwks_secret(&secret, header),
+      _ => Err(JWTError::Internal(format!(
+        "Invalid secret file type for {alg:?}"
+      ))),
+    },
+    Algorithm::EdDSA => match file_type {
+      SecretType::Pem => DecodingKey::from_ed_pem(&secret).map_err(Error::into),
+      SecretType::Der => Ok(DecodingKey::from_ed_der(&secret)),
+      SecretType::Jwks => decoding_key_from_jwks_secret(&secret, header),
+      _ => Err(JWTError::Internal(format!(
+        "Invalid secret file type for {alg:?}"
+      ))),
+    },
+  }
+}
+
+/// the key signing tokens with the algorithm, from a secret without passphrase. JWK secrets and
+/// encrypted keys are resolved by the caller, which then uses [`encoding_key_from_bytes`]
+pub fn encoding_key_from_secret(alg: &Algorithm, secret_string: &str) -> JWTResult<EncodingKey> {
+  let (secret, file_type) = get_secret_from_file_or_input(alg, secret_string);
+  encoding_key_from_bytes(alg, &secret?, file_type)
+}
+
+/// the key signing tokens with the algorithm, from the bytes of a secret read as `file_type`
+pub fn encoding_key_from_bytes(
+  alg: &Algorithm,
+  secret: &[u8],
+  file_type: SecretType,
+) -> JWTResult<EncodingKey> {
+  match alg {
+    Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => match file_type {
+      SecretType::Plain => Ok(EncodingKey::from_secret(secret)),
+      SecretType::B64 => EncodingKey::from_base64_secret(from_utf8(secret)?).map_err(Error::into),
+      _ => Err(JWTError::Internal(format!(
+        "Invalid secret file type for {alg:?}"
+      ))),
+    },
+    Algorithm::RS256
+    | Algorithm::RS384
+    | Algorithm::RS512
+    | Algorithm::PS256
+    | Algorithm::PS384
+    | Algorithm::PS512 => match file_type {
+      SecretType::Pem => EncodingKey::from_rsa_pem(secret).map_err(Error::into),
+      SecretType::Der => Ok(EncodingKey::from_rsa_der(secret)),
+      _ => Err(JWTError::Internal(format!(
+        "Invalid secret file type for {alg:?}"
+      ))),
+    },
+    Algorithm::ES256 | Algorithm::ES384 => match file_type {
+      SecretType::Pem => EncodingKey::from_ec_pem(secret).map_err(Error::into),
+      SecretType::Der => Ok(EncodingKey::from_ec_der(secret)),
+      _ => Err(JWTError::Internal(format!(
+        "Invalid secret file type for {alg:?}"
+      ))),
+    },
+    Algorithm::EdDSA => match file_type {
+      SecretType::Pem => EncodingKey::from_ed_pem(secret).map_err(Error::into),
+      SecretType::Der => Ok(EncodingKey::from_ed_der(secret)),
+      _ => Err(JWTError::Internal(format!(
+        "Invalid secret file type for {alg:?}"
+      ))),
+    },
+  }
+}
+
+/// the key of the JWKS secret with the `kid` of the header
+pub fn decoding_key_from_jwks_secret(
+  secret: &[u8],
+  header: Option<Header>,
+) -> JWTResult<DecodingKey> {
+  if let Some(h) = header {
+    return match parse_jwks(secret) {
+      Some(jwks) => decoding_key_from_jwks(jwks, &h),
+      None => Err(JWTError::Internal("Invalid jwks secret format".to_string())),
+    };
+  }
+  Err(JWTError::Internal(
+    "Invalid jwt header for jwks secret".to_string(),
+  ))
+}
+
+/// all keys of the JWKS secret that can verify the algorithm, labelled with their `kid` or their
+/// position in the set. Used to try every key when the JWT header has no `kid`
+pub fn decoding_keys_from_jwks_secret(
+  secret: &[u8],
+  alg: &Algorithm,
+) -> JWTResult<Vec<(String, DecodingKey)>> {
+  let jwks = parse_jwks(secret)
+    .ok_or_else(|| JWTError::Internal("Invalid jwks secret format".to_string()))?;
+
+  let keys: Vec<(String, DecodingKey)> = jwks
+    .keys
+    .iter()
+    .enumerate()
+    .filter(|(_, jwk)| is_compatible_jwk(jwk, alg))
+    .filter_map(|(i, jwk)| {
+      let label = jwk
+        .common
+        .key_id
+        .clone()
+        .unwrap_or_else(|| format!("#{}", i + 1));
+      DecodingKey::from_jwk(jwk).ok().map(|key| (label, key))
+    })
+    .collect();
+
+  if keys.is_empty() {
+    return Err(JWTError::Internal(format!(
+      "No jwk found for {alg:?} in the jwks secret"
+    )));
+  }
+  Ok(keys)
+}
+
+/// signature keys whose `alg`, or key type and curve when `alg` is not set, match the algorithm
+fn is_compatible_jwk(jwk: &jwk::Jwk, alg: &Algorithm) -> bool {
+  if jwk.common.public_key_use == Some(jwk::PublicKeyUse::Encryption) {
+    return false;
+  }
+  if let Some(key_algorithm) = jwk.common.key_algorithm {
+    return key_algorithm.to_string() == format!("{alg:?}");
+  }
+  match (&jwk.algorithm, alg) {
+    (AlgorithmParameters::OctetKey(_), Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) => {
+      true
+    }
+    (AlgorithmParameters::EllipticCurve(params), Algorithm::ES256) => {
+      params.curve == jwk::EllipticCurve::P256
+    }
+    (AlgorithmParameters::EllipticCurve(params), Algorithm::ES384) => {
+      params.curve == jwk::EllipticCurve::P384
+    }
+    (AlgorithmParameters::OctetKeyPair(_), Algorithm::EdDSA) => true,
+    (AlgorithmParameters::RSA(_), alg) => matches!(
+      alg,
+      Algorithm::RS256
+        | Algorithm::RS384
+        | Algorithm::RS512
+        | Algorithm::PS256
+        | Algorithm::PS384
+        | Algorithm::PS512
+    ),
+    _ => false,
+  }
+}
+
+/// the command run in the system shell, `sh -c` or `cmd /C` on Windows
+pub fn shell_command(cmd: &str) -> Command {
+  let mut command = match cfg!(windows) {
+    true => Command::new("cmd"),
+    false => Command::new("sh"),
+  };
+  command
+    .arg(if cfg!(windows) { "/C" } else { "-c" })
+    .arg(cmd);
+  command
+}
+
+/// run the command in the system shell and return its output without the trailing newline
+pub fn run_command(cmd: &str) -> JWTResult<String> {
+  let output = shell_command(cmd)
+    .output()
+    .map_err(|e| JWTError::Internal(format!("Unable to run `{cmd}`: {e}")))?;
+  if !output.status.success() {
+    return Err(JWTError::Internal(format!(
+      "`{cmd}` failed ({}): {}",
+      output.status,
+      String::from_utf8_lossy(&output.stderr).trim()
+    )));
+  }
+  Ok(
+    String::from_utf8_lossy(&output.stdout)
+      .trim_end_matches(['\n', '\r'])
+      .to_string(),
+  )
+}
+
+/// the content of the file
+pub fn slurp_file(file_name: String) -> io::Result<Vec<u8>> {
+  fs::read(file_name)
+}
+
+fn decoding_key_from_jwks(jwks: jwk::JwkSet, header: &Header) -> JWTResult<DecodingKey> {
+  let kid = match &header.kid {
+    Some(k) => k.to_owned(),
+    None => {
+      return Err(JWTError::Internal(
+        "Missing 'kid' from jwt header. Required for jwks secret".to_string(),
+      ));
+    }
+  };
+
+  let jwk = match jwks.find(&kid) {
+    Some(j) => j,
+    None => {
+      return Err(JWTError::Internal(format!(
+        "No jwk found for 'kid' {kid:?}",
+      )));
+    }
+  };
+
+  DecodingKey::from_jwk(jwk).map_err(Error::into)
+}
+
+fn parse_jwks(secret: &[u8]) -> Option<jwk::JwkSet> {
+  serde_json::from_slice(secret).ok()
+}
+
+fn get_secret_file_type(secret_string: &str) -> SecretType {
+  if secret_string.ends_with(".pem") {
+    SecretType::Pem
+  } else if secret_string.ends_with(".json") {
+    SecretType::Jwks
+  } else {
+    SecretType::Der
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{fs::File, io::Write};
+
+  use super::*;
+
+  #[test]
+  fn test_decoding_keys_from_jwks_secret() {
+    let rsa = fs::read(concat!(
+      env!("CARGO_MANIFEST_DIR"),
+      "/../test_data/test_rsa_public_jwks.json"
+    ))
+    .unwrap();
+    // the second key is for encryption
+    let keys = decoding_keys_from_jwks_secret(&rsa, &Algorithm::RS256).unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].0, "2caFcPx-aXaC6SevhV79UDIrs8LgUok2xo0A6DJPqJo");
+
+    let jwks = br#"{"keys": [
+      {"kty": "oct", "k": "c2VjcmV0"},
+      {"kty": "oct", "alg": "HS512", "k": "c2VjcmV0"},
+      {"kty": "EC", "crv": "P-256", "x": "w7JAoU_gJbZJvV-zCOvU9yFJq0FNC_edCMRM78P8eQQ", "y": "wQg1EytcsEmGrM70Gb53oluoDbVhCZ3Uq3hHMslHVb4"}
+    ]}"#;
+    let labels = |alg| {
+      decoding_keys_from_jwks_secret(jwks, &alg)
+        .unwrap()
+        .into_iter()
+        .map(|(label, _)| label)
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(labels(Algorithm::HS256), vec!["#1"]);
+    assert_eq!(labels(Algorithm::HS512), vec!["#1", "#2"]);
+    assert_eq!(labels(Algorithm::ES256), vec!["#3"]);
+    assert_eq!(
+      decoding_keys_from_jwks_secret(jwks, &Algorithm::ES384)
+        .err()
+        .unwrap()
+        .to_string(),
+      "No jwk found for ES384 in the jwks secret"
+    );
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn test_run_command() {
+    assert_eq!(
+      run_command("echo 'my passphrase'").unwrap(),
+      "my passphrase"
+    );
+    assert!(run_command("echo oops >&2; exit 3")
+      .unwrap_err()
+      .to_string()
+      .ends_with("oops"));
+  }
+
+  #[test]
+  fn test_split_secrets() {
+    assert_eq!(split_secrets(""), Vec::<&str>::new());
+    assert_eq!(split_secrets("secret"), vec!["secret"]);
+    assert_eq!(
+      split_secrets("old-secret, new-secret,,@./key.pem"),
+      vec!["old-secret", "new-secret", "@./key.pem"]
+    );
+    assert_eq!(
+      split_secrets("old-secret, cmd:jq -r '.a,.b' secrets.json"),
+      vec!["old-secret", "cmd:jq -r '.a,.b' secrets.json"]
+    );
+    assert_eq!(
+      split_secrets(r#" {"keys": [{"kty": "oct"}, {"kty": "RSA"}]}"#),
+      vec![r#"{"keys": [{"kty": "oct"}, {"kty": "RSA"}]}"#]
+    );
+  }
+
+  #[test]
+  fn test_slurp_file() {
+    let file_name = "test.txt";
+    let content = b"Hello, world!";
+
+    let mut file = File::create(file_name).unwrap();
+    file.write_all(content).unwrap();
+
+    let result = slurp_file(file_name.to_string()).unwrap();
+
+    assert_eq!(result, content);
+
+    std::fs::remove_file(file_name).unwrap();
+  }
+
+  #[test]
+  #[should_panic(expected = "The system cannot find the file specified.")]
+  #[cfg(target_os = "windows")]
+  fn test_slurp_file_nonexistent() {
+    let file_name = "nonexistent.txt";
+
+    slurp_file(file_name.to_string()).unwrap();
+  }
+
+  #[test]
+  #[should_panic(expected = "No such file or directory")]
+  #[cfg(not(target_os = "windows"))]
+  fn test_slurp_file_nonexistent() {
+    let file_name = "nonexistent.txt";
+
+    slurp_file(file_name.to_string()).unwrap();
+  }
+}
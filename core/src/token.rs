@@ -0,0 +1,269 @@
+use std::collections::HashSet;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+  decode, decode_header,
+  errors::{Error, ErrorKind},
+  Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+  error::{JWTError, JWTResult},
+  secret::{
+    decoding_key_from_secret, decoding_keys_from_jwks_secret, encoding_key_from_secret,
+    get_secret_from_file_or_input, SecretType,
+  },
+};
+
+/// a token whose signature matched, with the `kid` or position of the JWKS key that verified it
+/// when the header has no `kid` and every key of the JWKS secret was tried
+pub type VerifiedToken<T> = (TokenData<T>, Option<String>);
+
+/// decode a base64url encoded JSON segment of a token
+pub fn decode_segment<T: DeserializeOwned>(segment: &str) -> JWTResult<T> {
+  let bytes = URL_SAFE_NO_PAD
+    .decode(segment)
+    .map_err(|_| JWTError::from(Error::from(ErrorKind::InvalidToken)))?;
+  Ok(serde_json::from_slice(&bytes).map_err(|e| Error::from(ErrorKind::Json(e.into())))?)
+}
+
+/// the header and claims of the token, without checking its signature or claims
+pub fn decode_unverified<T: DeserializeOwned>(token: &str) -> JWTResult<TokenData<T>> {
+  let algorithm = decode_header(token).map_or(Algorithm::HS256, |header| header.alg);
+  let mut validation = Validation::new(algorithm);
+  // disable signature validation as its not needed for just decoding
+  validation.insecure_disable_signature_validation();
+  validation.required_spec_claims = HashSet::new();
+  validation.validate_exp = false;
+  validation.validate_aud = false;
+  Ok(decode::<T>(
+    token,
+    &insecure_decoding_key(algorithm),
+    &validation,
+  )?)
+}
+
+/// the validation of the claims of verified tokens: the expiry is checked with a leeway of 1000
+/// seconds unless ignored, the audience isn't checked
+pub fn validation(algorithm: Algorithm, ignore_exp: bool) -> Validation {
+  let mut validation = Validation::new(algorithm);
+  validation.leeway = 1000;
+  validation.validate_aud = false;
+  if ignore_exp {
+    validation
+      .required_spec_claims
+      .retain(|claim| claim != "exp");
+    validation.validate_exp = false;
+  }
+  validation
+}
+
+/// verify the signature of the token with the secret and check its claims with the validation.
+/// The secret is read like the secret input of JWT UI: text, `b64:`, `@file` or inline JWKS. The
+/// keys of a JWKS are tried in order when the header has no `kid`
+pub fn verify<T: DeserializeOwned>(
+  token: &str,
+  secret: &str,
+  validation: &Validation,
+) -> JWTResult<VerifiedToken<T>> {
+  let header = decode_header(token).ok();
+  let algorithm = header
+    .as_ref()
+    .map_or(Algorithm::HS256, |header| header.alg);
+  if !secret.is_empty() && header.as_ref().is_some_and(|header| header.kid.is_none()) {
+    if let (Ok(jwks), SecretType::Jwks) = get_secret_from_file_or_input(&algorithm, secret) {
+      let keys = decoding_keys_from_jwks_secret(&jwks, &algorithm)?;
+      return verify_with_jwks(token, keys, validation);
+    }
+  }
+  let key = match secret.is_empty() {
+    true => insecure_decoding_key(algorithm),
+    false => decoding_key_from_secret(&algorithm, secret, header)?,
+  };
+  Ok((decode::<T>(token, &key, validation)?, None))
+}
+
+/// claims are validated after the signature, so these errors mean the signature matched
+pub fn signature_matched<V>(verified: &JWTResult<V>) -> bool {
+  match verified {
+    Ok(_) => true,
+    Err(JWTError::External(e, _)) => matches!(
+      e.kind(),
+      ErrorKind::ExpiredSignature
+        | ErrorKind::ImmatureSignature
+        | ErrorKind::MissingRequiredClaim(_)
+        | ErrorKind::InvalidAudience
+        | ErrorKind::InvalidIssuer
+        | ErrorKind::InvalidSubject
+    ),
+    Err(_) => false,
+  }
+}
+
+/// sign the claims with the secret, using the algorithm of the header
+pub fn encode<T: Serialize>(header: &Header, claims: &T, secret: &str) -> JWTResult<String> {
+  let key = encoding_key_from_secret(&header.alg, secret)?;
+  Ok(jsonwebtoken::encode(header, claims, &key)?)
+}
+
+/// sign the claims typed as JSON with the header typed as JSON, with the key for the parsed header.
+/// The claims are parsed as `T`, which sets the order of the encoded claims
+pub fn encode_json<T: DeserializeOwned + Serialize>(
+  header: &str,
+  claims: &str,
+  key: impl FnOnce(&Header) -> JWTResult<EncodingKey>,
+) -> JWTResult<String> {
+  let header: Header =
+    serde_json::from_str(header).map_err(|e| format!("Error parsing header: {:}", e))?;
+  let claims: T =
+    serde_json::from_str(claims).map_err(|e| format!("Error parsing payload: {:}", e))?;
+  Ok(jsonwebtoken::encode(&header, &claims, &key(&header)?)?)
+}
+
+/// verify the JWT with each key in order and return the first result matching the signature
+fn verify_with_jwks<T: DeserializeOwned>(
+  token: &str,
+  keys: Vec<(String, DecodingKey)>,
+  validation: &Validation,
+) -> JWTResult<VerifiedToken<T>> {
+  let mut first = None;
+  for (kid, key) in keys {
+    let verified = decode::<T>(token, &key, validation).map_err(JWTError::from);
+    if signature_matched(&verified) {
+      return verified.map(|token| (token, Some(kid)));
+    }
+    first.get_or_insert(verified);
+  }
+  first
+    .unwrap_or_else(|| Err(Error::from(ErrorKind::InvalidSignature).into()))
+    .map(|token| (token, None))
+}
+
+/// a key of the family of the algorithm which doesn't verify anything, to decode tokens
+fn insecure_decoding_key(algorithm: Algorithm) -> DecodingKey {
+  match algorithm {
+    Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => Ok(DecodingKey::from_secret(b"")),
+    Algorithm::ES256 | Algorithm::ES384 => DecodingKey::from_ec_components("", ""),
+    Algorithm::EdDSA => DecodingKey::from_ed_components(""),
+    _ => DecodingKey::from_rsa_components("", ""),
+  }
+  .map_or(DecodingKey::from_secret(b""), |key| key)
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::{json, Value};
+
+  use super::*;
+
+  const TEST_DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test_data");
+
+  #[test]
+  fn test_encode_decode_and_verify() {
+    let claims = json!({"sub": "1234567890", "name": "John Doe"});
+    let token = encode(&Header::default(), &claims, "my-secret").unwrap();
+
+    let decoded = decode_unverified::<Value>(&token).unwrap();
+    assert_eq!(decoded.header.alg, Algorithm::HS256);
+    assert_eq!(decoded.claims, claims);
+
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.required_spec_claims.clear();
+    let (verified, kid) = verify::<Value>(&token, "my-secret", &validation).unwrap();
+    assert_eq!(verified.claims, claims);
+    assert_eq!(kid, None);
+    assert_eq!(
+      verify::<Value>(&token, "other-secret", &validation)
+        .unwrap_err()
+        .to_string(),
+      "The JWT provided has an invalid signature. Provide a valid secret: InvalidSignature"
+    );
+    assert!(!signature_matched(&verify::<Value>(
+      &token,
+      "",
+      &validation
+    )));
+    assert!(decode_unverified::<Value>("not-a-token").is_err());
+  }
+
+  #[test]
+  fn test_encode_and_verify_with_key_files() {
+    let claims = json!({"sub": "1234567890"});
+    let token = encode(
+      &Header::new(Algorithm::RS256),
+      &claims,
+      &format!("@{TEST_DATA}/test_rsa_private_key.pem"),
+    )
+    .unwrap();
+
+    let mut validation = Validation::new(Algorithm::RS256);
+    validation.required_spec_claims.clear();
+    let (decoded, _) = verify::<Value>(
+      &token,
+      &format!("@{TEST_DATA}/test_rsa_public_key.pem"),
+      &validation,
+    )
+    .unwrap();
+    assert_eq!(decoded.claims, claims);
+  }
+
+  #[test]
+  fn test_verify_with_jwks_without_kid() {
+    let token = encode(
+      &Header::new(Algorithm::RS256),
+      &json!({"sub": "1234567890"}),
+      &format!("@{TEST_DATA}/test_rsa_private_key.pem"),
+    )
+    .unwrap();
+    let mut validation = Validation::new(Algorithm::RS256);
+    validation.required_spec_claims.clear();
+    let (_, kid) = verify::<Value>(
+      &token,
+      &format!("@{TEST_DATA}/test_rsa_public_jwks_without_kid.json"),
+      &validation,
+    )
+    .unwrap();
+    // keys without `kid` are labelled by position
+    assert_eq!(kid.as_deref(), Some("#3"));
+  }
+
+  #[test]
+  fn test_validation() {
+    let expired = encode(
+      &Header::default(),
+      &json!({"sub": "1234567890", "exp": 1516239022}),
+      "my-secret",
+    )
+    .unwrap();
+    let verified = verify::<Value>(&expired, "my-secret", &validation(Algorithm::HS256, false));
+    assert_eq!(
+      verified.as_ref().unwrap_err().to_string(),
+      "The token has expired (or the `exp` claim is not set). This error can be ignored by pressing `i`.: ExpiredSignature"
+    );
+    // the signature matched, only the claims are invalid
+    assert!(signature_matched(&verified));
+    assert!(verify::<Value>(&expired, "my-secret", &validation(Algorithm::HS256, true)).is_ok());
+  }
+
+  #[test]
+  fn test_encode_json() {
+    let token = encode_json::<Value>(r#"{"alg": "HS384"}"#, r#"{"sub": "me"}"#, |header| {
+      encoding_key_from_secret(&header.alg, "my-secret")
+    })
+    .unwrap();
+    let decoded = decode_unverified::<Value>(&token).unwrap();
+    assert_eq!(decoded.header.alg, Algorithm::HS384);
+    assert_eq!(decoded.claims, json!({"sub": "me"}));
+
+    let key = |header: &Header| encoding_key_from_secret(&header.alg, "my-secret");
+    assert!(encode_json::<Value>("{", "{}", key)
+      .unwrap_err()
+      .to_string()
+      .starts_with("Error parsing header: "));
+    assert!(encode_json::<Value>(r#"{"alg": "HS256"}"#, "[", key)
+      .unwrap_err()
+      .to_string()
+      .starts_with("Error parsing payload: "));
+  }
+}
@@ -0,0 +1,304 @@
+use serde_derive::Serialize;
+use serde_json::{to_string_pretty, Value};
+
+use super::{
+  jwt_decoder::Payload,
+  paseto::is_paseto,
+  unsecured::check_unsecured,
+  utils::{decode_segment, JWTError, JWTResult},
+  validation::{check_token_size, claim_timestamp, format_duration},
+};
+
+/// lifetimes (`exp - iat`) above one day are reported as excessive
+const MAX_LIFETIME: i64 = 24 * 60 * 60;
+/// characters of a `kid` that hint at path traversal, SQL or command injection
+const KID_INJECTION_CHARS: [char; 13] = [
+  '/', '\\', '\'', '"', ';', '|', '&', '$', '`', '<', '>', '*', '\0',
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditSeverity {
+  Low,
+  Medium,
+  High,
+  Critical,
+}
+
+impl AuditSeverity {
+  /// points deducted from the score of the token
+  fn weight(self) -> u32 {
+    match self {
+      AuditSeverity::Low => 5,
+      AuditSeverity::Medium => 10,
+      AuditSeverity::High => 25,
+      AuditSeverity::Critical => 50,
+    }
+  }
+}
+
+/// a single issue found by the audit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditFinding {
+  pub severity: AuditSeverity,
+  /// name of the check that produced this finding
+  pub check: &'static str,
+  pub message: String,
+}
+
+/// scored result of `jwtui audit`, from 100 (no issue found) down to 0
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditReport {
+  pub score: u32,
+  pub findings: Vec<AuditFinding>,
+}
+
+impl AuditReport {
+  /// returns true if the report has high or critical findings
+  pub fn has_severe_findings(&self) -> bool {
+    self
+      .findings
+      .iter()
+      .any(|f| f.severity >= AuditSeverity::High)
+  }
+}
+
+/// run the security checks on a signed JWT
+pub fn audit_token(token: &str) -> JWTResult<AuditReport> {
+  let token = token.trim();
+  let parts: Vec<&str> = token.split('.').collect();
+  if parts.len() != 3 || is_paseto(token) {
+    return Err(JWTError::Internal("Only signed JWTs can be audited".into()));
+  }
+  let header: Value = decode_segment(parts[0])?;
+  let claims: Payload = decode_segment(parts[1])?;
+
+  let mut findings = vec![];
+  let mut add = |severity, check, message: String| {
+    findings.push(AuditFinding {
+      severity,
+      check,
+      message,
+    })
+  };
+
+  if let Some(finding) = check_unsecured(token) {
+    add(AuditSeverity::Critical, "unsecured", finding.message);
+  }
+  match header["alg"].as_str() {
+    None => add(
+      AuditSeverity::High,
+      "algorithm",
+      "The header has no `alg`".into(),
+    ),
+    Some(alg) if alg.starts_with("HS") => add(
+      AuditSeverity::Low,
+      "algorithm",
+      format!("{alg} is symmetric, every service able to verify the token can also forge it"),
+    ),
+    Some(_) => {}
+  }
+  if let Some(kid) = header["kid"].as_str() {
+    let mut chars: Vec<char> = kid
+      .chars()
+      .filter(|c| KID_INJECTION_CHARS.contains(c) || c.is_whitespace() || c.is_control())
+      .collect();
+    chars.sort_unstable();
+    chars.dedup();
+    if !chars.is_empty() || kid.contains("..") {
+      add(
+        AuditSeverity::High,
+        "kid",
+        format!(
+          "The `kid` {kid:?} contains characters used for path traversal or injection attacks{}",
+          if chars.is_empty() {
+            String::new()
+          } else {
+            format!(
+              " ({})",
+              chars
+                .iter()
+                .map(|c| format!("{c:?}"))
+                .collect::<Vec<_>>()
+                .join(" ")
+            )
+          }
+        ),
+      );
+    }
+  }
+
+  match (
+    claim_timestamp(&claims, "iat").or(claim_timestamp(&claims, "nbf")),
+    claim_timestamp(&claims, "exp"),
+  ) {
+    (_, None) => add(
+      AuditSeverity::High,
+      "exp",
+      "The token has no `exp` claim and never expires".into(),
+    ),
+    (Some(start), Some(exp)) if exp - start > MAX_LIFETIME => add(
+      AuditSeverity::Medium,
+      "lifetime",
+      format!(
+        "Token lifetime of {} exceeds {}",
+        format_duration(exp - start),
+        format_duration(MAX_LIFETIME)
+      ),
+    ),
+    _ => {}
+  }
+  if !claims.0.contains_key("aud") {
+    add(
+      AuditSeverity::Medium,
+      "aud",
+      "The token has no `aud` claim, it can be replayed against other services".into(),
+    );
+  }
+  if !claims.0.contains_key("iss") {
+    add(
+      AuditSeverity::Low,
+      "iss",
+      "The token has no `iss` claim".into(),
+    );
+  }
+  for finding in check_token_size(token) {
+    add(AuditSeverity::Medium, "size", finding.message);
+  }
+
+  let penalty: u32 = findings.iter().map(|f| f.severity.weight()).sum();
+  Ok(AuditReport {
+    score: 100u32.saturating_sub(penalty),
+    findings,
+  })
+}
+
+/// print the audit report as text or JSON
+pub fn print_audit_report(report: &AuditReport, json: bool) {
+  if json {
+    println!("{}", to_string_pretty(report).unwrap());
+    return;
+  }
+  println!("\nToken audit\n-----------");
+  if report.findings.is_empty() {
+    println!("No issues found");
+  }
+  for finding in &report.findings {
+    println!(
+      "[{:?}] {}: {}",
+      finding.severity, finding.check, finding.message
+    );
+  }
+  println!("\nScore: {}/100", report.score);
+}
+
+#[cfg(test)]
+mod tests {
+  use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+  use super::*;
+
+  fn jwt(header: &str, claims: &str) -> String {
+    format!(
+      "{}.{}.sig",
+      URL_SAFE_NO_PAD.encode(header),
+      URL_SAFE_NO_PAD.encode(claims)
+    )
+  }
+
+  fn checks(report: &AuditReport) -> Vec<(&'static str, AuditSeverity)> {
+    report
+      .findings
+      .iter()
+      .map(|f| (f.check, f.severity))
+      .collect()
+  }
+
+  #[test]
+  fn test_audit_token_without_issues() {
+    let report = audit_token(&jwt(
+      r#"{"alg":"RS256","kid":"key-1"}"#,
+      r#"{"iss":"https://idp","aud":"api","iat":1700000000,"exp":1700003600}"#,
+    ))
+    .unwrap();
+    assert_eq!(
+      report,
+      AuditReport {
+        score: 100,
+        findings: vec![]
+      }
+    );
+    assert!(!report.has_severe_findings());
+  }
+
+  #[test]
+  fn test_audit_token_findings() {
+    let report = audit_token(&jwt(
+      r#"{"alg":"HS256","kid":"../../dev/null"}"#,
+      r#"{"sub":"1234"}"#,
+    ))
+    .unwrap();
+    assert_eq!(
+      checks(&report),
+      vec![
+        ("algorithm", AuditSeverity::Low),
+        ("kid", AuditSeverity::High),
+        ("exp", AuditSeverity::High),
+        ("aud", AuditSeverity::Medium),
+        ("iss", AuditSeverity::Low),
+      ]
+    );
+    assert_eq!(
+      report.findings[1].message,
+      "The `kid` \"../../dev/null\" contains characters used for path traversal or injection attacks ('/')"
+    );
+    assert_eq!(report.score, 30);
+    assert!(report.has_severe_findings());
+
+    let report = audit_token(&jwt(
+      r#"{"alg":"none"}"#,
+      r#"{"iss":"i","aud":"a","iat":0,"exp":172800}"#,
+    ))
+    .unwrap();
+    assert_eq!(
+      checks(&report),
+      vec![
+        ("unsecured", AuditSeverity::Critical),
+        ("lifetime", AuditSeverity::Medium),
+      ]
+    );
+    assert_eq!(
+      report.findings[1].message,
+      "Token lifetime of 2d exceeds 1d"
+    );
+    assert_eq!(report.score, 40);
+  }
+
+  #[test]
+  fn test_audit_token_size_and_score_floor() {
+    let claims = format!(r#"{{"data":"{}"}}"#, "x".repeat(8192));
+    let report = audit_token(&jwt(r#"{"kid":"a b;c"}"#, &claims)).unwrap();
+    assert_eq!(
+      checks(&report),
+      vec![
+        ("algorithm", AuditSeverity::High),
+        ("kid", AuditSeverity::High),
+        ("exp", AuditSeverity::High),
+        ("aud", AuditSeverity::Medium),
+        ("iss", AuditSeverity::Low),
+        ("size", AuditSeverity::Medium),
+      ]
+    );
+    assert_eq!(report.score, 0);
+  }
+
+  #[test]
+  fn test_audit_token_errors() {
+    assert_eq!(
+      audit_token("abc").unwrap_err().to_string(),
+      "Only signed JWTs can be audited"
+    );
+    assert!(audit_token("a.b.c.d.e").is_err());
+    assert!(audit_token("!!.e30.sig").is_err());
+  }
+}
@@ -0,0 +1,415 @@
+//! Asymmetric keys of Azure Key Vault, referenced as
+//! `azurekv:https://<vault>.vault.azure.net/keys/<name>/<version>`. Tokens are verified with the
+//! public key fetched from the vault and signed by Key Vault, the private key never leaves it.
+//! Requests are authorized with the service principal of the `AZURE_*` environment variables, the
+//! Azure CLI or the managed identity of the workload
+
+use std::{
+  env,
+  sync::Mutex,
+  time::{Duration, Instant},
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::de::DeserializeOwned;
+use serde_derive::Deserialize;
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use super::{
+  jwt_decoder::Payload,
+  keys::jwks,
+  network::{send, RequestBody},
+  utils::{run_command, JWTError, JWTResult},
+};
+
+/// prefix of the keys of Azure Key Vault
+pub const AZURE_KV_PREFIX: &str = "azurekv:";
+const API_VERSION: &str = "7.4";
+const VAULT_RESOURCE: &str = "https://vault.azure.net";
+/// domains of the vaults and managed HSMs, the only hosts the access token is sent to
+const VAULT_DOMAINS: [&str; 2] = [".vault.azure.net", ".managedhsm.azure.net"];
+/// default host of the Microsoft Entra ID token endpoints, `AZURE_AUTHORITY_HOST` like for the
+/// Azure SDKs
+const AUTHORITY_HOST: &str = "https://login.microsoftonline.com";
+/// token of the managed identity of the Azure VM, App Service or AKS workload
+const IMDS_TOKEN_URL: &str = "http://169.254.169.254/metadata/identity/oauth2/token";
+/// lifetime assumed for the tokens of the Azure CLI when it doesn't tell
+const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(300);
+/// access tokens are requested again when they expire within this delay
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// the access token of the credentials and when it expires, reused by the requests to Key Vault
+static ACCESS_TOKEN: Mutex<Option<(String, Instant)>> = Mutex::new(None);
+
+/// access token of Microsoft Entra ID or of the Azure CLI, with the lifetime as number or string
+#[derive(Debug, Deserialize)]
+struct AccessToken {
+  #[serde(alias = "accessToken")]
+  access_token: String,
+  /// lifetime in seconds, a string for managed identities
+  expires_in: Option<Value>,
+  /// expiry as Unix timestamp, given by the Azure CLI
+  expires_on: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeyBundle {
+  key: Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SignResponse {
+  /// base64url encoded signature, `r || s` for ECDSA keys like in JWS
+  value: String,
+}
+
+impl AccessToken {
+  /// how long the token stays valid
+  fn lifetime(&self) -> Duration {
+    let expires_in = match &self.expires_in {
+      Some(Value::Number(seconds)) => seconds.as_u64(),
+      Some(Value::String(seconds)) => seconds.parse().ok(),
+      _ => None,
+    };
+    let expires_on = self
+      .expires_on
+      .and_then(|timestamp| u64::try_from(timestamp - chrono::Utc::now().timestamp()).ok());
+    expires_in
+      .or(expires_on)
+      .map(Duration::from_secs)
+      .unwrap_or(DEFAULT_TOKEN_LIFETIME)
+  }
+}
+
+/// the key identifier of an `azurekv:` reference
+pub fn key_id(secret: &str) -> Option<&str> {
+  secret
+    .strip_prefix(AZURE_KV_PREFIX)
+    .map(|id| id.trim().trim_end_matches('/'))
+}
+
+/// check that the key identifier is a key of a vault or managed HSM before the access token is
+/// sent to it, as the reference may come from a shared workspace
+fn check_key_id(key_id: &str) -> JWTResult<()> {
+  let invalid = || {
+    JWTError::Internal(format!(
+      "{key_id:?} is not a Key Vault key, expected https://<vault>.vault.azure.net/keys/<name>/<version>"
+    ))
+  };
+  let (host, path) = key_id
+    .strip_prefix("https://")
+    .and_then(|rest| rest.split_once('/'))
+    .ok_or_else(invalid)?;
+  let is_name =
+    |name: &str| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+  let host = host.to_ascii_lowercase();
+  let vault = VAULT_DOMAINS
+    .iter()
+    .find_map(|domain| host.strip_suffix(domain));
+  if !vault.is_some_and(is_name) {
+    return Err(invalid());
+  }
+  match path.split('/').collect::<Vec<_>>()[..] {
+    ["keys", name] if is_name(name) => Ok(()),
+    ["keys", name, version] if is_name(name) && is_name(version) => Ok(()),
+    _ => Err(invalid()),
+  }
+}
+
+/// the public key of the vault key as JWKS, with the key identifier as `kid`
+pub fn public_jwks(key_id: &str) -> JWTResult<String> {
+  check_key_id(key_id)?;
+  public_jwks_with_token(key_id, &access_token()?)
+}
+
+fn public_jwks_with_token(key_id: &str, token: &str) -> JWTResult<String> {
+  let bundle: KeyBundle = send_json(key_id, token, None)?;
+  Ok(jwks(vec![public_jwk(bundle.key)?]).to_string())
+}
+
+/// the public members of the key of the vault, whose key types end with `-HSM` for keys in an
+/// HSM and whose secp256k1 curve is `P-256K`
+fn public_jwk(key: Map<String, Value>) -> JWTResult<Value> {
+  let mut jwk = Map::new();
+  let kty = key.get("kty").and_then(Value::as_str).unwrap_or_default();
+  match kty.trim_end_matches("-HSM") {
+    "RSA" => jwk.insert("kty".into(), "RSA".into()),
+    "EC" => jwk.insert("kty".into(), "EC".into()),
+    _ => {
+      return Err(JWTError::Internal(format!(
+        "The {kty:?} Key Vault key can't sign tokens"
+      )))
+    }
+  };
+  for member in ["kid", "n", "e", "crv", "x", "y"] {
+    if let Some(value) = key.get(member) {
+      jwk.insert(member.into(), value.clone());
+    }
+  }
+  if jwk.get("crv").is_some_and(|crv| crv == "P-256K") {
+    jwk.insert("crv".into(), "secp256k1".into());
+  }
+  Ok(Value::Object(jwk))
+}
+
+/// sign the header and claims with the vault key, the `alg` of the header has to fit the key
+pub fn encode_azure_kv(header: &Value, payload: &Payload, key_id: &str) -> JWTResult<String> {
+  let message = format!(
+    "{}.{}",
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(header)?),
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(payload)?)
+  );
+  let alg = header["alg"].as_str().unwrap_or_default();
+  check_key_id(key_id)?;
+  let signature = sign(key_id, alg, message.as_bytes(), &access_token()?)?;
+  Ok(format!("{message}.{signature}"))
+}
+
+/// the base64url signature of the message by Key Vault, which signs its digest
+fn sign(key_id: &str, alg: &str, message: &[u8], token: &str) -> JWTResult<String> {
+  let digest = match alg {
+    "RS256" | "PS256" | "ES256" | "ES256K" => Sha256::digest(message).to_vec(),
+    "RS384" | "PS384" | "ES384" => Sha384::digest(message).to_vec(),
+    "RS512" | "PS512" | "ES512" => Sha512::digest(message).to_vec(),
+    alg => {
+      return Err(JWTError::Internal(format!(
+        "Key Vault keys can't sign {alg:?} tokens"
+      )))
+    }
+  };
+  let body = json!({ "alg": alg, "value": URL_SAFE_NO_PAD.encode(digest) });
+  let response: SignResponse = send_json(&format!("{key_id}/sign"), token, Some(&body))?;
+  Ok(response.value)
+}
+
+/// send a request to Key Vault, a POST of the body when there is one, and parse the response
+fn send_json<T: DeserializeOwned>(url: &str, token: &str, body: Option<&Value>) -> JWTResult<T> {
+  let url = format!("{url}?api-version={API_VERSION}");
+  let mut headers = vec![("Authorization".to_string(), format!("Bearer {token}"))];
+  let body = body.map(Value::to_string);
+  let response = match &body {
+    Some(body) => {
+      headers.push(("Content-Type".into(), "application/json".into()));
+      send(&url, &headers, RequestBody::Text(body))?
+    }
+    None => send(&url, &headers, RequestBody::None)?,
+  };
+  serde_json::from_slice(&response)
+    .map_err(|e| JWTError::Internal(format!("Invalid response from {url}: {e}")))
+}
+
+/// the access token of the credentials for Key Vault, requested again when it expires
+fn access_token() -> JWTResult<String> {
+  let mut cached = ACCESS_TOKEN.lock().unwrap_or_else(|e| e.into_inner());
+  if let Some((token, expires_at)) = cached.as_ref() {
+    if Instant::now() + TOKEN_EXPIRY_MARGIN < *expires_at {
+      return Ok(token.clone());
+    }
+  }
+  let token = request_access_token()?;
+  *cached = Some((
+    token.access_token.clone(),
+    Instant::now() + token.lifetime(),
+  ));
+  Ok(token.access_token)
+}
+
+/// the token of the service principal of `AZURE_TENANT_ID`, `AZURE_CLIENT_ID` and
+/// `AZURE_CLIENT_SECRET`, or else of the Azure CLI, or else of the managed identity
+fn request_access_token() -> JWTResult<AccessToken> {
+  let var = |name| env::var(name).ok().filter(|value| !value.is_empty());
+  if let (Some(tenant), Some(client_id), Some(client_secret)) = (
+    var("AZURE_TENANT_ID"),
+    var("AZURE_CLIENT_ID"),
+    var("AZURE_CLIENT_SECRET"),
+  ) {
+    let authority = var("AZURE_AUTHORITY_HOST").unwrap_or_else(|| AUTHORITY_HOST.into());
+    return client_secret_token(&authority, &tenant, &client_id, &client_secret);
+  }
+  let cli = run_command(&format!(
+    "az account get-access-token --resource {VAULT_RESOURCE} --output json"
+  ));
+  match cli {
+    Ok(output) => parse_token(output.as_bytes()),
+    Err(_) => parse_token(&send(
+      &format!("{IMDS_TOKEN_URL}?api-version=2018-02-01&resource={VAULT_RESOURCE}"),
+      &[("Metadata".into(), "true".into())],
+      RequestBody::None,
+    )?),
+  }
+}
+
+fn client_secret_token(
+  authority: &str,
+  tenant: &str,
+  client_id: &str,
+  client_secret: &str,
+) -> JWTResult<AccessToken> {
+  let scope = format!("{VAULT_RESOURCE}/.default");
+  parse_token(&send(
+    &format!(
+      "{}/{tenant}/oauth2/v2.0/token",
+      authority.trim_end_matches('/')
+    ),
+    &[],
+    RequestBody::Form(&[
+      ("grant_type", "client_credentials"),
+      ("client_id", client_id),
+      ("client_secret", client_secret),
+      ("scope", &scope),
+    ]),
+  )?)
+}
+
+fn parse_token(response: &[u8]) -> JWTResult<AccessToken> {
+  serde_json::from_slice(response)
+    .map_err(|e| JWTError::Internal(format!("Invalid access token response: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app::network::tests::{serve_in_order, serve_once_with_request};
+
+  #[test]
+  fn test_key_id() {
+    assert_eq!(
+      key_id("azurekv:https://my-vault.vault.azure.net/keys/signing/0123/"),
+      Some("https://my-vault.vault.azure.net/keys/signing/0123")
+    );
+    assert_eq!(key_id("gcpkms:projects/p/k/1"), None);
+  }
+
+  #[test]
+  fn test_check_key_id() {
+    assert!(check_key_id("https://my-vault.vault.azure.net/keys/signing/0123").is_ok());
+    assert!(check_key_id("https://my-hsm.managedhsm.azure.net/keys/signing").is_ok());
+    // the access token is never sent to other hosts
+    for key_id in [
+      "https://attacker.example.com/keys/signing/0123",
+      "https://vault.azure.net.attacker.example.com/keys/signing",
+      "https://attacker.example.com/.vault.azure.net/keys/signing",
+      "https://user@my-vault.vault.azure.net/keys/signing",
+      "https://my-vault.vault.azure.net:8443/keys/signing",
+      "http://my-vault.vault.azure.net/keys/signing",
+      "https://my-vault.vault.azure.net/secrets/signing",
+      "https://my-vault.vault.azure.net/keys/signing/0123/extra",
+      "https://my-vault.vault.azure.net/keys/signing?x=1",
+    ] {
+      assert!(
+        check_key_id(key_id)
+          .unwrap_err()
+          .to_string()
+          .contains("is not a Key Vault key"),
+        "{key_id}"
+      );
+    }
+    let payload = serde_json::from_str(r#"{"sub": "me"}"#).unwrap();
+    assert!(encode_azure_kv(
+      &json!({ "alg": "ES256" }),
+      &payload,
+      "https://attacker.example.com/keys/signing"
+    )
+    .is_err());
+  }
+
+  #[test]
+  fn test_public_jwks() {
+    let body = json!({
+      "key": {
+        "kid": "https://my-vault.vault.azure.net/keys/signing/0123",
+        "kty": "EC-HSM",
+        "key_ops": ["sign", "verify"],
+        "crv": "P-256K",
+        "x": "x-coordinate",
+        "y": "y-coordinate",
+      },
+      "attributes": { "enabled": true },
+    });
+    let (url, request) = serve_once_with_request("200 OK", body.to_string().into_bytes());
+    let key_id = format!("{url}/keys/signing/0123");
+    let jwks: Value =
+      serde_json::from_str(&public_jwks_with_token(&key_id, "eyJ.token").unwrap()).unwrap();
+    assert_eq!(
+      jwks["keys"][0],
+      json!({
+        "kid": "https://my-vault.vault.azure.net/keys/signing/0123",
+        "kty": "EC",
+        "crv": "secp256k1",
+        "x": "x-coordinate",
+        "y": "y-coordinate",
+      })
+    );
+    let request = request.recv().unwrap();
+    assert!(request.starts_with("GET /keys/signing/0123?api-version=7.4 HTTP/1.1"));
+    assert!(request
+      .to_lowercase()
+      .contains("authorization: bearer eyj.token"));
+
+    let oct = json!({ "kty": "oct-HSM" }).as_object().unwrap().clone();
+    assert!(public_jwk(oct).is_err());
+  }
+
+  #[test]
+  fn test_sign() {
+    let (url, requests) = serve_in_order(vec![
+      ("200 OK", br#"{"kid": "k", "value": "c2lnbmF0dXJl"}"#.to_vec()),
+      (
+        "403 Forbidden",
+        br#"{"error": {"code": "Forbidden", "message": "The user does not have keys sign permission"}}"#
+          .to_vec(),
+      ),
+    ]);
+    let key_id = format!("{url}/keys/signing/0123");
+    assert_eq!(
+      sign(&key_id, "ES256", b"a.b", "eyJ.token").unwrap(),
+      "c2lnbmF0dXJl"
+    );
+    let request = requests.recv().unwrap();
+    assert!(request.starts_with("POST /keys/signing/0123/sign?api-version=7.4 HTTP/1.1"));
+    let digest = URL_SAFE_NO_PAD.encode(Sha256::digest(b"a.b"));
+    assert!(request.ends_with(&json!({ "alg": "ES256", "value": digest }).to_string()));
+
+    let error = sign(&key_id, "RS256", b"a.b", "eyJ.token").unwrap_err();
+    assert!(error.to_string().contains("keys sign permission"));
+    assert!(sign(&key_id, "EdDSA", b"a.b", "eyJ.token")
+      .unwrap_err()
+      .to_string()
+      .contains("can't sign \"EdDSA\" tokens"));
+  }
+
+  #[test]
+  fn test_client_secret_token() {
+    let (url, request) = serve_once_with_request(
+      "200 OK",
+      br#"{"token_type": "Bearer", "expires_in": 3599, "access_token": "eyJ.token"}"#.to_vec(),
+    );
+    let token = client_secret_token(&format!("{url}/"), "my-tenant", "my-app", "s3cr3t").unwrap();
+    assert_eq!(token.access_token, "eyJ.token");
+    assert_eq!(token.lifetime(), Duration::from_secs(3599));
+    let request = request.recv().unwrap();
+    assert!(request.starts_with("POST /my-tenant/oauth2/v2.0/token HTTP/1.1"));
+    assert!(request.ends_with(
+      "grant_type=client_credentials&client_id=my-app&client_secret=s3cr3t&scope=https%3A%2F%2Fvault.azure.net%2F.default"
+    ));
+  }
+
+  #[test]
+  fn test_token_lifetime() {
+    // managed identities give the lifetime as string, the Azure CLI gives the expiry
+    let token = parse_token(br#"{"access_token": "a", "expires_in": "86399"}"#).unwrap();
+    assert_eq!(token.lifetime(), Duration::from_secs(86399));
+    let expires_on = chrono::Utc::now().timestamp() + 600;
+    let token = parse_token(
+      json!({ "accessToken": "a", "expires_on": expires_on, "expiresOn": "2024-01-01 10:00:00" })
+        .to_string()
+        .as_bytes(),
+    )
+    .unwrap();
+    assert!(token.lifetime() <= Duration::from_secs(600));
+    assert!(token.lifetime() > Duration::from_secs(590));
+    let token = parse_token(br#"{"accessToken": "a"}"#).unwrap();
+    assert_eq!(token.lifetime(), DEFAULT_TOKEN_LIFETIME);
+  }
+}
@@ -0,0 +1,141 @@
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::{
+  keys::write_key_file,
+  secrets::is_reference,
+  utils::{JWTError, JWTResult},
+};
+
+/// named secret, key file, keyring entry or JWKS URL picked in the secret blocks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bookmark {
+  pub name: String,
+  pub value: String,
+}
+
+impl Bookmark {
+  /// whether the value is a secret entered as text, which is masked in the bookmark list
+  pub fn is_secret(&self) -> bool {
+    !is_reference(&self.value) && !self.value.starts_with("https://")
+  }
+}
+
+/// bookmarks sorted by name, saved to a file readable only by the user as they can have secrets
+#[derive(Debug, Default)]
+pub struct Bookmarks {
+  path: Option<PathBuf>,
+  pub items: Vec<Bookmark>,
+}
+
+impl Bookmarks {
+  /// read the saved bookmarks, none when there is no bookmarks file yet
+  pub fn load(path: Option<PathBuf>) -> JWTResult<Self> {
+    let items = match path.as_deref() {
+      Some(path) => read_bookmarks(path)?,
+      None => vec![],
+    };
+    Ok(Self { path, items })
+  }
+
+  /// bookmark the value under the name, replacing the bookmark of the same name
+  pub fn add(&mut self, name: &str, value: &str) -> JWTResult<()> {
+    let (name, value) = (name.trim(), value.trim());
+    if name.is_empty() {
+      return Err(JWTError::Internal("The bookmark needs a name".into()));
+    }
+    if value.is_empty() {
+      return Err(JWTError::Internal("There is no secret to bookmark".into()));
+    }
+    self.items.retain(|bookmark| bookmark.name != name);
+    self.items.push(Bookmark {
+      name: name.into(),
+      value: value.into(),
+    });
+    self.items.sort_by(|a, b| a.name.cmp(&b.name));
+    self.save()
+  }
+
+  pub fn remove(&mut self, name: &str) -> JWTResult<()> {
+    self.items.retain(|bookmark| bookmark.name != name);
+    self.save()
+  }
+
+  fn save(&self) -> JWTResult<()> {
+    let Some(path) = self.path.as_ref() else {
+      return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+      fs::create_dir_all(dir)?;
+    }
+    let content = serde_json::to_string_pretty(&self.items)?;
+    write_key_file(path, content.as_bytes(), true)
+  }
+}
+
+fn read_bookmarks(path: &Path) -> JWTResult<Vec<Bookmark>> {
+  match fs::read_to_string(path) {
+    Ok(content) => serde_json::from_str(&content)
+      .map_err(|e| JWTError::Internal(format!("Invalid bookmarks file {}: {e}", path.display()))),
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+    Err(e) => Err(e.into()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_add_and_remove_bookmarks() {
+    let mut bookmarks = Bookmarks::default();
+    bookmarks
+      .add(" staging ", "@~/keys/staging_pub.pem")
+      .unwrap();
+    bookmarks.add("dev", "my-secret").unwrap();
+    bookmarks.add("staging", "keyring:staging").unwrap();
+    let names: Vec<&str> = bookmarks.items.iter().map(|b| b.name.as_str()).collect();
+    assert_eq!(names, ["dev", "staging"]);
+    assert_eq!(bookmarks.items[1].value, "keyring:staging");
+    assert!(bookmarks.items[0].is_secret());
+    assert!(!bookmarks.items[1].is_secret());
+
+    assert_eq!(
+      bookmarks.add("", "my-secret").unwrap_err().to_string(),
+      "The bookmark needs a name"
+    );
+    assert!(bookmarks.add("empty", " ").is_err());
+    bookmarks.remove("dev").unwrap();
+    assert_eq!(bookmarks.items.len(), 1);
+  }
+
+  #[test]
+  fn test_load_and_save_bookmarks() {
+    let path = std::env::temp_dir().join(format!("jwtui-bookmarks-{}.json", std::process::id()));
+    let mut bookmarks = Bookmarks::load(Some(path.clone())).unwrap();
+    assert!(bookmarks.items.is_empty());
+    bookmarks
+      .add("auth0", "https://example.auth0.com/.well-known/jwks.json")
+      .unwrap();
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::PermissionsExt;
+      let mode = fs::metadata(&path).unwrap().permissions().mode();
+      assert_eq!(mode & 0o777, 0o600);
+    }
+    let loaded = Bookmarks::load(Some(path.clone())).unwrap();
+    assert_eq!(loaded.items, bookmarks.items);
+    assert!(!loaded.items[0].is_secret());
+
+    fs::write(&path, "not json").unwrap();
+    assert!(Bookmarks::load(Some(path.clone()))
+      .unwrap_err()
+      .to_string()
+      .starts_with("Invalid bookmarks file"));
+    fs::remove_file(&path).unwrap();
+  }
+}
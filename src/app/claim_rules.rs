@@ -0,0 +1,143 @@
+use jsonwebtoken::Header;
+use rhai::{serde::to_dynamic, Dynamic, Engine, Scope};
+use serde_derive::Deserialize;
+
+use super::{
+  jwt_decoder::Payload,
+  validation::{Finding, Severity},
+};
+
+/// operations a rule can run, so a looping rule can't freeze the app
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// rhai script checking the decoded token, e.g. `claims.scope.contains("admin") && claims.aud ==
+/// "api"`. The token passes the rule when the script returns `true`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub struct ClaimRule {
+  /// name of the rule, shown as the check of its finding
+  pub name: String,
+  /// script with the `claims` and `header` of the token in scope
+  pub script: String,
+  /// severity of the finding when the token doesn't pass the rule
+  #[serde(default = "default_severity")]
+  pub severity: Severity,
+  /// message of the finding, the script when not set
+  pub message: Option<String>,
+}
+
+fn default_severity() -> Severity {
+  Severity::Error
+}
+
+/// run the rules on the decoded token, invalid scripts are error findings
+pub fn check_claim_rules(rules: &[ClaimRule], header: &Header, claims: &Payload) -> Vec<Finding> {
+  if rules.is_empty() {
+    return vec![];
+  }
+  let mut engine = Engine::new();
+  engine.set_max_operations(MAX_OPERATIONS);
+  let mut scope = Scope::new();
+  match (to_dynamic(claims), to_dynamic(header)) {
+    (Ok(claims), Ok(header)) => {
+      scope.push_constant("claims", claims);
+      scope.push_constant("header", header);
+    }
+    (Err(e), _) | (_, Err(e)) => {
+      return vec![Finding::new(
+        Severity::Error,
+        "rules",
+        format!("Unable to pass the token to the rules: {e}"),
+      )]
+    }
+  }
+  rules
+    .iter()
+    .filter_map(|rule| {
+      let result = engine.eval_with_scope::<Dynamic>(&mut scope.clone(), &rule.script);
+      match result.map(|value| value.as_bool()) {
+        Ok(Ok(true)) => None,
+        Ok(Ok(false)) => Some(Finding::new(
+          rule.severity,
+          &rule.name,
+          rule
+            .message
+            .clone()
+            .unwrap_or_else(|| format!("Rule failed: {}", rule.script)),
+        )),
+        Ok(Err(kind)) => Some(Finding::new(
+          Severity::Error,
+          &rule.name,
+          format!("The rule returned {kind} instead of true or false"),
+        )),
+        Err(e) => Some(Finding::new(
+          Severity::Error,
+          &rule.name,
+          format!("Invalid rule: {e}"),
+        )),
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use jsonwebtoken::Algorithm;
+
+  use super::*;
+
+  fn rule(name: &str, script: &str) -> ClaimRule {
+    ClaimRule {
+      name: name.into(),
+      script: script.into(),
+      severity: Severity::Error,
+      message: None,
+    }
+  }
+
+  #[test]
+  fn test_check_claim_rules() {
+    let claims: Payload =
+      serde_json::from_str(r#"{"scope": "read admin", "aud": "api", "roles": ["viewer"]}"#)
+        .unwrap();
+    let rules = [
+      rule(
+        "admin-api",
+        r#"claims.scope.contains("admin") && claims.aud == "api""#,
+      ),
+      ClaimRule {
+        severity: Severity::Warning,
+        message: Some("Editors only".into()),
+        ..rule("editor", r#"claims.roles.contains("editor")"#)
+      },
+      rule("rsa", r#"header.alg == "RS256""#),
+      rule("tenant", "claims.tenant"),
+      rule("syntax", "claims.aud =="),
+      rule("loop", "loop {}"),
+    ];
+    let findings = check_claim_rules(&rules, &Header::new(Algorithm::HS256), &claims);
+    let described: Vec<(Severity, &str, &str)> = findings
+      .iter()
+      .map(|f| (f.severity, f.check.as_str(), f.message.as_str()))
+      .collect();
+    assert_eq!(described[0], (Severity::Warning, "editor", "Editors only"));
+    assert_eq!(
+      described[1],
+      (
+        Severity::Error,
+        "rsa",
+        r#"Rule failed: header.alg == "RS256""#
+      )
+    );
+    assert_eq!(
+      described[2],
+      (
+        Severity::Error,
+        "tenant",
+        "The rule returned () instead of true or false"
+      )
+    );
+    assert!(described[3].2.starts_with("Invalid rule: "));
+    assert_eq!(described[4].1, "loop");
+    assert_eq!(findings.len(), 5);
+  }
+}
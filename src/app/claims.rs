@@ -0,0 +1,480 @@
+use std::fmt;
+
+use serde_json::Value;
+
+use super::jwt_decoder::Payload;
+
+/// Well-known identity providers whose tokens carry provider-specific claims
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+  Auth0,
+  Okta,
+  AzureAd,
+  Google,
+  Keycloak,
+  Cognito,
+}
+
+impl fmt::Display for Provider {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let name = match self {
+      Provider::Auth0 => "Auth0",
+      Provider::Okta => "Okta",
+      Provider::AzureAd => "Azure AD",
+      Provider::Google => "Google",
+      Provider::Keycloak => "Keycloak",
+      Provider::Cognito => "Amazon Cognito",
+    };
+    write!(f, "{name}")
+  }
+}
+
+impl Provider {
+  /// detect the provider from the `iss` claim. Custom domains can't be detected
+  pub fn from_issuer(iss: &str) -> Option<Self> {
+    let url = iss
+      .strip_prefix("https://")
+      .or_else(|| iss.strip_prefix("http://"))
+      .unwrap_or(iss);
+    let (host, path) = url.split_once('/').unwrap_or((url, ""));
+    let host = host.to_ascii_lowercase();
+
+    match host.as_str() {
+      _ if host.ends_with(".auth0.com") => Some(Provider::Auth0),
+      _ if [".okta.com", ".oktapreview.com", ".okta-emea.com"]
+        .iter()
+        .any(|domain| host.ends_with(domain)) =>
+      {
+        Some(Provider::Okta)
+      }
+      "login.microsoftonline.com" | "login.microsoftonline.us" | "sts.windows.net" => {
+        Some(Provider::AzureAd)
+      }
+      _ if host.ends_with(".b2clogin.com") => Some(Provider::AzureAd),
+      "accounts.google.com" => Some(Provider::Google),
+      _ if host.starts_with("cognito-idp.") && host.ends_with(".amazonaws.com") => {
+        Some(Provider::Cognito)
+      }
+      _ if path.starts_with("realms/") || path.contains("/realms/") => Some(Provider::Keycloak),
+      _ => None,
+    }
+  }
+
+  /// detect the provider from the `iss` claim of the payload
+  pub fn from_claims(claims: &Payload) -> Option<Self> {
+    claims
+      .0
+      .get("iss")
+      .and_then(Value::as_str)
+      .and_then(Self::from_issuer)
+  }
+
+  /// short description of a claim specific to the provider
+  pub fn describe_claim(&self, claim: &str) -> Option<&'static str> {
+    let claims = match self {
+      Provider::Auth0 => AUTH0_CLAIMS,
+      Provider::Okta => OKTA_CLAIMS,
+      Provider::AzureAd => AZURE_AD_CLAIMS,
+      Provider::Google => GOOGLE_CLAIMS,
+      Provider::Keycloak => KEYCLOAK_CLAIMS,
+      Provider::Cognito => COGNITO_CLAIMS,
+    };
+    claims
+      .iter()
+      .find(|(name, _)| *name == claim)
+      .map(|(_, description)| *description)
+  }
+}
+
+/// description of a claim, specific to the provider if known, otherwise from the IANA registry
+pub fn describe_claim(provider: Option<Provider>, claim: &str) -> Option<&'static str> {
+  provider
+    .and_then(|provider| provider.describe_claim(claim))
+    .or_else(|| {
+      REGISTERED_CLAIMS
+        .iter()
+        .find(|(name, _)| *name == claim)
+        .map(|(_, description)| *description)
+    })
+}
+
+/// IANA "JSON Web Token Claims" registry, https://www.iana.org/assignments/jwt/jwt.xhtml
+const REGISTERED_CLAIMS: &[(&str, &str)] = &[
+  ("iss", "Issuer: who created and signed the token"),
+  (
+    "sub",
+    "Subject: whom the token refers to, usually the user ID",
+  ),
+  ("aud", "Audience: the recipients the token is intended for"),
+  (
+    "exp",
+    "Expiration Time: the token must not be accepted after it",
+  ),
+  (
+    "nbf",
+    "Not Before: the token must not be accepted before it",
+  ),
+  ("iat", "Issued At: when the token was issued"),
+  ("jti", "JWT ID: unique identifier of the token"),
+  ("name", "Full name of the user"),
+  ("given_name", "Given name(s) or first name(s) of the user"),
+  ("family_name", "Surname(s) or last name(s) of the user"),
+  ("middle_name", "Middle name(s) of the user"),
+  ("nickname", "Casual name of the user"),
+  (
+    "preferred_username",
+    "Shorthand name the user wishes to be referred to by",
+  ),
+  ("profile", "URL of the profile page of the user"),
+  ("picture", "URL of the profile picture of the user"),
+  ("website", "URL of the web page or blog of the user"),
+  ("email", "Preferred e-mail address of the user"),
+  (
+    "email_verified",
+    "True if the e-mail address of the user has been verified",
+  ),
+  ("gender", "Gender of the user"),
+  ("birthdate", "Birthday of the user"),
+  ("zoneinfo", "Time zone of the user"),
+  ("locale", "Locale of the user"),
+  ("phone_number", "Preferred telephone number of the user"),
+  (
+    "phone_number_verified",
+    "True if the phone number of the user has been verified",
+  ),
+  ("address", "Preferred postal address of the user"),
+  (
+    "updated_at",
+    "Time the information of the user was last updated",
+  ),
+  (
+    "azp",
+    "Authorized party: the client the ID token was issued to",
+  ),
+  (
+    "nonce",
+    "Value binding the client session to the ID token, against replay attacks",
+  ),
+  ("auth_time", "Time when the user authenticated"),
+  ("at_hash", "Access token hash value"),
+  ("c_hash", "Authorization code hash value"),
+  ("acr", "Authentication Context Class Reference"),
+  (
+    "amr",
+    "Authentication Methods References, e.g. pwd, mfa, otp",
+  ),
+  (
+    "sub_jwk",
+    "Public key used to check the signature of a self-issued ID token",
+  ),
+  (
+    "cnf",
+    "Confirmation: the key the presenter must prove possession of",
+  ),
+  ("sip_from_tag", "SIP From tag header field parameter value"),
+  ("sip_date", "SIP Date header field value"),
+  ("sip_callid", "SIP Call-Id header field value"),
+  (
+    "sip_cseq_num",
+    "SIP CSeq numeric header field parameter value",
+  ),
+  (
+    "sip_via_branch",
+    "SIP Via branch header field parameter value",
+  ),
+  ("orig", "Originating identity string"),
+  ("dest", "Destination identity string"),
+  ("mky", "Media key fingerprint string"),
+  ("events", "Security events"),
+  ("toe", "Time of event"),
+  ("txn", "Transaction identifier"),
+  ("rph", "Resource Priority Header authorization"),
+  ("sid", "Session ID"),
+  ("vot", "Vector of Trust value"),
+  ("vtm", "Vector of Trust trustmark URL"),
+  (
+    "attest",
+    "Attestation level as defined in the SHAKEN framework",
+  ),
+  (
+    "origid",
+    "Originating identifier as defined in the SHAKEN framework",
+  ),
+  (
+    "act",
+    "Actor: the party acting on behalf of the subject (token exchange)",
+  ),
+  ("scope", "Scopes granted to the client, separated by spaces"),
+  ("client_id", "Client identifier"),
+  (
+    "may_act",
+    "Authorized actor: the party allowed to act on behalf of the subject",
+  ),
+  ("jcard", "jCard data"),
+  (
+    "at_use_nbr",
+    "Number of API requests the access token can be used for",
+  ),
+  ("div", "Diverted target of a call"),
+  ("opt", "Original PASSporT in full form"),
+  ("vc", "Verifiable Credential"),
+  ("vp", "Verifiable Presentation"),
+  ("sph", "SIP Priority header field"),
+  (
+    "ace_profile",
+    "ACE profile a token is supposed to be used with",
+  ),
+  ("cnonce", "Client nonce"),
+  (
+    "exi",
+    "Expires in: lifetime of the token in seconds from its first use",
+  ),
+  ("roles", "Roles of the user"),
+  ("groups", "Groups of the user"),
+  ("entitlements", "Entitlements of the user"),
+  ("token_introspection", "Token introspection response"),
+  (
+    "authorization_details",
+    "Rich authorization request details",
+  ),
+  (
+    "verified_claims",
+    "Verified claims about the user and the evidence",
+  ),
+  ("place_of_birth", "Place of birth of the user"),
+  ("nationalities", "Nationalities of the user"),
+  ("birth_family_name", "Family name(s) of the user at birth"),
+  ("birth_given_name", "Given name(s) of the user at birth"),
+  ("birth_middle_name", "Middle name(s) of the user at birth"),
+  ("salutation", "Salutation of the user"),
+  ("title", "Title of the user"),
+  ("msisdn", "Mobile phone number of the user"),
+  (
+    "also_known_as",
+    "Stage name, religious name or other pseudonym of the user",
+  ),
+  (
+    "htm",
+    "HTTP method of the request the DPoP proof is bound to",
+  ),
+  ("htu", "HTTP URI of the request the DPoP proof is bound to"),
+  ("ath", "Hash of the access token the DPoP proof is bound to"),
+  ("atc", "Authority token challenge"),
+  ("sub_id", "Subject identifier of a security event"),
+  ("rcd", "Rich call data information"),
+  ("rcdi", "Rich call data integrity information"),
+  ("crn", "Call reason"),
+  ("msgi", "Message integrity information"),
+  (
+    "_claim_names",
+    "Names of the aggregated and distributed claims",
+  ),
+  (
+    "_claim_sources",
+    "Sources of the aggregated and distributed claims",
+  ),
+  ("cdniv", "CDNI claim set version"),
+  ("cdnicrit", "CDNI critical claims set"),
+  ("cdniip", "CDNI IP address"),
+  ("cdniuc", "CDNI URI container"),
+  (
+    "cdniets",
+    "CDNI expiration time setting for signed token renewal",
+  ),
+  (
+    "cdnistt",
+    "CDNI signed token transport method for signed token renewal",
+  ),
+  ("cdnistd", "CDNI signed token depth"),
+  ("sig_val_claims", "Signature validation token"),
+  ("ueid", "Universal Entity ID of the device (EAT)"),
+  ("sueids", "Semi-permanent UEIDs of the device (EAT)"),
+  ("oemid", "Hardware OEM ID of the device (EAT)"),
+  ("hwmodel", "Hardware model of the device (EAT)"),
+  ("hwversion", "Hardware version of the device (EAT)"),
+  ("dbgstat", "Debug status of the device (EAT)"),
+  ("location", "Location of the device (EAT)"),
+  ("eat_profile", "EAT profile the token follows"),
+  ("submods", "Submodules of the device (EAT)"),
+  ("eat_nonce", "Nonce of the attestation (EAT)"),
+  ("intuse", "Intended use of the attestation (EAT)"),
+  ("geohash", "Geohash string of the location"),
+  (
+    "rdap_allowed_purposes",
+    "Purposes the RDAP data may be used for",
+  ),
+  (
+    "rdap_dnt_allowed",
+    "True if the RDAP data may be used with Do Not Track",
+  ),
+];
+
+const AUTH0_CLAIMS: &[(&str, &str)] = &[
+  (
+    "azp",
+    "Client ID of the application the token was issued to",
+  ),
+  ("scope", "Scopes granted to the application"),
+  ("permissions", "Permissions of the user for the API (RBAC)"),
+  ("gty", "Grant type used to get the token"),
+  (
+    "org_id",
+    "ID of the Auth0 organization the user logged in to",
+  ),
+  (
+    "org_name",
+    "Name of the Auth0 organization the user logged in to",
+  ),
+];
+
+const OKTA_CLAIMS: &[(&str, &str)] = &[
+  (
+    "cid",
+    "Client ID of the application the token was issued to",
+  ),
+  ("uid", "Okta ID of the user"),
+  ("scp", "Scopes granted to the application"),
+  ("ver", "Version of the token format"),
+  (
+    "groups",
+    "Groups of the user, as configured on the authorization server",
+  ),
+  (
+    "idp",
+    "ID of the identity provider that authenticated the user",
+  ),
+];
+
+const AZURE_AD_CLAIMS: &[(&str, &str)] = &[
+  ("tid", "Tenant ID of the directory the user signed in to"),
+  (
+    "oid",
+    "Object ID of the user or service principal in the tenant",
+  ),
+  ("azp", "Application ID of the client (v2.0 tokens)"),
+  ("appid", "Application ID of the client (v1.0 tokens)"),
+  (
+    "azpacr",
+    "Client authentication: 0 public, 1 secret, 2 certificate",
+  ),
+  (
+    "appidacr",
+    "Client authentication: 0 public, 1 secret, 2 certificate",
+  ),
+  ("scp", "Delegated scopes granted to the application"),
+  ("roles", "App roles assigned to the user or application"),
+  ("wids", "Directory role templates of the user"),
+  ("groups", "Object IDs of the groups of the user"),
+  ("upn", "User principal name"),
+  ("idp", "Identity provider that authenticated the user"),
+  ("ver", "Version of the token format: 1.0 or 2.0"),
+  ("aio", "Internal data used by Azure AD, ignore it"),
+  ("uti", "Token identifier used to revalidate tokens"),
+  ("rh", "Internal data used by Azure AD, ignore it"),
+];
+
+const GOOGLE_CLAIMS: &[(&str, &str)] = &[
+  ("azp", "Client ID of the authorized presenter"),
+  ("hd", "Hosted Google Workspace domain of the user"),
+  (
+    "at_hash",
+    "Hash of the access token issued with this ID token",
+  ),
+];
+
+const KEYCLOAK_CLAIMS: &[(&str, &str)] = &[
+  ("azp", "Client ID the token was issued to"),
+  ("typ", "Token type: Bearer, ID or Refresh"),
+  ("realm_access", "Realm roles of the user"),
+  ("resource_access", "Client roles of the user, per client ID"),
+  ("allowed-origins", "Web origins allowed for CORS requests"),
+  ("session_state", "ID of the Keycloak session"),
+  ("sid", "ID of the Keycloak session"),
+];
+
+const COGNITO_CLAIMS: &[(&str, &str)] = &[
+  ("token_use", "Token type: access or id"),
+  ("client_id", "ID of the app client the token was issued to"),
+  ("username", "User name of the user in the user pool"),
+  ("cognito:username", "User name of the user in the user pool"),
+  ("cognito:groups", "User pool groups of the user"),
+  (
+    "origin_jti",
+    "ID of the refresh token the token was issued from",
+  ),
+  ("event_id", "ID of the authentication event"),
+];
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_provider_from_issuer() {
+    let cases = [
+      ("https://dev-1234.us.auth0.com/", Some(Provider::Auth0)),
+      ("https://acme.okta.com/oauth2/default", Some(Provider::Okta)),
+      (
+        "https://login.microsoftonline.com/72f988bf-86f1-41af-91ab-2d7cd011db47/v2.0",
+        Some(Provider::AzureAd),
+      ),
+      ("https://sts.windows.net/72f988bf/", Some(Provider::AzureAd)),
+      ("accounts.google.com", Some(Provider::Google)),
+      ("https://accounts.google.com", Some(Provider::Google)),
+      (
+        "https://sso.example.com/realms/staging",
+        Some(Provider::Keycloak),
+      ),
+      (
+        "https://sso.example.com/auth/realms/staging",
+        Some(Provider::Keycloak),
+      ),
+      (
+        "https://cognito-idp.eu-west-1.amazonaws.com/eu-west-1_abc",
+        Some(Provider::Cognito),
+      ),
+      ("https://auth0.com.example.com/", None),
+      ("https://example.com/", None),
+      ("", None),
+    ];
+    for (iss, expected) in cases {
+      assert_eq!(Provider::from_issuer(iss), expected, "{iss}");
+    }
+  }
+
+  #[test]
+  fn test_describe_claim() {
+    let claims: Payload =
+      serde_json::from_str(r#"{"iss": "https://login.microsoftonline.com/tid/v2.0"}"#).unwrap();
+    let provider = Provider::from_claims(&claims).unwrap();
+
+    assert_eq!(provider.to_string(), "Azure AD");
+    assert_eq!(
+      provider.describe_claim("tid"),
+      Some("Tenant ID of the directory the user signed in to")
+    );
+    assert_eq!(provider.describe_claim("realm_access"), None);
+    assert_eq!(
+      Provider::Keycloak.describe_claim("realm_access"),
+      Some("Realm roles of the user")
+    );
+  }
+
+  #[test]
+  fn test_describe_registered_claim() {
+    assert_eq!(
+      describe_claim(None, "sub"),
+      Some("Subject: whom the token refers to, usually the user ID")
+    );
+    // provider-specific descriptions come first
+    assert_eq!(
+      describe_claim(Some(Provider::AzureAd), "azp"),
+      Some("Application ID of the client (v2.0 tokens)")
+    );
+    assert_eq!(
+      describe_claim(Some(Provider::AzureAd), "iss"),
+      describe_claim(None, "iss")
+    );
+    assert_eq!(describe_claim(None, "tid"), None);
+  }
+}
@@ -0,0 +1,321 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use serde_derive::Deserialize;
+
+use super::{
+  claim_rules::ClaimRule,
+  history::HistorySettings,
+  oauth::OAuthClient,
+  templates::Template,
+  utils::{JWTError, JWTResult},
+  validation::Preset,
+  validator_hooks::ValidatorHook,
+};
+
+const CONFIG_DIR: &str = "jwt-ui";
+const CONFIG_FILE: &str = "config.toml";
+const KEY_STORE_DIR: &str = "keys";
+const SESSION_FILE: &str = "session.json";
+const HISTORY_FILE: &str = "history.json";
+const BOOKMARKS_FILE: &str = "bookmarks.json";
+const WORKSPACES_DIR: &str = "workspaces";
+
+/// User configuration read from `<config dir>/jwt-ui/config.toml` or the `--config` flag
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+pub struct Config {
+  /// named profiles selectable with `--profile`
+  #[serde(default)]
+  pub profiles: BTreeMap<String, Profile>,
+  /// named header/payload templates listed in the encoder template picker
+  #[serde(default)]
+  pub templates: BTreeMap<String, Template>,
+  /// recording of the decoded tokens in the token history
+  #[serde(default)]
+  pub history: HistorySettings,
+  /// external validators run for every profile, before the validators of the profile
+  #[serde(default)]
+  pub validators: Vec<ValidatorHook>,
+  /// claim rules checked for every profile, before the rules of the profile
+  #[serde(default)]
+  pub rules: Vec<ClaimRule>,
+  /// named OAuth clients requesting tokens with `--grant`
+  #[serde(default)]
+  pub oauth: BTreeMap<String, OAuthClient>,
+  /// milliseconds between ticks, overridden by `--tick-rate`
+  pub tick_rate: Option<u64>,
+}
+
+/// Policy settings applied to decoded tokens
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub struct Profile {
+  /// maximum acceptable token lifetime (`exp` - `iat`) in seconds
+  pub max_lifetime: Option<u64>,
+  /// maximum tolerated clock skew in seconds for `iat`/`nbf` in the future
+  pub max_clock_skew: Option<u64>,
+  /// built-in checks of a token profile, like `rfc9068` for OAuth access tokens
+  pub preset: Option<Preset>,
+  /// external commands checking the decoded token
+  #[serde(default)]
+  pub validators: Vec<ValidatorHook>,
+  /// scripts checking the claims of the decoded token
+  #[serde(default)]
+  pub rules: Vec<ClaimRule>,
+}
+
+impl Config {
+  /// load the config from the given path or from the default location.
+  /// A missing default config file is not an error.
+  pub fn load(path: Option<PathBuf>) -> JWTResult<Self> {
+    let (path, explicit) = match path {
+      Some(p) => (p, true),
+      None => match default_config_path() {
+        Some(p) => (p, false),
+        None => return Ok(Config::default()),
+      },
+    };
+
+    match fs::read_to_string(&path) {
+      Ok(content) => Self::parse(&content)
+        .map_err(|e| JWTError::Internal(format!("Invalid config file {}: {e}", path.display()))),
+      Err(_) if !explicit => Ok(Config::default()),
+      Err(e) => Err(JWTError::Internal(format!(
+        "Unable to read config file {}: {e}",
+        path.display()
+      ))),
+    }
+  }
+
+  pub fn parse(content: &str) -> JWTResult<Self> {
+    toml::from_str(content).map_err(|e| JWTError::Internal(e.to_string()))
+  }
+
+  /// returns the named profile, or the default (empty) profile when no name is given, with the
+  /// validators and rules of the config
+  pub fn profile(&self, name: Option<&str>) -> JWTResult<Profile> {
+    let mut profile = match name {
+      Some(name) => self
+        .profiles
+        .get(name)
+        .cloned()
+        .ok_or_else(|| JWTError::Internal(format!("Profile {name:?} not found in config")))?,
+      None => Profile::default(),
+    };
+    profile
+      .validators
+      .splice(0..0, self.validators.iter().cloned());
+    profile.rules.splice(0..0, self.rules.iter().cloned());
+    Ok(profile)
+  }
+}
+
+pub fn default_config_path() -> Option<PathBuf> {
+  dirs::config_dir().map(|dir| dir.join(CONFIG_DIR).join(CONFIG_FILE))
+}
+
+/// directory of the keys listed in the Keys tab
+pub fn default_key_store_dir() -> Option<PathBuf> {
+  dirs::config_dir().map(|dir| dir.join(CONFIG_DIR).join(KEY_STORE_DIR))
+}
+
+/// file of the session saved with `--session`
+pub fn default_session_path() -> Option<PathBuf> {
+  dirs::data_local_dir().map(|dir| dir.join(CONFIG_DIR).join(SESSION_FILE))
+}
+
+/// file of the token history, when enabled in the config
+pub fn default_history_path() -> Option<PathBuf> {
+  dirs::data_local_dir().map(|dir| dir.join(CONFIG_DIR).join(HISTORY_FILE))
+}
+
+/// file of the secrets, key files and JWKS URLs bookmarked in the secret blocks
+pub fn default_bookmarks_path() -> Option<PathBuf> {
+  dirs::data_local_dir().map(|dir| dir.join(CONFIG_DIR).join(BOOKMARKS_FILE))
+}
+
+/// directory of the workspaces saved by name
+pub fn default_workspace_dir() -> Option<PathBuf> {
+  dirs::config_dir().map(|dir| dir.join(CONFIG_DIR).join(WORKSPACES_DIR))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app::{oauth::ClientAuth, validation::Severity};
+
+  #[test]
+  fn test_parse_config_with_profiles() {
+    let config = Config::parse(
+      r#"
+      [profiles.staging]
+      max_lifetime = 3600
+      max_clock_skew = 60
+      preset = "rfc9068"
+
+      [profiles.empty]
+      "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+      config.profile(Some("staging")).unwrap(),
+      Profile {
+        max_lifetime: Some(3600),
+        max_clock_skew: Some(60),
+        preset: Some(Preset::Rfc9068),
+        ..Profile::default()
+      }
+    );
+    assert_eq!(config.profile(Some("empty")).unwrap(), Profile::default());
+    assert_eq!(config.profile(None).unwrap(), Profile::default());
+    assert_eq!(
+      config.profile(Some("prod")).unwrap_err().to_string(),
+      "Profile \"prod\" not found in config"
+    );
+  }
+
+  #[test]
+  fn test_parse_config_with_templates() {
+    let config = Config::parse(
+      r#"
+      [templates.my-api]
+      header = { alg = "RS256", kid = "key-1" }
+      payload = { iss = "https://idp", aud = "my-api", exp = "+15m", admin = true }
+      "#,
+    )
+    .unwrap();
+
+    let template = &config.templates["my-api"];
+    assert_eq!(
+      template.header,
+      Some(serde_json::json!({"alg": "RS256", "kid": "key-1"}))
+    );
+    assert_eq!(
+      template.payload,
+      serde_json::json!({"iss": "https://idp", "aud": "my-api", "exp": "+15m", "admin": true})
+    );
+    assert!(Config::parse("[templates.empty]").is_err());
+  }
+
+  #[test]
+  fn test_parse_config_with_history() {
+    let config = Config::parse(
+      r#"
+      [history]
+      enabled = true
+      redact_claims = ["email"]
+      "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+      config.history,
+      HistorySettings {
+        enabled: true,
+        redact_claims: vec!["email".into()],
+        ..HistorySettings::default()
+      }
+    );
+    assert!(!Config::parse("").unwrap().history.enabled);
+  }
+
+  #[test]
+  fn test_parse_config_with_tick_rate() {
+    let config = Config::parse("tick_rate = 500").unwrap();
+    assert_eq!(config.tick_rate, Some(500));
+    assert_eq!(Config::parse("").unwrap().tick_rate, None);
+  }
+
+  #[test]
+  fn test_parse_config_with_validators() {
+    let config = Config::parse(
+      r#"
+      [[validators]]
+      name = "policy"
+      command = "policy-check --tenant acme"
+
+      [[profiles.staging.validators]]
+      name = "staging-audience"
+      command = "check-aud staging"
+      "#,
+    )
+    .unwrap();
+
+    let names = |profile: Profile| {
+      profile
+        .validators
+        .into_iter()
+        .map(|hook| hook.name)
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(
+      names(config.profile(Some("staging")).unwrap()),
+      ["policy", "staging-audience"]
+    );
+    assert_eq!(names(config.profile(None).unwrap()), ["policy"]);
+    assert!(Config::parse("[[validators]]\nname = \"no-command\"").is_err());
+  }
+
+  #[test]
+  fn test_parse_config_with_rules() {
+    let config = Config::parse(
+      r#"
+      [[rules]]
+      name = "audience"
+      script = 'claims.aud == "api"'
+
+      [[profiles.admin.rules]]
+      name = "admin-scope"
+      script = 'claims.scope.contains("admin")'
+      severity = "warning"
+      message = "Not an admin token"
+      "#,
+    )
+    .unwrap();
+
+    let rules = config.profile(Some("admin")).unwrap().rules;
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0].severity, Severity::Error);
+    assert_eq!(rules[1].severity, Severity::Warning);
+    assert_eq!(rules[1].message.as_deref(), Some("Not an admin token"));
+    assert_eq!(config.profile(None).unwrap().rules.len(), 1);
+  }
+
+  #[test]
+  fn test_parse_config_with_oauth_clients() {
+    let config = Config::parse(
+      r#"
+      [oauth.staging]
+      token_url = "https://idp.example.com/oauth/token"
+      client_id = "my-app"
+      client_secret = "keyring:staging-client"
+      client_auth = "post"
+      audience = "my-api"
+      introspection_url = "https://idp.example.com/oauth/introspect"
+      "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+      config.oauth["staging"],
+      OAuthClient {
+        token_url: "https://idp.example.com/oauth/token".into(),
+        client_id: "my-app".into(),
+        client_secret: Some("keyring:staging-client".into()),
+        client_auth: ClientAuth::Post,
+        audience: Some("my-api".into()),
+        introspection_url: Some("https://idp.example.com/oauth/introspect".into()),
+        ..OAuthClient::default()
+      }
+    );
+  }
+
+  #[test]
+  fn test_load_config_missing_explicit_file() {
+    let result = Config::load(Some(PathBuf::from("nonexistent.toml")));
+
+    assert!(result
+      .unwrap_err()
+      .to_string()
+      .starts_with("Unable to read config file nonexistent.toml"));
+  }
+}
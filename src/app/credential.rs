@@ -0,0 +1,399 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde_json::Value;
+
+use super::{
+  jwt_decoder::Payload,
+  utils::{decode_segment, JWTError, JWTResult},
+  validation::{claim_timestamp, Finding, Severity},
+};
+
+/// base contexts of the VC data model 1.1 and 2.0, the first `@context` of credentials
+const BASE_CONTEXTS: [&str; 2] = [
+  "https://www.w3.org/2018/credentials/v1",
+  "https://www.w3.org/ns/credentials/v2",
+];
+
+/// check the structure of the `vc` or `vp` claim and that the registered claims match the
+/// members they replace (VC data model 1.1 section 6.3.1). Presentations are checked with the
+/// credentials they embed as JWTs
+pub fn check_credential(claims: &Payload) -> Vec<Finding> {
+  let mut findings = vec![];
+  if let Some(vc) = claims.0.get("vc") {
+    check_vc(claims, vc, "", &mut findings);
+  }
+  if let Some(vp) = claims.0.get("vp") {
+    check_vp(claims, vp, &mut findings);
+  }
+  findings
+}
+
+fn check_vc(claims: &Payload, vc: &Value, prefix: &str, findings: &mut Vec<Finding>) {
+  let error = |message: &str| Finding::new(Severity::Error, "vc", format!("{prefix}{message}"));
+  if !vc.is_object() {
+    return findings.push(error("`vc` is not an object"));
+  }
+  if let Some(message) = check_context_and_type(vc, "VerifiableCredential") {
+    findings.push(error(&message));
+  }
+  if vc.get("credentialSubject").is_none() {
+    findings.push(error("The credential has no `credentialSubject`"));
+  }
+  let issuer = match &vc["issuer"] {
+    Value::Object(issuer) => issuer.get("id"),
+    issuer => Some(issuer).filter(|issuer| !issuer.is_null()),
+  };
+  match claims.0.get("iss") {
+    None => findings.push(error("VC-JWT has no `iss` claim for the issuer")),
+    Some(iss) if issuer.is_some_and(|issuer| issuer != iss) => {
+      findings.push(error("`iss` is not the `issuer` of the credential"))
+    }
+    _ => {}
+  }
+  if !claims.0.contains_key("nbf") {
+    findings.push(error("VC-JWT has no `nbf` claim for the issuance date"));
+  }
+  for (claim, member) in [
+    ("nbf", "issuanceDate"),
+    ("nbf", "validFrom"),
+    ("exp", "expirationDate"),
+    ("exp", "validUntil"),
+  ] {
+    if let (Some(ts), Some(date)) = (claim_timestamp(claims, claim), vc[member].as_str()) {
+      if DateTime::parse_from_rfc3339(date).map_or(true, |date| date.timestamp() != ts) {
+        findings.push(error(&format!(
+          "`{claim}` is not the `{member}` of the credential"
+        )));
+      }
+    }
+  }
+  if let (Some(jti), Some(id)) = (claims.0.get("jti"), vc.get("id")) {
+    if jti != id {
+      findings.push(error("`jti` is not the `id` of the credential"));
+    }
+  }
+  if let Some(id) = vc["credentialSubject"].get("id") {
+    match claims.0.get("sub") {
+      Some(sub) if sub != id => {
+        findings.push(error("`sub` is not the `id` of the `credentialSubject`"))
+      }
+      Some(_) => {}
+      None => findings.push(Finding::new(
+        Severity::Warning,
+        "vc",
+        format!("{prefix}VC-JWT has no `sub` claim for the `id` of the `credentialSubject`"),
+      )),
+    }
+  }
+}
+
+fn check_vp(claims: &Payload, vp: &Value, findings: &mut Vec<Finding>) {
+  let error = |message: String| Finding::new(Severity::Error, "vc", message);
+  if !vp.is_object() {
+    return findings.push(error("`vp` is not an object".into()));
+  }
+  if let Some(message) = check_context_and_type(vp, "VerifiablePresentation") {
+    findings.push(error(message));
+  }
+  if let (Some(iss), Some(holder)) = (claims.0.get("iss"), vp.get("holder")) {
+    if iss != holder {
+      findings.push(error(
+        "`iss` is not the `holder` of the presentation".into(),
+      ));
+    }
+  }
+  for claim in ["aud", "nonce"] {
+    if !claims.0.contains_key(claim) {
+      findings.push(Finding::new(
+        Severity::Warning,
+        "vc",
+        format!("The presentation has no `{claim}` claim, it can be replayed to other verifiers"),
+      ));
+    }
+  }
+  for (i, credential) in embedded_credentials(vp).iter().enumerate() {
+    let prefix = format!("Credential {}: ", i + 1);
+    match credential {
+      Ok(Embedded::Jwt(claims)) => match claims.0.get("vc") {
+        Some(vc) => check_vc(claims, vc, &prefix, findings),
+        None => findings.push(error(format!("{prefix}the JWT has no `vc` claim"))),
+      },
+      Ok(Embedded::Object(vc)) => {
+        if let Some(message) = check_context_and_type(vc, "VerifiableCredential") {
+          findings.push(error(format!("{prefix}{message}")));
+        }
+      }
+      Err(e) => findings.push(error(format!("{prefix}{e}"))),
+    }
+  }
+}
+
+/// the first `@context` must be a base context and `type` must have the type of the document
+fn check_context_and_type(document: &Value, kind: &str) -> Option<String> {
+  let context = match &document["@context"] {
+    Value::Array(contexts) => contexts.first(),
+    context => Some(context),
+  };
+  if !context
+    .and_then(Value::as_str)
+    .is_some_and(|context| BASE_CONTEXTS.contains(&context))
+  {
+    return Some(format!(
+      "The first `@context` is not {}",
+      BASE_CONTEXTS.join(" or ")
+    ));
+  }
+  let has_type = match &document["type"] {
+    Value::Array(types) => types.iter().any(|t| t == kind),
+    t => t == kind,
+  };
+  (!has_type).then(|| format!("`type` doesn't have {kind}"))
+}
+
+/// credential of a presentation, as a VC-JWT or a JSON object
+enum Embedded {
+  Jwt(Payload),
+  Object(Value),
+}
+
+fn embedded_credentials(vp: &Value) -> Vec<JWTResult<Embedded>> {
+  let credentials = match &vp["verifiableCredential"] {
+    Value::Array(credentials) => credentials.iter().collect(),
+    Value::Null => vec![],
+    credential => vec![credential],
+  };
+  credentials
+    .into_iter()
+    .map(|credential| match credential {
+      Value::String(jwt) => jwt
+        .split('.')
+        .nth(1)
+        .ok_or_else(|| JWTError::Internal("not a JWT".into()))
+        .and_then(decode_segment)
+        .map(Embedded::Jwt),
+      Value::Object(_) => Ok(Embedded::Object(credential.clone())),
+      _ => Err(JWTError::Internal("not a JWT or an object".into())),
+    })
+    .collect()
+}
+
+/// description of the credential or presentation of a VC-JWT for the credential popup: a
+/// summary, the `credentialSubject` and the pretty printed credential
+pub fn describe_credential(claims: &Payload) -> JWTResult<String> {
+  let mut sections = vec![];
+  if let Some(vc) = claims.0.get("vc") {
+    sections.push(describe_vc("Verifiable credential", claims, vc));
+  }
+  if let Some(vp) = claims.0.get("vp") {
+    sections.push(format!(
+      "Verifiable presentation\n  Type:     {}\n  Holder:   {}\n  Audience: {}",
+      types(vp),
+      text(vp.get("holder").or(claims.0.get("iss"))),
+      text(claims.0.get("aud")),
+    ));
+    for (i, credential) in embedded_credentials(vp).into_iter().enumerate() {
+      let title = format!("Credential {}", i + 1);
+      sections.push(match credential {
+        Ok(Embedded::Jwt(claims)) => match claims.0.get("vc") {
+          Some(vc) => describe_vc(&format!("{title} (JWT)"), &claims, vc),
+          None => format!(
+            "{title} (JWT)\n{}",
+            pretty(&serde_json::to_value(&claims).unwrap_or_default())
+          ),
+        },
+        Ok(Embedded::Object(vc)) => describe_vc(&title, &Payload(BTreeMap::new()), &vc),
+        Err(e) => format!("{title}\n  {e}"),
+      });
+    }
+  }
+  if sections.is_empty() {
+    return Err(JWTError::Internal(
+      "The token has no `vc` or `vp` claim".into(),
+    ));
+  }
+  Ok(sections.join("\n\n"))
+}
+
+fn describe_vc(title: &str, claims: &Payload, vc: &Value) -> String {
+  let issuer = match &vc["issuer"] {
+    Value::Object(issuer) => issuer.get("id"),
+    Value::Null => claims.0.get("iss"),
+    issuer => Some(issuer),
+  };
+  let date = |claim: &str, members: [&str; 2]| {
+    members
+      .iter()
+      .find_map(|member| vc[*member].as_str().map(String::from))
+      .or_else(|| claim_timestamp(claims, claim).map(format_time))
+      .unwrap_or_else(|| "-".into())
+  };
+  format!(
+    "{title}\n  Type:    {}\n  Issuer:  {}\n  Subject: {}\n  Issued:  {}\n  Expires: {}\n\nCredential subject\n{}\n\nCredential\n{}",
+    types(vc),
+    text(issuer),
+    text(vc["credentialSubject"].get("id").or(claims.0.get("sub"))),
+    date("nbf", ["issuanceDate", "validFrom"]),
+    date("exp", ["expirationDate", "validUntil"]),
+    pretty(&vc["credentialSubject"]),
+    pretty(vc),
+  )
+}
+
+fn types(document: &Value) -> String {
+  match &document["type"] {
+    Value::Array(types) => types
+      .iter()
+      .map(|t| text(Some(t)))
+      .collect::<Vec<_>>()
+      .join(", "),
+    t => text(Some(t)),
+  }
+}
+
+/// strings without quotes, other values as JSON and `-` when missing
+fn text(value: Option<&Value>) -> String {
+  match value {
+    Some(Value::String(s)) => s.clone(),
+    None | Some(Value::Null) => "-".into(),
+    Some(value) => value.to_string(),
+  }
+}
+
+fn pretty(value: &Value) -> String {
+  serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+fn format_time(ts: i64) -> String {
+  Utc
+    .timestamp_opt(ts, 0)
+    .single()
+    .map_or_else(|| ts.to_string(), |t| t.to_rfc3339())
+}
+
+#[cfg(test)]
+mod tests {
+  use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+  use serde_json::json;
+
+  use super::*;
+
+  fn payload(value: Value) -> Payload {
+    serde_json::from_value(value).unwrap()
+  }
+
+  fn messages(findings: Vec<Finding>) -> Vec<String> {
+    findings.into_iter().map(|f| f.message).collect()
+  }
+
+  fn credential() -> Value {
+    json!({
+      "iss": "did:example:issuer",
+      "sub": "did:example:alice",
+      "nbf": 1_262_373_804,
+      "jti": "urn:uuid:3978344f",
+      "vc": {
+        "@context": ["https://www.w3.org/2018/credentials/v1"],
+        "id": "urn:uuid:3978344f",
+        "type": ["VerifiableCredential", "UniversityDegreeCredential"],
+        "issuer": {"id": "did:example:issuer", "name": "Example University"},
+        "issuanceDate": "2010-01-01T19:23:24Z",
+        "credentialSubject": {"id": "did:example:alice", "degree": "BSc"}
+      }
+    })
+  }
+
+  #[test]
+  fn test_check_credential() {
+    assert!(check_credential(&payload(credential())).is_empty());
+    assert!(check_credential(&payload(json!({"sub": "alice"}))).is_empty());
+
+    let mut claims = credential();
+    claims["iss"] = "did:example:other".into();
+    claims["nbf"] = 1_262_373_805.into();
+    claims["vc"]["@context"] = json!(["https://schema.org"]);
+    claims["vc"]["credentialSubject"]["id"] = "did:example:bob".into();
+    assert_eq!(
+      messages(check_credential(&payload(claims))),
+      vec![
+        "The first `@context` is not https://www.w3.org/2018/credentials/v1 or https://www.w3.org/ns/credentials/v2",
+        "`iss` is not the `issuer` of the credential",
+        "`nbf` is not the `issuanceDate` of the credential",
+        "`sub` is not the `id` of the `credentialSubject`",
+      ]
+    );
+
+    let claims = payload(json!({"vc": {
+      "@context": "https://www.w3.org/ns/credentials/v2",
+      "type": "OtherCredential"
+    }}));
+    assert_eq!(
+      messages(check_credential(&claims)),
+      vec![
+        "`type` doesn't have VerifiableCredential",
+        "The credential has no `credentialSubject`",
+        "VC-JWT has no `iss` claim for the issuer",
+        "VC-JWT has no `nbf` claim for the issuance date",
+      ]
+    );
+    assert_eq!(
+      messages(check_credential(&payload(json!({"vc": "degree"})))),
+      vec!["`vc` is not an object"]
+    );
+  }
+
+  #[test]
+  fn test_check_presentation() {
+    let mut vc = credential();
+    vc["jti"] = "urn:uuid:other".into();
+    let jwt = format!(
+      "eyJhbGciOiJFUzI1NksifQ.{}.sig",
+      URL_SAFE_NO_PAD.encode(vc.to_string())
+    );
+    let claims = payload(json!({
+      "iss": "did:example:alice",
+      "aud": "did:example:verifier",
+      "vp": {
+        "@context": ["https://www.w3.org/2018/credentials/v1"],
+        "type": ["VerifiablePresentation"],
+        "holder": "did:example:bob",
+        "verifiableCredential": [jwt, "not-a-jwt", credential()["vc"]]
+      }
+    }));
+    let findings = check_credential(&claims);
+    assert_eq!(
+      messages(findings.clone()),
+      vec![
+        "`iss` is not the `holder` of the presentation",
+        "The presentation has no `nonce` claim, it can be replayed to other verifiers",
+        "Credential 1: `jti` is not the `id` of the credential",
+        "Credential 2: not a JWT",
+      ]
+    );
+    assert_eq!(findings[1].severity, Severity::Warning);
+  }
+
+  #[test]
+  fn test_describe_credential() {
+    let text = describe_credential(&payload(credential())).unwrap();
+    assert!(text.starts_with(
+      "Verifiable credential\n  Type:    VerifiableCredential, UniversityDegreeCredential\n  Issuer:  did:example:issuer\n  Subject: did:example:alice\n  Issued:  2010-01-01T19:23:24Z\n  Expires: -\n\nCredential subject\n{\n  \"degree\": \"BSc\",\n  \"id\": \"did:example:alice\"\n}\n\nCredential\n{"
+    ));
+
+    let claims = payload(json!({
+      "iss": "did:example:alice",
+      "vp": {"type": "VerifiablePresentation", "verifiableCredential": credential()["vc"]}
+    }));
+    let text = describe_credential(&claims).unwrap();
+    assert!(text.starts_with(
+      "Verifiable presentation\n  Type:     VerifiablePresentation\n  Holder:   did:example:alice\n  Audience: -\n\nCredential 1\n  Type:    VerifiableCredential"
+    ));
+
+    assert_eq!(
+      describe_credential(&payload(json!({"sub": "alice"})))
+        .unwrap_err()
+        .to_string(),
+      "The token has no `vc` or `vp` claim"
+    );
+  }
+}
@@ -0,0 +1,252 @@
+//! Network requests of the app run in the background, so a slow server doesn't freeze the UI.
+//! Their responses come back to the main loop as [`Event::Network`] events
+
+use std::sync::mpsc::Sender;
+
+use crossterm::event::{KeyEvent, MouseEvent};
+use tokio::runtime::{Builder, Runtime};
+use tracing::debug;
+
+use super::{
+  azure_kv::key_id,
+  introspection::Introspection,
+  jwt_encoder::{encode_remote, RemoteSigning},
+  oauth::{Grant, OAuthClient, TokenResponse},
+  secrets::{fetch_secret, KEYRING_PREFIX},
+  utils::{JWTError, JWTResult},
+  vault_transit::transit_key,
+  x509::fetch_x5u,
+};
+use crate::event::Event;
+
+/// a network request of the app
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetworkRequest {
+  /// the certificate chain of the `x5u` header URL
+  X5u(String),
+  /// a new token of the OAuth client with the grant
+  Token { client: OAuthClient, grant: Grant },
+  /// a new access token in exchange for the refresh token
+  Refresh {
+    client: OAuthClient,
+    refresh_token: String,
+  },
+  /// the introspection of the token by the authorization server
+  Introspection { client: OAuthClient, token: String },
+  /// the value of a keyring entry, or the public keys of a remote key to verify the tokens signed
+  /// with it. The keyring can block while it asks to be unlocked
+  Secret(String),
+  /// the token signed by a remote key
+  Sign(RemoteSigning),
+}
+
+/// response of a [`NetworkRequest`], with what the app needs to handle it
+#[derive(Debug)]
+pub enum NetworkResponse {
+  X5u {
+    url: String,
+    chain: JWTResult<Vec<Vec<u8>>>,
+  },
+  Token(JWTResult<TokenResponse>),
+  Refresh {
+    refresh_token: String,
+    tokens: JWTResult<TokenResponse>,
+  },
+  Introspection {
+    token: String,
+    introspection: JWTResult<Introspection>,
+  },
+  Secret {
+    secret: String,
+    value: JWTResult<String>,
+  },
+  Sign {
+    signing: RemoteSigning,
+    token: JWTResult<String>,
+  },
+}
+
+impl NetworkRequest {
+  /// shown while the request runs in the background
+  pub fn description(&self) -> &'static str {
+    match self {
+      NetworkRequest::X5u(_) => "Fetching the x5u certificate chain...",
+      NetworkRequest::Token { .. } => "Requesting a new token...",
+      NetworkRequest::Refresh { .. } => "Refreshing the access token...",
+      NetworkRequest::Introspection { .. } => "Introspecting the token...",
+      NetworkRequest::Secret(secret) if secret.starts_with(KEYRING_PREFIX) => {
+        "Reading the secret from the OS keyring..."
+      }
+      NetworkRequest::Secret(secret) if key_id(secret).is_some() => {
+        "Fetching the public key of the Key Vault key..."
+      }
+      NetworkRequest::Secret(secret) if transit_key(secret).is_some() => {
+        "Fetching the public key of the Vault transit key..."
+      }
+      NetworkRequest::Secret(_) => "Fetching the public key of the Cloud KMS key...",
+      NetworkRequest::Sign(signing) if key_id(&signing.secret).is_some() => {
+        "Signing the token with Key Vault..."
+      }
+      NetworkRequest::Sign(signing) if transit_key(&signing.secret).is_some() => {
+        "Signing the token with Vault transit..."
+      }
+      NetworkRequest::Sign(_) => "Signing the token with Cloud KMS...",
+    }
+  }
+
+  /// the URL or key the request is sent to, logged instead of the request as it has secrets (e.g.
+  /// the client secret, refresh token or claims to sign)
+  pub fn target(&self) -> &str {
+    match self {
+      NetworkRequest::X5u(url) => url,
+      NetworkRequest::Token { client, .. } | NetworkRequest::Refresh { client, .. } => {
+        &client.token_url
+      }
+      NetworkRequest::Introspection { client, .. } => {
+        client.introspection_url.as_deref().unwrap_or_default()
+      }
+      NetworkRequest::Secret(secret) => secret,
+      NetworkRequest::Sign(signing) => &signing.secret,
+    }
+  }
+
+  /// send the request, blocking until the response
+  pub fn send(self) -> NetworkResponse {
+    match self {
+      NetworkRequest::X5u(url) => NetworkResponse::X5u {
+        chain: fetch_x5u(&url),
+        url,
+      },
+      NetworkRequest::Token { client, grant } => {
+        NetworkResponse::Token(client.request_token(grant))
+      }
+      NetworkRequest::Refresh {
+        client,
+        refresh_token,
+      } => NetworkResponse::Refresh {
+        tokens: client.refresh(&refresh_token),
+        refresh_token,
+      },
+      NetworkRequest::Introspection { client, token } => NetworkResponse::Introspection {
+        introspection: client.introspect(&token),
+        token,
+      },
+      NetworkRequest::Secret(secret) => NetworkResponse::Secret {
+        value: fetch_secret(&secret),
+        secret,
+      },
+      NetworkRequest::Sign(signing) => NetworkResponse::Sign {
+        token: encode_remote(&signing.header, &signing.payload, &signing.secret),
+        signing,
+      },
+    }
+  }
+}
+
+/// runs the network requests concurrently on a background runtime and sends their responses to
+/// the events of the main loop
+pub struct Dispatcher {
+  /// only taken when dropped
+  runtime: Option<Runtime>,
+  events: Sender<Event<KeyEvent, MouseEvent>>,
+}
+
+impl Dispatcher {
+  pub fn new(events: Sender<Event<KeyEvent, MouseEvent>>) -> JWTResult<Self> {
+    let runtime = Builder::new_multi_thread()
+      .worker_threads(1)
+      .thread_name("jwtui-network")
+      .build()
+      .map_err(|e| JWTError::Internal(format!("Unable to start the network runtime: {e}")))?;
+    Ok(Dispatcher {
+      runtime: Some(runtime),
+      events,
+    })
+  }
+
+  /// send the request in the background, its response arrives as an [`Event::Network`]
+  pub fn dispatch(&self, request: NetworkRequest) {
+    let Some(runtime) = &self.runtime else {
+      return;
+    };
+    debug!(
+      description = request.description(),
+      target = request.target(),
+      "dispatching"
+    );
+    let events = self.events.clone();
+    // the HTTP client blocks, so requests run on the blocking threads of the runtime
+    runtime.spawn_blocking(move || {
+      let response = request.send();
+      // the main loop is gone when the app quits while a request runs
+      let _ = events.send(Event::Network(Box::new(response)));
+    });
+  }
+}
+
+impl Drop for Dispatcher {
+  fn drop(&mut self) {
+    // don't wait for running requests when quitting
+    if let Some(runtime) = self.runtime.take() {
+      runtime.shutdown_background();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{fs, sync::mpsc, time::Duration};
+
+  use super::*;
+  use crate::app::network::tests::serve_once;
+
+  #[test]
+  fn test_dispatch() {
+    let pem = fs::read("./test_data/test_rsa_cert.pem").unwrap();
+    let url = serve_once("200 OK", pem);
+    let (sender, receiver) = mpsc::channel();
+    let dispatcher = Dispatcher::new(sender).unwrap();
+
+    dispatcher.dispatch(NetworkRequest::X5u(url.clone()));
+    let Ok(Event::Network(response)) = receiver.recv_timeout(Duration::from_secs(5)) else {
+      panic!("expected a network event");
+    };
+    let NetworkResponse::X5u {
+      url: fetched,
+      chain,
+    } = *response
+    else {
+      panic!("expected the x5u response");
+    };
+    assert_eq!(fetched, url);
+    assert_eq!(chain.unwrap().len(), 1);
+  }
+
+  #[test]
+  fn test_send() {
+    let NetworkResponse::Token(tokens) = NetworkRequest::Token {
+      client: OAuthClient::default(),
+      grant: Grant::ClientCredentials,
+    }
+    .send() else {
+      panic!("expected the token response");
+    };
+    assert!(tokens.is_err());
+  }
+
+  #[test]
+  fn test_target() {
+    let client = OAuthClient {
+      token_url: "https://idp.example.com/token".into(),
+      client_secret: Some("s3cret".into()),
+      ..OAuthClient::default()
+    };
+    let request = NetworkRequest::Refresh {
+      client,
+      refresh_token: "refresh-token".into(),
+    };
+    assert_eq!(request.target(), "https://idp.example.com/token");
+    let request = NetworkRequest::Secret("gcpkms:projects/p".into());
+    assert_eq!(request.target(), "gcpkms:projects/p");
+  }
+}
@@ -0,0 +1,277 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use jsonwebtoken::errors::{Error, ErrorKind};
+use k256::{
+  ecdsa::{
+    signature::{Signer, Verifier},
+    Signature, SigningKey, VerifyingKey,
+  },
+  pkcs8::{DecodePrivateKey, DecodePublicKey},
+  SecretKey,
+};
+use serde_json::Value;
+
+use super::{
+  encrypted_key::decrypt_key,
+  jwt_decoder::{Payload, RawToken},
+  utils::{decode_segment, slurp_file, strip_leading_symbol, JWTError, JWTResult},
+};
+
+/// JWS algorithm name of ECDSA using secp256k1 and SHA-256 (RFC 8812)
+pub const ES256K: &str = "ES256K";
+
+/// leeway in seconds for the `exp` claim, same as for the other algorithms
+const EXP_LEEWAY: i64 = 1000;
+
+/// returns true if the header of the JWT uses the `ES256K` algorithm
+pub fn is_es256k(jwt: &str) -> bool {
+  jwt
+    .split('.')
+    .next()
+    .and_then(|header| URL_SAFE_NO_PAD.decode(header).ok())
+    .and_then(|header| serde_json::from_slice::<Value>(&header).ok())
+    .is_some_and(|header| header["alg"] == ES256K)
+}
+
+/// returns the decoded values and the signature verification result of an `ES256K` JWT
+pub fn decode_es256k(
+  jwt: &str,
+  secret: &str,
+  ignore_exp: bool,
+) -> (JWTResult<RawToken>, JWTResult<()>) {
+  let parts: Vec<&str> = jwt.trim().split('.').collect();
+  if parts.len() != 3 {
+    let err = || JWTError::from(Error::from(ErrorKind::InvalidToken));
+    return (Err(err()), Err(err()));
+  }
+
+  let decoded = decode_segment::<Value>(parts[0]).and_then(|header| {
+    Ok(RawToken {
+      header,
+      claims: Some(decode_segment::<Payload>(parts[1])?),
+    })
+  });
+
+  let verified = match secret.len() {
+    0 => Err(Error::from(ErrorKind::InvalidSignature).into()),
+    _ => decoded.as_ref().map_err(Clone::clone).and_then(|token| {
+      verify(&parts, token, secret)?;
+      check_exp(token.claims.as_ref(), ignore_exp)
+    }),
+  };
+  (decoded, verified)
+}
+
+/// sign the header and claims with the secp256k1 private key from the secret
+pub fn encode_es256k(
+  header: &Value,
+  payload: &Payload,
+  secret: &str,
+  passphrase: Option<&str>,
+) -> JWTResult<String> {
+  let key = signing_key(secret, passphrase)?;
+  let message = format!(
+    "{}.{}",
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(header)?),
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(payload)?)
+  );
+  let signature: Signature = key.sign(message.as_bytes());
+  Ok(format!(
+    "{message}.{}",
+    URL_SAFE_NO_PAD.encode(signature.to_bytes())
+  ))
+}
+
+fn verify(parts: &[&str], token: &RawToken, secret: &str) -> JWTResult<()> {
+  let key = verifying_key(secret, token.header["kid"].as_str())?;
+  let invalid_signature = || JWTError::from(Error::from(ErrorKind::InvalidSignature));
+
+  let signature = URL_SAFE_NO_PAD
+    .decode(parts[2])
+    .ok()
+    .and_then(|sig| Signature::from_slice(&sig).ok())
+    .ok_or_else(invalid_signature)?;
+  // signatures from other libraries are not always normalized to the low-S form
+  let signature = signature.normalize_s();
+
+  key
+    .verify(format!("{}.{}", parts[0], parts[1]).as_bytes(), &signature)
+    .map_err(|_| invalid_signature())
+}
+
+fn check_exp(claims: Option<&Payload>, ignore_exp: bool) -> JWTResult<()> {
+  if ignore_exp {
+    return Ok(());
+  }
+  match claims
+    .and_then(|claims| claims.0.get("exp"))
+    .and_then(Value::as_i64)
+  {
+    Some(exp) if exp + EXP_LEEWAY < Utc::now().timestamp() => {
+      Err(Error::from(ErrorKind::ExpiredSignature).into())
+    }
+    Some(_) => Ok(()),
+    None => Err(Error::from(ErrorKind::MissingRequiredClaim("exp".into())).into()),
+  }
+}
+
+/// read the secp256k1 public key from a PEM/DER file (public or private key) or a JWK/JWKS
+fn verifying_key(secret: &str, kid: Option<&str>) -> JWTResult<VerifyingKey> {
+  let invalid_key = |e: String| JWTError::Internal(format!("Invalid ES256K key: {e}"));
+  let data = read_secret(secret)?;
+
+  if let Ok(jwk) = serde_json::from_slice::<Value>(&data) {
+    return verifying_key_from_jwk(&jwk, kid);
+  }
+  let text = String::from_utf8_lossy(&data);
+  if text.contains("PUBLIC KEY") {
+    VerifyingKey::from_public_key_pem(&text).map_err(|e| invalid_key(e.to_string()))
+  } else if text.contains("-----BEGIN") {
+    signing_key(secret, None).map(|key| *key.verifying_key())
+  } else {
+    VerifyingKey::from_public_key_der(&data)
+      .or_else(|_| {
+        signing_key(secret, None)
+          .map(|key| *key.verifying_key())
+          .map_err(|_| ())
+      })
+      .map_err(|_| invalid_key("unsupported key format".into()))
+  }
+}
+
+fn verifying_key_from_jwk(jwk: &Value, kid: Option<&str>) -> JWTResult<VerifyingKey> {
+  let keys = match jwk["keys"].as_array() {
+    Some(keys) => keys.iter().collect(),
+    None => vec![jwk],
+  };
+  let jwk = keys
+    .into_iter()
+    .filter(|key| key["crv"] == "secp256k1")
+    .find(|key| kid.is_none() || key["kid"].as_str() == kid)
+    .ok_or_else(|| JWTError::Internal("No secp256k1 key found in the JWKS".into()))?;
+
+  let coordinate = |name: &str| {
+    jwk[name]
+      .as_str()
+      .and_then(|c| URL_SAFE_NO_PAD.decode(c).ok())
+      .filter(|c| c.len() == 32)
+      .ok_or_else(|| JWTError::Internal(format!("Invalid `{name}` coordinate in the JWK")))
+  };
+  // uncompressed SEC1 point
+  let mut point = vec![0x04];
+  point.extend(coordinate("x")?);
+  point.extend(coordinate("y")?);
+  VerifyingKey::from_sec1_bytes(&point)
+    .map_err(|e| JWTError::Internal(format!("Invalid ES256K key: {e}")))
+}
+
+/// read the secp256k1 private key from a PKCS8 (optionally encrypted) or SEC1 PEM/DER file
+fn signing_key(secret: &str, passphrase: Option<&str>) -> JWTResult<SigningKey> {
+  let data = decrypt_key(read_secret(secret)?, passphrase)?;
+  let text = String::from_utf8_lossy(&data);
+  let key = if text.contains("-----BEGIN") {
+    SigningKey::from_pkcs8_pem(&text)
+      .ok()
+      .or_else(|| SecretKey::from_sec1_pem(&text).ok().map(SigningKey::from))
+  } else {
+    SigningKey::from_pkcs8_der(&data)
+      .ok()
+      .or_else(|| SecretKey::from_sec1_der(&data).ok().map(SigningKey::from))
+  };
+  key.ok_or_else(|| JWTError::Internal("Invalid ES256K private key".into()))
+}
+
+/// secrets are file paths (beginning with `@`) or inline JWK/JWKS JSON
+fn read_secret(secret: &str) -> JWTResult<Vec<u8>> {
+  if secret.starts_with('@') {
+    Ok(slurp_file(strip_leading_symbol(secret))?)
+  } else {
+    Ok(secret.as_bytes().to_vec())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const PRIVATE_KEY: &str = "@./test_data/test_es256k_private_key.pem";
+  const PUBLIC_KEY: &str = "@./test_data/test_es256k_public_key.pem";
+
+  fn token(claims: &str) -> String {
+    let header = serde_json::json!({"alg": "ES256K", "typ": "JWT", "kid": "k1"});
+    encode_es256k(
+      &header,
+      &serde_json::from_str(claims).unwrap(),
+      PRIVATE_KEY,
+      None,
+    )
+    .unwrap()
+  }
+
+  #[test]
+  fn test_is_es256k() {
+    assert!(is_es256k(&token(r#"{"sub": "1234"}"#)));
+    assert!(!is_es256k("eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.e30.abc"));
+    assert!(!is_es256k("not a token"));
+  }
+
+  #[test]
+  fn test_encode_and_decode_es256k() {
+    let jwt = token(r#"{"sub": "1234"}"#);
+
+    let (decoded, verified) = decode_es256k(&jwt, PUBLIC_KEY, true);
+    let decoded = decoded.unwrap();
+    assert_eq!(decoded.header["kid"], "k1");
+    assert_eq!(decoded.claims.unwrap().0.get("sub").unwrap(), "1234");
+    assert!(verified.is_ok());
+
+    // the private key can verify as well
+    assert!(decode_es256k(&jwt, PRIVATE_KEY, true).1.is_ok());
+
+    // exp is required unless ignored
+    assert_eq!(
+      decode_es256k(&jwt, PUBLIC_KEY, false)
+        .1
+        .unwrap_err()
+        .to_string(),
+      "`exp` is missing, but is required. This error can be ignored by pressing `i`.: Missing required claim: exp"
+    );
+  }
+
+  #[test]
+  fn test_decode_es256k_with_jwks() {
+    let jwt = token(r#"{"sub": "1234", "exp": 1}"#);
+    let jwks = std::fs::read_to_string("./test_data/test_es256k_public_jwks.json").unwrap();
+
+    let (_, verified) = decode_es256k(&jwt, &jwks, true);
+    assert!(verified.is_ok());
+
+    let (_, verified) = decode_es256k(&jwt, &jwks, false);
+    assert_eq!(
+      verified.unwrap_err().to_string(),
+      "The token has expired (or the `exp` claim is not set). This error can be ignored by pressing `i`.: ExpiredSignature"
+    );
+  }
+
+  #[test]
+  fn test_decode_es256k_with_invalid_signature() {
+    let jwt = token(r#"{"sub": "1234"}"#);
+    let tampered = format!("{}A", &jwt[..jwt.len() - 1]);
+    let other = token(r#"{"sub": "5678"}"#);
+    let mixed = format!(
+      "{}.{}",
+      jwt.rsplit_once('.').unwrap().0,
+      other.rsplit_once('.').unwrap().1
+    );
+
+    for jwt in [tampered, mixed] {
+      let (decoded, verified) = decode_es256k(&jwt, PUBLIC_KEY, true);
+      assert!(decoded.is_ok());
+      assert_eq!(
+        verified.unwrap_err().to_string(),
+        "The JWT provided has an invalid signature. Provide a valid secret: InvalidSignature"
+      );
+    }
+    assert!(decode_es256k(&jwt, "", true).1.is_err());
+  }
+}
@@ -0,0 +1,90 @@
+use super::{theme::Theme, utils::JWTError, RouteId};
+
+/// command typed after `:` in vim mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExCommand {
+  /// `:q`, `:quit`
+  Quit,
+  /// `:theme <name>`, toggles between the dark and light theme without argument
+  Theme(Option<Theme>),
+  /// `:copy [block]`, the active block without argument
+  Copy(Option<String>),
+  /// `:42` scrolls the decoded payload to the line
+  GoToLine(String),
+  /// `:decoder`, `:encoder`, `:keys`, `:utilities`
+  View(RouteId),
+  /// `:help`
+  Help,
+}
+
+impl ExCommand {
+  pub fn parse(line: &str) -> Result<Self, JWTError> {
+    let line = line.trim().trim_start_matches(':');
+    let mut words = line.split_whitespace();
+    let (name, arg) = (words.next().unwrap_or_default(), words.next());
+    let unknown = || JWTError::Internal(format!("Unknown command \":{line}\""));
+    if words.next().is_some() {
+      return Err(unknown());
+    }
+    match (name, arg) {
+      ("q" | "quit", None) => Ok(Self::Quit),
+      ("theme", None) => Ok(Self::Theme(None)),
+      ("theme", Some(name)) => Ok(Self::Theme(Some(name.parse()?))),
+      ("copy", arg) => Ok(Self::Copy(arg.map(String::from))),
+      ("decoder", None) => Ok(Self::View(RouteId::Decoder)),
+      ("encoder", None) => Ok(Self::View(RouteId::Encoder)),
+      ("keys", None) => Ok(Self::View(RouteId::Keys)),
+      ("utilities", None) => Ok(Self::View(RouteId::Utilities)),
+      ("help", None) => Ok(Self::Help),
+      (line, None) if line.chars().all(|c| c.is_ascii_digit()) && !line.is_empty() => {
+        Ok(Self::GoToLine(line.into()))
+      }
+      _ => Err(unknown()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_ex_command() {
+    assert_eq!(ExCommand::parse("q").unwrap(), ExCommand::Quit);
+    assert_eq!(ExCommand::parse(":quit ").unwrap(), ExCommand::Quit);
+    assert_eq!(ExCommand::parse("theme").unwrap(), ExCommand::Theme(None));
+    assert_eq!(
+      ExCommand::parse("theme dracula").unwrap(),
+      ExCommand::Theme(Some(Theme::Dracula))
+    );
+    assert_eq!(
+      ExCommand::parse("copy  payload").unwrap(),
+      ExCommand::Copy(Some("payload".into()))
+    );
+    assert_eq!(ExCommand::parse("copy").unwrap(), ExCommand::Copy(None));
+    assert_eq!(
+      ExCommand::parse("keys").unwrap(),
+      ExCommand::View(RouteId::Keys)
+    );
+    assert_eq!(
+      ExCommand::parse("utilities").unwrap(),
+      ExCommand::View(RouteId::Utilities)
+    );
+    assert_eq!(
+      ExCommand::parse("42").unwrap(),
+      ExCommand::GoToLine("42".into())
+    );
+    assert!(ExCommand::parse("theme blue")
+      .unwrap_err()
+      .to_string()
+      .starts_with("Unknown theme \"blue\""));
+    assert_eq!(
+      ExCommand::parse("theme light dark")
+        .unwrap_err()
+        .to_string(),
+      "Unknown command \":theme light dark\""
+    );
+    assert!(ExCommand::parse("").is_err());
+    assert!(ExCommand::parse("q now").is_err());
+  }
+}
@@ -0,0 +1,435 @@
+//! Asymmetric keys of Google Cloud KMS, referenced as
+//! `gcpkms:projects/<project>/locations/<location>/keyRings/<ring>/cryptoKeys/<key>/cryptoKeyVersions/<version>`.
+//! Tokens are verified with the public key of the key version and signed by Cloud KMS, the private
+//! key never leaves it. Requests are authorized with the Application Default Credentials
+
+use std::{
+  env,
+  path::PathBuf,
+  sync::Mutex,
+  time::{Duration, Instant},
+};
+
+use base64::{
+  engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+  Engine,
+};
+use chrono::Utc;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde::de::DeserializeOwned;
+use serde_derive::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use super::{
+  jwt_decoder::Payload,
+  keys::{jwks, parse_key},
+  network::{send, RequestBody},
+  utils::{JWTError, JWTResult},
+};
+
+/// prefix of the key versions of Cloud KMS
+pub const GCP_KMS_PREFIX: &str = "gcpkms:";
+const KMS_API: &str = "https://cloudkms.googleapis.com/v1";
+const KMS_SCOPE: &str = "https://www.googleapis.com/auth/cloudkms";
+/// environment variable with the path of the credentials file, like for the Google Cloud SDKs
+const CREDENTIALS_ENV: &str = "GOOGLE_APPLICATION_CREDENTIALS";
+const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+/// token of the service account attached to the Compute Engine, Cloud Run or GKE workload
+const METADATA_TOKEN_URL: &str =
+  "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+/// access tokens are requested again when they expire within this delay
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// the access token of the credentials and when it expires, reused by the requests to Cloud KMS
+static ACCESS_TOKEN: Mutex<Option<(String, Instant)>> = Mutex::new(None);
+
+/// Application Default Credentials file, written by `gcloud auth application-default login` or
+/// downloaded as service account key
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Credentials {
+  AuthorizedUser {
+    client_id: String,
+    client_secret: String,
+    refresh_token: String,
+  },
+  ServiceAccount {
+    client_email: String,
+    private_key: String,
+    token_uri: Option<String>,
+  },
+}
+
+#[derive(Debug, Deserialize)]
+struct AccessToken {
+  access_token: String,
+  /// lifetime of the access token in seconds
+  expires_in: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct PublicKey {
+  pem: String,
+  /// Cloud KMS name of the algorithm, like `EC_SIGN_P256_SHA256`
+  algorithm: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SignResponse {
+  /// base64 encoded signature, DER encoded for ECDSA keys
+  signature: String,
+}
+
+/// the key version of a `gcpkms:` reference
+pub fn key_version(secret: &str) -> Option<&str> {
+  secret.strip_prefix(GCP_KMS_PREFIX).map(str::trim)
+}
+
+/// the public key of the key version as JWKS, with the key version as `kid` and the JWS algorithm
+/// of the key
+pub fn public_jwks(name: &str) -> JWTResult<String> {
+  public_jwks_from(KMS_API, name, &access_token()?)
+}
+
+fn public_jwks_from(api: &str, name: &str, token: &str) -> JWTResult<String> {
+  let key: PublicKey = send_json(&format!("{api}/{name}/publicKey"), token, None)?;
+  let mut jwk = parse_key(key.pem.as_bytes())?;
+  jwk["kid"] = name.into();
+  if let Some(alg) = jws_algorithm(&key.algorithm) {
+    jwk["alg"] = alg.into();
+  }
+  Ok(jwks(vec![jwk]).to_string())
+}
+
+/// sign the header and claims with the key version, the `alg` of the header has to be the
+/// algorithm of the key
+pub fn encode_gcp_kms(header: &Value, payload: &Payload, name: &str) -> JWTResult<String> {
+  let message = format!(
+    "{}.{}",
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(header)?),
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(payload)?)
+  );
+  let alg = header["alg"].as_str().unwrap_or_default();
+  let signature = sign(KMS_API, name, alg, message.as_bytes(), &access_token()?)?;
+  Ok(format!("{message}.{}", URL_SAFE_NO_PAD.encode(signature)))
+}
+
+fn sign(api: &str, name: &str, alg: &str, message: &[u8], token: &str) -> JWTResult<Vec<u8>> {
+  // Cloud KMS signs the digest of the message, except for Ed25519 which signs the message
+  let body = match alg {
+    "RS256" | "PS256" | "ES256" | "ES256K" => {
+      json!({ "digest": { "sha256": STANDARD.encode(Sha256::digest(message)) } })
+    }
+    "RS384" | "PS384" | "ES384" => {
+      json!({ "digest": { "sha384": STANDARD.encode(Sha384::digest(message)) } })
+    }
+    "RS512" | "PS512" => {
+      json!({ "digest": { "sha512": STANDARD.encode(Sha512::digest(message)) } })
+    }
+    "EdDSA" => json!({ "data": STANDARD.encode(message) }),
+    alg => {
+      return Err(JWTError::Internal(format!(
+        "Cloud KMS keys can't sign {alg:?} tokens"
+      )))
+    }
+  };
+  let response: SignResponse =
+    send_json(&format!("{api}/{name}:asymmetricSign"), token, Some(&body))?;
+  let signature = STANDARD
+    .decode(response.signature)
+    .map_err(|e| JWTError::Internal(format!("Invalid signature from Cloud KMS: {e}")))?;
+  jws_signature(alg, &signature)
+}
+
+/// the JWS signature of a Cloud KMS signature: ECDSA signatures are DER encoded by Cloud KMS and
+/// the fixed size `r || s` in JWS
+fn jws_signature(alg: &str, signature: &[u8]) -> JWTResult<Vec<u8>> {
+  let invalid = |e| JWTError::Internal(format!("Invalid ECDSA signature from Cloud KMS: {e}"));
+  match alg {
+    "ES256" => p256::ecdsa::Signature::from_der(signature)
+      .map(|signature| signature.to_bytes().to_vec())
+      .map_err(invalid),
+    "ES384" => p384::ecdsa::Signature::from_der(signature)
+      .map(|signature| signature.to_bytes().to_vec())
+      .map_err(invalid),
+    "ES256K" => k256::ecdsa::Signature::from_der(signature)
+      .map(|signature| signature.to_bytes().to_vec())
+      .map_err(invalid),
+    _ => Ok(signature.to_vec()),
+  }
+}
+
+/// the JWS algorithm of the Cloud KMS algorithm for the `alg` of the JWK, `None` for algorithms
+/// without one like raw PKCS#1 signatures, and for secp256k1 as `ES256K` isn't a JWK `alg` of
+/// `jsonwebtoken`
+fn jws_algorithm(algorithm: &str) -> Option<&'static str> {
+  Some(match algorithm {
+    "EC_SIGN_P256_SHA256" => "ES256",
+    "EC_SIGN_P384_SHA384" => "ES384",
+    "EC_SIGN_ED25519" => "EdDSA",
+    _ if algorithm.starts_with("RSA_SIGN_PKCS1_") => match algorithm.rsplit('_').next()? {
+      "SHA256" => "RS256",
+      "SHA512" => "RS512",
+      _ => return None,
+    },
+    _ if algorithm.starts_with("RSA_SIGN_PSS_") => match algorithm.rsplit('_').next()? {
+      "SHA256" => "PS256",
+      "SHA512" => "PS512",
+      _ => return None,
+    },
+    _ => return None,
+  })
+}
+
+/// send a request to Cloud KMS, a POST of the body when there is one, and parse the response
+fn send_json<T: DeserializeOwned>(url: &str, token: &str, body: Option<&Value>) -> JWTResult<T> {
+  let mut headers = vec![("Authorization".to_string(), format!("Bearer {token}"))];
+  let body = body.map(Value::to_string);
+  let response = match &body {
+    Some(body) => {
+      headers.push(("Content-Type".into(), "application/json".into()));
+      send(url, &headers, RequestBody::Text(body))?
+    }
+    None => send(url, &headers, RequestBody::None)?,
+  };
+  serde_json::from_slice(&response)
+    .map_err(|e| JWTError::Internal(format!("Invalid response from {url}: {e}")))
+}
+
+/// the access token of the Application Default Credentials, requested again when it expires
+fn access_token() -> JWTResult<String> {
+  let mut cached = ACCESS_TOKEN.lock().unwrap_or_else(|e| e.into_inner());
+  if let Some((token, expires_at)) = cached.as_ref() {
+    if Instant::now() + TOKEN_EXPIRY_MARGIN < *expires_at {
+      return Ok(token.clone());
+    }
+  }
+  let token = request_access_token(&default_credentials()?)?;
+  let expires_at = Instant::now() + Duration::from_secs(token.expires_in);
+  *cached = Some((token.access_token.clone(), expires_at));
+  Ok(token.access_token)
+}
+
+/// the credentials of `GOOGLE_APPLICATION_CREDENTIALS` or of the gcloud CLI, `None` to use the
+/// service account of the workload
+fn default_credentials() -> JWTResult<Option<Credentials>> {
+  let path = match env::var_os(CREDENTIALS_ENV).filter(|path| !path.is_empty()) {
+    Some(path) => PathBuf::from(path),
+    None => match gcloud_credentials_path().filter(|path| path.is_file()) {
+      Some(path) => path,
+      None => return Ok(None),
+    },
+  };
+  let content = std::fs::read(&path).map_err(|e| {
+    JWTError::Internal(format!(
+      "Unable to read the credentials {}: {e}",
+      path.display()
+    ))
+  })?;
+  serde_json::from_slice(&content).map(Some).map_err(|e| {
+    JWTError::Internal(format!(
+      "Invalid credentials {}, expected an authorized user or a service account: {e}",
+      path.display()
+    ))
+  })
+}
+
+/// credentials written by `gcloud auth application-default login`
+fn gcloud_credentials_path() -> Option<PathBuf> {
+  let dir = match cfg!(windows) {
+    true => dirs::config_dir()?,
+    false => dirs::home_dir()?.join(".config"),
+  };
+  Some(
+    dir
+      .join("gcloud")
+      .join("application_default_credentials.json"),
+  )
+}
+
+fn request_access_token(credentials: &Option<Credentials>) -> JWTResult<AccessToken> {
+  let response = match credentials {
+    Some(Credentials::AuthorizedUser {
+      client_id,
+      client_secret,
+      refresh_token,
+    }) => send(
+      GOOGLE_TOKEN_URL,
+      &[],
+      RequestBody::Form(&[
+        ("grant_type", "refresh_token"),
+        ("client_id", client_id),
+        ("client_secret", client_secret),
+        ("refresh_token", refresh_token),
+      ]),
+    )?,
+    Some(Credentials::ServiceAccount {
+      client_email,
+      private_key,
+      token_uri,
+    }) => {
+      let token_uri = token_uri.as_deref().unwrap_or(GOOGLE_TOKEN_URL);
+      let assertion = service_account_assertion(client_email, private_key, token_uri)?;
+      send(
+        token_uri,
+        &[],
+        RequestBody::Form(&[
+          ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+          ("assertion", &assertion),
+        ]),
+      )?
+    }
+    None => send(
+      METADATA_TOKEN_URL,
+      &[("Metadata-Flavor".into(), "Google".into())],
+      RequestBody::None,
+    )?,
+  };
+  serde_json::from_slice(&response)
+    .map_err(|e| JWTError::Internal(format!("Invalid access token response: {e}")))
+}
+
+/// the JWT of the service account exchanged for an access token (RFC 7523)
+fn service_account_assertion(
+  client_email: &str,
+  private_key: &str,
+  token_uri: &str,
+) -> JWTResult<String> {
+  let now = Utc::now().timestamp();
+  let claims = json!({
+    "iss": client_email,
+    "scope": KMS_SCOPE,
+    "aud": token_uri,
+    "iat": now,
+    "exp": now + 3600,
+  });
+  let key = EncodingKey::from_rsa_pem(private_key.as_bytes())?;
+  Ok(jsonwebtoken::encode(
+    &Header::new(Algorithm::RS256),
+    &claims,
+    &key,
+  )?)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use jsonwebtoken::{decode, DecodingKey, Validation};
+
+  use super::*;
+  use crate::app::network::tests::{serve_in_order, serve_once_with_request};
+
+  #[test]
+  fn test_key_version() {
+    assert_eq!(
+      key_version("gcpkms:projects/p/locations/global/keyRings/r/cryptoKeys/k/cryptoKeyVersions/1"),
+      Some("projects/p/locations/global/keyRings/r/cryptoKeys/k/cryptoKeyVersions/1")
+    );
+    assert_eq!(key_version("keyring:staging"), None);
+  }
+
+  #[test]
+  fn test_jws_algorithm() {
+    assert_eq!(jws_algorithm("EC_SIGN_P256_SHA256"), Some("ES256"));
+    assert_eq!(jws_algorithm("RSA_SIGN_PKCS1_3072_SHA256"), Some("RS256"));
+    assert_eq!(jws_algorithm("RSA_SIGN_PSS_4096_SHA512"), Some("PS512"));
+    assert_eq!(jws_algorithm("RSA_SIGN_RAW_PKCS1_2048"), None);
+    assert_eq!(jws_algorithm("EC_SIGN_SECP256K1_SHA256"), None);
+    assert_eq!(jws_algorithm("GOOGLE_SYMMETRIC_ENCRYPTION"), None);
+  }
+
+  #[test]
+  fn test_jws_signature() {
+    // r with its high bit set gets a leading zero in DER, a short s is padded in JWS
+    let mut der = vec![0x30, 0x44, 0x02, 0x21, 0x00];
+    der.extend([0x80; 32]);
+    der.extend([0x02, 0x1f]);
+    der.extend([0x01; 31]);
+    let raw = jws_signature("ES256", &der).unwrap();
+    assert_eq!(raw.len(), 64);
+    assert_eq!(raw[..32], [0x80; 32]);
+    assert_eq!(raw[32], 0);
+    assert_eq!(raw[33..], [0x01; 31]);
+
+    assert!(jws_signature("ES256", b"not der").is_err());
+    assert_eq!(jws_signature("RS256", b"raw").unwrap(), b"raw");
+  }
+
+  #[test]
+  fn test_public_jwks() {
+    let pem = fs::read_to_string("./test_data/test_rsa_public_key.pem").unwrap();
+    let body = json!({ "pem": pem, "algorithm": "RSA_SIGN_PKCS1_2048_SHA256" });
+    let (url, request) = serve_once_with_request("200 OK", body.to_string().into_bytes());
+    let jwks: Value =
+      serde_json::from_str(&public_jwks_from(&url, "projects/p/k/1", "ya29.token").unwrap())
+        .unwrap();
+    let jwk = &jwks["keys"][0];
+    assert_eq!(jwk["kty"], "RSA");
+    assert_eq!(jwk["kid"], "projects/p/k/1");
+    assert_eq!(jwk["alg"], "RS256");
+    let request = request.recv().unwrap();
+    assert!(request.starts_with("GET /projects/p/k/1/publicKey HTTP/1.1"));
+    assert!(request
+      .to_lowercase()
+      .contains("authorization: bearer ya29.token"));
+  }
+
+  #[test]
+  fn test_sign() {
+    let (url, requests) = serve_in_order(vec![
+      ("200 OK", br#"{"signature": "c2lnbmF0dXJl"}"#.to_vec()),
+      (
+        "403 Forbidden",
+        br#"{"error": {"message": "Permission denied"}}"#.to_vec(),
+      ),
+    ]);
+    let signature = sign(&url, "projects/p/k/1", "RS256", b"a.b", "ya29.token").unwrap();
+    assert_eq!(signature, b"signature");
+    let request = requests.recv().unwrap();
+    assert!(request.starts_with("POST /projects/p/k/1:asymmetricSign HTTP/1.1"));
+    let digest = STANDARD.encode(Sha256::digest(b"a.b"));
+    assert!(request.ends_with(&json!({ "digest": { "sha256": digest } }).to_string()));
+
+    let error = sign(&url, "projects/p/k/1", "RS256", b"a.b", "ya29.token").unwrap_err();
+    assert!(error.to_string().contains("Permission denied"));
+    assert!(sign(&url, "projects/p/k/1", "HS256", b"a.b", "ya29.token")
+      .unwrap_err()
+      .to_string()
+      .contains("can't sign \"HS256\" tokens"));
+  }
+
+  #[test]
+  fn test_service_account_token() {
+    let (url, request) = serve_once_with_request(
+      "200 OK",
+      br#"{"access_token": "ya29.token", "expires_in": 3599, "token_type": "Bearer"}"#.to_vec(),
+    );
+    let credentials: Credentials = serde_json::from_value(json!({
+      "type": "service_account",
+      "client_email": "signer@p.iam.gserviceaccount.com",
+      "private_key": fs::read_to_string("./test_data/test_rsa_private_key.pem").unwrap(),
+      "token_uri": url,
+    }))
+    .unwrap();
+    let token = request_access_token(&Some(credentials)).unwrap();
+    assert_eq!(token.access_token, "ya29.token");
+    assert_eq!(token.expires_in, 3599);
+
+    let request = request.recv().unwrap();
+    let (_, form) = request.split_once("\r\n\r\n").unwrap();
+    let assertion = form
+      .strip_prefix("grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Ajwt-bearer&assertion=")
+      .unwrap();
+    let key =
+      DecodingKey::from_rsa_pem(&fs::read("./test_data/test_rsa_public_key.pem").unwrap()).unwrap();
+    let mut validation = Validation::new(Algorithm::RS256);
+    validation.set_audience(&[url]);
+    let claims = decode::<Value>(assertion, &key, &validation)
+      .unwrap()
+      .claims;
+    assert_eq!(claims["iss"], "signer@p.iam.gserviceaccount.com");
+    assert_eq!(claims["scope"], KMS_SCOPE);
+  }
+}
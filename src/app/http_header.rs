@@ -0,0 +1,120 @@
+//! Tokens pasted with the HTTP header carrying them: an `Authorization: Bearer` line, or a `Cookie`
+//! header of which the cookies holding a JWT or a PASETO token are decoded
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde_json::Value;
+
+use super::paseto::is_paseto;
+
+const AUTHORIZATION_HEADER: &str = "authorization:";
+const COOKIE_HEADER: &str = "cookie:";
+const BEARER_SCHEME: &str = "bearer ";
+
+/// a token of a pasted header and where it was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderToken {
+  /// `Authorization` or the name of the cookie
+  pub name: String,
+  pub token: String,
+}
+
+/// the tokens of the text when it is an `Authorization` or a `Cookie` header, in the order of the
+/// header. None when the text isn't a header, empty when the cookies hold no token
+pub fn header_tokens(text: &str) -> Option<Vec<HeaderToken>> {
+  let text = text.trim();
+  if let Some(value) = strip_prefix_ignore_case(text, AUTHORIZATION_HEADER) {
+    let value = value.trim();
+    let token = strip_prefix_ignore_case(value, BEARER_SCHEME).unwrap_or(value);
+    return Some(vec![HeaderToken {
+      name: "Authorization".into(),
+      token: token.trim().into(),
+    }]);
+  }
+  if let Some(token) = strip_prefix_ignore_case(text, BEARER_SCHEME) {
+    return Some(vec![HeaderToken {
+      name: "Authorization".into(),
+      token: token.trim().into(),
+    }]);
+  }
+  let cookies = strip_prefix_ignore_case(text, COOKIE_HEADER)?;
+  Some(
+    cookies
+      .split(';')
+      .filter_map(|cookie| cookie.split_once('='))
+      .map(|(name, value)| (name.trim(), value.trim().trim_matches('"')))
+      .filter(|(_, value)| is_token(value))
+      .map(|(name, value)| HeaderToken {
+        name: name.into(),
+        token: value.into(),
+      })
+      .collect(),
+  )
+}
+
+/// whether the value is a JWS, a JWE or a PASETO token, judged by a JSON header with an `alg`
+pub fn is_token(value: &str) -> bool {
+  if is_paseto(value) {
+    return true;
+  }
+  let parts = value.split('.').count();
+  (parts == 3 || parts == 5)
+    && value
+      .split('.')
+      .next()
+      .and_then(|header| URL_SAFE_NO_PAD.decode(header).ok())
+      .and_then(|header| serde_json::from_slice::<Value>(&header).ok())
+      .is_some_and(|header| header["alg"].is_string())
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+  let head = text.get(..prefix.len())?;
+  head
+    .eq_ignore_ascii_case(prefix)
+    .then(|| &text[prefix.len()..])
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const JWT: &str = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.eyJzdWIiOiIxMjM0NTY3ODkwIn0.sig";
+
+  fn names(tokens: &[HeaderToken]) -> Vec<&str> {
+    tokens.iter().map(|token| token.name.as_str()).collect()
+  }
+
+  #[test]
+  fn test_authorization_header() {
+    let expected = Some(vec![HeaderToken {
+      name: "Authorization".into(),
+      token: JWT.into(),
+    }]);
+    assert_eq!(
+      header_tokens(&format!("Authorization: Bearer {JWT}")),
+      expected
+    );
+    assert_eq!(
+      header_tokens(&format!("authorization:bearer  {JWT}\n")),
+      expected
+    );
+    assert_eq!(header_tokens(&format!("Bearer {JWT}")), expected);
+    assert_eq!(header_tokens(JWT), None);
+    assert_eq!(header_tokens("v4.public.abc"), None);
+  }
+
+  #[test]
+  fn test_cookie_header() {
+    let text = format!(
+      "Cookie: theme=dark; session=\"{JWT}\"; _ga=GA1.2.3.4; paseto=v4.local.abc; id_token={JWT}"
+    );
+    let tokens = header_tokens(&text).unwrap();
+    assert_eq!(names(&tokens), ["session", "paseto", "id_token"]);
+    assert_eq!(tokens[0].token, JWT);
+    assert_eq!(tokens[1].token, "v4.local.abc");
+
+    assert_eq!(
+      header_tokens("cookie: theme=dark; a.b.c=x.y.z"),
+      Some(vec![])
+    );
+  }
+}
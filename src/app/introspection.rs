@@ -0,0 +1,129 @@
+use serde_derive::Deserialize;
+use serde_json::{Map, Value};
+
+use super::utils::decode_segment;
+
+/// response of the introspection endpoint (RFC 7662). Inactive tokens have no other members
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Introspection {
+  pub active: bool,
+  /// the other members, named like the claims of the token
+  #[serde(flatten)]
+  pub claims: Map<String, Value>,
+}
+
+/// member of the introspection response next to the claim of the decoded token with its name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntrospectedClaim {
+  pub name: String,
+  pub introspected: Value,
+  /// `None` when the token doesn't have the claim or can't be decoded, e.g. opaque tokens
+  pub decoded: Option<Value>,
+}
+
+impl IntrospectedClaim {
+  /// whether the server and the token disagree on the claim. Inactive tokens always do, as the
+  /// server revoked them or doesn't know them anymore
+  pub fn is_discrepancy(&self) -> bool {
+    match (&self.introspected, &self.decoded) {
+      (Value::Bool(active), None) if self.name == "active" => !active,
+      (introspected, Some(decoded)) => !same_value(introspected, decoded),
+      _ => false,
+    }
+  }
+}
+
+impl Introspection {
+  /// the `active` member and the other members of the response, with the claims of the token
+  pub fn compare(&self, token: &str) -> Vec<IntrospectedClaim> {
+    let payload = token
+      .trim()
+      .split('.')
+      .nth(1)
+      .and_then(|payload| decode_segment::<Map<String, Value>>(payload).ok())
+      .unwrap_or_default();
+    let active = IntrospectedClaim {
+      name: "active".into(),
+      introspected: Value::Bool(self.active),
+      decoded: None,
+    };
+    std::iter::once(active)
+      .chain(self.claims.iter().map(|(name, value)| IntrospectedClaim {
+        name: name.clone(),
+        introspected: value.clone(),
+        decoded: payload.get(name).cloned(),
+      }))
+      .collect()
+  }
+
+  /// how many members of the response disagree with the token
+  pub fn discrepancies(&self, token: &str) -> usize {
+    self
+      .compare(token)
+      .iter()
+      .filter(|claim| claim.is_discrepancy())
+      .count()
+  }
+}
+
+/// servers may return a single audience as a string where the token has an array of one
+fn same_value(introspected: &Value, decoded: &Value) -> bool {
+  match (introspected, decoded) {
+    (Value::Array(values), single) | (single, Value::Array(values)) if values.len() == 1 => {
+      &values[0] == single || introspected == decoded
+    }
+    _ => introspected == decoded,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+  use serde_json::json;
+
+  use super::*;
+
+  fn token(payload: Value) -> String {
+    format!(
+      "{}.{}.sig",
+      URL_SAFE_NO_PAD.encode(json!({"alg": "RS256"}).to_string()),
+      URL_SAFE_NO_PAD.encode(payload.to_string())
+    )
+  }
+
+  #[test]
+  fn test_compare_introspection() {
+    let introspection: Introspection = serde_json::from_value(json!({
+      "active": true,
+      "sub": "alice",
+      "aud": "api",
+      "exp": 1_700_000_000,
+      "client_id": "my-cli"
+    }))
+    .unwrap();
+    let token = token(json!({"sub": "alice", "aud": ["api"], "exp": 1_800_000_000}));
+    let claims = introspection.compare(&token);
+    let compared: Vec<(&str, bool)> = claims
+      .iter()
+      .map(|claim| (claim.name.as_str(), claim.is_discrepancy()))
+      .collect();
+    assert_eq!(
+      compared,
+      [
+        ("active", false),
+        ("aud", false),
+        ("client_id", false),
+        ("exp", true),
+        ("sub", false)
+      ]
+    );
+    assert_eq!(introspection.discrepancies(&token), 1);
+
+    let revoked: Introspection = serde_json::from_str(r#"{"active": false}"#).unwrap();
+    assert_eq!(revoked.discrepancies(&token), 1);
+    assert_eq!(revoked.compare("opaque-token").len(), 1);
+    let opaque = introspection.compare("opaque-token");
+    assert!(opaque.iter().all(|claim| claim.decoded.is_none()));
+    assert_eq!(introspection.discrepancies("opaque-token"), 0);
+  }
+}
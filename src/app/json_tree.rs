@@ -0,0 +1,179 @@
+use std::collections::BTreeSet;
+
+use serde_json::Value;
+
+use super::models::StatefulTable;
+
+/// visible node of the JSON tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeRow {
+  /// JSON pointer of the node, e.g. `/realm_access/roles`
+  pub path: String,
+  pub depth: usize,
+  /// key or index of the node followed by its value, or the size of objects and arrays
+  pub label: String,
+  /// whether the children of non-empty objects and arrays are shown, none for other values
+  pub expanded: Option<bool>,
+}
+
+/// collapsible tree of the decoded claims, nested objects and arrays are expanded until collapsed
+#[derive(Debug, Default)]
+pub struct JsonTree {
+  value: Value,
+  /// paths of the collapsed nodes, kept when the token changes
+  collapsed: BTreeSet<String>,
+  pub rows: StatefulTable<TreeRow>,
+}
+
+impl JsonTree {
+  /// rebuild the tree from the pretty printed claims, text that isn't JSON has no rows
+  pub fn update(&mut self, json: &str) {
+    self.value = serde_json::from_str(json).unwrap_or_default();
+    self.build_rows();
+  }
+
+  /// expand or collapse the selected node
+  pub fn toggle_selected(&mut self) {
+    let Some(row) = self.selected() else {
+      return;
+    };
+    if row.expanded.is_some() {
+      let path = row.path.clone();
+      if !self.collapsed.remove(&path) {
+        self.collapsed.insert(path);
+      }
+      self.build_rows();
+    }
+  }
+
+  pub fn selected(&self) -> Option<&TreeRow> {
+    self
+      .rows
+      .state
+      .selected()
+      .and_then(|i| self.rows.items.get(i))
+  }
+
+  /// top-level claim of the selected node
+  pub fn selected_claim(&self) -> Option<String> {
+    let row = self.selected()?;
+    let key = row.path.split('/').nth(1)?;
+    Some(key.replace("~1", "/").replace("~0", "~"))
+  }
+
+  fn build_rows(&mut self) {
+    let mut rows = vec![];
+    match &self.value {
+      Value::Null => {}
+      Value::Object(_) | Value::Array(_) => self.push_children(&mut rows, &self.value, "", 0),
+      value => rows.push(TreeRow {
+        path: String::new(),
+        depth: 0,
+        label: value.to_string(),
+        expanded: None,
+      }),
+    }
+    self.rows.set_items(rows);
+  }
+
+  fn push_children(&self, rows: &mut Vec<TreeRow>, value: &Value, path: &str, depth: usize) {
+    let children: Vec<(String, &Value)> = match value {
+      Value::Object(map) => map.iter().map(|(k, v)| (k.clone(), v)).collect(),
+      Value::Array(items) => items
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (i.to_string(), v))
+        .collect(),
+      _ => vec![],
+    };
+    for (key, child) in children {
+      let path = format!("{path}/{}", key.replace('~', "~0").replace('/', "~1"));
+      let (label, expanded) = match child {
+        Value::Object(map) if !map.is_empty() => (format!("{key} {{{}}}", map.len()), true),
+        Value::Array(items) if !items.is_empty() => (format!("{key} [{}]", items.len()), true),
+        _ => (format!("{key}: {child}"), false),
+      };
+      let expanded = expanded.then(|| !self.collapsed.contains(&path));
+      rows.push(TreeRow {
+        path: path.clone(),
+        depth,
+        label,
+        expanded,
+      });
+      if expanded == Some(true) {
+        self.push_children(rows, child, &path, depth + 1);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const CLAIMS: &str = r#"{
+  "realm_access": {"roles": ["admin", "user"]},
+  "scope": [],
+  "sub": "1234"
+}"#;
+
+  fn labels(tree: &JsonTree) -> Vec<(usize, &str, Option<bool>)> {
+    tree
+      .rows
+      .items
+      .iter()
+      .map(|row| (row.depth, row.label.as_str(), row.expanded))
+      .collect()
+  }
+
+  #[test]
+  fn test_json_tree_rows() {
+    let mut tree = JsonTree::default();
+    tree.update(CLAIMS);
+    assert_eq!(
+      labels(&tree),
+      [
+        (0, "realm_access {1}", Some(true)),
+        (1, "roles [2]", Some(true)),
+        (2, "0: \"admin\"", None),
+        (2, "1: \"user\"", None),
+        (0, "scope: []", None),
+        (0, "sub: \"1234\"", None),
+      ]
+    );
+    assert_eq!(tree.rows.items[1].path, "/realm_access/roles");
+    assert_eq!(tree.selected().unwrap().label, "realm_access {1}");
+    tree.rows.state.select(Some(2));
+    assert_eq!(tree.selected_claim(), Some("realm_access".into()));
+
+    tree.update("not JSON");
+    assert!(tree.rows.items.is_empty());
+    tree.update("\"a string\"");
+    assert_eq!(labels(&tree), [(0, "\"a string\"", None)]);
+  }
+
+  #[test]
+  fn test_json_tree_toggle_selected() {
+    let mut tree = JsonTree::default();
+    tree.update(CLAIMS);
+    tree.rows.state.select(Some(1));
+    tree.toggle_selected();
+    assert_eq!(tree.rows.items.len(), 4);
+    assert_eq!(tree.rows.items[1].expanded, Some(false));
+
+    // collapsed nodes stay collapsed when the claims change
+    tree.update(&CLAIMS.replace("1234", "5678"));
+    assert_eq!(tree.rows.items.len(), 4);
+
+    tree.rows.state.select(Some(0));
+    tree.toggle_selected();
+    assert_eq!(labels(&tree)[1], (0, "scope: []", None));
+    tree.toggle_selected();
+    assert_eq!(tree.rows.items.len(), 4);
+
+    // leaves can't be toggled
+    tree.rows.state.select(Some(3));
+    tree.toggle_selected();
+    assert_eq!(tree.rows.items.len(), 4);
+  }
+}
@@ -1,10 +1,37 @@
+use std::{
+  fs,
+  io::{self, Read},
+  path::{Path, PathBuf},
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
 use jsonwebtoken::{errors::Error, Algorithm, EncodingKey, Header};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
 
 use super::{
+  azure_kv::{encode_azure_kv, key_id},
+  dispatch::NetworkRequest,
+  encrypted_key::{decrypt_key, PASSPHRASE_REQUIRED, WRONG_PASSPHRASE},
+  es256k::{encode_es256k, ES256K},
+  gcp_kms::{encode_gcp_kms, key_version},
+  json_lines,
   jwt_decoder::Payload,
-  models::{BlockState, ScrollableTxt},
-  utils::{get_secret_from_file_or_input, JWTError, JWTResult, SecretType},
-  ActiveBlock, App, Route, RouteId, TextAreaInput, TextInput,
+  keys::{jwk_thumbprint, jwk_to_pem, parse_key, public_jwk},
+  models::{BlockState, ScrollableTxt, Splits},
+  relaxed_json::{syntax_error, to_strict_json, SyntaxError},
+  secrets::is_remote_key,
+  unsecured::{check_unsecured, encode_unsecured, INSECURE_NONE_DISABLED},
+  utils::{
+    decode_segment, encode_json, encoding_key_from_bytes, get_secret_from_file_or_input, JWTError,
+    JWTResult, SecretType,
+  },
+  validation::{check_hmac_secret, format_duration, parse_duration, Finding},
+  vault_transit::{encode_vault_transit, transit_key},
+  x509::{certificate_headers, certificate_jwk, parse_certificates},
+  ActiveBlock, App, ConfirmAction, InputMode, PromptAction, Route, RouteId, TextAreaInput,
+  TextInput,
 };
 
 #[derive(Default)]
@@ -15,6 +42,340 @@ pub struct Encoder<'a> {
   pub secret: TextInput,
   pub signature_verified: bool,
   pub blocks: BlockState,
+  /// use the compact layout for the encoder view
+  pub compact: bool,
+  /// sizes of the panes of the encoder view
+  pub splits: Splits,
+  /// passphrase of an encrypted private key secret
+  pub passphrase: Option<String>,
+  /// the secret the passphrase was last asked for, to not ask again on every tick
+  passphrase_requested_for: Option<String>,
+  /// warnings about the signing secret
+  pub findings: Vec<Finding>,
+  /// allow encoding unsigned `alg: none` tokens for testing
+  pub insecure_none: bool,
+  /// claims set from the current time or generated on every encode
+  pub managed_claims: ManagedClaims,
+  /// set the `kid` of the header from the signing key
+  pub auto_kid: bool,
+  /// the secret the `kid` was last computed for, to not read the key on every tick
+  kid_cache: Option<(String, Option<String>)>,
+  /// the secret the certificate headers were last offered for, to not ask again on every tick
+  certificate_offered_for: Option<String>,
+  /// show the decoded header and payload of the encoded token
+  pub preview: bool,
+  /// the last token signed by a remote key in the background, none while the request runs
+  pub remote_token: Option<(RemoteSigning, Option<JWTResult<String>>)>,
+}
+
+/// a token to sign with the key of a remote service, requested in the background so the UI doesn't
+/// wait for the service
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteSigning {
+  pub secret: String,
+  pub header: Value,
+  pub payload: Payload,
+  /// the payload as typed, before the relative and managed claims are set
+  pub typed_payload: String,
+  pub managed_claims: ManagedClaims,
+}
+
+impl RemoteSigning {
+  /// whether the token was signed for the same header, typed claims and key. The generated claims
+  /// (e.g. `iat` or `jti`) change on every encode and would send a billable request every time
+  fn same_inputs(&self, other: &RemoteSigning) -> bool {
+    (
+      &self.secret,
+      &self.header,
+      &self.typed_payload,
+      self.managed_claims,
+    ) == (
+      &other.secret,
+      &other.header,
+      &other.typed_payload,
+      other.managed_claims,
+    )
+  }
+}
+
+/// claims set on every encode, time claims from the current time instead of editing epochs and
+/// fresh `jti` and `nonce` values to test replay protection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ManagedClaims {
+  /// set `iat` to now
+  pub iat: bool,
+  /// set `exp` to now plus the lifetime in seconds
+  pub exp: Option<i64>,
+  /// set `nbf` to now plus the offset in seconds
+  pub nbf: Option<i64>,
+  /// set `jti` to a new UUIDv4
+  pub jti: bool,
+  /// set `nonce` to a new random value
+  pub nonce: bool,
+}
+
+impl ManagedClaims {
+  /// summary shown in the payload block title, none when no claim is managed
+  pub fn describe(&self) -> Option<String> {
+    let relative = |secs: i64| match secs {
+      0 => "now".to_string(),
+      secs if secs > 0 => format!("now+{}", format_duration(secs)),
+      secs => format!("now{}", format_duration(secs)),
+    };
+    let mut parts = vec![];
+    if self.iat {
+      parts.push("iat: now".to_string());
+    }
+    if let Some(exp) = self.exp {
+      parts.push(format!("exp: {}", relative(exp)));
+    }
+    if let Some(nbf) = self.nbf {
+      parts.push(format!("nbf: {}", relative(nbf)));
+    }
+    if self.jti {
+      parts.push("jti: uuid".to_string());
+    }
+    if self.nonce {
+      parts.push("nonce: random".to_string());
+    }
+    (!parts.is_empty()).then(|| parts.join(", "))
+  }
+
+  /// the payload with relative time claims (e.g. `"exp": "+15m"`) expanded and the managed
+  /// claims set. Payloads that aren't JSON objects are returned as is
+  fn apply(&self, payload: &str, now: i64) -> JWTResult<String> {
+    let Ok(Value::Object(mut claims)) = serde_json::from_str::<Value>(payload) else {
+      return Ok(payload.into());
+    };
+    let mut changed = false;
+    for claim in TIME_CLAIMS {
+      let Some(value) = claims.get(claim) else {
+        continue;
+      };
+      if let Some(offset) = relative_claim(claim, value)? {
+        claims.insert(claim.into(), (now + offset).into());
+        changed = true;
+      }
+    }
+    for (claim, offset) in [
+      ("iat", self.iat.then_some(0)),
+      ("exp", self.exp),
+      ("nbf", self.nbf),
+    ] {
+      if let Some(offset) = offset {
+        claims.insert(claim.into(), (now + offset).into());
+        changed = true;
+      }
+    }
+    if self.jti {
+      claims.insert("jti".into(), new_uuid().into());
+      changed = true;
+    }
+    if self.nonce {
+      claims.insert("nonce".into(), new_nonce().into());
+      changed = true;
+    }
+    Ok(match changed {
+      true => Value::Object(claims).to_string(),
+      false => payload.into(),
+    })
+  }
+}
+
+/// a random (version 4) UUID
+fn new_uuid() -> String {
+  let mut bytes: [u8; 16] = rand::random();
+  bytes[6] = (bytes[6] & 0x0f) | 0x40;
+  bytes[8] = (bytes[8] & 0x3f) | 0x80;
+  let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+  format!(
+    "{}-{}-{}-{}-{}",
+    &hex[..8],
+    &hex[8..12],
+    &hex[12..16],
+    &hex[16..20],
+    &hex[20..]
+  )
+}
+
+/// a random base64url encoded 128 bit nonce
+fn new_nonce() -> String {
+  URL_SAFE_NO_PAD.encode(rand::random::<[u8; 16]>())
+}
+
+/// registered claims holding timestamps
+const TIME_CLAIMS: [&str; 3] = ["iat", "exp", "nbf"];
+
+/// the offset of a relative time claim like `"+15m"`, `"-5 min"` or `"+0s"`, none for other values
+fn relative_claim(claim: &str, value: &Value) -> JWTResult<Option<i64>> {
+  match value.as_str() {
+    Some(duration) if duration.starts_with(['+', '-']) => {
+      parse_duration(duration).map(Some).ok_or_else(|| {
+        JWTError::Internal(format!(
+          "Invalid relative time {duration:?} for {claim}, use e.g. \"+15m\" or \"-5 min\""
+        ))
+      })
+    }
+    _ => Ok(None),
+  }
+}
+
+/// the claims of a JSON file (`@claims.json`), of STDIN (`@-`) or of the JSON text itself,
+/// pretty printed as lines of the payload textarea
+pub fn read_payload(source: &str) -> JWTResult<Vec<String>> {
+  let (origin, content) = match source.trim().strip_prefix('@') {
+    Some("-") => {
+      let mut content = String::new();
+      io::stdin()
+        .read_to_string(&mut content)
+        .map_err(|e| JWTError::Internal(format!("Unable to read the payload from STDIN: {e}")))?;
+      ("STDIN".to_string(), content)
+    }
+    Some(path) => (
+      path.to_string(),
+      fs::read_to_string(path)
+        .map_err(|e| JWTError::Internal(format!("Unable to read payload file {path}: {e}")))?,
+    ),
+    None => ("the payload".to_string(), source.to_string()),
+  };
+  let claims: Payload = serde_json::from_str(&content)
+    .map_err(|e| JWTError::Internal(format!("Invalid claims in {origin}: {e}")))?;
+  let pretty =
+    serde_json::to_string_pretty(&claims).map_err(|e| JWTError::Internal(e.to_string()))?;
+  Ok(pretty.lines().map(String::from).collect())
+}
+
+/// re-indent the JSON text of a header or payload textarea, keeping the order of the keys and the
+/// values as they are written
+pub fn format_json(json: &str) -> Result<Vec<String>, serde_json::Error> {
+  serde_json::from_str::<Value>(json)?;
+  let mut out = String::new();
+  let mut indent = 0;
+  let (mut in_string, mut escaped) = (false, false);
+  let newline = |out: &mut String, indent: usize| {
+    out.push('\n');
+    out.push_str(&"  ".repeat(indent));
+  };
+  let mut chars = json.chars().peekable();
+  while let Some(c) = chars.next() {
+    if in_string {
+      out.push(c);
+      match c {
+        _ if escaped => escaped = false,
+        '\\' => escaped = true,
+        '"' => in_string = false,
+        _ => {}
+      }
+      continue;
+    }
+    match c {
+      '"' => {
+        in_string = true;
+        out.push(c);
+      }
+      '{' | '[' => {
+        out.push(c);
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if let Some(close) = chars.next_if(|next| matches!((c, next), ('{', '}') | ('[', ']'))) {
+          out.push(close);
+        } else {
+          indent += 1;
+          newline(&mut out, indent);
+        }
+      }
+      '}' | ']' => {
+        indent = indent.saturating_sub(1);
+        newline(&mut out, indent);
+        out.push(c);
+      }
+      ',' => {
+        out.push(c);
+        newline(&mut out, indent);
+      }
+      ':' => out.push_str(": "),
+      _ if c.is_whitespace() => {}
+      _ => out.push(c),
+    }
+  }
+  Ok(out.lines().map(String::from).collect())
+}
+
+/// the syntax error of the relaxed JSON text of a header or payload textarea, empty text has none
+/// as there is nothing to point to
+pub fn json_syntax_error(text: &str) -> Option<SyntaxError> {
+  if text.trim().is_empty() {
+    return None;
+  }
+  serde_json::from_str::<Value>(&to_strict_json(text))
+    .err()
+    .map(|e| syntax_error(text, &e))
+}
+
+/// parse a `KEY=VALUE` argument of the `encode` command, values that are valid JSON (numbers,
+/// booleans, arrays, objects) keep their type, anything else is a string
+pub fn parse_key_value(arg: &str) -> Result<(String, Value), String> {
+  let (key, value) = arg
+    .split_once('=')
+    .ok_or_else(|| format!("invalid KEY=VALUE: no `=` found in {arg:?}"))?;
+  let value = serde_json::from_str(value).unwrap_or_else(|_| Value::from(value));
+  Ok((key.to_string(), value))
+}
+
+/// options of the `encode` command, signing a token without the UI
+#[derive(Debug, Default)]
+pub struct EncodeOptions {
+  pub alg: String,
+  pub secret: String,
+  /// extra header parameters, e.g. `kid`
+  pub header: Vec<(String, Value)>,
+  /// the base claims, as JSON text, `@file` or `@-`
+  pub payload: Option<String>,
+  /// claims set on top of the payload
+  pub claims: Vec<(String, Value)>,
+  /// `exp` as timestamp or relative to now (e.g. `+1h`)
+  pub exp: Option<String>,
+  /// `nbf` as timestamp or relative to now (e.g. `-5m`)
+  pub nbf: Option<String>,
+  /// `iat`, `jti` and `nonce` set on every encode
+  pub managed_claims: ManagedClaims,
+  pub passphrase: Option<String>,
+  pub insecure_none: bool,
+}
+
+/// encode a token with the options of the `encode` command
+pub fn encode_with_options(options: &EncodeOptions, now: i64) -> JWTResult<String> {
+  let mut header = serde_json::Map::new();
+  header.insert("alg".into(), options.alg.as_str().into());
+  header.insert("typ".into(), "JWT".into());
+  header.extend(options.header.iter().cloned());
+
+  let mut claims = match &options.payload {
+    Some(payload) => serde_json::from_str(&read_payload(payload)?.join("\n"))
+      .map_err(|e| JWTError::Internal(e.to_string()))?,
+    None => serde_json::Map::new(),
+  };
+  claims.extend(options.claims.iter().cloned());
+  for (claim, value) in [("exp", &options.exp), ("nbf", &options.nbf)] {
+    if let Some(value) = value {
+      let value = match value.parse::<i64>() {
+        Ok(timestamp) => timestamp.into(),
+        Err(_) if value.starts_with(['+', '-']) => value.as_str().into(),
+        Err(_) => format!("+{value}").into(),
+      };
+      claims.insert(claim.into(), value);
+    }
+  }
+
+  encode_token(&EncodeArgs {
+    header: Value::Object(header).to_string(),
+    payload: options
+      .managed_claims
+      .apply(&Value::Object(claims).to_string(), now)?,
+    secret: options.secret.clone(),
+    passphrase: options.passphrase.clone(),
+    insecure_none: options.insecure_none,
+  })
 }
 
 impl Encoder<'_> {
@@ -28,7 +389,7 @@ impl Encoder<'_> {
 
     Self {
       header,
-      secret: TextInput::new(secret),
+      secret: TextInput::new_masked(secret),
       blocks: BlockState::new(vec![
         Route {
           id: RouteId::Encoder,
@@ -47,9 +408,129 @@ impl Encoder<'_> {
           active_block: ActiveBlock::EncoderToken,
         },
       ]),
+      auto_kid: true,
       ..Encoder::default()
     }
   }
+
+  /// pretty printed header and payload of the encoded token, to see what was signed after the
+  /// relative time claims, `kid` and generated claims were set
+  pub fn decoded_preview(&self) -> Vec<String> {
+    let token = self.encoded.get_txt();
+    let mut segments = token.split('.');
+    let (Some(header), Some(payload)) = (segments.next(), segments.next()) else {
+      return vec![];
+    };
+    [header, payload]
+      .iter()
+      .filter_map(|segment| decode_segment::<Value>(segment).ok())
+      .flat_map(|value| json_lines(&value))
+      .collect()
+  }
+
+  /// strict JSON of the header, which may be typed as relaxed JSON
+  pub fn header_json(&self) -> String {
+    to_strict_json(&self.header.input.lines().join("\n"))
+  }
+
+  /// strict JSON of the payload, which may be typed as relaxed JSON
+  pub fn payload_json(&self) -> String {
+    to_strict_json(&self.payload.input.lines().join("\n"))
+  }
+
+  /// insert the `x5c` chain and `x5t#S256` thumbprint of the certificate into the header, after
+  /// checking that the certificate is the one of the signing key
+  pub fn add_certificate_headers(&mut self, path: &Path, secret: &str) -> JWTResult<()> {
+    let data = fs::read(path).map_err(|e| {
+      JWTError::Internal(format!(
+        "Unable to read certificate {}: {e}",
+        path.display()
+      ))
+    })?;
+    let chain = parse_certificates(&data)?;
+    let (x5c, x5t) = certificate_headers(&chain)?;
+    if let Some(key) = signing_jwk(secret) {
+      let thumbprint = |jwk: &Value| jwk_thumbprint(&public_jwk(jwk)).ok();
+      if thumbprint(&key) != thumbprint(&certificate_jwk(&chain[0])?) {
+        return Err(JWTError::Internal(format!(
+          "The certificate {} is not the certificate of the signing key",
+          path.display()
+        )));
+      }
+    }
+    let mut header = serde_json::from_str::<Value>(&self.header_json())
+      .map_err(|e| JWTError::Internal(format!("Error parsing header: {e}")))?;
+    let members = header
+      .as_object_mut()
+      .ok_or_else(|| JWTError::Internal("The header is not a JSON object".into()))?;
+    members.insert("x5c".into(), x5c.into());
+    members.insert("x5t#S256".into(), x5t.into());
+    self.header = TextAreaInput::new(json_lines(&header));
+    Ok(())
+  }
+
+  /// insert or refresh the `kid` of the header with the one of the signing key. HMAC tokens and
+  /// headers being edited are left alone
+  fn sync_kid(&mut self, secret: &str) {
+    if !self.auto_kid || self.header.input_mode == InputMode::Editing {
+      return;
+    }
+    if self.kid_cache.as_ref().map(|(s, _)| s.as_str()) != Some(secret) {
+      self.kid_cache = Some((secret.to_string(), signing_kid(secret)));
+    }
+    let Some((_, Some(kid))) = &self.kid_cache else {
+      return;
+    };
+    let Ok(Value::Object(mut header)) = serde_json::from_str::<Value>(&self.header_json()) else {
+      return;
+    };
+    let hmac = header
+      .get("alg")
+      .and_then(Value::as_str)
+      .is_none_or(|alg| alg.starts_with("HS"));
+    if hmac || header.get("kid").and_then(Value::as_str) == Some(kid) {
+      return;
+    }
+    header.insert("kid".into(), kid.as_str().into());
+    self.header = TextAreaInput::new(json_lines(&Value::Object(header)));
+  }
+}
+
+/// the key file of the secret as JWK: a JWK, the only key of a JWKS or a PEM/DER key
+fn signing_jwk(secret: &str) -> Option<Value> {
+  let content = fs::read(secret.strip_prefix('@')?).ok()?;
+  match serde_json::from_slice::<Value>(&content) {
+    Ok(Value::Object(mut jwks)) if jwks.contains_key("keys") => match jwks.remove("keys") {
+      Some(Value::Array(mut keys)) if keys.len() == 1 => Some(keys.remove(0)),
+      _ => None,
+    },
+    Ok(jwk) => Some(jwk),
+    Err(_) => parse_key(&content).ok(),
+  }
+}
+
+/// the `kid` of the key file of the secret: the `kid` of a JWK or of the only key of a JWKS, or
+/// the RFC 7638 thumbprint of PEM and DER keys and JWKs without `kid`
+fn signing_kid(secret: &str) -> Option<String> {
+  let jwk = signing_jwk(secret)?;
+  match jwk["kid"].as_str() {
+    Some(kid) => Some(kid.into()),
+    None => jwk_thumbprint(&public_jwk(&jwk)).ok(),
+  }
+}
+
+/// the certificate of the key file of the secret: `key.crt`, `key.cert.pem` or `key.crt.pem`
+/// next to `key.pem`, or the key file itself when it also holds the certificate chain
+fn certificate_for_key(secret: &str) -> Option<PathBuf> {
+  let key = Path::new(secret.strip_prefix('@')?);
+  if fs::read_to_string(key).is_ok_and(|pem| pem.contains("-----BEGIN CERTIFICATE-----")) {
+    return Some(key.to_path_buf());
+  }
+  let stem = key.file_stem()?.to_string_lossy();
+  ["crt", "cert.pem", "crt.pem"]
+    .iter()
+    .map(|ext| key.with_file_name(format!("{stem}.{ext}")))
+    .find(|path| path.is_file())
 }
 
 #[derive(Debug)]
@@ -59,29 +540,172 @@ struct EncodeArgs {
   pub payload: String,
   /// The secret to sign the JWT with.
   pub secret: String,
+  /// The passphrase of an encrypted private key
+  pub passphrase: Option<String>,
+  /// allow `alg: none` tokens without signature
+  pub insecure_none: bool,
 }
 
 pub fn encode_jwt_token(app: &mut App) {
-  let out = encode_token(&EncodeArgs {
-    header: app.data.encoder.header.input.lines().join("\n"),
-    payload: app.data.encoder.payload.input.lines().join("\n"),
-    secret: app.data.encoder.secret.input.value().to_string(),
-  });
-
+  app.fetch_queued_secrets();
+  let secret = match app
+    .secrets
+    .resolve_for_signing(app.data.encoder.secret.input.value())
+  {
+    Ok(secret) => secret.into_owned(),
+    Err(e) => {
+      app.handle_error(e);
+      return;
+    }
+  };
+  app.data.encoder.sync_kid(&secret);
+  offer_certificate_headers(app, &secret);
+  let typed_payload = app.data.encoder.payload_json();
+  let payload = app
+    .data
+    .encoder
+    .managed_claims
+    .apply(&typed_payload, Utc::now().timestamp());
+  let payload = match payload {
+    Ok(payload) => payload,
+    Err(e) => {
+      app.handle_error(e);
+      return;
+    }
+  };
+  let args = EncodeArgs {
+    header: app.data.encoder.header_json(),
+    payload,
+    secret,
+    passphrase: app.data.encoder.passphrase.clone(),
+    insecure_none: app.data.encoder.insecure_none,
+  };
+  let out = match remote_signing(&args, typed_payload, app.data.encoder.managed_claims) {
+    Some(signing) => sign_in_background(app, signing),
+    None => encode_token(&args),
+  };
+
+  app.data.encoder.findings = match (&out, serde_json::from_str::<Header>(&args.header)) {
+    (Ok(_), Ok(header)) => check_hmac_secret(&header.alg, &args.secret),
+    (Ok(token), Err(_)) => check_unsecured(token).into_iter().collect(),
+    _ => vec![],
+  };
   match out {
     Ok(token) => {
       if token != app.data.encoder.encoded.get_txt() {
+        // keep the scroll position as tokens with generated claims change on every tick
+        let offset = app.data.encoder.encoded.offset;
         app.data.encoder.encoded = ScrollableTxt::new(token);
+        app.data.encoder.encoded.offset = offset;
         app.data.encoder.signature_verified = true;
       }
       app.data.error = String::new();
     }
+    Err(JWTError::Internal(e)) if e == PASSPHRASE_REQUIRED || e == WRONG_PASSPHRASE => {
+      request_passphrase(app, &e);
+      app.handle_error(JWTError::Internal(e));
+    }
     Err(e) => {
       app.handle_error(e);
     }
   }
 }
 
+/// the token to sign in the background when the secret is a key of Cloud KMS, Key Vault or Vault
+/// transit. Invalid inputs are reported by [`encode_token`]
+fn remote_signing(
+  args: &EncodeArgs,
+  typed_payload: String,
+  managed_claims: ManagedClaims,
+) -> Option<RemoteSigning> {
+  if !is_remote_key(&args.secret) {
+    return None;
+  }
+  let header: Value = serde_json::from_str(&args.header).ok()?;
+  let alg_none = header["alg"]
+    .as_str()
+    .is_some_and(|alg| alg.eq_ignore_ascii_case("none"));
+  if alg_none {
+    return None;
+  }
+  Some(RemoteSigning {
+    secret: args.secret.clone(),
+    header,
+    payload: serde_json::from_str(&args.payload).ok()?,
+    typed_payload,
+    managed_claims,
+  })
+}
+
+/// the token signed by the remote key, requested again when the header, claims or key change but
+/// not when only the generated claims do. One request runs at a time and the current token is
+/// kept until it's done
+fn sign_in_background(app: &mut App, signing: RemoteSigning) -> JWTResult<String> {
+  let encoder = &app.data.encoder;
+  match &encoder.remote_token {
+    Some((requested, Some(token))) if requested.same_inputs(&signing) => return token.clone(),
+    Some((_, None)) => return Ok(encoder.encoded.get_txt().into()),
+    _ => {}
+  }
+  // like `cmd:` secrets, a partially typed key isn't sent to the service
+  if encoder.secret.input_mode == InputMode::Editing {
+    return Err(JWTError::Internal(
+      "Stop editing the secret to sign the token with the key".into(),
+    ));
+  }
+  // nor a restored key the user didn't confirm
+  if !app.secrets.is_allowed(&signing.secret) {
+    return Err(JWTError::Internal(
+      "Confirm or edit the secret to sign the token with the key".into(),
+    ));
+  }
+  app.data.encoder.remote_token = Some((signing.clone(), None));
+  app.dispatch(NetworkRequest::Sign(signing));
+  // the request runs inline when there is no network runtime
+  match &app.data.encoder.remote_token {
+    Some((_, Some(token))) => token.clone(),
+    _ => Ok(app.data.encoder.encoded.get_txt().into()),
+  }
+}
+
+/// offer to add the `x5c` and `x5t#S256` headers when the signing key has a certificate, once per
+/// secret
+fn offer_certificate_headers(app: &mut App, secret: &str) {
+  let encoder = &mut app.data.encoder;
+  if app.confirm.is_some()
+    || app.prompt.is_some()
+    || encoder.certificate_offered_for.as_deref() == Some(secret)
+  {
+    return;
+  }
+  encoder.certificate_offered_for = Some(secret.to_string());
+  let has_x5c = serde_json::from_str::<Value>(&encoder.header_json())
+    .is_ok_and(|header| header.get("x5c").is_some());
+  if let (false, Some(path)) = (has_x5c, certificate_for_key(secret)) {
+    app.request_confirmation(
+      format!(
+        "Add the x5c and x5t#S256 headers from the certificate {}?",
+        path.display()
+      ),
+      ConfirmAction::CertificateHeaders(path),
+    );
+  }
+}
+
+/// ask for the passphrase of the encrypted private key, once per secret unless it was wrong
+fn request_passphrase(app: &mut App, reason: &str) {
+  let secret = app.data.encoder.secret.input.value().to_string();
+  if reason == WRONG_PASSPHRASE {
+    app.data.encoder.passphrase = None;
+    app.data.encoder.passphrase_requested_for = None;
+  }
+  if app.prompt.is_some() || app.data.encoder.passphrase_requested_for.as_ref() == Some(&secret) {
+    return;
+  }
+  app.data.encoder.passphrase_requested_for = Some(secret);
+  app.request_prompt(format!("{reason}:"), true, PromptAction::Passphrase);
+}
+
 fn encode_token(args: &EncodeArgs) -> JWTResult<String> {
   if args.header.is_empty() {
     return Err(String::from("Header should not be empty").into());
@@ -89,73 +713,147 @@ fn encode_token(args: &EncodeArgs) -> JWTResult<String> {
   if args.payload.is_empty() {
     return Err(String::from("Payload should not be empty").into());
   }
-  let header: Result<Header, serde_json::Error> = serde_json::from_str(&args.header);
-  match header {
-    Ok(header) => {
-      let alg = header.alg;
-
-      let payload: Result<Payload, serde_json::Error> = serde_json::from_str(&args.payload);
-      match payload {
-        Ok(payload) => {
-          let encoding_key = encoding_key_from_secret(&alg, &args.secret)?;
-          Ok(jsonwebtoken::encode(&header, &payload, &encoding_key)?)
-        }
-        Err(e) => Err(format!("Error parsing payload: {:}", e).into()),
+  // `ES256K` and `none` are not supported by `jsonwebtoken`, and the keys of Cloud KMS and Key
+  // Vault sign remotely
+  if let Ok(header) = serde_json::from_str::<Value>(&args.header) {
+    let alg_none = header["alg"]
+      .as_str()
+      .is_some_and(|alg| alg.eq_ignore_ascii_case("none"));
+    if alg_none && !args.insecure_none {
+      return Err(JWTError::Internal(INSECURE_NONE_DISABLED.into()));
+    }
+    if header["alg"] == ES256K || alg_none || is_remote_key(&args.secret) {
+      let payload: Payload = serde_json::from_str(&args.payload)
+        .map_err(|e| format!("Error parsing payload: {:}", e))?;
+      if alg_none {
+        return encode_unsecured(&header, &payload);
       }
+      if is_remote_key(&args.secret) {
+        return encode_remote(&header, &payload, &args.secret);
+      }
+      return encode_es256k(&header, &payload, &args.secret, args.passphrase.as_deref());
     }
-    Err(e) => Err(format!("Error parsing header: {:}", e).into()),
+  }
+  encode_json::<Payload>(&args.header, &args.payload, |header| {
+    encoding_key_from_secret(
+      &header.alg,
+      &args.secret,
+      args.passphrase.as_deref(),
+      header.kid.as_deref(),
+    )
+  })
+}
+
+/// sign the header and claims with the key of Cloud KMS, Key Vault or Vault transit, blocking
+/// until the service responds
+pub fn encode_remote(header: &Value, payload: &Payload, secret: &str) -> JWTResult<String> {
+  if let Some(name) = key_version(secret) {
+    return encode_gcp_kms(header, payload, name);
+  }
+  if let Some(id) = key_id(secret) {
+    return encode_azure_kv(header, payload, id);
+  }
+  match transit_key(secret) {
+    Some(key) => encode_vault_transit(header, payload, &key),
+    None => Err(JWTError::Internal(format!(
+      "{secret:?} is not a key of Cloud KMS, Key Vault or Vault transit"
+    ))),
   }
 }
 
-pub fn encoding_key_from_secret(alg: &Algorithm, secret_string: &str) -> JWTResult<EncodingKey> {
+/// the key signing the token, the `kid` of the header selects the key of a JWKS secret
+pub fn encoding_key_from_secret(
+  alg: &Algorithm,
+  secret_string: &str,
+  passphrase: Option<&str>,
+  kid: Option<&str>,
+) -> JWTResult<EncodingKey> {
   let (secret, file_type) = get_secret_from_file_or_input(alg, secret_string);
-  let secret = secret?;
+  let secret = match file_type {
+    SecretType::Pem => decrypt_key(secret?, passphrase)?,
+    SecretType::Jwks => return encoding_key_from_jwk(alg, &private_jwk(&secret?, kid)?),
+    _ => secret?,
+  };
 
-  match alg {
-    Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => match file_type {
-      SecretType::Plain => Ok(EncodingKey::from_secret(&secret)),
-      SecretType::B64 => {
-        EncodingKey::from_base64_secret(std::str::from_utf8(&secret)?).map_err(Error::into)
-      }
-      _ => Err(JWTError::Internal(format!(
-        "Invalid secret file type for {alg:?}"
-      ))),
-    },
-    Algorithm::RS256
-    | Algorithm::RS384
-    | Algorithm::RS512
-    | Algorithm::PS256
-    | Algorithm::PS384
-    | Algorithm::PS512 => match file_type {
-      SecretType::Pem => EncodingKey::from_rsa_pem(&secret).map_err(Error::into),
-      SecretType::Der => Ok(EncodingKey::from_rsa_der(&secret)),
-      _ => Err(JWTError::Internal(format!(
-        "Invalid secret file type for {alg:?}"
-      ))),
-    },
-    Algorithm::ES256 | Algorithm::ES384 => match file_type {
-      SecretType::Pem => EncodingKey::from_ec_pem(&secret).map_err(Error::into),
-      SecretType::Der => Ok(EncodingKey::from_ec_der(&secret)),
-      _ => Err(JWTError::Internal(format!(
-        "Invalid secret file type for {alg:?}"
-      ))),
-    },
-    Algorithm::EdDSA => match file_type {
-      SecretType::Pem => EncodingKey::from_ed_pem(&secret).map_err(Error::into),
-      SecretType::Der => Ok(EncodingKey::from_ed_der(&secret)),
-      _ => Err(JWTError::Internal(format!(
-        "Invalid secret file type for {alg:?}"
-      ))),
+  encoding_key_from_bytes(alg, &secret, file_type)
+}
+
+/// the private JWK of a JWK or JWKS secret. Keys of a JWKS are selected with the `kid` of the
+/// header, which can be left out when the JWKS has a single private key
+fn private_jwk(secret: &[u8], kid: Option<&str>) -> JWTResult<Value> {
+  let is_private = |jwk: &Value| jwk.get("d").is_some() || jwk["kty"] == "oct";
+  let jwk: Value = serde_json::from_slice(secret)
+    .map_err(|e| JWTError::Internal(format!("Invalid JWK secret: {e}")))?;
+  let Some(keys) = jwk.get("keys").and_then(Value::as_array) else {
+    return match is_private(&jwk) {
+      true => Ok(jwk),
+      false => Err(JWTError::Internal(
+        "The JWK is a public key, sign tokens with a private JWK".into(),
+      )),
+    };
+  };
+  let mut private = keys.iter().filter(|jwk| is_private(jwk));
+  match kid {
+    Some(kid) => private
+      .find(|jwk| jwk["kid"] == kid)
+      .cloned()
+      .ok_or_else(|| JWTError::Internal(format!("The JWKS has no private key with kid {kid:?}"))),
+    None => match (private.next(), private.next()) {
+      (Some(jwk), None) => Ok(jwk.clone()),
+      (None, _) => Err(JWTError::Internal("The JWKS has no private key".into())),
+      (Some(_), Some(_)) => Err(JWTError::Internal(
+        "The JWKS has several private keys, set the `kid` of the header to select one".into(),
+      )),
     },
   }
 }
 
+/// the encoding key of a private JWK, converted to PEM for asymmetric keys
+fn encoding_key_from_jwk(alg: &Algorithm, jwk: &Value) -> JWTResult<EncodingKey> {
+  let invalid_key = || {
+    JWTError::Internal(format!(
+      "The {} JWK can't sign {alg:?} tokens",
+      jwk["kty"].as_str().unwrap_or("unknown")
+    ))
+  };
+  match (alg, jwk["kty"].as_str()) {
+    (Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512, Some("oct")) => {
+      let secret = jwk["k"]
+        .as_str()
+        .and_then(|k| URL_SAFE_NO_PAD.decode(k).ok())
+        .ok_or_else(|| JWTError::Internal("The oct JWK has no valid `k` member".into()))?;
+      Ok(EncodingKey::from_secret(&secret))
+    }
+    (
+      Algorithm::RS256
+      | Algorithm::RS384
+      | Algorithm::RS512
+      | Algorithm::PS256
+      | Algorithm::PS384
+      | Algorithm::PS512,
+      Some("RSA"),
+    ) => EncodingKey::from_rsa_pem(jwk_to_pem(jwk)?.as_bytes()).map_err(Error::into),
+    (Algorithm::ES256 | Algorithm::ES384, Some("EC")) => {
+      EncodingKey::from_ec_pem(jwk_to_pem(jwk)?.as_bytes()).map_err(Error::into)
+    }
+    (Algorithm::EdDSA, Some("OKP")) => {
+      EncodingKey::from_ed_pem(jwk_to_pem(jwk)?.as_bytes()).map_err(Error::into)
+    }
+    _ => Err(invalid_key()),
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use tui_textarea::TextArea;
 
   use super::*;
-  use crate::app::jwt_decoder::{decode_token, DecodeArgs};
+  use crate::app::{
+    dispatch::NetworkResponse,
+    es256k::decode_es256k,
+    jwt_decoder::{decode_token, DecodeArgs, KeySource},
+    utils::decode_segment,
+  };
 
   #[test]
   fn test_encode_hmac_jwt_token_with_valid_payload_and_defaults() {
@@ -180,10 +878,11 @@ mod tests {
     assert!(app.data.encoder.signature_verified);
 
     let args = DecodeArgs {
-      jwt: app.data.encoder.encoded.get_txt(),
+      jwt: app.data.encoder.encoded.get_txt().into(),
       secret: String::from("secrets"),
       time_format_utc: false,
       ignore_exp: true,
+      key_source: KeySource::Secret,
     };
 
     let decoded = decode_token(&args).1;
@@ -191,9 +890,37 @@ mod tests {
     assert!(decoded.is_ok())
   }
 
+  #[test]
+  fn test_encode_hmac_jwt_token_with_weak_secret() {
+    let mut app = App::new(None, "changeme".into());
+    app.data.encoder.payload.input = vec![r#"{"sub": "1234567890"}"#].into();
+    encode_jwt_token(&mut app);
+
+    assert!(app.data.error.is_empty());
+    let checks: Vec<&str> = app
+      .data
+      .encoder
+      .findings
+      .iter()
+      .map(|f| f.message.as_str())
+      .collect();
+    assert_eq!(
+      checks,
+      vec![
+        "The HS256 secret is a well-known secret that is easily guessed",
+        "The HS256 secret is 8 bytes long, shorter than the 32 bytes required by RFC 7518"
+      ]
+    );
+
+    app.data.encoder.secret = TextInput::new("a-string-secret-at-least-256-bits-long".into());
+    encode_jwt_token(&mut app);
+    assert!(app.data.encoder.findings.is_empty());
+  }
+
   #[test]
   fn test_encode_rsa_jwt_token_with_valid_payload_and_header() {
     let mut app = App::new(None, "".into());
+    app.data.encoder.auto_kid = false;
 
     let header = vec!["{", r#"  "alg": "RS256","#, r#"  "typ": "JWT""#, "}"];
     app.data.encoder.header.input = header.clone().into();
@@ -220,10 +947,11 @@ mod tests {
 
     // decode the key and verify
     let args = DecodeArgs {
-      jwt: app.data.encoder.encoded.get_txt(),
+      jwt: app.data.encoder.encoded.get_txt().into(),
       secret: String::from("@./test_data/test_rsa_public_key.pem"),
       time_format_utc: false,
       ignore_exp: true,
+      key_source: KeySource::Secret,
     };
 
     let decoded = decode_token(&args).1.unwrap();
@@ -241,6 +969,7 @@ mod tests {
   #[test]
   fn test_encode_rsa_pss_jwt_token_with_valid_payload_and_header() {
     let mut app = App::new(None, "".into());
+    app.data.encoder.auto_kid = false;
 
     let header = vec!["{", r#"  "alg": "PS256","#, r#"  "typ": "JWT""#, "}"];
     app.data.encoder.header.input = header.clone().into();
@@ -263,10 +992,11 @@ mod tests {
 
     // decode the key and verify
     let args = DecodeArgs {
-      jwt: app.data.encoder.encoded.get_txt(),
+      jwt: app.data.encoder.encoded.get_txt().into(),
       secret: String::from("@./test_data/test_rsa_public_key.der"),
       time_format_utc: false,
       ignore_exp: true,
+      key_source: KeySource::Secret,
     };
 
     let decoded = decode_token(&args).1.unwrap();
@@ -284,6 +1014,7 @@ mod tests {
   #[test]
   fn test_encode_ecdsa_jwt_token_with_valid_payload_and_header() {
     let mut app = App::new(None, "".into());
+    app.data.encoder.auto_kid = false;
 
     let header = vec!["{", r#"  "alg": "ES256","#, r#"  "typ": "JWT""#, "}"];
     app.data.encoder.header.input = header.clone().into();
@@ -306,10 +1037,11 @@ mod tests {
 
     // decode the key and verify
     let args = DecodeArgs {
-      jwt: app.data.encoder.encoded.get_txt(),
+      jwt: app.data.encoder.encoded.get_txt().into(),
       secret: String::from("@./test_data/test_ecdsa_public_key.pk8"),
       time_format_utc: false,
       ignore_exp: true,
+      key_source: KeySource::Secret,
     };
 
     let decoded = decode_token(&args).1.unwrap();
@@ -327,6 +1059,7 @@ mod tests {
   #[test]
   fn test_encode_eddsa_jwt_token_with_valid_payload_and_header() {
     let mut app = App::new(None, "".into());
+    app.data.encoder.auto_kid = false;
 
     let header = vec!["{", r#"  "alg": "EdDSA","#, r#"  "typ": "JWT""#, "}"];
     app.data.encoder.header.input = header.clone().into();
@@ -349,10 +1082,11 @@ mod tests {
 
     // decode the key and verify
     let args = DecodeArgs {
-      jwt: app.data.encoder.encoded.get_txt(),
+      jwt: app.data.encoder.encoded.get_txt().into(),
       secret: String::from("@./test_data/test_eddsa_public_key.pem"),
       time_format_utc: false,
       ignore_exp: true,
+      key_source: KeySource::Secret,
     };
 
     let decoded = decode_token(&args).1.unwrap();
@@ -367,6 +1101,408 @@ mod tests {
     );
   }
 
+  #[test]
+  fn test_encode_jwt_token_with_encrypted_private_key() {
+    let mut app = App::new(
+      None,
+      "@./test_data/test_rsa_private_key_encrypted.pem".into(),
+    );
+    app.data.encoder.header.input = vec!["{", r#"  "alg": "RS256""#, "}"].into();
+    app.data.encoder.payload.input = vec!["{", r#"  "sub": "1234567890""#, "}"].into();
+
+    encode_jwt_token(&mut app);
+    assert_eq!(app.data.error, PASSPHRASE_REQUIRED);
+    assert_eq!(
+      app.prompt.as_ref().unwrap().action,
+      PromptAction::Passphrase
+    );
+    assert!(app.prompt.as_ref().unwrap().input.masked);
+
+    // a wrong passphrase asks again
+    app.prompt.as_mut().unwrap().input.input = "wrong".into();
+    app.resolve_prompt(true);
+    assert_eq!(app.data.error, WRONG_PASSPHRASE);
+    assert!(app
+      .prompt
+      .as_ref()
+      .unwrap()
+      .message
+      .starts_with(WRONG_PASSPHRASE));
+
+    app.prompt.as_mut().unwrap().input.input = "jwt-ui-passphrase".into();
+    app.resolve_prompt(true);
+    assert!(app.prompt.is_none());
+    assert_eq!(app.data.error, "");
+    assert!(app.data.encoder.signature_verified);
+
+    let args = DecodeArgs {
+      jwt: app.data.encoder.encoded.get_txt().into(),
+      secret: "@./test_data/test_rsa_public_key.pem".into(),
+      time_format_utc: false,
+      ignore_exp: true,
+      key_source: KeySource::Secret,
+    };
+    assert!(decode_token(&args).1.is_ok());
+
+    // cancelling doesn't ask again for the same key
+    let mut app = App::new(
+      None,
+      "@./test_data/test_es256k_private_key_encrypted.pem".into(),
+    );
+    app.data.encoder.header.input = vec!["{", r#"  "alg": "ES256K""#, "}"].into();
+    app.data.encoder.payload.input = vec!["{", r#"  "sub": "1234567890""#, "}"].into();
+    encode_jwt_token(&mut app);
+    app.resolve_prompt(false);
+    encode_jwt_token(&mut app);
+    assert!(app.prompt.is_none());
+    assert_eq!(app.data.error, PASSPHRASE_REQUIRED);
+
+    app.data.encoder.passphrase = Some("jwt-ui-passphrase".into());
+    encode_jwt_token(&mut app);
+    assert_eq!(app.data.error, "");
+    let (_, verified) = decode_es256k(
+      app.data.encoder.encoded.get_txt(),
+      "@./test_data/test_es256k_public_key.pem",
+      true,
+    );
+    assert!(verified.is_ok());
+  }
+
+  #[test]
+  fn test_encode_es256k_jwt_token_with_valid_payload_and_header() {
+    let mut app = App::new(None, "".into());
+    app.data.encoder.auto_kid = false;
+
+    let header = vec!["{", r#"  "alg": "ES256K","#, r#"  "typ": "JWT""#, "}"];
+    app.data.encoder.header.input = header.clone().into();
+
+    let claims = vec![
+      "{",
+      r#"  "sub": "1234567890","#,
+      r#"  "iat": 1516239022"#,
+      "}",
+    ];
+    app.data.encoder.payload.input = claims.clone().into();
+
+    app.data.encoder.secret.input = "@./test_data/test_es256k_private_key.pem".into();
+
+    encode_jwt_token(&mut app);
+    assert_eq!(app.data.error, "");
+    assert!(app.data.encoder.signature_verified);
+
+    // decode the key and verify
+    let (decoded, verified) = decode_es256k(
+      app.data.encoder.encoded.get_txt(),
+      "@./test_data/test_es256k_public_key.pem",
+      true,
+    );
+    assert!(verified.is_ok());
+    let decoded = decoded.unwrap();
+    assert_eq!(
+      decoded.header,
+      serde_json::from_str::<Value>(header.join("\n").as_str()).unwrap()
+    );
+    assert_eq!(
+      decoded.claims.unwrap(),
+      serde_json::from_str(claims.join("\n").as_str()).unwrap()
+    );
+
+    // ES256K needs a secp256k1 private key
+    app.data.encoder.secret.input = "@./test_data/test_ecdsa_private_key.pem".into();
+    encode_jwt_token(&mut app);
+    assert_eq!(app.data.error, "Invalid ES256K private key");
+  }
+
+  #[test]
+  fn test_encode_jwt_token_with_auto_kid() {
+    let mut app = App::new(None, "@./test_data/test_rsa_private_key.pem".into());
+    app.data.encoder.header.input = vec![r#"{"alg": "RS256", "kid": "old"}"#].into();
+    app.data.encoder.payload.input = vec![r#"{"sub": "1234567890"}"#].into();
+    encode_jwt_token(&mut app);
+    assert_eq!(app.data.error, "");
+    assert_eq!(
+      app.data.encoder.header.input.lines(),
+      [
+        "{",
+        r#"  "alg": "RS256","#,
+        r#"  "kid": "oGDmHqCJ7yzMkWEkrlZVHkvrHIyQwtlDmeq5ForUHeY""#,
+        "}"
+      ]
+    );
+    let header = decode_segment::<Value>(
+      app
+        .data
+        .encoder
+        .encoded
+        .get_txt()
+        .split('.')
+        .next()
+        .unwrap(),
+    );
+    assert_eq!(
+      header.unwrap()["kid"],
+      "oGDmHqCJ7yzMkWEkrlZVHkvrHIyQwtlDmeq5ForUHeY"
+    );
+
+    // the kid of a JWK is used as is, a JWKS with several keys is ambiguous
+    let jwk = std::env::temp_dir().join("jwtui-test-auto-kid.json");
+    fs::write(
+      &jwk,
+      r#"{"keys": [{"kty": "oct", "k": "c2VjcmV0", "kid": "hmac-1"}]}"#,
+    )
+    .unwrap();
+    assert_eq!(
+      signing_kid(&format!("@{}", jwk.display())),
+      Some("hmac-1".into())
+    );
+    let _ = fs::remove_file(jwk);
+    assert_eq!(
+      signing_kid("@./test_data/test_ecdsa_public_jwks.json"),
+      None
+    );
+    assert_eq!(signing_kid("a-string-secret-at-least-256-bits-long"), None);
+
+    // HMAC tokens and disabled auto kid keep the header
+    app.data.encoder.header.input = vec![r#"{"alg": "HS256"}"#].into();
+    encode_jwt_token(&mut app);
+    assert_eq!(
+      app.data.encoder.header.input.lines(),
+      [r#"{"alg": "HS256"}"#]
+    );
+    app.data.encoder.auto_kid = false;
+    app.data.encoder.header.input = vec![r#"{"alg": "RS256"}"#].into();
+    encode_jwt_token(&mut app);
+    assert_eq!(
+      app.data.encoder.header.input.lines(),
+      [r#"{"alg": "RS256"}"#]
+    );
+  }
+
+  #[test]
+  fn test_encode_jwt_token_with_certificate_headers() {
+    let dir = std::env::temp_dir().join("jwtui-test-certificate-headers");
+    fs::create_dir_all(&dir).unwrap();
+    fs::copy("./test_data/test_rsa_private_key.pem", dir.join("key.pem")).unwrap();
+    fs::copy("./test_data/test_rsa_cert.pem", dir.join("key.crt")).unwrap();
+    let secret = format!("@{}", dir.join("key.pem").display());
+    assert_eq!(certificate_for_key(&secret), Some(dir.join("key.crt")));
+    assert_eq!(
+      certificate_for_key("@./test_data/test_rsa_private_key.pem"),
+      None
+    );
+
+    let mut app = App::new(None, secret);
+    app.data.encoder.auto_kid = false;
+    app.data.encoder.header.input = vec![r#"{"alg": "RS256"}"#].into();
+    app.data.encoder.payload.input = vec![r#"{"sub": "1234567890"}"#].into();
+    encode_jwt_token(&mut app);
+    assert_eq!(
+      app.confirm.as_ref().unwrap().action,
+      ConfirmAction::CertificateHeaders(dir.join("key.crt"))
+    );
+    app.resolve_confirmation(true);
+    assert_eq!(app.data.error, "");
+    let header = decode_segment::<Value>(
+      app
+        .data
+        .encoder
+        .encoded
+        .get_txt()
+        .split('.')
+        .next()
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(header["x5c"].as_array().unwrap().len(), 1);
+    assert_eq!(
+      header["x5t#S256"],
+      "ZzrdHhw2y0j4Bs4kpXSMQgxw_Xhjb75-FipFO4FCpXI"
+    );
+    // only offered once per secret
+    encode_jwt_token(&mut app);
+    assert!(app.confirm.is_none());
+
+    app.data.encoder.secret.input = "@./test_data/test_ecdsa_private_key.pem".into();
+    assert_eq!(
+      app
+        .data
+        .encoder
+        .add_certificate_headers(
+          &dir.join("key.crt"),
+          "@./test_data/test_ecdsa_private_key.pem"
+        )
+        .unwrap_err()
+        .to_string(),
+      format!(
+        "The certificate {} is not the certificate of the signing key",
+        dir.join("key.crt").display()
+      )
+    );
+    let _ = fs::remove_dir_all(dir);
+  }
+
+  #[test]
+  fn test_encode_jwt_token_with_private_jwk() {
+    let encode = |header: &str, secret: &str| {
+      let mut app = App::new(None, secret.into());
+      app.data.encoder.header.input = vec![header].into();
+      app.data.encoder.payload.input = vec![r#"{"sub": "1234567890"}"#].into();
+      encode_jwt_token(&mut app);
+      (
+        app.data.encoder.encoded.get_txt().to_string(),
+        app.data.error,
+      )
+    };
+    let verify = |token: String, secret: &str| {
+      let args = DecodeArgs {
+        jwt: token,
+        secret: secret.into(),
+        time_format_utc: false,
+        ignore_exp: true,
+        key_source: KeySource::Secret,
+      };
+      decode_token(&args).1
+    };
+
+    let (token, error) = encode(
+      r#"{"alg": "RS256"}"#,
+      "@./test_data/test_rsa_private_jwk.json",
+    );
+    assert_eq!(error, "");
+    assert!(verify(token, "@./test_data/test_rsa_public_key.pem").is_ok());
+
+    // the kid selects the key of the JWKS
+    let (token, error) = encode(
+      r#"{"alg": "ES256", "kid": "ec-1"}"#,
+      "@./test_data/test_private_jwks.json",
+    );
+    assert_eq!(error, "");
+    assert!(verify(token, "@./test_data/test_ecdsa_public_key.pk8").is_ok());
+    assert_eq!(
+      encode(r#"{"alg": "RS256"}"#, "@./test_data/test_private_jwks.json").1,
+      "The JWKS has several private keys, set the `kid` of the header to select one"
+    );
+    assert_eq!(
+      encode(
+        r#"{"alg": "RS256", "kid": "ec-1"}"#,
+        "@./test_data/test_private_jwks.json"
+      )
+      .1,
+      "The EC JWK can't sign RS256 tokens"
+    );
+    assert_eq!(
+      encode(
+        r#"{"alg": "RS256", "kid": "rsa-2"}"#,
+        "@./test_data/test_private_jwks.json"
+      )
+      .1,
+      "The JWKS has no private key with kid \"rsa-2\""
+    );
+    assert_eq!(
+      encode(
+        r#"{"alg": "ES256"}"#,
+        "@./test_data/test_ecdsa_public_jwks.json"
+      )
+      .1,
+      "The JWKS has no private key"
+    );
+
+    // inline oct JWK for HMAC
+    let key = private_jwk(br#"{"kty": "oct", "k": "c2VjcmV0"}"#, None).unwrap();
+    assert!(encoding_key_from_jwk(&Algorithm::HS256, &key).is_ok());
+    assert_eq!(
+      private_jwk(br#"{"kty": "RSA", "n": "AQAB", "e": "AQAB"}"#, None)
+        .unwrap_err()
+        .to_string(),
+      "The JWK is a public key, sign tokens with a private JWK"
+    );
+  }
+
+  #[test]
+  fn test_encode_jwt_token_with_alg_none() {
+    let mut app = App::new(None, "".into());
+    app.data.encoder.header.input = vec![r#"{"alg": "none", "typ": "JWT"}"#].into();
+    app.data.encoder.payload.input = vec![r#"{"sub": "1234"}"#].into();
+
+    encode_jwt_token(&mut app);
+    assert_eq!(app.data.error, INSECURE_NONE_DISABLED);
+    assert_eq!(app.data.encoder.encoded.get_txt(), "");
+
+    app.data.encoder.insecure_none = true;
+    encode_jwt_token(&mut app);
+    assert!(app.data.error.is_empty());
+    assert_eq!(
+      app.data.encoder.encoded.get_txt(),
+      "eyJhbGciOiJub25lIiwidHlwIjoiSldUIn0.eyJzdWIiOiIxMjM0In0."
+    );
+    assert_eq!(
+      app.data.encoder.findings,
+      check_unsecured(app.data.encoder.encoded.get_txt())
+        .into_iter()
+        .collect::<Vec<_>>()
+    );
+    assert_eq!(app.data.encoder.findings.len(), 1);
+  }
+
+  #[test]
+  fn test_encode_jwt_token_with_remote_key() {
+    let secret = "gcpkms:projects/p/locations/global/keyRings/r/cryptoKeys/k/cryptoKeyVersions/1";
+    let mut app = App::new(None, secret.into());
+    app.data.encoder.header.input = vec![r#"{"alg": "RS256"}"#].into();
+    app.data.encoder.payload.input = vec![r#"{"sub": "1234567890"}"#].into();
+    app.route_encoder();
+
+    // a partially typed key isn't sent to Cloud KMS
+    app.data.encoder.secret.input_mode = InputMode::Editing;
+    encode_jwt_token(&mut app);
+    assert_eq!(
+      app.data.error,
+      "Stop editing the secret to sign the token with the key"
+    );
+    assert!(app.data.encoder.remote_token.is_none());
+
+    // the token is kept while the request runs
+    app.data.encoder.secret.input_mode = InputMode::Normal;
+    let signing = RemoteSigning {
+      secret: secret.into(),
+      header: serde_json::json!({ "alg": "RS256" }),
+      payload: serde_json::from_str(r#"{"sub": "1234567890"}"#).unwrap(),
+      typed_payload: r#"{"sub": "1234567890"}"#.into(),
+      managed_claims: ManagedClaims::default(),
+    };
+    app.data.encoder.remote_token = Some((signing.clone(), None));
+    encode_jwt_token(&mut app);
+    assert_eq!(app.data.error, "");
+    assert_eq!(app.data.encoder.encoded.get_txt(), "");
+    assert_eq!(app.pending_requests, 0);
+
+    // responses of other inputs are ignored
+    let mut other = signing.clone();
+    other.header["kid"] = "old".into();
+    app.handle_network_response(NetworkResponse::Sign {
+      signing: other,
+      token: Err(JWTError::Internal("Permission denied".into())),
+    });
+    assert_eq!(app.data.encoder.remote_token, Some((signing.clone(), None)));
+
+    app.handle_network_response(NetworkResponse::Sign {
+      signing,
+      token: Ok("eyJhbGciOiJSUzI1NiJ9.eyJzdWIiOiIxMjM0NTY3ODkwIn0.c2ln".into()),
+    });
+    assert_eq!(
+      app.data.encoder.encoded.get_txt(),
+      "eyJhbGciOiJSUzI1NiJ9.eyJzdWIiOiIxMjM0NTY3ODkwIn0.c2ln"
+    );
+
+    // a new `iat` alone isn't signed again, unlike a change of the managed claims
+    let (signed, _) = app.data.encoder.remote_token.clone().unwrap();
+    let mut later = signed.clone();
+    later.payload.0.insert("iat".into(), 1_700_000_000.into());
+    assert!(signed.same_inputs(&later));
+    later.managed_claims.iat = true;
+    assert!(!signed.same_inputs(&later));
+  }
+
   #[test]
   fn test_encode_jwt_token_with_empty_header() {
     let mut app = App::new(None, "".into());
@@ -404,4 +1540,322 @@ mod tests {
       "Error parsing header: missing field `alg` at line 3 column 1"
     );
   }
+
+  #[test]
+  fn test_decoded_preview() {
+    let mut encoder = Encoder::new("".into());
+    assert!(encoder.decoded_preview().is_empty());
+
+    // {"alg":"HS256","typ":"JWT"}.{"sub":"1234","exp":1900000000}
+    encoder.encoded = ScrollableTxt::new(
+      "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.eyJzdWIiOiIxMjM0IiwiZXhwIjoxOTAwMDAwMDAwfQ.sig".into(),
+    );
+    assert_eq!(
+      encoder.decoded_preview(),
+      [
+        "{",
+        r#"  "alg": "HS256","#,
+        r#"  "typ": "JWT""#,
+        "}",
+        "{",
+        r#"  "exp": 1900000000,"#,
+        r#"  "sub": "1234""#,
+        "}"
+      ]
+    );
+  }
+
+  #[test]
+  fn test_encode_jwt_token_with_relaxed_json() {
+    let mut app = App::new(None, "a-string-secret-at-least-256-bits-long".into());
+    app.data.encoder.payload.input = vec![r#"{"sub": "1234", "admin": true}"#].into();
+    encode_jwt_token(&mut app);
+    let strict = app.data.encoder.encoded.get_txt().to_string();
+
+    app.data.encoder.header.input = vec!["{'alg': 'HS256', 'typ': 'JWT',}"].into();
+    app.data.encoder.payload.input = vec![
+      "{",
+      "  // the subject",
+      "  'sub': '1234',",
+      "  /* admin */ 'admin': true,",
+      "}",
+    ]
+    .into();
+    encode_jwt_token(&mut app);
+    assert_eq!(app.data.error, "");
+    assert_eq!(app.data.encoder.encoded.get_txt(), strict);
+  }
+
+  #[test]
+  fn test_time_claims() {
+    let claims = ManagedClaims::default();
+    assert_eq!(claims.describe(), None);
+    assert_eq!(
+      claims.apply(r#"{"sub": "1"}"#, 1000).unwrap(),
+      r#"{"sub": "1"}"#
+    );
+
+    let claims = ManagedClaims {
+      iat: true,
+      exp: Some(900),
+      nbf: Some(-60),
+      ..ManagedClaims::default()
+    };
+    assert_eq!(
+      claims.describe(),
+      Some("iat: now, exp: now+15m, nbf: now-1m".into())
+    );
+    assert_eq!(
+      claims
+        .apply(r#"{"sub": "1", "iat": 1, "exp": "old"}"#, 1000)
+        .unwrap(),
+      r#"{"exp":1900,"iat":1000,"nbf":940,"sub":"1"}"#
+    );
+    // payloads that aren't objects are left to the payload parser
+    assert_eq!(claims.apply("[1]", 1000).unwrap(), "[1]");
+    assert_eq!(claims.apply("{", 1000).unwrap(), "{");
+  }
+
+  #[test]
+  fn test_relative_time_claims() {
+    let claims = ManagedClaims::default();
+    assert_eq!(
+      claims
+        .apply(
+          r#"{"exp": "+15m", "nbf": "+0s", "iat": "-10 min", "auth_time": "+1h"}"#,
+          1000
+        )
+        .unwrap(),
+      r#"{"auth_time":"+1h","exp":1900,"iat":400,"nbf":1000}"#
+    );
+    // managed claims win over the payload
+    let claims = ManagedClaims {
+      exp: Some(60),
+      ..ManagedClaims::default()
+    };
+    assert_eq!(
+      claims.apply(r#"{"exp": "+15m"}"#, 1000).unwrap(),
+      r#"{"exp":1060}"#
+    );
+    assert_eq!(
+      claims
+        .apply(r#"{"exp": "+soon"}"#, 1000)
+        .unwrap_err()
+        .to_string(),
+      r#"Invalid relative time "+soon" for exp, use e.g. "+15m" or "-5 min""#
+    );
+
+    let mut app = App::new(None, "a-string-secret-at-least-256-bits-long".into());
+    app.data.encoder.payload.input = vec![r#"{"sub": "1", "exp": "+10 min"}"#].into();
+    encode_jwt_token(&mut app);
+    assert!(app.data.error.is_empty());
+    let args = DecodeArgs {
+      jwt: app.data.encoder.encoded.get_txt().into(),
+      secret: "a-string-secret-at-least-256-bits-long".into(),
+      time_format_utc: false,
+      ignore_exp: false,
+      key_source: KeySource::Secret,
+    };
+    let claims = decode_token(&args).1.unwrap().claims.0;
+    let exp = claims["exp"].as_i64().unwrap();
+    assert!((exp - Utc::now().timestamp() - 600).abs() < 5);
+  }
+
+  #[test]
+  fn test_generated_claims() {
+    let claims = ManagedClaims {
+      jti: true,
+      nonce: true,
+      ..ManagedClaims::default()
+    };
+    assert_eq!(claims.describe(), Some("jti: uuid, nonce: random".into()));
+    let generate = || -> Value {
+      serde_json::from_str(&claims.apply(r#"{"sub": "1", "jti": "old"}"#, 1000).unwrap()).unwrap()
+    };
+    let (first, second) = (generate(), generate());
+    let jti = first["jti"].as_str().unwrap();
+    assert_eq!(jti.len(), 36);
+    assert_eq!(
+      jti.split('-').map(str::len).collect::<Vec<_>>(),
+      [8, 4, 4, 4, 12]
+    );
+    assert_eq!(&jti[14..15], "4");
+    assert!(matches!(&jti[19..20], "8" | "9" | "a" | "b"));
+    assert_eq!(first["nonce"].as_str().unwrap().len(), 22);
+    assert_eq!(first["sub"], "1");
+    // fresh values on every encode
+    assert_ne!(first["jti"], second["jti"]);
+    assert_ne!(first["nonce"], second["nonce"]);
+  }
+
+  #[test]
+  fn test_read_payload() {
+    let lines = read_payload("@./test_data/test_claims.json").unwrap();
+    assert_eq!(lines[0], "{");
+    assert_eq!(lines[1], r#"  "exp": "+1h","#);
+    assert_eq!(lines.len(), 9);
+    assert_eq!(
+      read_payload(r#"{"sub": "1"}"#).unwrap(),
+      ["{", r#"  "sub": "1""#, "}"]
+    );
+
+    assert!(read_payload("@./test_data/missing.json")
+      .unwrap_err()
+      .to_string()
+      .starts_with("Unable to read payload file ./test_data/missing.json"));
+    assert!(read_payload("@./test_data/test_rsa_public_jwks.json").is_ok());
+    assert!(read_payload("[1]")
+      .unwrap_err()
+      .to_string()
+      .starts_with("Invalid claims in the payload: "));
+  }
+
+  #[test]
+  fn test_format_json() {
+    assert_eq!(
+      format_json("{\"sub\":\"1234\",  \"aud\" : [\"a\",\"b\"],\"ext\":{ },\"n\":1.50}").unwrap(),
+      [
+        "{",
+        "  \"sub\": \"1234\",",
+        "  \"aud\": [",
+        "    \"a\",",
+        "    \"b\"",
+        "  ],",
+        "  \"ext\": {},",
+        "  \"n\": 1.50",
+        "}"
+      ]
+    );
+    // strings are kept as written
+    assert_eq!(
+      format_json("{\"name\": \"a, {b}: \\\"c\\\"\"}").unwrap(),
+      ["{", "  \"name\": \"a, {b}: \\\"c\\\"\"", "}"]
+    );
+    let err = format_json("{\n  \"sub\": \"1234\"\n  \"aud\": \"a\"\n}").unwrap_err();
+    assert_eq!((err.line(), err.column()), (3, 3));
+  }
+
+  #[test]
+  fn test_json_syntax_error() {
+    assert!(json_syntax_error("{\"sub\": \"1234\"}").is_none());
+    assert!(json_syntax_error("  \n").is_none());
+    // trailing commas are relaxed JSON
+    assert!(json_syntax_error("{\n  \"sub\": 1234,\n}").is_none());
+    let err = json_syntax_error("{\n  \"sub\": 1234\n  \"a\": 1\n}").unwrap();
+    assert_eq!((err.line, err.column), (3, 3));
+    // at the column of the typed text, before the quotes escaped in the strict JSON
+    let err = json_syntax_error("{'name': 'a \"b\" c' 'sub': 1}").unwrap();
+    assert_eq!((err.line, err.column), (1, 20));
+  }
+
+  #[test]
+  fn test_parse_key_value() {
+    assert_eq!(
+      parse_key_value("sub=1234").unwrap(),
+      ("sub".into(), Value::from(1234))
+    );
+    assert_eq!(
+      parse_key_value("admin=true").unwrap(),
+      ("admin".into(), Value::from(true))
+    );
+    assert_eq!(
+      parse_key_value("roles=[\"a\"]").unwrap(),
+      ("roles".into(), serde_json::json!(["a"]))
+    );
+    assert_eq!(
+      parse_key_value("name=John=Doe").unwrap(),
+      ("name".into(), Value::from("John=Doe"))
+    );
+    assert_eq!(
+      parse_key_value("sub").unwrap_err(),
+      "invalid KEY=VALUE: no `=` found in \"sub\""
+    );
+  }
+
+  #[test]
+  fn test_encode_with_options() {
+    let secret = "a-string-secret-at-least-256-bits-long";
+    let options = EncodeOptions {
+      alg: "HS256".into(),
+      secret: secret.into(),
+      header: vec![("kid".into(), "key-1".into())],
+      payload: Some("@./test_data/test_claims.json".into()),
+      claims: vec![("sub".into(), 42.into()), ("admin".into(), true.into())],
+      exp: Some("1h".into()),
+      nbf: Some("1000".into()),
+      managed_claims: ManagedClaims {
+        iat: true,
+        ..ManagedClaims::default()
+      },
+      ..EncodeOptions::default()
+    };
+    let token = encode_with_options(&options, Utc::now().timestamp()).unwrap();
+
+    let args = DecodeArgs {
+      jwt: token,
+      secret: secret.into(),
+      time_format_utc: false,
+      ignore_exp: false,
+      key_source: KeySource::Secret,
+    };
+    let decoded = decode_token(&args).1.unwrap();
+    assert_eq!(decoded.header.kid, Some("key-1".into()));
+    let claims = decoded.claims.0;
+    assert_eq!(claims["sub"], 42);
+    assert_eq!(claims["admin"], true);
+    assert_eq!(claims["name"], "John Doe");
+    assert_eq!(claims["nbf"], 1000);
+    let iat = claims["iat"].as_i64().unwrap();
+    assert_eq!(claims["exp"].as_i64().unwrap() - iat, 3600);
+
+    let options = EncodeOptions {
+      alg: "HS256".into(),
+      exp: Some("+later".into()),
+      ..EncodeOptions::default()
+    };
+    assert_eq!(
+      encode_with_options(&options, 0).unwrap_err().to_string(),
+      r#"Invalid relative time "+later" for exp, use e.g. "+15m" or "-5 min""#
+    );
+    let options = EncodeOptions {
+      alg: "none".into(),
+      ..EncodeOptions::default()
+    };
+    assert_eq!(
+      encode_with_options(&options, 0).unwrap_err().to_string(),
+      INSECURE_NONE_DISABLED
+    );
+  }
+
+  #[test]
+  fn test_encode_jwt_token_with_time_claims() {
+    let mut app = App::new(None, "a-string-secret-at-least-256-bits-long".into());
+    app.data.encoder.payload.input = vec![r#"{"sub": "1234567890"}"#].into();
+    app.data.encoder.managed_claims = ManagedClaims {
+      iat: true,
+      exp: Some(900),
+      nbf: Some(0),
+      ..ManagedClaims::default()
+    };
+    encode_jwt_token(&mut app);
+    assert!(app.data.error.is_empty());
+
+    let args = DecodeArgs {
+      jwt: app.data.encoder.encoded.get_txt().into(),
+      secret: "a-string-secret-at-least-256-bits-long".into(),
+      time_format_utc: false,
+      ignore_exp: false,
+      key_source: KeySource::Secret,
+    };
+    let claims = decode_token(&args).1.unwrap().claims.0;
+    let iat = claims["iat"].as_i64().unwrap();
+    assert!((iat - Utc::now().timestamp()).abs() < 5);
+    assert_eq!(claims["exp"].as_i64().unwrap() - iat, 900);
+    assert_eq!(claims["nbf"].as_i64().unwrap(), iat);
+    // the payload text is not changed
+    assert_eq!(
+      app.data.encoder.payload.input.lines(),
+      [r#"{"sub": "1234567890"}"#]
+    );
+  }
 }
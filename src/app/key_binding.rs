@@ -25,10 +25,27 @@ generate_keybindings! {
   help,
   refresh,
   toggle_theme,
+  pick_theme,
+  toggle_compact,
+  faster_ticks,
+  slower_ticks,
+  shrink_left_pane,
+  widen_left_pane,
+  shrink_header_pane,
+  grow_header_pane,
   cycle_main_views,
   jump_to_decoder,
   jump_to_encoder,
+  jump_to_keys,
+  jump_to_utilities,
   copy_to_clipboard,
+  copy_history,
+  token_history,
+  save_workspace,
+  open_workspace,
+  search,
+  search_next,
+  search_prev,
   pg_up,
   pg_down,
   up,
@@ -37,8 +54,50 @@ generate_keybindings! {
   right,
   toggle_utc_dates,
   toggle_ignore_exp,
+  show_certificates,
+  show_credential,
+  show_jwks,
+  toggle_x5c_verify,
+  fetch_x5u,
+  toggle_chain_validation,
+  algorithm_confusion,
+  edit_in_encoder,
+  toggle_payload_tree,
+  toggle_line_numbers,
+  go_to_line,
+  new_decoder_tab,
+  next_decoder_tab,
+  close_decoder_tab,
+  scan_clipboard,
+  request_token,
+  refresh_token,
+  introspect_token,
+  save_to_keyring,
+  bookmarks,
+  toggle_secret_mask,
+  toggle_auto_iat,
+  set_auto_exp,
+  set_auto_nbf,
+  toggle_auto_jti,
+  toggle_auto_nonce,
+  show_templates,
+  import_payload,
+  toggle_auto_kid,
+  add_certificate_headers,
+  format_json,
+  toggle_preview,
+  import_key,
+  generate_key,
+  use_key_to_verify,
+  use_key_to_sign,
+  intro_paste,
+  intro_open_file,
+  intro_encoder,
+  intro_decoder,
   toggle_input_edit,
   clear_input,
+  undo,
+  redo,
   delete_prev_char,
   go_to_prev_char,
   go_to_prev_word,
@@ -48,7 +107,10 @@ generate_keybindings! {
   delete_next_word,
   delete_till_end,
   go_to_start,
-  go_to_end
+  go_to_end,
+  vim_bottom,
+  vim_edit,
+  vim_command
 }
 
 #[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
@@ -56,7 +118,10 @@ pub enum HContext {
   General,
   Editable,
   Decoder,
-  //   Encoder,
+  Encoder,
+  Keys,
+  Intro,
+  Vim,
 }
 
 impl fmt::Display for HContext {
@@ -89,7 +154,7 @@ pub const DEFAULT_KEYBINDING: KeyBindings = KeyBindings {
   help: KeyBinding {
     key: Key::Char('?'),
     alt: None,
-    desc: "Help page",
+    desc: "Help popup, type to filter the keybindings",
     context: HContext::General,
   },
   refresh: KeyBinding {
@@ -104,6 +169,54 @@ pub const DEFAULT_KEYBINDING: KeyBindings = KeyBindings {
     desc: "Toggle theme",
     context: HContext::General,
   },
+  pick_theme: KeyBinding {
+    key: Key::Ctrl('t'),
+    alt: None,
+    desc: "Pick a theme",
+    context: HContext::General,
+  },
+  toggle_compact: KeyBinding {
+    key: Key::Char('z'),
+    alt: None,
+    desc: "Toggle compact layout for current view",
+    context: HContext::General,
+  },
+  faster_ticks: KeyBinding {
+    key: Key::Char('+'),
+    alt: None,
+    desc: "Tick faster, to refresh the UI more often",
+    context: HContext::General,
+  },
+  slower_ticks: KeyBinding {
+    key: Key::Char('-'),
+    alt: None,
+    desc: "Tick slower, to use less CPU",
+    context: HContext::General,
+  },
+  shrink_left_pane: KeyBinding {
+    key: Key::CtrlK(KeyCode::Left),
+    alt: None,
+    desc: "Narrow the left side of the current view",
+    context: HContext::General,
+  },
+  widen_left_pane: KeyBinding {
+    key: Key::CtrlK(KeyCode::Right),
+    alt: None,
+    desc: "Widen the left side of the current view",
+    context: HContext::General,
+  },
+  shrink_header_pane: KeyBinding {
+    key: Key::CtrlK(KeyCode::Up),
+    alt: None,
+    desc: "Shrink the header block in favor of the payload",
+    context: HContext::General,
+  },
+  grow_header_pane: KeyBinding {
+    key: Key::CtrlK(KeyCode::Down),
+    alt: None,
+    desc: "Grow the header block in favor of the payload",
+    context: HContext::General,
+  },
   jump_to_decoder: KeyBinding {
     key: Key::Char('D'),
     alt: None,
@@ -116,6 +229,18 @@ pub const DEFAULT_KEYBINDING: KeyBindings = KeyBindings {
     desc: "Switch to encoder view",
     context: HContext::General,
   },
+  jump_to_keys: KeyBinding {
+    key: Key::Char('K'),
+    alt: None,
+    desc: "Switch to keys view",
+    context: HContext::General,
+  },
+  jump_to_utilities: KeyBinding {
+    key: Key::Char('M'),
+    alt: None,
+    desc: "Switch to utilities view",
+    context: HContext::General,
+  },
   cycle_main_views: KeyBinding {
     key: Key::Tab,
     alt: None,
@@ -128,6 +253,48 @@ pub const DEFAULT_KEYBINDING: KeyBindings = KeyBindings {
     desc: "Copy content to clipboard",
     context: HContext::General,
   },
+  copy_history: KeyBinding {
+    key: Key::Char('Y'),
+    alt: None,
+    desc: "Copy history, copy an earlier value again",
+    context: HContext::General,
+  },
+  token_history: KeyBinding {
+    key: Key::Char('H'),
+    alt: None,
+    desc: "Token history, type to filter by issuer, subject or date",
+    context: HContext::General,
+  },
+  save_workspace: KeyBinding {
+    key: Key::Char('W'),
+    alt: None,
+    desc: "Save the inputs and settings as a workspace",
+    context: HContext::General,
+  },
+  open_workspace: KeyBinding {
+    key: Key::Char('O'),
+    alt: None,
+    desc: "Open a saved workspace",
+    context: HContext::General,
+  },
+  search: KeyBinding {
+    key: Key::Char('/'),
+    alt: None,
+    desc: "Search the decoder header/payload",
+    context: HContext::General,
+  },
+  search_next: KeyBinding {
+    key: Key::Char('n'),
+    alt: None,
+    desc: "Go to the next search match",
+    context: HContext::General,
+  },
+  search_prev: KeyBinding {
+    key: Key::Char('N'),
+    alt: None,
+    desc: "Go to the previous search match",
+    context: HContext::General,
+  },
   down: KeyBinding {
     key: Key::Down,
     alt: Some(Key::Char('j')),
@@ -176,6 +343,246 @@ pub const DEFAULT_KEYBINDING: KeyBindings = KeyBindings {
     desc: "Toggle ignoring exp claim from validation",
     context: HContext::Decoder,
   },
+  show_certificates: KeyBinding {
+    key: Key::Char('x'),
+    alt: None,
+    desc: "Show x5c certificate chain",
+    context: HContext::Decoder,
+  },
+  show_credential: KeyBinding {
+    key: Key::Char('V'),
+    alt: None,
+    desc: "Show the verifiable credential of a VC-JWT",
+    context: HContext::Decoder,
+  },
+  show_jwks: KeyBinding {
+    key: Key::Char('b'),
+    alt: None,
+    desc: "Browse the keys of the JWKS secret",
+    context: HContext::Decoder,
+  },
+  toggle_x5c_verify: KeyBinding {
+    key: Key::Char('v'),
+    alt: None,
+    desc: "Toggle verifying signature with x5c leaf certificate",
+    context: HContext::Decoder,
+  },
+  fetch_x5u: KeyBinding {
+    key: Key::Char('f'),
+    alt: None,
+    desc: "Fetch x5u certificate to verify signature",
+    context: HContext::Decoder,
+  },
+  toggle_chain_validation: KeyBinding {
+    key: Key::Char('r'),
+    alt: None,
+    desc: "Toggle validating x5c certificate chain against trusted roots",
+    context: HContext::Decoder,
+  },
+  algorithm_confusion: KeyBinding {
+    key: Key::Char('a'),
+    alt: None,
+    desc: "Re-sign as HS256 with the public key file as secret (algorithm confusion test)",
+    context: HContext::Decoder,
+  },
+  edit_in_encoder: KeyBinding {
+    key: Key::Char('o'),
+    alt: None,
+    desc: "Open the token in the encoder with the same secret to edit it",
+    context: HContext::Decoder,
+  },
+  toggle_payload_tree: KeyBinding {
+    key: Key::Char('T'),
+    alt: None,
+    desc: "Toggle the tree view of the payload, <enter> expands/collapses the selected node",
+    context: HContext::Decoder,
+  },
+  toggle_line_numbers: KeyBinding {
+    key: Key::Char('L'),
+    alt: None,
+    desc: "Toggle line numbers of the payload",
+    context: HContext::Decoder,
+  },
+  go_to_line: KeyBinding {
+    key: Key::Char(':'),
+    alt: None,
+    desc: "Go to a line of the payload",
+    context: HContext::Decoder,
+  },
+  new_decoder_tab: KeyBinding {
+    key: Key::Char('A'),
+    alt: None,
+    desc: "Open another decoder tab, with the same secret",
+    context: HContext::Decoder,
+  },
+  next_decoder_tab: KeyBinding {
+    key: Key::Char(']'),
+    alt: Some(Key::Char('[')),
+    desc: "Next/previous decoder tab",
+    context: HContext::Decoder,
+  },
+  close_decoder_tab: KeyBinding {
+    key: Key::Char('X'),
+    alt: None,
+    desc: "Close the decoder tab",
+    context: HContext::Decoder,
+  },
+  scan_clipboard: KeyBinding {
+    key: Key::Char('S'),
+    alt: None,
+    desc: "Scan copied logs, JSON or URLs for tokens and pick one to decode",
+    context: HContext::Decoder,
+  },
+  request_token: KeyBinding {
+    key: Key::Char('R'),
+    alt: None,
+    desc: "Request a new token with the OAuth grant of `--grant`",
+    context: HContext::Decoder,
+  },
+  refresh_token: KeyBinding {
+    key: Key::Char('U'),
+    alt: None,
+    desc: "Exchange the OAuth refresh token, or the pasted one, for a new access token",
+    context: HContext::Decoder,
+  },
+  introspect_token: KeyBinding {
+    key: Key::Char('I'),
+    alt: None,
+    desc: "Introspect the token at the OAuth introspection endpoint",
+    context: HContext::Decoder,
+  },
+  save_to_keyring: KeyBinding {
+    key: Key::Char('s'),
+    alt: None,
+    desc: "Save the secret to the OS keyring",
+    context: HContext::General,
+  },
+  bookmarks: KeyBinding {
+    key: Key::Char('B'),
+    alt: None,
+    desc: "Bookmarked secrets, key files and JWKS URLs, <a> bookmarks the secret",
+    context: HContext::General,
+  },
+  toggle_secret_mask: KeyBinding {
+    key: Key::Char('m'),
+    alt: None,
+    desc: "Show/hide the secret",
+    context: HContext::General,
+  },
+  toggle_auto_iat: KeyBinding {
+    key: Key::Char('i'),
+    alt: None,
+    desc: "Toggle setting iat to the current time on every encode",
+    context: HContext::Encoder,
+  },
+  set_auto_exp: KeyBinding {
+    key: Key::Char('x'),
+    alt: None,
+    desc: "Set exp relative to the current time on every encode",
+    context: HContext::Encoder,
+  },
+  set_auto_nbf: KeyBinding {
+    key: Key::Char('n'),
+    alt: None,
+    desc: "Set nbf relative to the current time on every encode",
+    context: HContext::Encoder,
+  },
+  toggle_auto_jti: KeyBinding {
+    key: Key::Char('u'),
+    alt: None,
+    desc: "Toggle setting jti to a new UUID on every encode",
+    context: HContext::Encoder,
+  },
+  toggle_auto_nonce: KeyBinding {
+    key: Key::Char('o'),
+    alt: None,
+    desc: "Toggle setting nonce to a new random value on every encode",
+    context: HContext::Encoder,
+  },
+  show_templates: KeyBinding {
+    key: Key::Char('p'),
+    alt: None,
+    desc: "Pick a header/payload template",
+    context: HContext::Encoder,
+  },
+  import_payload: KeyBinding {
+    key: Key::Char('f'),
+    alt: None,
+    desc: "Import the payload from a JSON file",
+    context: HContext::Encoder,
+  },
+  toggle_auto_kid: KeyBinding {
+    key: Key::Char('a'),
+    alt: None,
+    desc: "Toggle setting kid from the signing key",
+    context: HContext::Encoder,
+  },
+  add_certificate_headers: KeyBinding {
+    key: Key::Char('C'),
+    alt: None,
+    desc: "Add x5c and x5t#S256 headers from the certificate of the signing key",
+    context: HContext::Encoder,
+  },
+  format_json: KeyBinding {
+    key: Key::Char('F'),
+    alt: None,
+    desc: "Format the JSON of the selected header or payload block",
+    context: HContext::Encoder,
+  },
+  toggle_preview: KeyBinding {
+    key: Key::Char('v'),
+    alt: None,
+    desc: "Toggle the decoded preview of the encoded token",
+    context: HContext::Encoder,
+  },
+  import_key: KeyBinding {
+    key: Key::Char('i'),
+    alt: None,
+    desc: "Import a key or secret file into the key store",
+    context: HContext::Keys,
+  },
+  generate_key: KeyBinding {
+    key: Key::Char('g'),
+    alt: None,
+    desc: "Generate a key pair in the key store",
+    context: HContext::Keys,
+  },
+  use_key_to_verify: KeyBinding {
+    key: Key::Char('v'),
+    alt: None,
+    desc: "Verify tokens in the decoder with the selected key",
+    context: HContext::Keys,
+  },
+  use_key_to_sign: KeyBinding {
+    key: Key::Char('s'),
+    alt: None,
+    desc: "Sign tokens in the encoder with the selected key",
+    context: HContext::Keys,
+  },
+  intro_paste: KeyBinding {
+    key: Key::Char('p'),
+    alt: None,
+    desc: "Paste a copied token, or paste it in the terminal",
+    context: HContext::Intro,
+  },
+  intro_open_file: KeyBinding {
+    key: Key::Char('f'),
+    alt: None,
+    desc: "Open a file with a token",
+    context: HContext::Intro,
+  },
+  intro_encoder: KeyBinding {
+    key: Key::Char('e'),
+    alt: None,
+    desc: "Go to the encoder to create a token",
+    context: HContext::Intro,
+  },
+  intro_decoder: KeyBinding {
+    key: Key::Char('d'),
+    alt: Some(Key::Esc),
+    desc: "Type a token in the empty decoder",
+    context: HContext::Intro,
+  },
   toggle_input_edit: KeyBinding {
     key: Key::Enter,
     alt: Some(Key::Char('e')),
@@ -188,6 +595,18 @@ pub const DEFAULT_KEYBINDING: KeyBindings = KeyBindings {
     desc: "Clear input",
     context: HContext::Editable,
   },
+  undo: KeyBinding {
+    key: Key::Ctrl('z'),
+    alt: None,
+    desc: "Undo the last edit",
+    context: HContext::Editable,
+  },
+  redo: KeyBinding {
+    key: Key::Ctrl('y'),
+    alt: None,
+    desc: "Redo the last undone edit",
+    context: HContext::Editable,
+  },
   delete_prev_char: KeyBinding {
     key: Key::Backspace,
     alt: Some(Key::Ctrl('h')),
@@ -248,18 +667,129 @@ pub const DEFAULT_KEYBINDING: KeyBindings = KeyBindings {
     desc: "Goto end",
     context: HContext::Editable,
   },
+  vim_bottom: KeyBinding {
+    key: Key::Char('G'),
+    alt: None,
+    desc: "Go to the bottom of the block",
+    context: HContext::Vim,
+  },
+  vim_edit: KeyBinding {
+    key: Key::Char('i'),
+    alt: None,
+    desc: "Edit the selected input",
+    context: HContext::Vim,
+  },
+  vim_command: KeyBinding {
+    key: Key::Char(':'),
+    alt: None,
+    desc: "Run a command like :copy payload, :theme light, :42 or :q",
+    context: HContext::Vim,
+  },
+};
+
+/// key that starts the leader chords
+pub const LEADER: Key = Key::Char(' ');
+
+/// two keys pressed one after the other, the second within [`crate::event::PendingKey::TIMEOUT`]
+#[derive(Clone)]
+pub struct KeyChord {
+  pub keys: [Key; 2],
+  pub desc: &'static str,
+  pub context: HContext,
+}
+
+pub struct KeyChords {
+  pub go_to_top: KeyChord,
+  pub jump_to_decoder: KeyChord,
+  pub jump_to_encoder: KeyChord,
+  pub jump_to_keys: KeyChord,
+  pub jump_to_utilities: KeyChord,
+  pub leader_copy: KeyChord,
+  pub leader_toggle_theme: KeyChord,
+}
+
+impl KeyChords {
+  pub fn as_iter(&self) -> Vec<&KeyChord> {
+    vec![
+      &self.go_to_top,
+      &self.jump_to_decoder,
+      &self.jump_to_encoder,
+      &self.jump_to_keys,
+      &self.jump_to_utilities,
+      &self.leader_copy,
+      &self.leader_toggle_theme,
+    ]
+  }
+
+  /// whether the key starts a chord and waits for the second key. Keys bound in the view keep
+  /// working at once, so chords don't start with them there
+  pub fn is_prefix(&self, key: Key, view: HContext) -> bool {
+    let bound = DEFAULT_KEYBINDING
+      .as_iter()
+      .iter()
+      .any(|binding| binding.context == view && (binding.key == key || binding.alt == Some(key)));
+    !bound && self.as_iter().iter().any(|chord| chord.keys[0] == key)
+  }
+}
+
+pub const DEFAULT_CHORDS: KeyChords = KeyChords {
+  go_to_top: KeyChord {
+    keys: [Key::Char('g'), Key::Char('g')],
+    desc: "Go to the top of the block",
+    context: HContext::General,
+  },
+  jump_to_decoder: KeyChord {
+    keys: [Key::Char('g'), Key::Char('d')],
+    desc: "Go to the decoder view",
+    context: HContext::General,
+  },
+  jump_to_encoder: KeyChord {
+    keys: [Key::Char('g'), Key::Char('e')],
+    desc: "Go to the encoder view",
+    context: HContext::General,
+  },
+  jump_to_keys: KeyChord {
+    keys: [Key::Char('g'), Key::Char('k')],
+    desc: "Go to the keys view",
+    context: HContext::General,
+  },
+  jump_to_utilities: KeyChord {
+    keys: [Key::Char('g'), Key::Char('u')],
+    desc: "Go to the utilities view",
+    context: HContext::General,
+  },
+  leader_copy: KeyChord {
+    keys: [LEADER, Key::Char('y')],
+    desc: "Copy content to clipboard",
+    context: HContext::General,
+  },
+  leader_toggle_theme: KeyChord {
+    keys: [LEADER, Key::Char('t')],
+    desc: "Toggle theme",
+    context: HContext::General,
+  },
 };
 
 pub fn get_help_docs() -> Vec<Vec<String>> {
   let items = DEFAULT_KEYBINDING.as_iter();
 
-  items.iter().map(|it| help_row(it)).collect()
+  items
+    .iter()
+    .map(|it| help_row(it))
+    .chain(DEFAULT_CHORDS.as_iter().iter().map(|chord| {
+      vec![
+        format!("{} {}", chord.keys[0], chord.keys[1]),
+        String::from(chord.desc),
+        chord.context.to_string(),
+      ]
+    }))
+    .collect()
 }
 
 fn help_row(item: &KeyBinding) -> Vec<String> {
   vec![
-    if item.alt.is_some() {
-      format!("{} | {}", item.key, item.alt.unwrap())
+    if let Some(alt) = item.alt {
+      format!("{} | {}", item.key, alt)
     } else {
       item.key.to_string()
     },
@@ -270,10 +800,69 @@ fn help_row(item: &KeyBinding) -> Vec<String> {
 
 #[cfg(test)]
 mod tests {
-  use super::DEFAULT_KEYBINDING;
+  use super::*;
 
   #[test]
   fn test_as_iter() {
     assert!(DEFAULT_KEYBINDING.as_iter().len() >= 28);
   }
+
+  #[test]
+  fn test_chords() {
+    assert!(DEFAULT_CHORDS.is_prefix(Key::Char('g'), HContext::Decoder));
+    assert!(DEFAULT_CHORDS.is_prefix(LEADER, HContext::Keys));
+    assert!(!DEFAULT_CHORDS.is_prefix(Key::Char('d'), HContext::General));
+    // `g` generates a key pair in the keys view
+    assert!(!DEFAULT_CHORDS.is_prefix(Key::Char('g'), HContext::Keys));
+    let docs = get_help_docs();
+    assert!(docs.contains(&vec![
+      "<g> <d>".to_string(),
+      "Go to the decoder view".to_string(),
+      "General".to_string()
+    ]));
+    assert!(docs
+      .iter()
+      .any(|row| row[0] == "<Space> <y>" && row[1] == "Copy content to clipboard"));
+  }
+
+  #[test]
+  fn test_no_conflicting_vim_keys() {
+    let keys = |binding: &KeyBinding| [Some(binding.key), binding.alt];
+    // shadowed in vim mode only, `:` goes to a line with `:<line>` and `i` toggles ignoring `exp`
+    // and auto `iat` outside of the inputs
+    let shadowed = [
+      DEFAULT_KEYBINDING.toggle_ignore_exp.desc,
+      DEFAULT_KEYBINDING.go_to_line.desc,
+      DEFAULT_KEYBINDING.toggle_auto_iat.desc,
+    ];
+    for vim in DEFAULT_KEYBINDING
+      .as_iter()
+      .into_iter()
+      .filter(|binding| binding.context == HContext::Vim)
+    {
+      // vim keys come first in every view, `i` only applies to inputs and the keys view has none
+      let views: &[HContext] = match vim.key == DEFAULT_KEYBINDING.vim_edit.key {
+        true => &[HContext::General, HContext::Decoder, HContext::Encoder],
+        false => &[
+          HContext::General,
+          HContext::Decoder,
+          HContext::Encoder,
+          HContext::Keys,
+        ],
+      };
+      for binding in DEFAULT_KEYBINDING.as_iter() {
+        if !views.contains(&binding.context) || shadowed.contains(&binding.desc) {
+          continue;
+        }
+        for key in keys(binding).into_iter().flatten() {
+          assert!(
+            !keys(vim).contains(&Some(key)),
+            "{key} is bound to \"{}\" and \"{}\"",
+            vim.desc,
+            binding.desc
+          );
+        }
+      }
+    }
+  }
 }
@@ -0,0 +1,413 @@
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::Algorithm;
+use serde_json::Value;
+
+use super::{
+  config::default_key_store_dir,
+  keys::{
+    generate_key_pair, jwk_thumbprint, jwk_to_pem, parse_key, public_jwk, write_key_file,
+    KeyFormat, DEFAULT_RSA_BITS,
+  },
+  models::StatefulTable,
+  utils::{JWTError, JWTResult},
+};
+
+/// metadata of a key or secret file in the key store
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredKey {
+  /// file name in the key store
+  pub name: String,
+  pub path: PathBuf,
+  /// JWK key type (`RSA`, `EC`, `OKP`) or `Secret` for HMAC secrets
+  pub key_type: String,
+  /// RSA modulus size, curve or secret length
+  pub size: String,
+  pub private: bool,
+  /// RFC 7638 JWK thumbprint, none for secrets
+  pub thumbprint: Option<String>,
+}
+
+impl StoredKey {
+  fn read(path: &Path) -> JWTResult<Self> {
+    let content = fs::read(path)?;
+    let name = path
+      .file_name()
+      .map(|name| name.to_string_lossy().into_owned())
+      .unwrap_or_default();
+    let key = match parse_key(&content) {
+      Ok(jwk) => StoredKey {
+        name,
+        path: path.to_path_buf(),
+        key_type: jwk["kty"].as_str().unwrap_or_default().into(),
+        size: key_size(&jwk),
+        private: jwk.get("d").is_some(),
+        thumbprint: jwk_thumbprint(&jwk).ok(),
+      },
+      // anything that isn't a key is used as HMAC secret
+      Err(_) => StoredKey {
+        name,
+        path: path.to_path_buf(),
+        key_type: "Secret".into(),
+        size: format!("{} bytes", content.len()),
+        private: true,
+        thumbprint: None,
+      },
+    };
+    Ok(key)
+  }
+
+  fn is_secret(&self) -> bool {
+    self.thumbprint.is_none()
+  }
+
+  /// the `@file` secret input value of the key
+  fn secret_input(&self) -> String {
+    format!("@{}", self.path.display())
+  }
+
+  /// returns true if the key can be used with the algorithm
+  pub fn supports(&self, alg: &str) -> bool {
+    match self.key_type.as_str() {
+      "Secret" => alg.starts_with("HS"),
+      "RSA" => alg.starts_with("RS") || alg.starts_with("PS"),
+      _ => self.default_alg() == Some(alg),
+    }
+  }
+
+  /// algorithm the encoder switches to when signing with the key
+  pub fn default_alg(&self) -> Option<&'static str> {
+    match (self.key_type.as_str(), self.size.as_str()) {
+      ("Secret", _) => Some("HS256"),
+      ("RSA", _) => Some("RS256"),
+      (_, "P-256") => Some("ES256"),
+      (_, "P-384") => Some("ES384"),
+      (_, "secp256k1") => Some("ES256K"),
+      (_, "Ed25519") => Some("EdDSA"),
+      _ => None,
+    }
+  }
+}
+
+/// modulus size of RSA keys and curve of EC and OKP keys
+fn key_size(jwk: &Value) -> String {
+  match jwk["kty"].as_str() {
+    Some("RSA") => {
+      let modulus = jwk["n"]
+        .as_str()
+        .and_then(|n| URL_SAFE_NO_PAD.decode(n).ok())
+        .unwrap_or_default();
+      let modulus: Vec<u8> = modulus.into_iter().skip_while(|b| *b == 0).collect();
+      let bits = match modulus.first() {
+        Some(first) => modulus.len() * 8 - first.leading_zeros() as usize,
+        None => 0,
+      };
+      format!("{bits} bits")
+    }
+    _ => jwk["crv"].as_str().unwrap_or_default().into(),
+  }
+}
+
+/// keys and secrets saved as files in `<config dir>/jwt-ui/keys`, listed in the Keys tab
+pub struct KeyStore {
+  pub dir: Option<PathBuf>,
+  pub keys: StatefulTable<StoredKey>,
+}
+
+impl Default for KeyStore {
+  fn default() -> Self {
+    KeyStore::new(default_key_store_dir())
+  }
+}
+
+impl KeyStore {
+  pub fn new(dir: Option<PathBuf>) -> Self {
+    KeyStore {
+      dir,
+      keys: StatefulTable::new(),
+    }
+  }
+
+  fn dir(&self) -> JWTResult<&Path> {
+    self
+      .dir
+      .as_deref()
+      .ok_or_else(|| JWTError::Internal("No config directory for the key store".into()))
+  }
+
+  /// read the keys of the store directory, a missing directory is an empty store
+  pub fn load(&mut self) -> JWTResult<()> {
+    let mut keys = vec![];
+    if let Some(dir) = self.dir.as_deref().filter(|dir| dir.is_dir()) {
+      for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+          keys.push(StoredKey::read(&path)?);
+        }
+      }
+    }
+    keys.sort_by(|a, b| a.name.cmp(&b.name));
+    self.keys.set_items(keys);
+    Ok(())
+  }
+
+  pub fn selected(&self) -> Option<&StoredKey> {
+    self
+      .keys
+      .state
+      .selected()
+      .and_then(|i| self.keys.items.get(i))
+  }
+
+  fn select(&mut self, path: &Path) {
+    if let Some(i) = self.keys.items.iter().position(|key| key.path == path) {
+      self.keys.state.select(Some(i));
+    }
+  }
+
+  /// copy a key or secret file into the store. Keys are saved as PEM named after the file, with
+  /// the public key of private keys next to them. Returns the path of the imported key
+  pub fn import(&mut self, file: &Path) -> JWTResult<PathBuf> {
+    let content = fs::read(file)
+      .map_err(|e| JWTError::Internal(format!("Unable to read {}: {e}", file.display())))?;
+    // `key.pub.pem` and `key.jwk.json` are saved as `key`
+    let base = file
+      .file_name()
+      .and_then(|name| name.to_string_lossy().split('.').next().map(String::from))
+      .filter(|base| !base.is_empty())
+      .ok_or_else(|| JWTError::Internal(format!("Invalid key file {}", file.display())))?;
+
+    let looks_like_key = serde_json::from_slice::<Value>(&content).is_ok()
+      || content.trim_ascii_start().starts_with(b"-----");
+    let files = match parse_key(&content) {
+      Ok(jwk) if jwk.get("d").is_some() => vec![
+        (format!("{base}.pem"), jwk_to_pem(&jwk)?.into_bytes(), true),
+        (
+          format!("{base}.pub.pem"),
+          jwk_to_pem(&public_jwk(&jwk))?.into_bytes(),
+          false,
+        ),
+      ],
+      Ok(jwk) => vec![(
+        format!("{base}.pub.pem"),
+        jwk_to_pem(&jwk)?.into_bytes(),
+        false,
+      )],
+      Err(e) if looks_like_key => return Err(e),
+      Err(_) => vec![(format!("{base}.secret"), content, true)],
+    };
+    self.save(files)
+  }
+
+  /// generate a key pair for the algorithm and save it as PEM. Returns the path of the private key
+  pub fn generate(&mut self, alg: Algorithm) -> JWTResult<PathBuf> {
+    let pair = generate_key_pair(alg, DEFAULT_RSA_BITS)?;
+    let kid = pair.public_jwk["kid"].as_str().unwrap_or_default();
+    let prefix = self.dir()?.join(format!(
+      "{}-{}",
+      format!("{alg:?}").to_lowercase(),
+      &kid[..kid.len().min(8)]
+    ));
+    fs::create_dir_all(self.dir()?)?;
+    let path = pair.write(KeyFormat::Pem, &prefix)?.remove(0);
+    self.load()?;
+    self.select(&path);
+    Ok(path)
+  }
+
+  /// write the files into the store without overwriting existing keys and select the first one
+  fn save(&mut self, files: Vec<(String, Vec<u8>, bool)>) -> JWTResult<PathBuf> {
+    let dir = self.dir()?.to_path_buf();
+    if let Some((name, _, _)) = files.iter().find(|(name, _, _)| dir.join(name).exists()) {
+      return Err(JWTError::Internal(format!(
+        "{name} already exists in the key store"
+      )));
+    }
+    fs::create_dir_all(&dir)?;
+    for (name, content, private) in &files {
+      write_key_file(&dir.join(name), content, *private)?;
+    }
+    let path = dir.join(&files[0].0);
+    self.load()?;
+    self.select(&path);
+    Ok(path)
+  }
+
+  /// secret input of the decoder to verify tokens with the selected key, private keys are
+  /// replaced by the public key with the same thumbprint
+  pub fn verification_secret(&self) -> JWTResult<String> {
+    let key = self.selected().ok_or_else(no_key_selected)?;
+    if !key.private || key.is_secret() {
+      return Ok(key.secret_input());
+    }
+    self
+      .keys
+      .items
+      .iter()
+      .find(|other| !other.private && other.thumbprint == key.thumbprint)
+      .map(StoredKey::secret_input)
+      .ok_or_else(|| {
+        JWTError::Internal(format!(
+          "The key store has no public key matching {}",
+          key.name
+        ))
+      })
+  }
+
+  /// secret input of the encoder to sign tokens with the selected key
+  pub fn signing_secret(&self) -> JWTResult<String> {
+    let key = self.selected().ok_or_else(no_key_selected)?;
+    if key.private {
+      Ok(key.secret_input())
+    } else {
+      Err(JWTError::Internal(format!(
+        "{} is a public key, select a private key or secret to sign tokens",
+        key.name
+      )))
+    }
+  }
+}
+
+fn no_key_selected() -> JWTError {
+  JWTError::Internal("No key selected".into())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn store(name: &str) -> KeyStore {
+    let dir = std::env::temp_dir().join(format!("jwtui-key-store-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    KeyStore::new(Some(dir))
+  }
+
+  #[test]
+  fn test_load_missing_dir() {
+    let mut store = store("missing");
+    store.load().unwrap();
+    assert!(store.keys.items.is_empty());
+    assert!(store.selected().is_none());
+    assert_eq!(
+      store.signing_secret().unwrap_err().to_string(),
+      "No key selected"
+    );
+  }
+
+  #[test]
+  fn test_import_keys_and_secrets() {
+    let mut store = store("import");
+    let path = store
+      .import(Path::new("./test_data/test_rsa_private_key.pem"))
+      .unwrap();
+    assert!(path.ends_with("test_rsa_private_key.pem"));
+    store
+      .import(Path::new("./test_data/test_ecdsa_public_key.pem"))
+      .unwrap();
+    store.import(Path::new("./Cargo.toml")).unwrap();
+
+    let keys: Vec<_> = store
+      .keys
+      .items
+      .iter()
+      .map(|k| (k.name.as_str(), k.key_type.as_str(), k.private))
+      .collect();
+    assert_eq!(
+      keys,
+      vec![
+        ("Cargo.secret", "Secret", true),
+        ("test_ecdsa_public_key.pub.pem", "EC", false),
+        ("test_rsa_private_key.pem", "RSA", true),
+        ("test_rsa_private_key.pub.pem", "RSA", false),
+      ]
+    );
+    assert!(store.keys.items[0].size.ends_with(" bytes"));
+    assert_eq!(store.keys.items[1].size, "P-384");
+    assert_eq!(store.keys.items[2].size, "2048 bits");
+    assert_eq!(
+      store.keys.items[2].thumbprint,
+      store.keys.items[3].thumbprint
+    );
+    assert!(store.keys.items[0].thumbprint.is_none());
+    // the last imported key is selected
+    assert_eq!(store.selected().unwrap().name, "Cargo.secret");
+
+    assert_eq!(
+      store
+        .import(Path::new("./test_data/test_rsa_private_key.der"))
+        .unwrap_err()
+        .to_string(),
+      "test_rsa_private_key.pem already exists in the key store"
+    );
+    assert!(store
+      .import(Path::new("./test_data/test_rsa_public_jwks.json"))
+      .is_err());
+    assert!(store.import(Path::new("./test_data/missing.pem")).is_err());
+
+    let _ = fs::remove_dir_all(store.dir.unwrap());
+  }
+
+  #[test]
+  fn test_verification_and_signing_secrets() {
+    let mut store = store("secrets");
+    let private = store
+      .import(Path::new("./test_data/test_eddsa_private_key.pem"))
+      .unwrap();
+    let public = private.with_file_name("test_eddsa_private_key.pub.pem");
+
+    store.keys.state.select(Some(0));
+    assert_eq!(store.selected().unwrap().default_alg(), Some("EdDSA"));
+    assert!(store.selected().unwrap().supports("EdDSA"));
+    assert!(!store.selected().unwrap().supports("ES256"));
+    assert_eq!(
+      store.verification_secret().unwrap(),
+      format!("@{}", public.display())
+    );
+    assert_eq!(
+      store.signing_secret().unwrap(),
+      format!("@{}", private.display())
+    );
+
+    store.keys.state.select(Some(1));
+    assert_eq!(
+      store.verification_secret().unwrap(),
+      format!("@{}", public.display())
+    );
+    assert_eq!(
+      store.signing_secret().unwrap_err().to_string(),
+      "test_eddsa_private_key.pub.pem is a public key, select a private key or secret to sign tokens"
+    );
+
+    // a private key without its public key
+    fs::remove_file(&public).unwrap();
+    store.load().unwrap();
+    assert_eq!(
+      store.verification_secret().unwrap_err().to_string(),
+      "The key store has no public key matching test_eddsa_private_key.pem"
+    );
+
+    let _ = fs::remove_dir_all(store.dir.unwrap());
+  }
+
+  #[test]
+  fn test_generate_key() {
+    let mut store = store("generate");
+    let path = store.generate(Algorithm::ES256).unwrap();
+    let name = path.file_name().unwrap().to_string_lossy().into_owned();
+    assert!(name.starts_with("es256-") && name.ends_with(".pem"));
+
+    let key = store.selected().unwrap();
+    assert_eq!(key.path, path);
+    assert_eq!((key.key_type.as_str(), key.size.as_str()), ("EC", "P-256"));
+    assert!(key.private);
+    assert_eq!(store.keys.items.len(), 2);
+
+    assert!(store.generate(Algorithm::HS256).is_err());
+
+    let _ = fs::remove_dir_all(store.dir.unwrap());
+  }
+}
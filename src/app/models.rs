@@ -60,6 +60,20 @@ impl<T> StatefulTable<T> {
       self.state.select(Some(i));
     }
   }
+
+  /// select the first or the last item
+  pub fn select_edge(&mut self, first: bool) {
+    if !self.items.is_empty() {
+      let i = if first { 0 } else { self.items.len() - 1 };
+      self.state.select(Some(i));
+    }
+  }
+}
+
+impl<T> Default for StatefulTable<T> {
+  fn default() -> Self {
+    StatefulTable::new()
+  }
 }
 
 impl<T> Scrollable for StatefulTable<T> {
@@ -155,29 +169,123 @@ impl BlockState {
   }
 }
 
+/// percentages of the panes of the decoder and encoder views
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Splits {
+  /// width of the left side of the view
+  pub left: u16,
+  /// height of the header block, the payload takes the rest
+  pub header: u16,
+}
+
+impl Default for Splits {
+  fn default() -> Self {
+    Self {
+      left: 50,
+      header: 40,
+    }
+  }
+}
+
+impl Splits {
+  /// smallest and largest percentage of a pane, so no block disappears
+  const MIN: u16 = 20;
+  const MAX: u16 = 80;
+  /// percentage a split moves by on every resize
+  pub const STEP: i16 = 5;
+
+  pub fn resize(&mut self, left: i16, header: i16) {
+    let resize = |value: u16, by: i16| value.saturating_add_signed(by).clamp(Self::MIN, Self::MAX);
+    self.left = resize(self.left, left);
+    self.header = resize(self.header, header);
+  }
+}
+
 #[derive(Debug, Eq, PartialEq, Default)]
 pub struct ScrollableTxt {
-  items: Vec<String>,
+  /// the text is kept whole, it is drawn on every frame
+  text: String,
+  /// number of lines of the text
+  lines: usize,
   pub offset: u16,
 }
 
 impl ScrollableTxt {
-  pub fn new(item: String) -> ScrollableTxt {
-    let items: Vec<&str> = item.split('\n').collect();
-    let items: Vec<String> = items.iter().map(|it| it.to_string()).collect();
-    ScrollableTxt { items, offset: 0 }
+  pub fn new(text: String) -> ScrollableTxt {
+    let lines = text.split('\n').count();
+    ScrollableTxt {
+      text,
+      lines,
+      offset: 0,
+    }
+  }
+
+  pub fn get_txt(&self) -> &str {
+    &self.text
+  }
+
+  /// scroll to the line, the last line when it is past the end of the text
+  pub fn scroll_to(&mut self, line: usize) {
+    self.offset = line.min(self.lines.saturating_sub(1)) as u16;
   }
 
-  pub fn get_txt(&self) -> String {
-    self.items.join("\n")
+  /// scroll to the next line containing the query from the top line, returns false when no line
+  /// matches
+  pub fn find(&mut self, query: &str, forward: bool, skip_current: bool) -> bool {
+    let current = self.offset as usize;
+    let lines: Vec<&str> = self.text.split('\n').collect();
+    match find_line(lines.len(), current, forward, skip_current, |i| {
+      contains_ignore_case(lines[i], query)
+    }) {
+      Some(line) => {
+        self.offset = line as u16;
+        true
+      }
+      None => false,
+    }
   }
 }
 
+/// ASCII case-insensitive search, which keeps the byte positions of matches in the original text
+pub fn contains_ignore_case(text: &str, query: &str) -> bool {
+  !query.is_empty()
+    && text
+      .to_ascii_lowercase()
+      .contains(&query.to_ascii_lowercase())
+}
+
+/// whether the characters of every word of the query appear in order in the text, ignoring case
+pub fn fuzzy_match(text: &str, query: &str) -> bool {
+  let text = text.to_lowercase();
+  query.split_whitespace().all(|word| {
+    let mut chars = text.chars();
+    word.to_lowercase().chars().all(|c| chars.any(|t| t == c))
+  })
+}
+
+/// first of `len` lines from the current one that matches, searching forward or backward and
+/// wrapping around at the ends like in vim
+pub fn find_line(
+  len: usize,
+  current: usize,
+  forward: bool,
+  skip_current: bool,
+  matches: impl Fn(usize) -> bool,
+) -> Option<usize> {
+  let first = usize::from(skip_current);
+  (first..len + first)
+    .map(|i| match forward {
+      true => (current + i) % len,
+      false => (current + 2 * len - i) % len,
+    })
+    .find(|&i| matches(i))
+}
+
 impl Scrollable for ScrollableTxt {
   fn scroll_down(&mut self, increment: usize) {
     // scroll only if offset is less than total lines in text
     // we subtract increment + 2 to keep the text in view. Its just an arbitrary number that works
-    if self.offset < self.items.len().saturating_sub(increment + 2) as u16 {
+    if self.offset < self.lines.saturating_sub(increment + 2) as u16 {
       self.offset += increment as u16;
     }
   }
@@ -195,6 +303,27 @@ mod tests {
   use super::*;
   use crate::app::RouteId;
 
+  #[test]
+  fn test_splits_resize() {
+    let mut splits = Splits::default();
+    splits.resize(Splits::STEP, -Splits::STEP);
+    assert_eq!(
+      splits,
+      Splits {
+        left: 55,
+        header: 35
+      }
+    );
+    splits.resize(100, -100);
+    assert_eq!(
+      splits,
+      Splits {
+        left: 80,
+        header: 20
+      }
+    );
+  }
+
   #[test]
   fn test_stateful_table() {
     let mut sft: StatefulTable<String> = StatefulTable::new();
@@ -307,12 +436,33 @@ mod tests {
     assert_eq!(tab.get_active_route().active_block, ActiveBlock::Help);
   }
 
+  #[test]
+  fn test_scrollable_txt_find() {
+    let mut stxt =
+      ScrollableTxt::new("{\n  \"sub\": \"1\",\n  \"Roles\": [],\n  \"roles2\": []\n}".into());
+    assert!(stxt.find("roles", true, false));
+    assert_eq!(stxt.offset, 2);
+    assert!(stxt.find("roles", true, false));
+    assert_eq!(stxt.offset, 2);
+    assert!(stxt.find("roles", true, true));
+    assert_eq!(stxt.offset, 3);
+    // wraps around at the end
+    assert!(stxt.find("roles", true, true));
+    assert_eq!(stxt.offset, 2);
+    assert!(stxt.find("ROLES", false, true));
+    assert_eq!(stxt.offset, 3);
+    assert!(!stxt.find("aud", true, false));
+    assert!(!stxt.find("", true, false));
+    assert_eq!(stxt.offset, 3);
+    assert_eq!(find_line(0, 0, true, true, |_| true), None);
+  }
+
   #[test]
   fn test_scrollable_txt() {
     let mut stxt = ScrollableTxt::new("test\n multiline\n string".into());
 
     assert_eq!(stxt.offset, 0);
-    assert_eq!(stxt.items.len(), 3);
+    assert_eq!(stxt.lines, 3);
 
     assert_eq!(stxt.get_txt(), "test\n multiline\n string");
 
@@ -320,7 +470,7 @@ mod tests {
     assert_eq!(stxt.offset, 0);
 
     let mut stxt2 = ScrollableTxt::new("te\nst\nmul\ntil\ni\nne\nstr\ni\nn\ng".into());
-    assert_eq!(stxt2.items.len(), 10);
+    assert_eq!(stxt2.lines, 10);
     stxt2.scroll_down(1);
     assert_eq!(stxt2.offset, 1);
     stxt2.scroll_down(1);
@@ -330,6 +480,7 @@ mod tests {
     stxt2.scroll_down(1);
     // no overflow past (len - 2)
     assert_eq!(stxt2.offset, 7);
+
     stxt2.scroll_up(1);
     assert_eq!(stxt2.offset, 6);
     stxt2.scroll_up(6);
@@ -337,5 +488,10 @@ mod tests {
     stxt2.scroll_up(1);
     // no overflow past (0)
     assert_eq!(stxt2.offset, 0);
+
+    stxt2.scroll_to(3);
+    assert_eq!(stxt2.offset, 3);
+    stxt2.scroll_to(42);
+    assert_eq!(stxt2.offset, 9);
   }
 }
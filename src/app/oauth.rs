@@ -0,0 +1,923 @@
+use std::{
+  io::{BufRead, BufReader, ErrorKind, Write},
+  net::TcpListener,
+  process::{Command, Stdio},
+  thread,
+  time::{Duration, Instant},
+};
+
+use base64::{
+  engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+  Engine,
+};
+use clap::ValueEnum;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::de::DeserializeOwned;
+use serde_derive::Deserialize;
+use sha2::{Digest, Sha256};
+
+use super::{
+  introspection::Introspection,
+  network::{request, RequestBody},
+  secrets::{SecretStore, COMMAND_PREFIX},
+  utils::{run_command, JWTError, JWTResult},
+  validation::format_duration,
+};
+
+/// `grant_type` of the token requests of the device flow
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+/// added to the polling interval when the token endpoint asks to slow down
+const SLOW_DOWN_INTERVAL: Duration = Duration::from_secs(5);
+/// time the browser has to be redirected with the code of the sign in
+pub const SIGN_IN_TIMEOUT: Duration = Duration::from_secs(300);
+/// how often the redirect listener checks for the browser
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+/// redirect URI of the authorization code grant when the client has none
+const DEFAULT_REDIRECT_URI: &str = "http://localhost:8400/callback";
+/// characters encoded in the query of the authorization URL, all but the unreserved ones
+const QUERY_VALUE: &AsciiSet = &NON_ALPHANUMERIC
+  .remove(b'-')
+  .remove(b'.')
+  .remove(b'_')
+  .remove(b'~');
+
+/// OAuth grant used to request a token with `--grant`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Grant {
+  /// token of the client itself, with its client id and secret
+  ClientCredentials,
+  /// token of a user signing in on another device with a code, for clients without browser
+  DeviceCode,
+  /// token of a user signing in with the browser, the code is exchanged with PKCE
+  AuthorizationCode,
+}
+
+impl Grant {
+  /// name of the grant in `--grant`
+  pub fn name(self) -> &'static str {
+    match self {
+      Grant::ClientCredentials => "client-credentials",
+      Grant::DeviceCode => "device-code",
+      Grant::AuthorizationCode => "authorization-code",
+    }
+  }
+}
+
+/// how the client authenticates at the token endpoint
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientAuth {
+  /// `client_secret_basic`: the client id and secret in the `Authorization` header
+  #[default]
+  Basic,
+  /// `client_secret_post`: the client id and secret in the request body
+  Post,
+}
+
+/// OAuth client of the config (`[oauth.<name>]`), the flags of `--grant` override its settings
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct OAuthClient {
+  pub token_url: String,
+  pub client_id: String,
+  /// client secret as text, or a `keyring:` or `cmd:` reference to it
+  pub client_secret: Option<String>,
+  pub client_auth: ClientAuth,
+  /// space separated scopes requested
+  pub scope: Option<String>,
+  /// `audience` of the token, required by some providers (e.g. Auth0)
+  pub audience: Option<String>,
+  /// device authorization endpoint of the device code grant
+  pub device_authorization_url: Option<String>,
+  /// authorization endpoint of the authorization code grant
+  pub authorization_url: Option<String>,
+  /// local `http` URI the browser is redirected to with the code, `http://localhost:8400/callback`
+  /// when not set. It has to be registered for the client
+  pub redirect_uri: Option<String>,
+  /// token introspection endpoint (RFC 7662)
+  pub introspection_url: Option<String>,
+}
+
+/// successful response of the token endpoint
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct TokenResponse {
+  pub access_token: String,
+  pub token_type: Option<String>,
+  /// lifetime of the access token in seconds
+  pub expires_in: Option<i64>,
+  pub refresh_token: Option<String>,
+  pub id_token: Option<String>,
+  pub scope: Option<String>,
+}
+
+impl TokenResponse {
+  /// when the access token expires, as shown after a token is received
+  pub fn expiry(&self) -> String {
+    match self.expires_in {
+      Some(expires_in) => format!("Access token expires in {}", format_duration(expires_in)),
+      None => "Access token received, the token endpoint didn't tell when it expires".into(),
+    }
+  }
+}
+
+/// response of the device authorization endpoint, with the code the user signs in with
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DeviceAuthorization {
+  pub device_code: String,
+  pub user_code: String,
+  /// page where the user enters the code, `verification_url` for Google
+  #[serde(alias = "verification_url")]
+  pub verification_uri: String,
+  /// page with the code already entered
+  pub verification_uri_complete: Option<String>,
+  /// lifetime of the codes in seconds
+  pub expires_in: u64,
+  /// seconds to wait between polls of the token endpoint
+  #[serde(default = "default_interval")]
+  pub interval: u64,
+}
+
+impl DeviceAuthorization {
+  /// the instructions for the user to sign in
+  pub fn instructions(&self) -> String {
+    match &self.verification_uri_complete {
+      Some(uri) => format!(
+        "To sign in, open {uri}\nor open {} and enter the code {}",
+        self.verification_uri, self.user_code
+      ),
+      None => format!(
+        "To sign in, open {} and enter the code {}",
+        self.verification_uri, self.user_code
+      ),
+    }
+  }
+}
+
+fn default_interval() -> u64 {
+  5
+}
+
+/// authorization request of the authorization code grant, opened in the browser
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorizationRequest {
+  /// URL of the authorization endpoint with the parameters of the request
+  pub url: String,
+  pub redirect_uri: String,
+  state: String,
+  code_verifier: String,
+}
+
+impl AuthorizationRequest {
+  /// listen on the address of the redirect URI, before the browser is opened
+  pub fn listen(&self) -> JWTResult<TcpListener> {
+    let (address, _) = redirect_address(&self.redirect_uri)?;
+    TcpListener::bind(address.as_str())
+      .map_err(|e| JWTError::Internal(format!("Unable to listen on {address}: {e}")))
+  }
+
+  /// wait for the browser to be redirected with the code of the sign in, until the timeout. Other
+  /// requests, like the favicon of the browser, are answered with a 404
+  pub fn wait_for_code(&self, listener: &TcpListener, timeout: Duration) -> JWTResult<String> {
+    let (_, path) = redirect_address(&self.redirect_uri)?;
+    let deadline = Instant::now() + timeout;
+    listener.set_nonblocking(true)?;
+    loop {
+      let mut stream = match listener.accept() {
+        Ok((stream, _)) => stream,
+        Err(e) if e.kind() == ErrorKind::WouldBlock => {
+          if Instant::now() >= deadline {
+            return Err(JWTError::Internal(format!(
+              "The sign in timed out after {}",
+              format_duration(timeout.as_secs() as i64)
+            )));
+          }
+          thread::sleep(ACCEPT_INTERVAL);
+          continue;
+        }
+        Err(e) => return Err(e.into()),
+      };
+      // a connection that sends nothing doesn't hold the sign in past the deadline
+      stream.set_nonblocking(false)?;
+      let remaining = deadline.saturating_duration_since(Instant::now());
+      stream.set_read_timeout(Some(remaining.max(ACCEPT_INTERVAL)))?;
+      let mut request_line = String::new();
+      BufReader::new(&stream).read_line(&mut request_line)?;
+      let target = request_line.split_whitespace().nth(1).unwrap_or_default();
+      let (target_path, query) = target.split_once('?').unwrap_or((target, ""));
+      if target_path != path {
+        let _ = stream
+          .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+        continue;
+      }
+      let params = query_params(query);
+      let param = |name: &str| {
+        params
+          .iter()
+          .find(|(key, _)| key == name)
+          .map(|(_, value)| value.clone())
+      };
+      let result = match (param("error"), param("code")) {
+        (Some(error), _) => Err(JWTError::Internal(match param("error_description") {
+          Some(description) => format!("The sign in failed with {error}: {description}"),
+          None => format!("The sign in failed with {error}"),
+        })),
+        _ if param("state").as_deref() != Some(self.state.as_str()) => Err(JWTError::Internal(
+          "The state of the redirect doesn't match the authorization request".into(),
+        )),
+        (None, Some(code)) => Ok(code),
+        (None, None) => Err(JWTError::Internal("The redirect has no code".into())),
+      };
+      let page = match &result {
+        Ok(_) => "Signed in, you can close this tab and go back to JWT UI.".to_string(),
+        Err(e) => e.to_string(),
+      };
+      let _ = write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{page}",
+        page.len()
+      );
+      return result;
+    }
+  }
+}
+
+/// open the URL in the default browser
+pub fn open_browser(url: &str) -> JWTResult<()> {
+  let mut command = match std::env::consts::OS {
+    "macos" => Command::new("open"),
+    // `cmd /C start` would split the URL at its `&`
+    "windows" => {
+      let mut command = Command::new("rundll32");
+      command.arg("url.dll,FileProtocolHandler");
+      command
+    }
+    _ => Command::new("xdg-open"),
+  };
+  let status = command
+    .arg(url)
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .status()
+    .map_err(|e| JWTError::Internal(format!("Unable to open the browser: {e}")))?;
+  match status.success() {
+    true => Ok(()),
+    false => Err(JWTError::Internal(format!(
+      "Unable to open the browser ({status})"
+    ))),
+  }
+}
+
+/// PKCE `S256` challenge of the verifier
+fn code_challenge(code_verifier: &str) -> String {
+  URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+/// the `host:port` to listen on and the path of a local `http` redirect URI
+fn redirect_address(redirect_uri: &str) -> JWTResult<(String, String)> {
+  let invalid = || {
+    JWTError::Internal(format!(
+      "Invalid redirect URI {redirect_uri}, expected a local http URI like {DEFAULT_REDIRECT_URI}"
+    ))
+  };
+  let rest = redirect_uri.strip_prefix("http://").ok_or_else(invalid)?;
+  let (authority, path) = match rest.find('/') {
+    Some(i) => (&rest[..i], &rest[i..]),
+    None => (rest, "/"),
+  };
+  let (host, port) = authority.rsplit_once(':').unwrap_or((authority, "80"));
+  if host.is_empty() || port.parse::<u16>().is_err() {
+    return Err(invalid());
+  }
+  Ok((format!("{host}:{port}"), path.to_string()))
+}
+
+/// the decoded parameters of a query string
+fn query_params(query: &str) -> Vec<(String, String)> {
+  let decode = |value: &str| {
+    percent_decode_str(&value.replace('+', " "))
+      .decode_utf8_lossy()
+      .into_owned()
+  };
+  query
+    .split('&')
+    .filter(|param| !param.is_empty())
+    .map(|param| {
+      let (name, value) = param.split_once('=').unwrap_or((param, ""));
+      (decode(name), decode(value))
+    })
+    .collect()
+}
+
+/// error response of an OAuth endpoint (RFC 6749 section 5.2)
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct ErrorResponse {
+  error: String,
+  error_description: Option<String>,
+}
+
+/// OAuth client of `--grant` or `--oauth-client`, with the tokens of the grant to request new ones
+/// from the decoder with `R`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OAuthSession {
+  pub client: OAuthClient,
+  pub grant: Option<Grant>,
+  pub tokens: Option<TokenResponse>,
+}
+
+impl OAuthClient {
+  /// check the settings needed to request a token
+  pub fn validate(&self) -> JWTResult<()> {
+    if self.token_url.is_empty() {
+      return Err(JWTError::Internal(
+        "The OAuth client has no token URL, set `token_url` or --token-endpoint".into(),
+      ));
+    }
+    if self.client_id.is_empty() {
+      return Err(JWTError::Internal(
+        "The OAuth client has no client id, set `client_id` or --client-id".into(),
+      ));
+    }
+    Ok(())
+  }
+
+  /// request a token with a grant that doesn't need the user
+  pub fn request_token(&self, grant: Grant) -> JWTResult<TokenResponse> {
+    self.validate()?;
+    match grant {
+      Grant::ClientCredentials => self.token_request(&[("grant_type", "client_credentials")], true),
+      Grant::DeviceCode | Grant::AuthorizationCode => Err(JWTError::Internal(format!(
+        "The {} grant needs the user to sign in, start again with --grant {}",
+        grant.name().replace('-', " "),
+        grant.name()
+      ))),
+    }
+  }
+
+  /// exchange a refresh token for a new access token (RFC 6749 section 6), with the scope of the
+  /// refresh token
+  pub fn refresh(&self, refresh_token: &str) -> JWTResult<TokenResponse> {
+    self.validate()?;
+    self.token_request(
+      &[
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token.trim()),
+      ],
+      false,
+    )
+  }
+
+  /// the authorization request of the authorization code grant, with a new PKCE verifier and
+  /// state (RFC 7636)
+  pub fn authorization_request(&self) -> JWTResult<AuthorizationRequest> {
+    self.validate()?;
+    let authorization_url = self.authorization_url.as_deref().ok_or_else(|| {
+      JWTError::Internal(
+        "The OAuth client has no authorization URL, set `authorization_url` or --authorization-endpoint"
+          .into(),
+      )
+    })?;
+    let redirect_uri = self
+      .redirect_uri
+      .clone()
+      .unwrap_or_else(|| DEFAULT_REDIRECT_URI.into());
+    redirect_address(&redirect_uri)?;
+    let code_verifier = URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>());
+    let state = URL_SAFE_NO_PAD.encode(rand::random::<[u8; 16]>());
+    let challenge = code_challenge(&code_verifier);
+    let mut params = vec![
+      ("response_type", "code"),
+      ("client_id", self.client_id.as_str()),
+      ("redirect_uri", redirect_uri.as_str()),
+      ("state", state.as_str()),
+      ("code_challenge", challenge.as_str()),
+      ("code_challenge_method", "S256"),
+    ];
+    if let Some(scope) = &self.scope {
+      params.push(("scope", scope));
+    }
+    if let Some(audience) = &self.audience {
+      params.push(("audience", audience));
+    }
+    let query = params
+      .iter()
+      .map(|(name, value)| format!("{name}={}", utf8_percent_encode(value, QUERY_VALUE)))
+      .collect::<Vec<_>>()
+      .join("&");
+    let separator = if authorization_url.contains('?') {
+      '&'
+    } else {
+      '?'
+    };
+    Ok(AuthorizationRequest {
+      url: format!("{authorization_url}{separator}{query}"),
+      redirect_uri,
+      state,
+      code_verifier,
+    })
+  }
+
+  /// exchange the code the browser was redirected with for the tokens
+  pub fn exchange_code(
+    &self,
+    request: &AuthorizationRequest,
+    code: &str,
+  ) -> JWTResult<TokenResponse> {
+    self.token_request(
+      &[
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", &request.redirect_uri),
+        ("code_verifier", &request.code_verifier),
+      ],
+      false,
+    )
+  }
+
+  /// start the device flow, the user signs in with the code of the response (RFC 8628)
+  pub fn device_authorization(&self) -> JWTResult<DeviceAuthorization> {
+    self.validate()?;
+    let url = self.device_authorization_url.as_deref().ok_or_else(|| {
+      JWTError::Internal(
+        "The OAuth client has no device authorization URL, set `device_authorization_url` or --device-endpoint"
+          .into(),
+      )
+    })?;
+    self.post(url, &[], true)?.map_err(|e| e.into_error(url))
+  }
+
+  /// poll the token endpoint until the user signed in with the code of the device authorization,
+  /// denied it or the code expired
+  pub fn poll_device_token(&self, authorization: &DeviceAuthorization) -> JWTResult<TokenResponse> {
+    let expires_at = Instant::now() + Duration::from_secs(authorization.expires_in);
+    let mut interval = Duration::from_secs(authorization.interval);
+    let fields = [
+      ("grant_type", DEVICE_CODE_GRANT),
+      ("device_code", authorization.device_code.as_str()),
+    ];
+    loop {
+      thread::sleep(interval);
+      match self.post(&self.token_url, &fields, false)? {
+        Ok(tokens) => return Ok(tokens),
+        Err(e) if e.error == "authorization_pending" => {}
+        Err(e) if e.error == "slow_down" => interval += SLOW_DOWN_INTERVAL,
+        Err(e) => return Err(e.into_error(&self.token_url)),
+      }
+      if Instant::now() >= expires_at {
+        return Err(JWTError::Internal(
+          "The device code expired before the sign in, start again".into(),
+        ));
+      }
+    }
+  }
+
+  /// ask the introspection endpoint whether the token is active and what it knows about it
+  pub fn introspect(&self, token: &str) -> JWTResult<Introspection> {
+    let url = self.introspection_url.as_deref().ok_or_else(|| {
+      JWTError::Internal(
+        "The OAuth client has no introspection URL, set `introspection_url` or --introspection-endpoint"
+          .into(),
+      )
+    })?;
+    if self.client_id.is_empty() {
+      return Err(JWTError::Internal(
+        "The OAuth client has no client id, set `client_id` or --client-id".into(),
+      ));
+    }
+    self
+      .post(url, &[("token", token.trim())], false)?
+      .map_err(|e| e.into_error(url))
+  }
+
+  fn token_request(&self, fields: &[(&str, &str)], scoped: bool) -> JWTResult<TokenResponse> {
+    self
+      .post(&self.token_url, fields, scoped)?
+      .map_err(|e| e.into_error(&self.token_url))
+  }
+
+  /// POST the fields to an endpoint of the authorization server with the client authentication,
+  /// and the scope and audience when `scoped`. OAuth errors are kept apart from the other errors
+  /// for the callers expecting some of them
+  fn post<T: DeserializeOwned>(
+    &self,
+    url: &str,
+    fields: &[(&str, &str)],
+    scoped: bool,
+  ) -> JWTResult<Result<T, ErrorResponse>> {
+    let secret = self
+      .client_secret
+      .as_deref()
+      .map(resolve_client_secret)
+      .transpose()?;
+    let mut form = fields.to_vec();
+    if scoped {
+      if let Some(scope) = &self.scope {
+        form.push(("scope", scope));
+      }
+      if let Some(audience) = &self.audience {
+        form.push(("audience", audience));
+      }
+    }
+    let mut headers = vec![("Accept".to_string(), "application/json".to_string())];
+    match (self.client_auth, &secret) {
+      (ClientAuth::Basic, Some(secret)) => {
+        headers.push(("Authorization".into(), basic_auth(&self.client_id, secret)))
+      }
+      (ClientAuth::Post, Some(secret)) => {
+        form.push(("client_id", &self.client_id));
+        form.push(("client_secret", secret));
+      }
+      // public clients only identify themselves
+      (_, None) => form.push(("client_id", &self.client_id)),
+    }
+    let (status, body) = request(url, &headers, RequestBody::Form(&form))?;
+    if status.is_success() {
+      return serde_json::from_slice(&body)
+        .map(Ok)
+        .map_err(|e| JWTError::Internal(format!("Invalid response from {url}: {e}")));
+    }
+    serde_json::from_slice(&body).map(Err).map_err(|_| {
+      JWTError::Internal(format!(
+        "{url} returned {status}: {}",
+        String::from_utf8_lossy(&body).trim()
+      ))
+    })
+  }
+}
+
+impl ErrorResponse {
+  fn into_error(self, url: &str) -> JWTError {
+    JWTError::Internal(match self.error_description {
+      Some(description) => format!("{url} returned {}: {description}", self.error),
+      None => format!("{url} returned {}", self.error),
+    })
+  }
+}
+
+/// `client_secret_basic` header, the id and secret are form encoded as required by RFC 6749
+fn basic_auth(client_id: &str, client_secret: &str) -> String {
+  let encode = |value| utf8_percent_encode(value, NON_ALPHANUMERIC).to_string();
+  let credentials = format!("{}:{}", encode(client_id), encode(client_secret));
+  format!("Basic {}", STANDARD.encode(credentials))
+}
+
+/// the client secret of a `keyring:` or `cmd:` reference, other secrets are used as is
+fn resolve_client_secret(secret: &str) -> JWTResult<String> {
+  match secret.strip_prefix(COMMAND_PREFIX) {
+    Some(cmd) => run_command(cmd.trim()),
+    None => {
+      let mut secrets = SecretStore::default();
+      secrets.fetch_keyring_entries(secret);
+      secrets.fetch_queued();
+      secrets.resolve(secret).map(|secret| secret.into_owned())
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app::network::tests::{serve_in_order, serve_once_with_request};
+
+  fn client(token_url: String) -> OAuthClient {
+    OAuthClient {
+      token_url,
+      client_id: "my-app".into(),
+      client_secret: Some("s3cr3t/+".into()),
+      scope: Some("read write".into()),
+      ..OAuthClient::default()
+    }
+  }
+
+  #[test]
+  fn test_client_credentials() {
+    let (url, request) = serve_once_with_request(
+      "200 OK",
+      br#"{"access_token": "eyJ.a.b", "token_type": "Bearer", "expires_in": 3600}"#.to_vec(),
+    );
+    let tokens = client(url).request_token(Grant::ClientCredentials).unwrap();
+    assert_eq!(tokens.access_token, "eyJ.a.b");
+    assert_eq!(tokens.expiry(), "Access token expires in 1h");
+    let request = request.recv().unwrap();
+    assert!(request.starts_with("POST / HTTP/1.1"));
+    assert!(request
+      .to_lowercase()
+      .contains(&format!("authorization: {}", basic_auth("my-app", "s3cr3t/+")).to_lowercase()));
+    assert!(request.ends_with("grant_type=client_credentials&scope=read+write"));
+
+    let (url, request) = serve_once_with_request("200 OK", br#"{"access_token": "x"}"#.to_vec());
+    let tokens = OAuthClient {
+      client_auth: ClientAuth::Post,
+      scope: None,
+      ..client(url)
+    }
+    .request_token(Grant::ClientCredentials)
+    .unwrap();
+    assert!(tokens.expiry().starts_with("Access token received"));
+    assert!(request
+      .recv()
+      .unwrap()
+      .ends_with("grant_type=client_credentials&client_id=my-app&client_secret=s3cr3t%2F%2B"));
+  }
+
+  #[test]
+  fn test_client_credentials_errors() {
+    assert!(OAuthClient::default()
+      .request_token(Grant::ClientCredentials)
+      .unwrap_err()
+      .to_string()
+      .starts_with("The OAuth client has no token URL"));
+
+    let (url, _) = serve_once_with_request(
+      "401 Unauthorized",
+      br#"{"error": "invalid_client", "error_description": "Unknown client"}"#.to_vec(),
+    );
+    assert_eq!(
+      client(url.clone())
+        .request_token(Grant::ClientCredentials)
+        .unwrap_err()
+        .to_string(),
+      format!("{url} returned invalid_client: Unknown client")
+    );
+
+    let (url, _) = serve_once_with_request("502 Bad Gateway", b"upstream down".to_vec());
+    assert_eq!(
+      client(url.clone())
+        .request_token(Grant::ClientCredentials)
+        .unwrap_err()
+        .to_string(),
+      format!("{url} returned 502 Bad Gateway: upstream down")
+    );
+  }
+
+  #[test]
+  fn test_device_code() {
+    let (device_url, device_request) = serve_once_with_request(
+      "200 OK",
+      br#"{"device_code": "dev-123", "user_code": "WDJB-MJHT", "verification_uri": "https://idp.example.com/device", "expires_in": 600, "interval": 0}"#.to_vec(),
+    );
+    let (token_url, token_requests) = serve_in_order(vec![
+      (
+        "400 Bad Request",
+        br#"{"error": "authorization_pending"}"#.to_vec(),
+      ),
+      (
+        "200 OK",
+        br#"{"access_token": "at.t.s", "id_token": "id.t.s", "refresh_token": "rt"}"#.to_vec(),
+      ),
+    ]);
+    let client = OAuthClient {
+      token_url,
+      client_id: "my-tv-app".into(),
+      scope: Some("openid offline_access".into()),
+      device_authorization_url: Some(device_url),
+      ..OAuthClient::default()
+    };
+
+    let authorization = client.device_authorization().unwrap();
+    assert_eq!(
+      authorization.instructions(),
+      "To sign in, open https://idp.example.com/device and enter the code WDJB-MJHT"
+    );
+    assert!(device_request
+      .recv()
+      .unwrap()
+      .ends_with("scope=openid+offline_access&client_id=my-tv-app"));
+
+    let tokens = client.poll_device_token(&authorization).unwrap();
+    assert_eq!(tokens.id_token.as_deref(), Some("id.t.s"));
+    assert_eq!(tokens.refresh_token.as_deref(), Some("rt"));
+    let poll = token_requests.recv().unwrap();
+    assert!(poll.ends_with(
+      "grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Adevice_code&device_code=dev-123&client_id=my-tv-app"
+    ));
+    assert!(client
+      .request_token(Grant::DeviceCode)
+      .unwrap_err()
+      .to_string()
+      .starts_with("The device code grant needs the user to sign in"));
+  }
+
+  #[test]
+  fn test_device_code_denied() {
+    let (token_url, _) =
+      serve_once_with_request("400 Bad Request", br#"{"error": "access_denied"}"#.to_vec());
+    let client = OAuthClient {
+      token_url: token_url.clone(),
+      client_id: "my-tv-app".into(),
+      ..OAuthClient::default()
+    };
+    let authorization = DeviceAuthorization {
+      device_code: "dev-123".into(),
+      user_code: "WDJB-MJHT".into(),
+      verification_uri: "https://idp.example.com/device".into(),
+      verification_uri_complete: Some("https://idp.example.com/device?code=WDJB-MJHT".into()),
+      expires_in: 600,
+      interval: 0,
+    };
+    assert!(authorization
+      .instructions()
+      .starts_with("To sign in, open https://idp.example.com/device?code=WDJB-MJHT\n"));
+    assert_eq!(
+      client
+        .poll_device_token(&authorization)
+        .unwrap_err()
+        .to_string(),
+      format!("{token_url} returned access_denied")
+    );
+    assert!(client
+      .device_authorization()
+      .unwrap_err()
+      .to_string()
+      .starts_with("The OAuth client has no device authorization URL"));
+  }
+
+  fn send_redirect(address: String, target: &'static str) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+      let mut stream = std::net::TcpStream::connect(address).unwrap();
+      write!(stream, "GET {target} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+      let mut response = String::new();
+      std::io::Read::read_to_string(&mut stream, &mut response).unwrap();
+      response
+    })
+  }
+
+  #[test]
+  fn test_authorization_code() {
+    let client = OAuthClient {
+      token_url: "https://idp.example.com/oauth/token".into(),
+      client_id: "my-cli".into(),
+      scope: Some("openid profile".into()),
+      authorization_url: Some("https://idp.example.com/authorize".into()),
+      redirect_uri: Some("http://127.0.0.1:0/callback".into()),
+      ..OAuthClient::default()
+    };
+    let request = client.authorization_request().unwrap();
+    assert!(request.url.starts_with(
+      "https://idp.example.com/authorize?response_type=code&client_id=my-cli&redirect_uri=http%3A%2F%2F127.0.0.1%3A0%2Fcallback&state="
+    ));
+    assert!(request.url.contains(&format!(
+      "&code_challenge={}&code_challenge_method=S256&scope=openid%20profile",
+      code_challenge(&request.code_verifier)
+    )));
+
+    let listener = request.listen().unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let favicon = send_redirect(address.clone(), "/favicon.ico");
+    let target: &'static str =
+      Box::leak(format!("/callback?code=abc%2F123&state={}", request.state).into_boxed_str());
+    let redirect = send_redirect(address.clone(), target);
+    assert_eq!(
+      request.wait_for_code(&listener, SIGN_IN_TIMEOUT).unwrap(),
+      "abc/123"
+    );
+    assert!(favicon.join().unwrap().starts_with("HTTP/1.1 404"));
+    assert!(redirect
+      .join()
+      .unwrap()
+      .ends_with("Signed in, you can close this tab and go back to JWT UI."));
+
+    let denied = send_redirect(
+      address.clone(),
+      "/callback?error=access_denied&error_description=User+cancelled",
+    );
+    assert_eq!(
+      request
+        .wait_for_code(&listener, SIGN_IN_TIMEOUT)
+        .unwrap_err()
+        .to_string(),
+      "The sign in failed with access_denied: User cancelled"
+    );
+    denied.join().unwrap();
+    let forged = send_redirect(address, "/callback?code=abc&state=forged");
+    assert!(request
+      .wait_for_code(&listener, SIGN_IN_TIMEOUT)
+      .unwrap_err()
+      .to_string()
+      .starts_with("The state of the redirect doesn't match"));
+    forged.join().unwrap();
+
+    assert_eq!(
+      request
+        .wait_for_code(&listener, Duration::from_secs(1))
+        .unwrap_err()
+        .to_string(),
+      "The sign in timed out after 1s"
+    );
+  }
+
+  #[test]
+  fn test_exchange_code() {
+    let (token_url, token_request) = serve_once_with_request(
+      "200 OK",
+      br#"{"access_token": "at.t.s", "id_token": "id.t.s"}"#.to_vec(),
+    );
+    let client = OAuthClient {
+      token_url,
+      client_id: "my-cli".into(),
+      authorization_url: Some("https://idp.example.com/authorize?tenant=acme".into()),
+      ..OAuthClient::default()
+    };
+    let request = client.authorization_request().unwrap();
+    assert!(request
+      .url
+      .starts_with("https://idp.example.com/authorize?tenant=acme&response_type=code"));
+    assert_eq!(request.redirect_uri, "http://localhost:8400/callback");
+
+    let tokens = client.exchange_code(&request, "abc").unwrap();
+    assert_eq!(tokens.id_token.as_deref(), Some("id.t.s"));
+    assert!(token_request.recv().unwrap().ends_with(&format!(
+      "grant_type=authorization_code&code=abc&redirect_uri=http%3A%2F%2Flocalhost%3A8400%2Fcallback&code_verifier={}&client_id=my-cli",
+      request.code_verifier
+    )));
+  }
+
+  #[test]
+  fn test_refresh() {
+    let (url, request) = serve_once_with_request(
+      "200 OK",
+      br#"{"access_token": "eyJ.new.token", "refresh_token": "rt-2"}"#.to_vec(),
+    );
+    let tokens = client(url).refresh("rt-1\n").unwrap();
+    assert_eq!(tokens.access_token, "eyJ.new.token");
+    assert_eq!(tokens.refresh_token.as_deref(), Some("rt-2"));
+    assert!(request
+      .recv()
+      .unwrap()
+      .ends_with("\r\n\r\ngrant_type=refresh_token&refresh_token=rt-1"));
+
+    let (url, _) = serve_once_with_request(
+      "400 Bad Request",
+      br#"{"error": "invalid_grant", "error_description": "Token is expired"}"#.to_vec(),
+    );
+    assert_eq!(
+      client(url.clone()).refresh("rt-1").unwrap_err().to_string(),
+      format!("{url} returned invalid_grant: Token is expired")
+    );
+  }
+
+  #[test]
+  fn test_introspect() {
+    let (url, request) = serve_once_with_request(
+      "200 OK",
+      br#"{"active": true, "scope": "read write", "exp": 1700000000}"#.to_vec(),
+    );
+    let client = OAuthClient {
+      introspection_url: Some(url),
+      ..client(String::new())
+    };
+    let introspection = client.introspect(" eyJ.a.b\n").unwrap();
+    assert!(introspection.active);
+    assert_eq!(introspection.claims["scope"], "read write");
+    let request = request.recv().unwrap();
+    assert!(request
+      .to_lowercase()
+      .contains(&format!("authorization: {}", basic_auth("my-app", "s3cr3t/+")).to_lowercase()));
+    assert!(request.ends_with("\r\n\r\ntoken=eyJ.a.b"));
+
+    let (url, _) = serve_once_with_request(
+      "401 Unauthorized",
+      br#"{"error": "invalid_client"}"#.to_vec(),
+    );
+    let client = OAuthClient {
+      introspection_url: Some(url.clone()),
+      ..client
+    };
+    assert_eq!(
+      client.introspect("eyJ.a.b").unwrap_err().to_string(),
+      format!("{url} returned invalid_client")
+    );
+    assert!(OAuthClient::default()
+      .introspect("eyJ.a.b")
+      .unwrap_err()
+      .to_string()
+      .starts_with("The OAuth client has no introspection URL"));
+  }
+
+  #[test]
+  fn test_code_challenge_and_redirect_address() {
+    // RFC 7636 appendix B
+    assert_eq!(
+      code_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+      "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+    );
+    assert_eq!(
+      redirect_address("http://localhost:8400/callback").unwrap(),
+      ("localhost:8400".into(), "/callback".into())
+    );
+    assert_eq!(
+      redirect_address("http://127.0.0.1").unwrap(),
+      ("127.0.0.1:80".into(), "/".into())
+    );
+    assert!(redirect_address("https://localhost:8400/callback").is_err());
+    assert!(redirect_address("http://localhost:port/callback").is_err());
+  }
+
+  #[test]
+  fn test_basic_auth() {
+    assert_eq!(basic_auth("id", "secret"), "Basic aWQ6c2VjcmV0");
+    assert_eq!(
+      basic_auth("a:b", "c"),
+      format!("Basic {}", STANDARD.encode("a%3Ab:c"))
+    );
+  }
+}
@@ -0,0 +1,321 @@
+use std::fs;
+
+use base64::{
+  engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+  Engine,
+};
+use pasetors::{
+  keys::{AsymmetricPublicKey, SymmetricKey},
+  token::{TrustedToken, UntrustedToken},
+  version2::{self, V2},
+  version4::{self, V4},
+  Local, Public,
+};
+use serde_json::json;
+use x509_parser::{pem::Pem, prelude::FromDer, x509::SubjectPublicKeyInfo};
+
+use super::{
+  jwt_decoder::{Payload, RawToken},
+  utils::{JWTError, JWTResult},
+};
+
+/// size of the Ed25519 signature appended to the message of public tokens
+const SIGNATURE_SIZE: usize = 64;
+
+const PASETO_HEADERS: [&str; 4] = ["v2.local.", "v2.public.", "v4.local.", "v4.public."];
+
+/// returns true if the token looks like a supported PASETO token
+pub fn is_paseto(token: &str) -> bool {
+  PASETO_HEADERS.iter().any(|h| token.starts_with(h))
+}
+
+/// returns the decoded values and the verification (or decryption) result
+pub fn decode_paseto(token: &str, secret: &str) -> (JWTResult<RawToken>, JWTResult<()>) {
+  let token = token.trim();
+  let Some(paseto_header) = PASETO_HEADERS.iter().find(|h| token.starts_with(*h)) else {
+    let err = || JWTError::Internal("Unsupported PASETO token".into());
+    return (Err(err()), Err(err()));
+  };
+  let is_public = paseto_header.ends_with("public.");
+
+  let decoded = decode_untrusted(token, paseto_header, is_public);
+
+  if secret.is_empty() {
+    let err = if is_public {
+      "Provide the public key to verify the PASETO signature"
+    } else {
+      "Provide the symmetric key to decrypt the PASETO payload"
+    };
+    return (decoded, Err(JWTError::Internal(err.into())));
+  }
+
+  let verified = paseto_key(secret).and_then(|key| verify(token, paseto_header, &key));
+  match (decoded, verified) {
+    (Ok(mut decoded), Ok(trusted)) => {
+      // local tokens can only be read after decryption
+      decoded.claims = Some(parse_claims(trusted.payload().as_bytes()));
+      (Ok(decoded), Ok(()))
+    }
+    (decoded, verified) => (decoded, verified.map(|_| ())),
+  }
+}
+
+/// read version, purpose, footer and (for public tokens) claims without verifying the token
+fn decode_untrusted(token: &str, paseto_header: &str, is_public: bool) -> JWTResult<RawToken> {
+  let parts: Vec<&str> = token[paseto_header.len()..].split('.').collect();
+  let invalid = |e: String| JWTError::Internal(format!("Invalid PASETO token: {e}"));
+  if parts.len() > 2 {
+    return Err(invalid("too many segments".into()));
+  }
+
+  let message = URL_SAFE_NO_PAD
+    .decode(parts[0])
+    .map_err(|e| invalid(e.to_string()))?;
+  let footer = match parts.get(1) {
+    Some(footer) => URL_SAFE_NO_PAD
+      .decode(footer)
+      .map_err(|e| invalid(e.to_string()))?,
+    None => vec![],
+  };
+
+  let mut segments = paseto_header.split('.');
+  let mut header = json!({
+    "version": segments.next(),
+    "purpose": segments.next(),
+  });
+  if !footer.is_empty() {
+    header["footer"] = serde_json::from_slice(&footer)
+      .unwrap_or_else(|_| String::from_utf8_lossy(&footer).into_owned().into());
+  }
+
+  let claims = if is_public {
+    if message.len() < SIGNATURE_SIZE {
+      return Err(invalid("message is too short".into()));
+    }
+    Some(parse_claims(&message[..message.len() - SIGNATURE_SIZE]))
+  } else {
+    None
+  };
+
+  Ok(RawToken { header, claims })
+}
+
+/// PASETO payloads are JSON claims, anything else is shown as a single `payload` claim
+fn parse_claims(payload: &[u8]) -> Payload {
+  serde_json::from_slice(payload).unwrap_or_else(|_| {
+    Payload(
+      [(
+        "payload".to_string(),
+        String::from_utf8_lossy(payload).into_owned().into(),
+      )]
+      .into(),
+    )
+  })
+}
+
+fn verify(token: &str, paseto_header: &str, key: &[u8]) -> JWTResult<TrustedToken> {
+  let result = match paseto_header {
+    "v2.public." => AsymmetricPublicKey::<V2>::from(key).and_then(|key| {
+      version2::PublicToken::verify(&key, &UntrustedToken::<Public, V2>::try_from(token)?, None)
+    }),
+    "v4.public." => AsymmetricPublicKey::<V4>::from(key).and_then(|key| {
+      let untrusted = UntrustedToken::<Public, V4>::try_from(token)?;
+      version4::PublicToken::verify(&key, &untrusted, None, None)
+    }),
+    "v2.local." => SymmetricKey::<V2>::from(key).and_then(|key| {
+      version2::LocalToken::decrypt(&key, &UntrustedToken::<Local, V2>::try_from(token)?, None)
+    }),
+    _ => SymmetricKey::<V4>::from(key).and_then(|key| {
+      let untrusted = UntrustedToken::<Local, V4>::try_from(token)?;
+      version4::LocalToken::decrypt(&key, &untrusted, None, None)
+    }),
+  };
+  result.map_err(|e| {
+    JWTError::Internal(match e {
+      pasetors::errors::Error::Key => format!("Invalid key for {paseto_header} token"),
+      pasetors::errors::Error::TokenValidation => {
+        "The PASETO token can't be verified with the provided key".into()
+      }
+      e => format!("Invalid PASETO token: {e:?}"),
+    })
+  })
+}
+
+/// read the raw key bytes from the secret input. Supported formats are PASERK (`k4.public.…`),
+/// 64 hex characters, `b64:` prefixed base64 and files (`@key.pem` for Ed25519 public keys)
+fn paseto_key(secret: &str) -> JWTResult<Vec<u8>> {
+  let secret = match secret.strip_prefix('@') {
+    Some(path) => {
+      let data = fs::read(path)
+        .map_err(|e| JWTError::Internal(format!("Unable to read key file {path}: {e}")))?;
+      if let Some(pem) = Pem::iter_from_buffer(&data).find_map(|pem| pem.ok()) {
+        return public_key_from_spki(&pem.contents);
+      }
+      String::from_utf8_lossy(&data).trim().to_string()
+    }
+    None => secret.to_string(),
+  };
+
+  let paserk = ["k2.local.", "k4.local.", "k2.public.", "k4.public."]
+    .iter()
+    .find_map(|prefix| secret.strip_prefix(prefix));
+  let invalid_key = |e: String| JWTError::Internal(format!("Invalid PASETO key: {e}"));
+
+  if let Some(key) = paserk {
+    URL_SAFE_NO_PAD
+      .decode(key)
+      .map_err(|e| invalid_key(e.to_string()))
+  } else if let Some(key) = secret.strip_prefix("b64:") {
+    STANDARD.decode(key).map_err(|e| invalid_key(e.to_string()))
+  } else if secret.len() == 64 && secret.chars().all(|c| c.is_ascii_hexdigit()) {
+    (0..secret.len())
+      .step_by(2)
+      .map(|i| u8::from_str_radix(&secret[i..i + 2], 16).map_err(|e| invalid_key(e.to_string())))
+      .collect()
+  } else {
+    Ok(secret.into_bytes())
+  }
+}
+
+fn public_key_from_spki(der: &[u8]) -> JWTResult<Vec<u8>> {
+  SubjectPublicKeyInfo::from_der(der)
+    .map(|(_, spki)| spki.subject_public_key.data.to_vec())
+    .map_err(|e| JWTError::Internal(format!("Invalid public key file: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+  use pasetors::keys::{AsymmetricKeyPair, AsymmetricSecretKey, Generate};
+
+  use super::*;
+
+  const LOCAL_KEY_HEX: &str = "707172737475767778797a7b7c7d7e7f808182838485868788898a8b8c8d8e8f";
+
+  /// Ed25519 secret key (seed || public key) matching test_data/test_eddsa_public_key.pem
+  fn eddsa_secret_key() -> Vec<u8> {
+    let der = |path: &str| {
+      let data = fs::read(path).unwrap();
+      Pem::iter_from_buffer(&data)
+        .next()
+        .unwrap()
+        .unwrap()
+        .contents
+    };
+    let private_key = der("./test_data/test_eddsa_private_key.pem");
+    let public_key = der("./test_data/test_eddsa_public_key.pem");
+    let mut key = private_key[private_key.len() - 32..].to_vec();
+    key.extend_from_slice(&public_key[public_key.len() - 32..]);
+    key
+  }
+
+  fn v4_public_token(footer: Option<&[u8]>) -> String {
+    let key = AsymmetricSecretKey::<V4>::from(&eddsa_secret_key()).unwrap();
+    version4::PublicToken::sign(
+      &key,
+      br#"{"sub":"1234","exp":"2030-01-01T00:00:00+00:00"}"#,
+      footer,
+      None,
+    )
+    .unwrap()
+  }
+
+  fn local_key() -> Vec<u8> {
+    paseto_key(LOCAL_KEY_HEX).unwrap()
+  }
+
+  #[test]
+  fn test_is_paseto() {
+    assert!(is_paseto("v4.public.abc"));
+    assert!(is_paseto("v2.local.abc"));
+    assert!(!is_paseto("v3.local.abc"));
+    assert!(!is_paseto("eyJhbGciOiJIUzI1NiJ9.e30.abc"));
+  }
+
+  #[test]
+  fn test_decode_v4_public_without_key() {
+    let token = v4_public_token(Some(br#"{"kid":"k1"}"#));
+
+    let (decoded, verified) = decode_paseto(&token, "");
+    let decoded = decoded.unwrap();
+
+    assert_eq!(
+      decoded.header,
+      json!({"version": "v4", "purpose": "public", "footer": {"kid": "k1"}})
+    );
+    assert_eq!(decoded.claims.unwrap().0.get("sub").unwrap(), "1234");
+    assert_eq!(
+      verified.unwrap_err().to_string(),
+      "Provide the public key to verify the PASETO signature"
+    );
+  }
+
+  #[test]
+  fn test_decode_v4_public_with_pem_key() {
+    let token = v4_public_token(None);
+
+    let (decoded, verified) = decode_paseto(&token, "@./test_data/test_eddsa_public_key.pem");
+    assert!(decoded.is_ok());
+    assert!(verified.is_ok());
+
+    // public key of another key pair
+    let other = AsymmetricKeyPair::<V4>::generate().unwrap();
+    let (_, verified) = decode_paseto(
+      &token,
+      &format!(
+        "k4.public.{}",
+        URL_SAFE_NO_PAD.encode(other.public.as_bytes())
+      ),
+    );
+    assert_eq!(
+      verified.unwrap_err().to_string(),
+      "The PASETO token can't be verified with the provided key"
+    );
+  }
+
+  #[test]
+  fn test_decode_local_tokens() {
+    let v4_key = SymmetricKey::<V4>::from(&local_key()).unwrap();
+    let v4 =
+      version4::LocalToken::encrypt(&v4_key, br#"{"sub":"1234"}"#, Some(b"footer"), None).unwrap();
+    let v2_key = SymmetricKey::<V2>::from(&local_key()).unwrap();
+    let v2 = version2::LocalToken::encrypt(&v2_key, b"not json", None).unwrap();
+
+    let (decoded, verified) = decode_paseto(&v4, "");
+    let decoded = decoded.unwrap();
+    assert_eq!(
+      decoded.header,
+      json!({"version": "v4", "purpose": "local", "footer": "footer"})
+    );
+    assert_eq!(decoded.claims, None);
+    assert_eq!(
+      verified.unwrap_err().to_string(),
+      "Provide the symmetric key to decrypt the PASETO payload"
+    );
+
+    let (decoded, verified) = decode_paseto(&v4, LOCAL_KEY_HEX);
+    assert!(verified.is_ok());
+    assert_eq!(
+      decoded.unwrap().claims.unwrap().0.get("sub").unwrap(),
+      "1234"
+    );
+
+    let paserk = format!("k2.local.{}", URL_SAFE_NO_PAD.encode(local_key()));
+    let (decoded, verified) = decode_paseto(&v2, &paserk);
+    assert!(verified.is_ok());
+    assert_eq!(
+      decoded.unwrap().claims.unwrap().0.get("payload").unwrap(),
+      "not json"
+    );
+
+    let (_, verified) = decode_paseto(&v4, &format!("b64:{}", STANDARD.encode([7u8; 32])));
+    assert_eq!(
+      verified.unwrap_err().to_string(),
+      "The PASETO token can't be verified with the provided key"
+    );
+    let (_, verified) = decode_paseto(&v4, "short");
+    assert_eq!(
+      verified.unwrap_err().to_string(),
+      "Invalid key for v4.local. token"
+    );
+  }
+}
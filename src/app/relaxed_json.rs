@@ -0,0 +1,211 @@
+use std::fmt;
+
+/// strict JSON of the relaxed JSON typed in the encoder: `//` and `/* */` comments, trailing commas
+/// and single quoted strings are accepted. Comments and trailing commas are blanked out instead of
+/// removed so the lines of the result are the lines of the typed text
+pub fn to_strict_json(text: &str) -> String {
+  convert(text).into_iter().map(|(c, _)| c).collect()
+}
+
+/// a syntax error of relaxed JSON, at the 1-based line and column of the typed text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+  pub message: String,
+  pub line: usize,
+  pub column: usize,
+}
+
+impl fmt::Display for SyntaxError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{} at line {} column {}",
+      self.message, self.line, self.column
+    )
+  }
+}
+
+/// the error of the strict JSON of the typed text, moved back to where it is in the typed text as
+/// escaped quotes change the length of strings
+pub fn syntax_error(text: &str, error: &serde_json::Error) -> SyntaxError {
+  let message = error.to_string();
+  let message = message
+    .strip_suffix(&format!(
+      " at line {} column {}",
+      error.line(),
+      error.column()
+    ))
+    .unwrap_or(&message)
+    .to_string();
+  let (line, column) = typed_position(text, error.line(), error.column());
+  SyntaxError {
+    message,
+    line,
+    column,
+  }
+}
+
+/// the line and column of the typed text at a line and byte column of its strict JSON
+fn typed_position(text: &str, line: usize, column: usize) -> (usize, usize) {
+  if column == 0 {
+    return (line, column);
+  }
+  let (mut current, mut offset) = (1, 0);
+  for (c, typed) in convert(text) {
+    if current == line && offset + c.len_utf8() >= column {
+      let line_start = text[..typed].rfind('\n').map_or(0, |i| i + 1);
+      return (line, typed - line_start + 1);
+    }
+    match c {
+      '\n' => (current, offset) = (current + 1, 0),
+      c => offset += c.len_utf8(),
+    }
+  }
+  (line, column)
+}
+
+/// the chars of the strict JSON with the byte offset of the typed char each one comes from
+fn convert(text: &str) -> Vec<(char, usize)> {
+  remove_trailing_commas(strip_comments_and_quotes(text))
+}
+
+fn strip_comments_and_quotes(text: &str) -> Vec<(char, usize)> {
+  let chars: Vec<(usize, char)> = text.char_indices().collect();
+  let char_at = |i: usize| chars.get(i).map(|&(_, c)| c);
+  let blank = |c: char| if c == '\n' { '\n' } else { ' ' };
+  let mut out = Vec::with_capacity(chars.len());
+  let mut i = 0;
+  while i < chars.len() {
+    let (offset, c) = chars[i];
+    match (c, char_at(i + 1)) {
+      (quote @ ('"' | '\''), _) => {
+        out.push(('"', offset));
+        i += 1;
+        while i < chars.len() && chars[i].1 != quote {
+          let (offset, c) = chars[i];
+          match (c, char_at(i + 1)) {
+            // not a JSON escape, in either quote style
+            ('\\', Some('\'')) => {
+              out.push(('\'', offset));
+              i += 1;
+            }
+            ('\\', Some(escaped)) => {
+              out.extend([('\\', offset), (escaped, offset + 1)]);
+              i += 1;
+            }
+            ('"', _) => out.extend([('\\', offset), ('"', offset)]),
+            (c, _) => out.push((c, offset)),
+          }
+          i += 1;
+        }
+        // an unterminated string stays unterminated so the error is reported
+        if let Some(&(offset, _)) = chars.get(i) {
+          out.push(('"', offset));
+        }
+      }
+      ('/', Some('/')) => {
+        while i < chars.len() && chars[i].1 != '\n' {
+          out.push((' ', chars[i].0));
+          i += 1;
+        }
+        continue;
+      }
+      ('/', Some('*')) => {
+        let end = (i + 2..chars.len())
+          .find(|&j| chars[j].1 == '*' && char_at(j + 1) == Some('/'))
+          .map_or(chars.len(), |j| j + 2);
+        out.extend(chars[i..end].iter().map(|&(offset, c)| (blank(c), offset)));
+        i = end;
+        continue;
+      }
+      (c, _) => out.push((c, offset)),
+    }
+    i += 1;
+  }
+  out
+}
+
+fn remove_trailing_commas(mut json: Vec<(char, usize)>) -> Vec<(char, usize)> {
+  let (mut in_string, mut escaped) = (false, false);
+  for i in 0..json.len() {
+    let c = json[i].0;
+    if in_string {
+      match c {
+        _ if escaped => escaped = false,
+        '\\' => escaped = true,
+        '"' => in_string = false,
+        _ => {}
+      }
+    } else if c == '"' {
+      in_string = true;
+    } else if c == ',' {
+      let next = json[i + 1..].iter().find(|(c, _)| !c.is_whitespace());
+      if matches!(next, Some(('}' | ']', _))) {
+        json[i].0 = ' ';
+      }
+    }
+  }
+  json
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::{json, Value};
+
+  use super::*;
+
+  #[test]
+  fn test_to_strict_json() {
+    let relaxed = r#"{
+  // the subject
+  'sub': '1234',
+  "name": 'John "JD" O\'Doe', /* nickname
+  and name */
+  'roles': ['admin', 'user',],
+  "url": "https://example.com/*path*/",
+}"#;
+    let strict: Value = serde_json::from_str(&to_strict_json(relaxed)).unwrap();
+    assert_eq!(
+      strict,
+      json!({
+        "sub": "1234",
+        "name": "John \"JD\" O'Doe",
+        "roles": ["admin", "user"],
+        "url": "https://example.com/*path*/"
+      })
+    );
+    assert_eq!(to_strict_json("{\"a\": 1}"), "{\"a\": 1}");
+  }
+
+  #[test]
+  fn test_to_strict_json_keeps_error_positions() {
+    let relaxed = "{\n  // comment\n  'sub': '1234',\n  \"exp\" 1\n}";
+    let strict = to_strict_json(relaxed);
+    assert_eq!(strict.lines().count(), relaxed.lines().count());
+    let err = serde_json::from_str::<Value>(&strict).unwrap_err();
+    assert_eq!((err.line(), err.column()), (4, 9));
+    // unterminated strings stay invalid
+    assert!(serde_json::from_str::<Value>(&to_strict_json("{'sub: 1}")).is_err());
+  }
+
+  #[test]
+  fn test_to_strict_json_unescapes_single_quotes() {
+    let strict: Value =
+      serde_json::from_str(&to_strict_json(r#"{"a": "O\'Doe", 'b': 'O\'Doe'}"#)).unwrap();
+    assert_eq!(strict, json!({"a": "O'Doe", "b": "O'Doe"}));
+  }
+
+  #[test]
+  fn test_syntax_error() {
+    let text = "{\n  'name': 'a \"b\" c' 'sub': 1,\n  \"x\": \"O\\'D\" 2\n}";
+    let err = |text: &str| {
+      let e = serde_json::from_str::<Value>(&to_strict_json(text)).unwrap_err();
+      syntax_error(text, &e)
+    };
+    let e = err(text);
+    assert_eq!((e.line, e.column), (2, 21));
+    assert_eq!(e.to_string(), "expected `,` or `}` at line 2 column 21");
+    let e = err(&text.replace("'sub'", ", 'sub'"));
+    assert_eq!((e.line, e.column), (3, 15));
+  }
+}
@@ -0,0 +1,504 @@
+use std::{
+  borrow::Cow,
+  collections::{HashMap, HashSet},
+  env,
+};
+
+use keyring::Entry;
+use serde_json::Value;
+
+pub use jwt_ui_core::COMMAND_PREFIX;
+
+use super::{
+  azure_kv::{self, key_id, AZURE_KV_PREFIX},
+  gcp_kms::{self, key_version, GCP_KMS_PREFIX},
+  network::fetch,
+  utils::{run_command, split_secrets, JWTError, JWTResult},
+  vault_transit::{self, transit_key, VAULT_PREFIX},
+};
+
+/// prefix of secrets stored in the OS keyring (macOS Keychain, Secret Service, Windows Credential Manager)
+pub const KEYRING_PREFIX: &str = "keyring:";
+/// service name of the keyring entries
+const KEYRING_SERVICE: &str = "jwt-ui";
+/// environment variable with the default secret, used when no secret is given on the command line
+pub const SECRET_ENV: &str = "JWT_UI_SECRET";
+/// environment variable with the URL of the default JWKS, used when `JWT_UI_SECRET` isn't set
+pub const JWKS_URL_ENV: &str = "JWT_UI_JWKS_URL";
+
+/// default secret read from the environment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvSecret {
+  /// name of the environment variable the secret came from
+  pub variable: &'static str,
+  pub value: String,
+}
+
+/// returns the default secret from `JWT_UI_SECRET`, or the JWKS fetched from `JWT_UI_JWKS_URL`
+pub fn secret_from_env() -> JWTResult<Option<EnvSecret>> {
+  secret_from_vars(|name| env::var(name).ok())
+}
+
+fn secret_from_vars(var: impl Fn(&str) -> Option<String>) -> JWTResult<Option<EnvSecret>> {
+  let var = |name| var(name).filter(|value| !value.is_empty());
+  if let Some(value) = var(SECRET_ENV) {
+    return Ok(Some(EnvSecret {
+      variable: SECRET_ENV,
+      value,
+    }));
+  }
+  let Some(url) = var(JWKS_URL_ENV) else {
+    return Ok(None);
+  };
+  // the JWKS is used as inline JSON secret, which has to fit on a single line
+  let jwks: Value = serde_json::from_slice(&fetch(&url)?)
+    .map_err(|e| JWTError::Internal(format!("Invalid JWKS from {url}: {e}")))?;
+  Ok(Some(EnvSecret {
+    variable: JWKS_URL_ENV,
+    value: jwks.to_string(),
+  }))
+}
+
+/// Resolves secret references to their values. Values are cached so the keyring isn't queried and
+/// commands don't run on every tick
+#[derive(Default)]
+pub struct SecretStore {
+  cache: HashMap<String, JWTResult<String>>,
+  /// keyring entries and remote keys the app has to read in the background
+  queued: Vec<String>,
+  /// keyring entries and remote keys queued or being read
+  fetching: HashSet<String>,
+  /// remote keys typed or confirmed by the user, the only ones requests are sent for
+  allowed: HashSet<String>,
+}
+
+impl SecretStore {
+  /// returns the value of a secret reference, other secrets are returned as is
+  pub fn resolve<'a>(&mut self, secret: &'a str) -> JWTResult<Cow<'a, str>> {
+    if secret.starts_with(COMMAND_PREFIX) {
+      // commands only run with `run_commands`, not while the secret is being typed
+      return match self.cache.get(secret) {
+        Some(value) => value.clone().map(Cow::Owned),
+        None => Err(JWTError::Internal(
+          "Stop editing the secret to run the secret command".into(),
+        )),
+      };
+    }
+    if is_remote_key(secret) {
+      // public keys are fetched in the background with `fetch_secrets`, not while typing
+      return match self.cache.get(secret) {
+        Some(value) => value.clone().map(Cow::Owned),
+        None if self.fetching.contains(secret) => Err(JWTError::Internal(
+          "Fetching the public key of the secret...".into(),
+        )),
+        None => Err(JWTError::Internal(
+          "Stop editing the secret to fetch its public key".into(),
+        )),
+      };
+    }
+    if !secret.starts_with(KEYRING_PREFIX) {
+      return Ok(Cow::Borrowed(secret));
+    }
+    // the keyring can prompt to unlock it, entries are read in the background like public keys
+    match self.cache.get(secret) {
+      Some(value) => value.clone().map(Cow::Owned),
+      None if self.fetching.contains(secret) => Err(JWTError::Internal(
+        "Reading the secret from the OS keyring...".into(),
+      )),
+      None => Err(JWTError::Internal(
+        "Stop editing the secret to read it from the OS keyring".into(),
+      )),
+    }
+  }
+
+  /// returns the secret signing tokens like [`SecretStore::resolve`], except for the keys of
+  /// Cloud KMS and Key Vault which are kept as reference as they sign the tokens remotely
+  pub fn resolve_for_signing<'a>(&mut self, secret: &'a str) -> JWTResult<Cow<'a, str>> {
+    match is_remote_key(secret) {
+      true => Ok(Cow::Borrowed(secret)),
+      false => self.resolve(secret),
+    }
+  }
+
+  /// save the secret in the OS keyring and return the reference to use instead of the secret
+  pub fn save_to_keyring(&mut self, name: &str, secret: &str) -> JWTResult<String> {
+    if name.is_empty() {
+      return Err(JWTError::Internal(
+        "The keyring entry name should not be empty".into(),
+      ));
+    }
+    if secret.is_empty() || is_reference(secret) {
+      return Err(JWTError::Internal(
+        "Only secrets entered as text can be saved in the keyring".into(),
+      ));
+    }
+    keyring_entry(name)?
+      .set_password(secret)
+      .map_err(|e| keyring_error(name, e))?;
+
+    let reference = format!("{KEYRING_PREFIX}{name}");
+    self.cache.insert(reference.clone(), Ok(secret.into()));
+    Ok(reference)
+  }
+
+  /// run the `cmd:` secret commands of the secret input that didn't run yet
+  pub fn run_commands(&mut self, secret: &str) {
+    for secret in split_secrets(secret) {
+      if let Some(cmd) = secret.strip_prefix(COMMAND_PREFIX) {
+        self
+          .cache
+          .entry(secret.to_string())
+          .or_insert_with(|| run_command(cmd.trim()));
+      }
+    }
+  }
+
+  /// queue the keyring entries and the remote keys of the secret input which weren't read yet,
+  /// failed reads are tried again. The app sends the requests with [`SecretStore::take_fetches`].
+  /// Only called for secrets the user typed or confirmed, their keys can be used to sign from then
+  /// on
+  pub fn fetch_secrets(&mut self, secret: &str) {
+    for secret in split_secrets(secret) {
+      if is_remote_key(secret) {
+        self.allowed.insert(secret.into());
+        self.queue(secret);
+      }
+    }
+    self.fetch_keyring_entries(secret);
+  }
+
+  /// queue the keyring entries of the secret input like [`SecretStore::fetch_secrets`], without
+  /// its remote keys
+  pub fn fetch_keyring_entries(&mut self, secret: &str) {
+    for secret in split_secrets(secret) {
+      if secret.starts_with(KEYRING_PREFIX) {
+        self.queue(secret);
+      }
+    }
+  }
+
+  fn queue(&mut self, secret: &str) {
+    let fetched = self.cache.get(secret).is_some_and(Result::is_ok);
+    if !fetched && self.fetching.insert(secret.into()) {
+      self.cache.remove(secret);
+      self.queued.push(secret.into());
+    }
+  }
+
+  /// the keyring entries and remote keys to read
+  pub fn take_fetches(&mut self) -> Vec<String> {
+    std::mem::take(&mut self.queued)
+  }
+
+  /// read the queued secrets right away, for the command line which doesn't read them in the
+  /// background
+  pub fn fetch_queued(&mut self) {
+    for secret in self.take_fetches() {
+      let value = fetch_secret(&secret);
+      self.fetched(secret, value);
+    }
+  }
+
+  /// keep the value of the keyring entry or the public keys of the remote key, an error is kept
+  /// until the secret is done being edited again
+  pub fn fetched(&mut self, secret: String, value: JWTResult<String>) {
+    self.fetching.remove(&secret);
+    self.cache.insert(secret, value);
+  }
+
+  /// the `cmd:` secret commands of the secret input that didn't run yet
+  pub fn pending_commands<'a>(&self, secret: &'a str) -> Vec<&'a str> {
+    split_secrets(secret)
+      .into_iter()
+      .filter(|secret| !self.cache.contains_key(*secret))
+      .filter_map(|secret| secret.strip_prefix(COMMAND_PREFIX))
+      .map(str::trim)
+      .collect()
+  }
+
+  /// the remote keys of the secret input the user didn't type or confirm yet
+  pub fn pending_public_keys<'a>(&self, secret: &'a str) -> Vec<&'a str> {
+    split_secrets(secret)
+      .into_iter()
+      .filter(|secret| is_remote_key(secret) && !self.allowed.contains(*secret))
+      .collect()
+  }
+
+  /// whether requests can be sent to the remote key, see [`SecretStore::fetch_secrets`]
+  pub fn is_allowed(&self, secret: &str) -> bool {
+    self.allowed.contains(secret)
+  }
+
+  /// forget the resolved values, they are read again on next use
+  pub fn clear(&mut self) {
+    self.cache.clear();
+  }
+}
+
+/// whether the secret refers to a file, a keyring entry, a command or a key of Cloud KMS, Key
+/// Vault or Vault transit instead of being the secret
+pub fn is_reference(secret: &str) -> bool {
+  secret.starts_with('@')
+    || secret.starts_with(KEYRING_PREFIX)
+    || secret.starts_with(COMMAND_PREFIX)
+    || secret.starts_with(GCP_KMS_PREFIX)
+    || secret.starts_with(AZURE_KV_PREFIX)
+    || secret.starts_with(VAULT_PREFIX)
+}
+
+/// whether the secret is a key of Cloud KMS, Key Vault or Vault transit, which sign tokens remotely
+pub fn is_remote_key(secret: &str) -> bool {
+  key_version(secret).is_some() || key_id(secret).is_some() || transit_key(secret).is_some()
+}
+
+/// the references of the secret input, without the secrets entered as text
+pub fn references_only(secret: &str) -> String {
+  split_secrets(secret)
+    .into_iter()
+    .filter(|secret| is_reference(secret))
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+/// the public keys of a Cloud KMS, Key Vault or Vault transit key which verify the tokens signed
+/// with it, blocking until the service responds
+pub fn remote_public_jwks(secret: &str) -> JWTResult<String> {
+  if let Some(name) = key_version(secret) {
+    return gcp_kms::public_jwks(name);
+  }
+  if let Some(id) = key_id(secret) {
+    return azure_kv::public_jwks(id);
+  }
+  match transit_key(secret) {
+    Some(key) => vault_transit::public_jwks(&key),
+    None => Err(JWTError::Internal(format!(
+      "{secret:?} is not a key of Cloud KMS, Key Vault or Vault transit"
+    ))),
+  }
+}
+
+/// the value of a keyring entry or the public keys of a remote key queued by
+/// [`SecretStore::fetch_secrets`], blocking until the keyring or the service answers
+pub fn fetch_secret(secret: &str) -> JWTResult<String> {
+  match secret.strip_prefix(KEYRING_PREFIX) {
+    Some(name) => read_keyring(name),
+    None => remote_public_jwks(secret),
+  }
+}
+
+fn read_keyring(name: &str) -> JWTResult<String> {
+  keyring_entry(name)?
+    .get_password()
+    .map_err(|e| keyring_error(name, e))
+}
+
+fn keyring_entry(name: &str) -> JWTResult<Entry> {
+  Entry::new(KEYRING_SERVICE, name).map_err(|e| keyring_error(name, e))
+}
+
+fn keyring_error(name: &str, e: keyring::Error) -> JWTError {
+  JWTError::Internal(match e {
+    keyring::Error::NoEntry => format!("No secret named {name:?} in the OS keyring"),
+    e => format!("Unable to access the secret {name:?} in the OS keyring: {e}"),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn store() -> SecretStore {
+    keyring::set_default_credential_builder(keyring::mock::default_credential_builder());
+    SecretStore::default()
+  }
+
+  #[test]
+  fn test_resolve_plain_secret() {
+    let mut store = store();
+    assert_eq!(store.resolve("my-secret").unwrap(), "my-secret");
+    assert_eq!(store.resolve("@./key.pem").unwrap(), "@./key.pem");
+    assert!(store.cache.is_empty());
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn test_run_commands_and_resolve() {
+    let mut store = store();
+    let secret = "cmd:printf 'a,b'";
+    assert_eq!(
+      store.resolve(secret).unwrap_err().to_string(),
+      "Stop editing the secret to run the secret command"
+    );
+    assert_eq!(
+      store.pending_commands(&format!("plain, {secret}")),
+      ["printf 'a,b'"]
+    );
+
+    store.run_commands(&format!("plain, {secret}"));
+    assert_eq!(store.resolve(secret).unwrap(), "a,b");
+    assert!(store.pending_commands(secret).is_empty());
+    assert_eq!(store.cache.len(), 1);
+
+    store.run_commands("cmd:exit 1");
+    assert!(store
+      .resolve("cmd:exit 1")
+      .unwrap_err()
+      .to_string()
+      .starts_with("`exit 1` failed"));
+  }
+
+  #[test]
+  fn test_secret_from_vars() {
+    let url = crate::app::network::tests::serve_once("200 OK", b"{\n  \"keys\": []\n}".to_vec());
+    let vars = |secret: &'static str| {
+      let url = url.clone();
+      move |name: &str| match name {
+        SECRET_ENV => Some(secret.to_string()),
+        JWKS_URL_ENV => Some(url.clone()),
+        _ => None,
+      }
+    };
+
+    assert_eq!(
+      secret_from_vars(vars("staging-secret")).unwrap(),
+      Some(EnvSecret {
+        variable: SECRET_ENV,
+        value: "staging-secret".into()
+      })
+    );
+    // empty variables are ignored
+    assert_eq!(
+      secret_from_vars(vars("")).unwrap(),
+      Some(EnvSecret {
+        variable: JWKS_URL_ENV,
+        value: r#"{"keys":[]}"#.into()
+      })
+    );
+    assert_eq!(secret_from_vars(|_| None).unwrap(), None);
+
+    let url = crate::app::network::tests::serve_once("200 OK", b"not json".to_vec());
+    assert!(
+      secret_from_vars(|name| (name == JWKS_URL_ENV).then(|| url.clone()))
+        .unwrap_err()
+        .to_string()
+        .starts_with(&format!("Invalid JWKS from {url}"))
+    );
+  }
+
+  #[test]
+  fn test_references_only() {
+    assert_eq!(
+      references_only("my-secret, @./key.pem,keyring:staging, b64:c2VjcmV0, cmd:pass show a,b"),
+      "@./key.pem,keyring:staging,cmd:pass show a,b"
+    );
+    assert_eq!(references_only("my-secret"), "");
+    assert_eq!(references_only(r#"{"keys": []}"#), "");
+    assert_eq!(
+      references_only("my-secret,gcpkms:projects/p/k/1"),
+      "gcpkms:projects/p/k/1"
+    );
+  }
+
+  #[test]
+  fn test_resolve_remote_key_for_signing() {
+    let mut store = SecretStore::default();
+    // the reference is kept to sign with Cloud KMS, Key Vault or Vault transit
+    assert_eq!(
+      store.resolve_for_signing("gcpkms:projects/p/k/1").unwrap(),
+      "gcpkms:projects/p/k/1"
+    );
+    assert_eq!(
+      store
+        .resolve_for_signing("azurekv:https://v.vault.azure.net/keys/k/1")
+        .unwrap(),
+      "azurekv:https://v.vault.azure.net/keys/k/1"
+    );
+    assert_eq!(
+      store
+        .resolve_for_signing("vault:transit/keys/jwt-signing")
+        .unwrap(),
+      "vault:transit/keys/jwt-signing"
+    );
+    assert_eq!(store.resolve_for_signing("my-secret").unwrap(), "my-secret");
+  }
+
+  #[test]
+  fn test_fetch_secrets() {
+    let mut store = SecretStore::default();
+    let secret = "gcpkms:projects/p/k/1";
+    // nothing is fetched while the key is being typed
+    assert_eq!(
+      store.resolve(secret).unwrap_err().to_string(),
+      "Stop editing the secret to fetch its public key"
+    );
+    assert!(store.take_fetches().is_empty());
+
+    store.fetch_secrets(&format!("plain, {secret}"));
+    store.fetch_secrets(secret);
+    assert_eq!(store.take_fetches(), [secret]);
+    assert_eq!(
+      store.resolve(secret).unwrap_err().to_string(),
+      "Fetching the public key of the secret..."
+    );
+
+    // failed fetches are tried again
+    store.fetched(
+      secret.into(),
+      Err(JWTError::Internal("Permission denied".into())),
+    );
+    assert_eq!(
+      store.resolve(secret).unwrap_err().to_string(),
+      "Permission denied"
+    );
+    store.fetch_secrets(secret);
+    assert_eq!(store.take_fetches(), [secret]);
+
+    store.fetched(secret.into(), Ok(r#"{"keys": []}"#.into()));
+    store.fetch_secrets("azurekv:https://v.vault.azure.net/keys/k/1");
+    store.fetch_secrets("vault:transit/keys/jwt-signing");
+    assert_eq!(
+      store.take_fetches(),
+      [
+        "azurekv:https://v.vault.azure.net/keys/k/1",
+        "vault:transit/keys/jwt-signing"
+      ]
+    );
+    assert_eq!(store.resolve(secret).unwrap(), r#"{"keys": []}"#);
+    store.fetch_secrets(secret);
+    assert!(store.take_fetches().is_empty());
+  }
+
+  #[test]
+  fn test_save_to_keyring_and_resolve() {
+    let mut store = store();
+    // entries are only read once the secret is done being edited
+    assert_eq!(
+      store.resolve("keyring:missing").unwrap_err().to_string(),
+      "Stop editing the secret to read it from the OS keyring"
+    );
+    store.fetch_secrets("keyring:missing");
+    assert_eq!(
+      store.resolve("keyring:missing").unwrap_err().to_string(),
+      "Reading the secret from the OS keyring..."
+    );
+    store.fetch_queued();
+    assert_eq!(
+      store.resolve("keyring:missing").unwrap_err().to_string(),
+      "No secret named \"missing\" in the OS keyring"
+    );
+    // errors are read again
+    store.fetch_keyring_entries("keyring:missing");
+    assert_eq!(store.take_fetches(), ["keyring:missing"]);
+
+    let reference = store.save_to_keyring("staging", "my-secret").unwrap();
+    assert_eq!(reference, "keyring:staging");
+    assert_eq!(store.resolve(&reference).unwrap(), "my-secret");
+
+    assert!(store.save_to_keyring("file", "@./key.pem").is_err());
+    assert!(store.save_to_keyring("again", "keyring:staging").is_err());
+    assert!(store.save_to_keyring("", "my-secret").is_err());
+
+    store.clear();
+    assert!(store.cache.is_empty());
+  }
+}
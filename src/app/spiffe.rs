@@ -0,0 +1,347 @@
+use base64::{
+  engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+  Engine,
+};
+use jsonwebtoken::{decode_header, Algorithm, Header};
+use serde_json::{json, Value};
+
+use super::{
+  jwt_decoder::Payload,
+  utils::{get_secret_from_file_or_input, JWTError, JWTResult, SecretType},
+  validation::{claim_timestamp, format_duration, Finding, Severity},
+};
+
+const SPIFFE_SCHEME: &str = "spiffe://";
+/// longest JWT-SVID lifetime not flagged, SPIRE issues them for 5 minutes by default
+const MAX_SVID_LIFETIME: i64 = 3600;
+
+/// the trust domain of a SPIFFE ID, or why it is not a valid one
+pub fn trust_domain(spiffe_id: &str) -> Result<&str, String> {
+  let rest = spiffe_id
+    .strip_prefix(SPIFFE_SCHEME)
+    .ok_or_else(|| format!("{spiffe_id:?} is not a spiffe:// ID"))?;
+  let (domain, path) = match rest.split_once('/') {
+    Some((domain, path)) => (domain, Some(path)),
+    None => (rest, None),
+  };
+  if domain.is_empty() {
+    return Err(format!("{spiffe_id:?} has no trust domain"));
+  }
+  if !domain
+    .bytes()
+    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b"-._".contains(&b))
+  {
+    return Err(format!(
+      "Trust domain {domain:?} may only have lowercase letters, digits, `-`, `.` and `_`"
+    ));
+  }
+  for segment in path.into_iter().flat_map(|path| path.split('/')) {
+    if segment.is_empty() || segment == "." || segment == ".." {
+      return Err(format!(
+        "{spiffe_id:?} has an empty, `.` or `..` path segment"
+      ));
+    }
+    if !segment
+      .bytes()
+      .all(|b| b.is_ascii_alphanumeric() || b"-._".contains(&b))
+    {
+      return Err(format!(
+        "Path segment {segment:?} may only have letters, digits, `-`, `.` and `_`"
+      ));
+    }
+  }
+  Ok(domain)
+}
+
+/// check the requirements of the JWT-SVID specification: a SPIFFE ID `sub`, an `aud`, a short
+/// `exp` and an asymmetric signature
+pub fn check_jwt_svid(header: &Header, claims: &Payload, now: i64) -> Vec<Finding> {
+  let finding = |severity, message| Finding::new(severity, "jwt-svid", message);
+  let mut findings = vec![];
+  if matches!(
+    header.alg,
+    Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 | Algorithm::EdDSA
+  ) {
+    findings.push(finding(
+      Severity::Error,
+      format!(
+        "JWT-SVIDs must be signed with RS, ES or PS algorithms, not {:?}",
+        header.alg
+      ),
+    ));
+  }
+  if let Some(typ) = header
+    .typ
+    .as_deref()
+    .filter(|t| !matches!(*t, "JWT" | "JOSE"))
+  {
+    findings.push(finding(
+      Severity::Error,
+      format!("`typ` is {typ:?}, JWT-SVIDs must have `JWT` or `JOSE`"),
+    ));
+  }
+  match claims.0.get("sub") {
+    Some(Value::String(sub)) => {
+      if let Err(reason) = trust_domain(sub) {
+        findings.push(finding(Severity::Error, format!("`sub` {reason}")));
+      }
+    }
+    Some(_) => findings.push(finding(
+      Severity::Error,
+      "`sub` is not a SPIFFE ID string".into(),
+    )),
+    None => findings.push(finding(
+      Severity::Error,
+      "JWT-SVID has no `sub` claim".into(),
+    )),
+  }
+  match claims.0.get("aud") {
+    Some(Value::String(aud)) if !aud.is_empty() => {}
+    Some(Value::Array(aud)) if !aud.is_empty() && aud.iter().all(Value::is_string) => {
+      if aud.len() > 1 {
+        findings.push(finding(
+          Severity::Warning,
+          format!(
+            "JWT-SVID has {} audiences, a single one is recommended",
+            aud.len()
+          ),
+        ));
+      }
+    }
+    Some(_) => findings.push(finding(
+      Severity::Error,
+      "`aud` is not a string or an array of strings".into(),
+    )),
+    None => findings.push(finding(
+      Severity::Error,
+      "JWT-SVID has no `aud` claim".into(),
+    )),
+  }
+  match claim_timestamp(claims, "exp") {
+    Some(exp) => {
+      let lifetime = exp - claim_timestamp(claims, "iat").unwrap_or(now);
+      if lifetime > MAX_SVID_LIFETIME {
+        findings.push(finding(
+          Severity::Warning,
+          format!(
+            "JWT-SVID is valid for {}, JWT-SVIDs should be short lived (at most {})",
+            format_duration(lifetime),
+            format_duration(MAX_SVID_LIFETIME)
+          ),
+        ));
+      }
+    }
+    None => findings.push(finding(
+      Severity::Error,
+      "JWT-SVID has no `exp` claim".into(),
+    )),
+  }
+  findings
+}
+
+/// the JWT-SVID keys of the SPIFFE trust bundle secret for the trust domain of the token, as an
+/// inline JWKS. The secret is a SPIFFE bundle (a JWKS with `jwt-svid` and `x509-svid` keys) or the
+/// bundles exported from the Workload API (`{"bundles": {"<trust domain>": <bundle>}}`, the bundles
+/// being JSON or base64). `None` when the secret is not a SPIFFE bundle
+pub fn jwt_bundle_secret(secret: &str, token: &str) -> JWTResult<Option<String>> {
+  let Ok(header) = decode_header(token) else {
+    return Ok(None);
+  };
+  let (Ok(bytes), SecretType::Jwks) = get_secret_from_file_or_input(&header.alg, secret) else {
+    return Ok(None);
+  };
+  let Ok(value) = serde_json::from_slice::<Value>(&bytes) else {
+    return Ok(None);
+  };
+  let bundle = match workload_bundles(&value) {
+    Some(bundles) => {
+      let domain = token_trust_domain(token)?;
+      let bundle = bundles
+        .get(&domain)
+        .or_else(|| bundles.get(&format!("{SPIFFE_SCHEME}{domain}")))
+        .ok_or_else(|| {
+          JWTError::Internal(format!("No SPIFFE bundle for the trust domain {domain}"))
+        })?;
+      parse_bundle(bundle).ok_or_else(|| {
+        JWTError::Internal(format!(
+          "Invalid SPIFFE bundle for the trust domain {domain}"
+        ))
+      })?
+    }
+    None if is_spiffe_bundle(&value) => value,
+    None => return Ok(None),
+  };
+  let keys: Vec<&Value> = bundle["keys"]
+    .as_array()
+    .into_iter()
+    .flatten()
+    .filter(|key| key["use"] == "jwt-svid")
+    .collect();
+  Ok(Some(json!({ "keys": keys }).to_string()))
+}
+
+/// the bundles by trust domain of a Workload API export, alone or in the array of the responses
+fn workload_bundles(value: &Value) -> Option<&serde_json::Map<String, Value>> {
+  match value {
+    Value::Array(responses) => responses.iter().find_map(workload_bundles),
+    _ => value.get("bundles")?.as_object(),
+  }
+}
+
+/// a bundle of a Workload API export, as JSON or base64 encoded JSON
+fn parse_bundle(bundle: &Value) -> Option<Value> {
+  match bundle {
+    Value::String(encoded) => serde_json::from_slice(&STANDARD.decode(encoded).ok()?).ok(),
+    Value::Object(_) => Some(bundle.clone()),
+    _ => None,
+  }
+}
+
+/// a JWKS is a SPIFFE bundle when it has a sequence number or keys for SVIDs
+fn is_spiffe_bundle(value: &Value) -> bool {
+  value.get("spiffe_sequence").is_some()
+    || value["keys"].as_array().is_some_and(|keys| {
+      keys
+        .iter()
+        .any(|key| key["use"] == "jwt-svid" || key["use"] == "x509-svid")
+    })
+}
+
+/// the trust domain of the SPIFFE ID in the `sub` of the token, read without verification
+fn token_trust_domain(token: &str) -> JWTResult<String> {
+  let claims: Value = token
+    .split('.')
+    .nth(1)
+    .and_then(|claims| URL_SAFE_NO_PAD.decode(claims).ok())
+    .and_then(|claims| serde_json::from_slice(&claims).ok())
+    .unwrap_or_default();
+  let sub = claims["sub"].as_str().unwrap_or_default();
+  trust_domain(sub)
+    .map(String::from)
+    .map_err(|reason| JWTError::Internal(format!("`sub` {reason}")))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn payload(json: &str) -> Payload {
+    serde_json::from_str(json).unwrap()
+  }
+
+  fn messages(findings: Vec<Finding>) -> Vec<String> {
+    findings.into_iter().map(|f| f.message).collect()
+  }
+
+  #[test]
+  fn test_trust_domain() {
+    assert_eq!(trust_domain("spiffe://example.org"), Ok("example.org"));
+    assert_eq!(
+      trust_domain("spiffe://prod.example-1.org/ns/web/sa/frontend"),
+      Ok("prod.example-1.org")
+    );
+    assert_eq!(
+      trust_domain("https://example.org/web").unwrap_err(),
+      "\"https://example.org/web\" is not a spiffe:// ID"
+    );
+    assert_eq!(
+      trust_domain("spiffe:///web").unwrap_err(),
+      "\"spiffe:///web\" has no trust domain"
+    );
+    assert_eq!(
+      trust_domain("spiffe://Example.org:443/web").unwrap_err(),
+      "Trust domain \"Example.org:443\" may only have lowercase letters, digits, `-`, `.` and `_`"
+    );
+    for id in [
+      "spiffe://example.org/",
+      "spiffe://example.org/web//api",
+      "spiffe://example.org/web/../admin",
+    ] {
+      assert!(trust_domain(id).unwrap_err().contains("path segment"));
+    }
+    assert_eq!(
+      trust_domain("spiffe://example.org/web?admin").unwrap_err(),
+      "Path segment \"web?admin\" may only have letters, digits, `-`, `.` and `_`"
+    );
+  }
+
+  #[test]
+  fn test_check_jwt_svid() {
+    let header = Header::new(Algorithm::ES256);
+    let claims =
+      payload(r#"{"sub": "spiffe://example.org/web", "aud": ["api"], "iat": 1000, "exp": 1300}"#);
+    assert!(check_jwt_svid(&header, &claims, 0).is_empty());
+
+    let mut header = Header::new(Algorithm::HS256);
+    header.typ = Some("at+jwt".into());
+    let claims = payload(r#"{"sub": "web", "aud": ["api", "db"], "exp": 90000}"#);
+    assert_eq!(
+      messages(check_jwt_svid(&header, &claims, 1000)),
+      vec![
+        "JWT-SVIDs must be signed with RS, ES or PS algorithms, not HS256",
+        "`typ` is \"at+jwt\", JWT-SVIDs must have `JWT` or `JOSE`",
+        "`sub` \"web\" is not a spiffe:// ID",
+        "JWT-SVID has 2 audiences, a single one is recommended",
+        "JWT-SVID is valid for 1d 43m 20s, JWT-SVIDs should be short lived (at most 1h)",
+      ]
+    );
+
+    let findings = check_jwt_svid(&Header::new(Algorithm::RS256), &payload("{}"), 0);
+    assert_eq!(
+      messages(findings),
+      vec![
+        "JWT-SVID has no `sub` claim",
+        "JWT-SVID has no `aud` claim",
+        "JWT-SVID has no `exp` claim",
+      ]
+    );
+  }
+
+  #[test]
+  fn test_jwt_bundle_secret() {
+    // {"alg":"ES256"}.{"sub":"spiffe://example.org/web"}
+    let token = "eyJhbGciOiJFUzI1NiJ9.eyJzdWIiOiJzcGlmZmU6Ly9leGFtcGxlLm9yZy93ZWIifQ.sig";
+    let bundle = json!({
+      "spiffe_sequence": 1,
+      "keys": [
+        {"use": "x509-svid", "kty": "EC", "crv": "P-256", "x": "a", "y": "b"},
+        {"use": "jwt-svid", "kty": "EC", "kid": "k1", "crv": "P-256", "x": "a", "y": "b"},
+      ]
+    });
+    let jwt_keys =
+      r#"{"keys":[{"crv":"P-256","kid":"k1","kty":"EC","use":"jwt-svid","x":"a","y":"b"}]}"#;
+    assert_eq!(
+      jwt_bundle_secret(&bundle.to_string(), token)
+        .unwrap()
+        .as_deref(),
+      Some(jwt_keys)
+    );
+
+    let exported = json!([
+      {"svids": []},
+      {"bundles": {
+        "spiffe://other.org": {"keys": []},
+        "spiffe://example.org": STANDARD.encode(bundle.to_string()),
+      }}
+    ]);
+    assert_eq!(
+      jwt_bundle_secret(&exported.to_string(), token)
+        .unwrap()
+        .as_deref(),
+      Some(jwt_keys)
+    );
+
+    let exported = json!({"bundles": {"other.org": bundle}});
+    assert_eq!(
+      jwt_bundle_secret(&exported.to_string(), token)
+        .unwrap_err()
+        .to_string(),
+      "No SPIFFE bundle for the trust domain example.org"
+    );
+
+    // other secrets are left as they are
+    let jwks = std::fs::read_to_string("./test_data/test_ecdsa_public_jwks.json").unwrap();
+    assert_eq!(jwt_bundle_secret(&jwks, token).unwrap(), None);
+    assert_eq!(jwt_bundle_secret("@missing.pem", token).unwrap(), None);
+  }
+}
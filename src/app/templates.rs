@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+
+use serde_derive::Deserialize;
+use serde_json::{json, to_string_pretty, Value};
+
+/// header and payload preset loaded into the encoder, time claims use relative values like
+/// `"+1h"` so the token is valid when encoded
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Template {
+  /// key of the template in the config, or the name of the built-in template
+  #[serde(skip)]
+  pub name: String,
+  /// header without `alg` keeps the algorithm of the encoder, defaults to `{"typ": "JWT"}`
+  #[serde(default)]
+  pub header: Option<Value>,
+  pub payload: Value,
+}
+
+impl Template {
+  fn new(name: &str, header: Value, payload: Value) -> Self {
+    Self {
+      name: name.into(),
+      header: Some(header),
+      payload,
+    }
+  }
+
+  /// lines of the header textarea, keeping the `alg` of the current header when the template has
+  /// none so the token can still be signed with the current secret
+  pub fn header_lines(&self, current: &str) -> Vec<String> {
+    let mut header = self.header.clone().unwrap_or_else(|| json!({"typ": "JWT"}));
+    if let (Some(header), Ok(current)) = (
+      header.as_object_mut(),
+      serde_json::from_str::<Value>(current),
+    ) {
+      if let (false, Some(alg)) = (header.contains_key("alg"), current.get("alg")) {
+        header.insert("alg".into(), alg.clone());
+      }
+    }
+    pretty_lines(&header)
+  }
+
+  /// lines of the payload textarea
+  pub fn payload_lines(&self) -> Vec<String> {
+    pretty_lines(&self.payload)
+  }
+}
+
+fn pretty_lines(value: &Value) -> Vec<String> {
+  to_string_pretty(value)
+    .unwrap_or_default()
+    .lines()
+    .map(String::from)
+    .collect()
+}
+
+/// the built-in templates followed by the templates of the config, sorted by name
+pub fn all_templates(custom: &BTreeMap<String, Template>) -> Vec<Template> {
+  builtin_templates()
+    .into_iter()
+    .chain(custom.iter().map(|(name, template)| Template {
+      name: name.clone(),
+      ..template.clone()
+    }))
+    .collect()
+}
+
+/// presets for tokens of common identity providers
+pub fn builtin_templates() -> Vec<Template> {
+  vec![
+    Template::new(
+      "Auth0 access token",
+      json!({"typ": "JWT", "kid": "auth0-signing-key"}),
+      json!({
+        "iss": "https://example.auth0.com/",
+        "sub": "auth0|1234567890",
+        "aud": ["https://api.example.com", "https://example.auth0.com/userinfo"],
+        "iat": "+0s",
+        "exp": "+24h",
+        "azp": "client-id",
+        "scope": "openid profile email",
+        "permissions": ["read:messages"]
+      }),
+    ),
+    Template::new(
+      "OIDC id_token",
+      json!({"typ": "JWT"}),
+      json!({
+        "iss": "https://idp.example.com",
+        "sub": "1234567890",
+        "aud": "client-id",
+        "iat": "+0s",
+        "exp": "+1h",
+        "auth_time": 0,
+        "nonce": "n-0S6_WzA2Mj",
+        "name": "John Doe",
+        "email": "john.doe@example.com",
+        "email_verified": true
+      }),
+    ),
+    Template::new(
+      "Keycloak access token",
+      json!({"typ": "JWT", "kid": "keycloak-realm-key"}),
+      json!({
+        "iss": "https://keycloak.example.com/realms/example",
+        "sub": "f3d8a6a6-2b6c-4e4f-9c7e-1b2a3c4d5e6f",
+        "aud": "account",
+        "typ": "Bearer",
+        "azp": "client-id",
+        "iat": "+0s",
+        "exp": "+5m",
+        "realm_access": {"roles": ["offline_access", "uma_authorization"]},
+        "resource_access": {"account": {"roles": ["manage-account", "view-profile"]}},
+        "scope": "openid profile email",
+        "preferred_username": "john.doe",
+        "email": "john.doe@example.com"
+      }),
+    ),
+  ]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_all_templates() {
+    let mut custom = BTreeMap::new();
+    custom.insert(
+      "my-api".to_string(),
+      Template {
+        name: String::new(),
+        header: None,
+        payload: json!({"aud": "my-api"}),
+      },
+    );
+    let names: Vec<String> = all_templates(&custom).into_iter().map(|t| t.name).collect();
+    assert_eq!(
+      names,
+      [
+        "Auth0 access token",
+        "OIDC id_token",
+        "Keycloak access token",
+        "my-api"
+      ]
+    );
+  }
+
+  #[test]
+  fn test_template_lines() {
+    let template = &builtin_templates()[1];
+    assert_eq!(
+      template.header_lines("{\"alg\": \"RS256\", \"typ\": \"JWT\"}"),
+      ["{", "  \"alg\": \"RS256\",", "  \"typ\": \"JWT\"", "}"]
+    );
+    // the current header may be invalid while editing
+    assert_eq!(template.header_lines("{"), ["{", "  \"typ\": \"JWT\"", "}"]);
+    assert_eq!(template.payload_lines()[1], "  \"aud\": \"client-id\",");
+
+    let template = Template {
+      name: "custom".into(),
+      header: Some(json!({"alg": "ES256"})),
+      payload: json!({}),
+    };
+    assert_eq!(
+      template.header_lines("{\"alg\": \"HS256\"}"),
+      ["{", "  \"alg\": \"ES256\"", "}"]
+    );
+    assert_eq!(template.payload_lines(), ["{}"]);
+  }
+}
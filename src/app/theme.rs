@@ -0,0 +1,107 @@
+use std::{fmt, str::FromStr};
+
+use super::utils::JWTError;
+
+/// color theme of the UI, `dark` and `light` are the original pair toggled with `t`. `plain` has
+/// no colors, ASCII borders and text markers for limited terminals and colorblind users
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Theme {
+  #[default]
+  Dark,
+  Light,
+  SolarizedDark,
+  SolarizedLight,
+  Gruvbox,
+  Dracula,
+  Monochrome,
+  Plain,
+}
+
+impl Theme {
+  /// themes in the order of the theme picker
+  pub const ALL: [Theme; 8] = [
+    Theme::Dark,
+    Theme::Light,
+    Theme::SolarizedDark,
+    Theme::SolarizedLight,
+    Theme::Gruvbox,
+    Theme::Dracula,
+    Theme::Monochrome,
+    Theme::Plain,
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Theme::Dark => "dark",
+      Theme::Light => "light",
+      Theme::SolarizedDark => "solarized-dark",
+      Theme::SolarizedLight => "solarized-light",
+      Theme::Gruvbox => "gruvbox",
+      Theme::Dracula => "dracula",
+      Theme::Monochrome => "monochrome",
+      Theme::Plain => "plain",
+    }
+  }
+
+  /// the light variant of a dark theme and the other way around, themes without a light variant
+  /// toggle to the default light theme. The plain theme has no colors to toggle
+  pub fn toggled(&self) -> Theme {
+    match self {
+      Theme::Plain => Theme::Plain,
+      Theme::Light => Theme::Dark,
+      Theme::SolarizedDark => Theme::SolarizedLight,
+      Theme::SolarizedLight => Theme::SolarizedDark,
+      _ => Theme::Light,
+    }
+  }
+}
+
+impl fmt::Display for Theme {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+impl FromStr for Theme {
+  type Err = JWTError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Theme::ALL
+      .into_iter()
+      .find(|theme| theme.name() == s.trim().to_ascii_lowercase())
+      .ok_or_else(|| {
+        let names: Vec<&str> = Theme::ALL.iter().map(Theme::name).collect();
+        JWTError::Internal(format!(
+          "Unknown theme \"{s}\", use one of {}",
+          names.join(", ")
+        ))
+      })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_theme_from_str() {
+    for theme in Theme::ALL {
+      assert_eq!(theme.name().parse::<Theme>().unwrap(), theme);
+    }
+    assert_eq!("Gruvbox ".parse::<Theme>().unwrap(), Theme::Gruvbox);
+    assert_eq!(
+      "blue".parse::<Theme>().unwrap_err().to_string(),
+      "Unknown theme \"blue\", use one of dark, light, solarized-dark, solarized-light, gruvbox, dracula, monochrome, plain"
+    );
+  }
+
+  #[test]
+  fn test_theme_toggled() {
+    assert_eq!(Theme::Dark.toggled(), Theme::Light);
+    assert_eq!(Theme::Light.toggled(), Theme::Dark);
+    assert_eq!(Theme::SolarizedDark.toggled(), Theme::SolarizedLight);
+    assert_eq!(Theme::SolarizedLight.toggled(), Theme::SolarizedDark);
+    assert_eq!(Theme::Dracula.toggled(), Theme::Light);
+    assert_eq!(Theme::Plain.toggled(), Theme::Plain);
+  }
+}
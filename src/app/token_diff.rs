@@ -0,0 +1,133 @@
+use serde_json::{Map, Value};
+
+use super::utils::{decode_segment, JWTError, JWTResult};
+
+/// part of the token a changed field is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenPart {
+  Header,
+  Payload,
+}
+
+/// how a header field or claim differs between two tokens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+  Added(Value),
+  Removed(Value),
+  Changed(Value, Value),
+}
+
+/// header field or claim that differs between two tokens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+  pub part: TokenPart,
+  pub name: String,
+  pub change: Change,
+}
+
+impl FieldChange {
+  /// the change as shown in the diff popup, e.g. `"alice" → "bob"`
+  pub fn describe(&self) -> String {
+    match &self.change {
+      Change::Added(value) => format!("+ {value}"),
+      Change::Removed(value) => format!("- {value}"),
+      Change::Changed(old, new) => format!("{old} → {new}"),
+    }
+  }
+}
+
+/// the header fields and claims added, removed or changed from the old token to the new one,
+/// header fields first. Nested objects are compared as a whole
+pub fn diff_tokens(old: &str, new: &str) -> JWTResult<Vec<FieldChange>> {
+  let (old_header, old_payload) = token_parts(old)?;
+  let (new_header, new_payload) = token_parts(new)?;
+  let mut changes = diff_objects(TokenPart::Header, &old_header, &new_header);
+  changes.extend(diff_objects(TokenPart::Payload, &old_payload, &new_payload));
+  Ok(changes)
+}
+
+fn token_parts(token: &str) -> JWTResult<(Map<String, Value>, Map<String, Value>)> {
+  let mut parts = token.trim().split('.');
+  let (Some(header), Some(payload)) = (parts.next(), parts.next()) else {
+    return Err(JWTError::Internal("Only JWTs can be compared".into()));
+  };
+  Ok((decode_segment(header)?, decode_segment(payload)?))
+}
+
+fn diff_objects(
+  part: TokenPart,
+  old: &Map<String, Value>,
+  new: &Map<String, Value>,
+) -> Vec<FieldChange> {
+  let mut names: Vec<&String> = old.keys().chain(new.keys()).collect();
+  names.sort();
+  names.dedup();
+  names
+    .into_iter()
+    .filter_map(|name| {
+      let change = match (old.get(name), new.get(name)) {
+        (None, Some(value)) => Change::Added(value.clone()),
+        (Some(value), None) => Change::Removed(value.clone()),
+        (Some(old), Some(new)) if old != new => Change::Changed(old.clone(), new.clone()),
+        _ => return None,
+      };
+      Some(FieldChange {
+        part,
+        name: name.clone(),
+        change,
+      })
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+  use serde_json::json;
+
+  use super::*;
+
+  fn token(header: Value, payload: Value) -> String {
+    format!(
+      "{}.{}.sig",
+      URL_SAFE_NO_PAD.encode(header.to_string()),
+      URL_SAFE_NO_PAD.encode(payload.to_string())
+    )
+  }
+
+  #[test]
+  fn test_diff_tokens() {
+    let old = token(
+      json!({"alg": "HS256", "typ": "JWT"}),
+      json!({"sub": "alice", "scope": "read", "iat": 1}),
+    );
+    let new = token(
+      json!({"alg": "RS256", "typ": "JWT", "kid": "key-1"}),
+      json!({"sub": "alice", "iat": 2, "aud": ["api"]}),
+    );
+    let changes = diff_tokens(&old, &new).unwrap();
+    let described: Vec<(TokenPart, &str, String)> = changes
+      .iter()
+      .map(|change| (change.part, change.name.as_str(), change.describe()))
+      .collect();
+    assert_eq!(
+      described,
+      [
+        (TokenPart::Header, "alg", "\"HS256\" → \"RS256\"".into()),
+        (TokenPart::Header, "kid", "+ \"key-1\"".into()),
+        (TokenPart::Payload, "aud", "+ [\"api\"]".into()),
+        (TokenPart::Payload, "iat", "1 → 2".into()),
+        (TokenPart::Payload, "scope", "- \"read\"".into()),
+      ]
+    );
+    assert!(diff_tokens(&old, &old).unwrap().is_empty());
+    assert_eq!(
+      diff_tokens("v4.public", &new).unwrap_err().to_string(),
+      "The JWT provided is invalid: InvalidToken"
+    );
+    assert_eq!(
+      diff_tokens("opaque", &new).unwrap_err().to_string(),
+      "Only JWTs can be compared"
+    );
+  }
+}
@@ -0,0 +1,184 @@
+//! Tokens embedded in arbitrary text like logs, JSON or URLs with `#access_token=`, found with a
+//! regex and listed with a short preview of their claims to pick one to decode
+
+use std::sync::OnceLock;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{TimeZone, Utc};
+use regex::Regex;
+use serde_json::Value;
+
+use super::{http_header::is_token, paseto::is_paseto};
+
+/// JWEs, JWSs and PASETO tokens. The header of JWTs is a base64url JSON object so it starts with
+/// `eyJ`, JWEs are tried first as a JWS would match their first three parts
+static TOKEN_REGEX: OnceLock<Regex> = OnceLock::new();
+
+/// claims shown in the preview of a token, in order
+const PREVIEW_CLAIMS: [&str; 4] = ["iss", "sub", "aud", "exp"];
+
+/// a token found in the scanned text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScannedToken {
+  pub token: String,
+  /// `alg` of a JWT, or the version and purpose of a PASETO token
+  pub kind: String,
+  /// issuer, subject, audience and expiry of a JWS
+  pub preview: String,
+}
+
+/// every distinct token of the text, in the order they appear
+pub fn scan_tokens(text: &str) -> Vec<ScannedToken> {
+  let mut tokens: Vec<ScannedToken> = vec![];
+  let regex = TOKEN_REGEX.get_or_init(|| {
+    Regex::new(concat!(
+      r"eyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]*\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+",
+      r"|eyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]*",
+      r"|v[24]\.(?:local|public)\.[A-Za-z0-9_-]+(?:\.[A-Za-z0-9_-]+)?",
+    ))
+    .expect("valid token regex")
+  });
+  for found in regex.find_iter(text) {
+    let token = found.as_str();
+    if is_token(token) && tokens.iter().all(|scanned| scanned.token != token) {
+      tokens.push(preview_token(token));
+    }
+  }
+  tokens
+}
+
+/// whether the text holds tokens with other text around them, and isn't just a token
+pub fn has_embedded_tokens(text: &str) -> bool {
+  match scan_tokens(text).as_slice() {
+    [] => false,
+    [scanned] => scanned.token != text.trim(),
+    _ => true,
+  }
+}
+
+/// the kind of the token and the preview of its claims, empty for the parts that can't be decoded
+pub fn preview_token(token: &str) -> ScannedToken {
+  let token = token.trim();
+  let mut parts = token.split('.');
+  let (header, payload) = (parts.next(), parts.next());
+  if is_paseto(token) {
+    return ScannedToken {
+      token: token.into(),
+      kind: format!(
+        "PASETO {}.{}",
+        header.unwrap_or_default(),
+        payload.unwrap_or_default()
+      ),
+      preview: String::new(),
+    };
+  }
+  let decode = |part: Option<&str>| {
+    part
+      .and_then(|part| URL_SAFE_NO_PAD.decode(part).ok())
+      .and_then(|json| serde_json::from_slice::<Value>(&json).ok())
+      .unwrap_or_default()
+  };
+  let header = decode(header);
+  let kind = match header["enc"].as_str() {
+    Some(enc) => format!("JWE {} {enc}", header["alg"].as_str().unwrap_or_default()),
+    None => header["alg"].as_str().unwrap_or_default().into(),
+  };
+  // the second part of a JWE is its encrypted key
+  let payload = match header["enc"].is_null() {
+    true => decode(payload),
+    false => Value::Null,
+  };
+  let preview = PREVIEW_CLAIMS
+    .iter()
+    .filter_map(|claim| {
+      Some(format!(
+        "{claim} {}",
+        preview_claim(claim, &payload[claim])?
+      ))
+    })
+    .collect::<Vec<_>>()
+    .join(", ");
+  ScannedToken {
+    token: token.into(),
+    kind,
+    preview,
+  }
+}
+
+fn preview_claim(claim: &str, value: &Value) -> Option<String> {
+  match value {
+    Value::String(value) => Some(value.clone()),
+    Value::Number(value) if claim == "exp" => Utc
+      .timestamp_opt(value.as_i64()?, 0)
+      .single()
+      .map(|time| time.format("%Y-%m-%d %H:%M").to_string()),
+    Value::Array(values) => Some(
+      values
+        .iter()
+        .filter_map(Value::as_str)
+        .collect::<Vec<_>>()
+        .join(" "),
+    ),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  fn jwt(header: Value, payload: Value) -> String {
+    format!(
+      "{}.{}.c2ln",
+      URL_SAFE_NO_PAD.encode(header.to_string()),
+      URL_SAFE_NO_PAD.encode(payload.to_string())
+    )
+  }
+
+  #[test]
+  fn test_scan_tokens() {
+    let access = jwt(
+      json!({"alg": "RS256", "typ": "JWT"}),
+      json!({"iss": "https://idp", "sub": "1234", "aud": ["api", "web"], "exp": 1717243200}),
+    );
+    let id = jwt(json!({"alg": "HS256"}), json!({"sub": "5678"}));
+    let jwe = "eyJhbGciOiJkaXIiLCJlbmMiOiJBMjU2R0NNIn0..aXY.Y2lwaGVy.dGFn";
+    let text = format!(
+      "INFO request ok token={access}\n\
+       {{\"id_token\": \"{id}\", \"again\": \"{access}\"}}\n\
+       https://app.example.com/callback#access_token={jwe}&token_type=Bearer\n\
+       paseto v4.public.cGF5bG9hZA. not eyJ.a token eyJub3Q.anNvbg.sig"
+    );
+    let tokens = scan_tokens(&text);
+    let kinds: Vec<&str> = tokens.iter().map(|token| token.kind.as_str()).collect();
+    assert_eq!(
+      kinds,
+      ["RS256", "HS256", "JWE dir A256GCM", "PASETO v4.public"]
+    );
+    assert_eq!(tokens[0].token, access);
+    assert_eq!(
+      tokens[0].preview,
+      "iss https://idp, sub 1234, aud api web, exp 2024-06-01 12:00"
+    );
+    assert_eq!(tokens[1].preview, "sub 5678");
+    assert_eq!(tokens[2].token, jwe);
+    assert_eq!(tokens[2].preview, "");
+    assert_eq!(tokens[3].token, "v4.public.cGF5bG9hZA");
+
+    assert!(has_embedded_tokens(&text));
+    assert!(!has_embedded_tokens(&format!(" {id}\n")));
+    assert!(has_embedded_tokens(&format!("Bearer {id}")));
+    assert!(!has_embedded_tokens("no tokens here"));
+  }
+
+  #[test]
+  fn test_preview_token() {
+    let token = preview_token(&jwt(json!({"alg": "ES256"}), json!({"iss": "https://idp"})));
+    assert_eq!(token.kind, "ES256");
+    assert_eq!(token.preview, "iss https://idp");
+    let token = preview_token("opaque-refresh-token");
+    assert_eq!((token.kind.as_str(), token.preview.as_str()), ("", ""));
+  }
+}
@@ -0,0 +1,138 @@
+use serde_json::Value;
+
+use super::{
+  network::{send, RequestBody},
+  utils::{JWTError, JWTResult},
+};
+
+/// fields holding the token in the JSON responses of token endpoints, tried in order
+const TOKEN_FIELDS: [&str; 4] = ["access_token", "id_token", "token", "jwt"];
+
+/// request of `--token-url` fetching the token to decode
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenRequest {
+  pub url: String,
+  pub headers: Vec<(String, String)>,
+  /// body of the request, sent with a POST instead of a GET
+  pub body: Option<String>,
+  /// dot separated path of the token in the JSON response, e.g. `data.token`
+  pub field: Option<String>,
+}
+
+impl TokenRequest {
+  /// send the request and extract the token of the response
+  pub fn fetch(&self) -> JWTResult<String> {
+    let body = match &self.body {
+      Some(body) => RequestBody::Text(body),
+      None => RequestBody::None,
+    };
+    let body = send(&self.url, &self.headers, body)?;
+    extract_token(&body, self.field.as_deref())
+  }
+}
+
+/// the token of the response: the field of a JSON response, or the whole body. JSON responses
+/// without field use the first of `access_token`, `id_token`, `token` and `jwt`
+pub fn extract_token(body: &[u8], field: Option<&str>) -> JWTResult<String> {
+  let text = std::str::from_utf8(body)?.trim();
+  let json = serde_json::from_str::<Value>(text);
+  let token = match (field, &json) {
+    (Some(field), Ok(json)) => field
+      .split('.')
+      .try_fold(json, |value, name| value.get(name))
+      .ok_or_else(|| JWTError::Internal(format!("The response has no `{field}` field")))?,
+    (Some(_), Err(e)) => {
+      return Err(JWTError::Internal(format!(
+        "The response isn't JSON, leave out the token field to use the whole response: {e}"
+      )))
+    }
+    (None, Ok(json)) if json.is_object() => TOKEN_FIELDS
+      .iter()
+      .find_map(|name| json.get(name).filter(|value| value.is_string()))
+      .ok_or_else(|| {
+        JWTError::Internal(format!(
+          "The response has none of the {} fields, select the token with --token-field",
+          TOKEN_FIELDS.join(", ")
+        ))
+      })?,
+    (None, Ok(Value::String(token))) => return Ok(token.clone()),
+    (None, _) => return Ok(text.to_string()),
+  };
+  token
+    .as_str()
+    .map(|token| token.trim().to_string())
+    .ok_or_else(|| JWTError::Internal(format!("The token of the response isn't a text: {token}")))
+}
+
+/// a `Name: Value` header of the token request
+pub fn parse_header(header: &str) -> Result<(String, String), String> {
+  match header.split_once(':') {
+    Some((name, value)) if !name.trim().is_empty() => {
+      Ok((name.trim().to_string(), value.trim().to_string()))
+    }
+    _ => Err(format!("Invalid header {header:?}, expected `Name: Value`")),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app::network::tests::serve_once_with_request;
+
+  #[test]
+  fn test_extract_token() {
+    assert_eq!(extract_token(b"eyJ.a.b\n", None).unwrap(), "eyJ.a.b");
+    assert_eq!(extract_token(br#""eyJ.a.b""#, None).unwrap(), "eyJ.a.b");
+    assert_eq!(
+      extract_token(
+        br#"{"token_type": "Bearer", "id_token": "id.t.s", "access_token": "at.t.s"}"#,
+        None
+      )
+      .unwrap(),
+      "at.t.s"
+    );
+    assert_eq!(
+      extract_token(br#"{"data": {"jwt": "eyJ.a.b"}}"#, Some("data.jwt")).unwrap(),
+      "eyJ.a.b"
+    );
+    assert_eq!(
+      extract_token(br#"{"data": {}}"#, Some("data.jwt"))
+        .unwrap_err()
+        .to_string(),
+      "The response has no `data.jwt` field"
+    );
+    assert!(extract_token(br#"{"expires_in": 300}"#, None)
+      .unwrap_err()
+      .to_string()
+      .ends_with("select the token with --token-field"));
+    assert!(extract_token(b"eyJ.a.b", Some("token")).is_err());
+  }
+
+  #[test]
+  fn test_parse_header() {
+    assert_eq!(
+      parse_header("Authorization: Basic dXNlcjpwYXNz").unwrap(),
+      ("Authorization".into(), "Basic dXNlcjpwYXNz".into())
+    );
+    assert!(parse_header("no-colon").is_err());
+    assert!(parse_header(": value").is_err());
+  }
+
+  #[test]
+  fn test_fetch_token() {
+    let (url, request) =
+      serve_once_with_request("200 OK", br#"{"access_token": "eyJ.a.b"}"#.to_vec());
+    let token = TokenRequest {
+      url,
+      headers: vec![("Content-Type".into(), "application/json".into())],
+      body: Some(r#"{"user": "alice"}"#.into()),
+      field: None,
+    }
+    .fetch()
+    .unwrap();
+    assert_eq!(token, "eyJ.a.b");
+    let request = request.recv().unwrap();
+    assert!(request.starts_with("POST / HTTP/1.1"));
+    assert!(request.ends_with(r#"{"user": "alice"}"#));
+  }
+}
@@ -0,0 +1,129 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::errors::{Error, ErrorKind};
+use serde_json::Value;
+
+use super::{
+  jwt_decoder::{Payload, RawToken},
+  utils::{decode_segment, JWTError, JWTResult},
+  validation::{Finding, Severity},
+};
+
+pub const UNSECURED_TOKEN: &str =
+  "The token is unsecured (`alg: none`), it has no signature and must never be trusted";
+pub const EMPTY_SIGNATURE: &str =
+  "The signature of the token is empty, it can't be verified and must never be trusted";
+pub const REDACTED_SIGNATURE: &str =
+  "The signature was removed when the token was saved in the history, it can't be verified";
+pub const INSECURE_NONE_DISABLED: &str =
+  "Encoding `alg: none` tokens is disabled, start with `--insecure-none` to create unsigned test tokens";
+
+/// returns true if the header of the JWT declares the `none` algorithm, in any case
+pub fn is_alg_none(jwt: &str) -> bool {
+  jwt
+    .split('.')
+    .next()
+    .and_then(|header| decode_segment::<Value>(header).ok())
+    .and_then(|header| {
+      header["alg"]
+        .as_str()
+        .map(|alg| alg.eq_ignore_ascii_case("none"))
+    })
+    .unwrap_or(false)
+}
+
+/// returns true if the JWT has an empty signature segment
+pub fn has_empty_signature(jwt: &str) -> bool {
+  let parts: Vec<&str> = jwt.trim().split('.').collect();
+  parts.len() == 3 && parts[2].is_empty()
+}
+
+/// security finding for unsecured tokens and tokens without signature
+pub fn check_unsecured(jwt: &str) -> Option<Finding> {
+  let message = if is_alg_none(jwt) {
+    UNSECURED_TOKEN
+  } else if has_empty_signature(jwt) {
+    EMPTY_SIGNATURE
+  } else {
+    return None;
+  };
+  Some(Finding::new(Severity::Error, "unsecured", message.into()))
+}
+
+/// returns the decoded values of an `alg: none` JWT, which never verifies
+pub fn decode_unsecured(jwt: &str) -> (JWTResult<RawToken>, JWTResult<()>) {
+  let parts: Vec<&str> = jwt.trim().split('.').collect();
+  if parts.len() != 3 {
+    let err = || JWTError::from(Error::from(ErrorKind::InvalidToken));
+    return (Err(err()), Err(err()));
+  }
+  let decoded = decode_segment::<Value>(parts[0]).and_then(|header| {
+    Ok(RawToken {
+      header,
+      claims: Some(decode_segment::<Payload>(parts[1])?),
+    })
+  });
+  (decoded, Err(JWTError::Internal(UNSECURED_TOKEN.into())))
+}
+
+/// returns an unsigned JWT with an empty signature, keeping the `alg` of the header as is so
+/// variants like `NoNe` can be tested
+pub fn encode_unsecured(header: &Value, claims: &Payload) -> JWTResult<String> {
+  let encode = |value: String| URL_SAFE_NO_PAD.encode(value);
+  let claims = serde_json::to_string(claims).map_err(|e| JWTError::Internal(e.to_string()))?;
+  Ok(format!(
+    "{}.{}.",
+    encode(header.to_string()),
+    encode(claims)
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // {"alg":"none","typ":"JWT"}.{"sub":"1234"}.
+  const NONE_JWT: &str = "eyJhbGciOiJub25lIiwidHlwIjoiSldUIn0.eyJzdWIiOiIxMjM0In0.";
+  // {"alg":"HS256","typ":"JWT"}.{"sub":"1234"}.
+  const EMPTY_SIGNATURE_JWT: &str = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.eyJzdWIiOiIxMjM0In0.";
+
+  #[test]
+  fn test_is_alg_none() {
+    assert!(is_alg_none(NONE_JWT));
+    // {"alg":"NoNe"}
+    assert!(is_alg_none("eyJhbGciOiJOb05lIn0.e30."));
+    assert!(!is_alg_none(EMPTY_SIGNATURE_JWT));
+    assert!(!is_alg_none("not a token"));
+  }
+
+  #[test]
+  fn test_check_unsecured() {
+    assert_eq!(check_unsecured(NONE_JWT).unwrap().message, UNSECURED_TOKEN);
+    assert_eq!(
+      check_unsecured(EMPTY_SIGNATURE_JWT).unwrap(),
+      Finding::new(Severity::Error, "unsecured", EMPTY_SIGNATURE.into())
+    );
+    assert_eq!(check_unsecured(&format!("{EMPTY_SIGNATURE_JWT}sig")), None);
+  }
+
+  #[test]
+  fn test_decode_unsecured() {
+    let (decoded, verified) = decode_unsecured(NONE_JWT);
+    let decoded = decoded.unwrap();
+    assert_eq!(decoded.header["alg"], "none");
+    assert_eq!(decoded.claims.unwrap().0.get("sub").unwrap(), "1234");
+    assert_eq!(verified.unwrap_err().to_string(), UNSECURED_TOKEN);
+
+    assert!(decode_unsecured("abc").0.is_err());
+  }
+
+  #[test]
+  fn test_encode_unsecured() {
+    let header = serde_json::json!({"alg": "none", "typ": "JWT"});
+    let claims: Payload = serde_json::from_str(r#"{"sub": "1234"}"#).unwrap();
+    let token = encode_unsecured(&header, &claims).unwrap();
+    assert_eq!(token, NONE_JWT);
+
+    let (decoded, _) = decode_unsecured(&token);
+    assert_eq!(decoded.unwrap().header, header);
+  }
+}
@@ -0,0 +1,288 @@
+//! Conversions of the utilities view for the values met while debugging tokens: base64url and URL
+//! encoded text, UNIX timestamps and dates
+
+use base64::{
+  engine::general_purpose::{STANDARD_NO_PAD, URL_SAFE_NO_PAD},
+  Engine,
+};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+use super::{
+  models::StatefulTable,
+  validation::{format_duration, parse_duration},
+  TextInput,
+};
+
+/// characters kept as is when URL encoding, the unreserved characters of RFC 3986
+const UNRESERVED: &AsciiSet = &NON_ALPHANUMERIC
+  .remove(b'-')
+  .remove(b'.')
+  .remove(b'_')
+  .remove(b'~');
+
+/// timestamps above are taken as milliseconds, seconds only get there in the year 5138
+const MAX_SECONDS: i64 = 100_000_000_000;
+
+/// date formats accepted besides RFC 3339, as UTC
+const DATE_TIME_FORMATS: [&str; 3] = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"];
+
+/// a conversion of the input of the utilities view
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conversion {
+  pub name: &'static str,
+  pub value: String,
+}
+
+/// input of the utilities view and its conversions
+#[derive(Debug, Clone, Default)]
+pub struct Utilities {
+  pub input: TextInput,
+  pub conversions: StatefulTable<Conversion>,
+  /// the input and time of the last conversion, to only convert again when they change
+  converted: Option<(String, i64)>,
+}
+
+impl Utilities {
+  /// convert the input again when it changed since the last conversion. The current time only
+  /// changes the conversions of empty inputs and relative durations
+  pub fn update(&mut self, now: i64) {
+    let input = self.input.input.value();
+    let last = self.converted.as_ref();
+    if last.is_some_and(|(converted, at)| converted == input && *at == now) {
+      return;
+    }
+    let changed = last.is_none_or(|(converted, _)| converted != input);
+    self.conversions.set_items(convert(input, now));
+    // the selection is kept while the same input is converted again
+    if changed {
+      self.conversions.select_edge(true);
+    }
+    self.converted = Some((input.to_string(), now));
+  }
+
+  /// the value of the selected conversion
+  pub fn selected(&self) -> Option<&str> {
+    let selected = self.conversions.state.selected()?;
+    Some(self.conversions.items.get(selected)?.value.as_str())
+  }
+}
+
+/// every conversion that fits the input, the current time for an empty input
+pub fn convert(input: &str, now: i64) -> Vec<Conversion> {
+  let mut conversions = vec![];
+  let mut add = |name, value: Option<String>| {
+    if let Some(value) = value {
+      conversions.push(Conversion { name, value });
+    }
+  };
+  let trimmed = input.trim();
+  if trimmed.is_empty() {
+    add("Now, UNIX time", Some(now.to_string()));
+    add("Now, UTC", utc_date(now));
+    add("Now, local time", local_date(now));
+    return conversions;
+  }
+
+  let timestamp = trimmed.parse::<i64>().ok();
+  if let Some(timestamp) = timestamp {
+    let (secs, unit) = match timestamp.abs() > MAX_SECONDS {
+      true => (timestamp / 1000, "milliseconds"),
+      false => (timestamp, "seconds"),
+    };
+    add("UNIX time unit", Some(unit.into()));
+    add("UTC date", utc_date(secs));
+    add("Local date", local_date(secs));
+    add("Relative to now", Some(relative(secs - now)));
+  } else if let Some(secs) = parse_date(trimmed) {
+    add("UNIX time", Some(secs.to_string()));
+    add("UTC date", utc_date(secs));
+    add("Relative to now", Some(relative(secs - now)));
+  } else if let Some(duration) = parse_duration(trimmed).filter(|_| trimmed.starts_with(['+', '-']))
+  {
+    add("UNIX time", Some((now + duration).to_string()));
+    add("UTC date", utc_date(now + duration));
+  }
+
+  // numbers are valid base64, decoding them isn't worth a row
+  if timestamp.is_none() {
+    add("Base64url decoded", base64_decode(trimmed));
+  }
+  add("Base64url encoded", Some(URL_SAFE_NO_PAD.encode(input)));
+  let decoded = percent_decode_str(&input.replace('+', " "))
+    .decode_utf8()
+    .ok()
+    .filter(|decoded| decoded != input)
+    .map(String::from);
+  add("URL decoded", decoded);
+  add(
+    "URL encoded",
+    Some(utf8_percent_encode(input, UNRESERVED).to_string()),
+  );
+  conversions
+}
+
+/// the text of a base64url or base64 value, or its bytes in hex when they aren't text
+fn base64_decode(value: &str) -> Option<String> {
+  let value = value.trim_end_matches('=');
+  let bytes = URL_SAFE_NO_PAD
+    .decode(value)
+    .or_else(|_| STANDARD_NO_PAD.decode(value))
+    .ok()
+    .filter(|bytes| !bytes.is_empty())?;
+  Some(match String::from_utf8(bytes) {
+    Ok(text) if !text.chars().any(|c| c.is_control() && !c.is_whitespace()) => text,
+    Ok(text) => hex(text.as_bytes()),
+    Err(e) => hex(e.as_bytes()),
+  })
+}
+
+fn hex(bytes: &[u8]) -> String {
+  let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+  format!("0x{hex}")
+}
+
+/// the UNIX time of an RFC 3339 date, or of a UTC date like `2024-06-01 12:00` or `2024-06-01`
+fn parse_date(value: &str) -> Option<i64> {
+  if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+    return Some(date.timestamp());
+  }
+  DATE_TIME_FORMATS
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+    .or_else(|| {
+      NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()?
+        .and_hms_opt(0, 0, 0)
+    })
+    .map(|date| date.and_utc().timestamp())
+}
+
+fn utc_date(secs: i64) -> Option<String> {
+  Utc
+    .timestamp_opt(secs, 0)
+    .single()
+    .map(|date| date.to_rfc3339())
+}
+
+fn local_date(secs: i64) -> Option<String> {
+  Local
+    .timestamp_opt(secs, 0)
+    .single()
+    .map(|date| date.to_rfc3339())
+}
+
+fn relative(secs: i64) -> String {
+  match secs {
+    0 => "now".into(),
+    secs if secs > 0 => format!("in {}", format_duration(secs)),
+    secs => format!("{} ago", format_duration(-secs)),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use tui_input::Input;
+
+  use super::*;
+
+  /// 2024-06-01T12:00:00Z
+  const NOW: i64 = 1_717_243_200;
+
+  fn value(conversions: &[Conversion], name: &str) -> Option<String> {
+    conversions
+      .iter()
+      .find(|conversion| conversion.name == name)
+      .map(|conversion| conversion.value.clone())
+  }
+
+  #[test]
+  fn test_convert_timestamps() {
+    let conversions = convert("1717246800", NOW);
+    assert_eq!(
+      value(&conversions, "UTC date").unwrap(),
+      "2024-06-01T13:00:00+00:00"
+    );
+    assert_eq!(value(&conversions, "UNIX time unit").unwrap(), "seconds");
+    assert_eq!(value(&conversions, "Relative to now").unwrap(), "in 1h");
+
+    let conversions = convert("1717239600000", NOW);
+    assert_eq!(
+      value(&conversions, "UNIX time unit").unwrap(),
+      "milliseconds"
+    );
+    assert_eq!(value(&conversions, "Relative to now").unwrap(), "1h ago");
+
+    for date in [
+      "2024-06-01T14:00:00+02:00",
+      "2024-06-01 12:00",
+      "2024-06-01",
+    ] {
+      let secs = value(&convert(date, NOW), "UNIX time").unwrap();
+      assert!(secs == NOW.to_string() || secs == "1717200000", "{date}");
+    }
+    assert_eq!(
+      value(&convert("+1h 30m", NOW), "UNIX time").unwrap(),
+      (NOW + 5400).to_string()
+    );
+
+    let now = convert("", NOW);
+    assert_eq!(value(&now, "Now, UNIX time").unwrap(), NOW.to_string());
+    assert_eq!(
+      value(&now, "Now, UTC").unwrap(),
+      "2024-06-01T12:00:00+00:00"
+    );
+  }
+
+  #[test]
+  fn test_convert_text() {
+    let conversions = convert("eyJhbGciOiJIUzI1NiJ9", NOW);
+    assert_eq!(
+      value(&conversions, "Base64url decoded").unwrap(),
+      r#"{"alg":"HS256"}"#
+    );
+    assert_eq!(value(&conversions, "URL decoded"), None);
+    // padded and standard base64
+    assert_eq!(
+      value(&convert("aGk/Pw==", NOW), "Base64url decoded").unwrap(),
+      "hi??"
+    );
+    assert_eq!(
+      value(&convert("AAEC", NOW), "Base64url decoded").unwrap(),
+      "0x000102"
+    );
+
+    let conversions = convert(
+      "redirect_uri=https%3A%2F%2Fapp%2Fcb&scope=openid+email",
+      NOW,
+    );
+    assert_eq!(
+      value(&conversions, "URL decoded").unwrap(),
+      "redirect_uri=https://app/cb&scope=openid email"
+    );
+    assert_eq!(value(&conversions, "Base64url decoded"), None);
+    assert_eq!(
+      value(&convert("a b/c", NOW), "URL encoded").unwrap(),
+      "a%20b%2Fc"
+    );
+    assert_eq!(
+      value(&convert("{\"alg\":\"HS256\"}", NOW), "Base64url encoded").unwrap(),
+      "eyJhbGciOiJIUzI1NiJ9"
+    );
+  }
+
+  #[test]
+  fn test_update_keeps_selection() {
+    let mut utilities = Utilities::default();
+    utilities.input.input = Input::new("1717246800".into());
+    utilities.update(NOW);
+    assert_eq!(utilities.selected(), Some("seconds"));
+    utilities.conversions.state.select(Some(1));
+    utilities.update(NOW + 1);
+    assert_eq!(utilities.selected(), Some("2024-06-01T13:00:00+00:00"));
+
+    utilities.input.input = Input::default();
+    utilities.update(NOW + 1);
+    assert_eq!(utilities.selected(), Some("1717243201"));
+  }
+}
@@ -0,0 +1,589 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::DateTime;
+use clap::ValueEnum;
+use jsonwebtoken::{Algorithm, Header};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{
+  config::Profile,
+  jwt_decoder::Payload,
+  paseto::is_paseto,
+  spiffe::check_jwt_svid,
+  utils::{get_secret_from_file_or_input, SecretType},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+  Warning,
+  Error,
+}
+
+/// A single result of a validation check, shown in the decoder validation pane
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Finding {
+  pub severity: Severity,
+  /// name of the check that produced this finding
+  pub check: String,
+  pub message: String,
+}
+
+impl Finding {
+  pub fn new(severity: Severity, check: &str, message: String) -> Self {
+    Finding {
+      severity,
+      check: check.into(),
+      message,
+    }
+  }
+}
+
+/// returns true if any of the findings should fail validation
+pub fn has_errors(findings: &[Finding]) -> bool {
+  findings.iter().any(|f| f.severity == Severity::Error)
+}
+
+/// check the token lifetime and clock skew against the given profile policy
+pub fn check_lifetime_policy(claims: &Payload, profile: &Profile, now: i64) -> Vec<Finding> {
+  let mut findings = vec![];
+
+  let iat = claim_timestamp(claims, "iat");
+  let nbf = claim_timestamp(claims, "nbf");
+  let exp = claim_timestamp(claims, "exp");
+
+  if let Some(max_lifetime) = profile.max_lifetime {
+    match (iat.or(nbf), exp) {
+      (Some(start), Some(exp)) => {
+        let lifetime = exp - start;
+        if lifetime > max_lifetime as i64 {
+          findings.push(Finding::new(
+            Severity::Error,
+            "lifetime",
+            format!(
+              "Token lifetime of {} exceeds the maximum of {}",
+              format_duration(lifetime),
+              format_duration(max_lifetime as i64)
+            ),
+          ));
+        }
+      }
+      (_, None) => findings.push(Finding::new(
+        Severity::Error,
+        "lifetime",
+        "Token has no `exp` claim, lifetime is unbounded".into(),
+      )),
+      (None, Some(_)) => findings.push(Finding::new(
+        Severity::Warning,
+        "lifetime",
+        "Token has no `iat` or `nbf` claim, lifetime can't be checked".into(),
+      )),
+    }
+  }
+
+  if let Some(max_skew) = profile.max_clock_skew {
+    for (claim, value) in [("iat", iat), ("nbf", nbf)] {
+      if let Some(ts) = value {
+        let skew = ts - now;
+        if skew > max_skew as i64 {
+          findings.push(Finding::new(
+            Severity::Error,
+            "clock-skew",
+            format!(
+              "`{claim}` is {} in the future, more than the allowed clock skew of {}",
+              format_duration(skew),
+              format_duration(max_skew as i64)
+            ),
+          ));
+        }
+      }
+    }
+  }
+
+  findings
+}
+
+/// built-in set of checks for a token profile, selected with `preset` in a config profile or
+/// with `--preset`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+  /// JWT profile for OAuth 2.0 access tokens (RFC 9068)
+  Rfc9068,
+  /// SPIFFE JWT-SVIDs, verified with the SPIFFE bundle of their trust domain
+  JwtSvid,
+}
+
+/// claims every RFC 9068 access token must have
+const ACCESS_TOKEN_CLAIMS: [&str; 7] = ["iss", "exp", "aud", "sub", "client_id", "iat", "jti"];
+
+/// check the header and claims of the token against the preset
+pub fn check_preset(preset: Preset, header: &Header, claims: &Payload, now: i64) -> Vec<Finding> {
+  match preset {
+    Preset::Rfc9068 => check_access_token_profile(header, claims),
+    Preset::JwtSvid => check_jwt_svid(header, claims, now),
+  }
+}
+
+/// check the requirements of the JWT profile for OAuth 2.0 access tokens (RFC 9068 section 2)
+fn check_access_token_profile(header: &Header, claims: &Payload) -> Vec<Finding> {
+  let mut findings = vec![];
+  match header.typ.as_deref() {
+    Some(typ) if typ.eq_ignore_ascii_case("at+jwt") => {}
+    Some(typ) if typ.eq_ignore_ascii_case("application/at+jwt") => {}
+    Some(typ) => findings.push(Finding::new(
+      Severity::Error,
+      "rfc9068",
+      format!("`typ` is {typ:?}, access tokens must have `at+jwt`"),
+    )),
+    None => findings.push(Finding::new(
+      Severity::Error,
+      "rfc9068",
+      "Header has no `typ`, access tokens must have `at+jwt`".into(),
+    )),
+  }
+  for claim in ACCESS_TOKEN_CLAIMS {
+    if !claims.0.contains_key(claim) {
+      findings.push(Finding::new(
+        Severity::Error,
+        "rfc9068",
+        format!("Access token has no `{claim}` claim"),
+      ));
+    }
+  }
+  for claim in ["exp", "iat"] {
+    if claims.0.contains_key(claim) && claim_timestamp(claims, claim).is_none() {
+      findings.push(Finding::new(
+        Severity::Error,
+        "rfc9068",
+        format!("`{claim}` is not a timestamp"),
+      ));
+    }
+  }
+  match claims.0.get("aud") {
+    Some(Value::String(_)) | None => {}
+    Some(Value::Array(aud)) if !aud.is_empty() && aud.iter().all(Value::is_string) => {}
+    Some(_) => findings.push(Finding::new(
+      Severity::Error,
+      "rfc9068",
+      "`aud` is not a string or an array of strings".into(),
+    )),
+  }
+  if matches!(claims.0.get("scope"), Some(scope) if !scope.is_string()) {
+    findings.push(Finding::new(
+      Severity::Error,
+      "rfc9068",
+      "`scope` is not a space separated string".into(),
+    ));
+  }
+  findings
+}
+
+/// secrets found in tutorials and default configurations, which are the first ones brute-forced
+const COMMON_SECRETS: [&str; 20] = [
+  "secret",
+  "changeme",
+  "password",
+  "your-256-bit-secret",
+  "your-384-bit-secret",
+  "your-512-bit-secret",
+  "your-secret-key",
+  "secretkey",
+  "secret-key",
+  "secret_key",
+  "mysecret",
+  "my-secret",
+  "supersecret",
+  "jwtsecret",
+  "jwt-secret",
+  "jwt_secret",
+  "shhhhh",
+  "keyboard cat",
+  "test",
+  "123456",
+];
+
+/// warn when a HMAC secret is shorter than the hash output (RFC 7518 section 3.2) or well-known
+pub fn check_hmac_secret(alg: &Algorithm, secret: &str) -> Vec<Finding> {
+  let min_size = match alg {
+    Algorithm::HS256 => 32,
+    Algorithm::HS384 => 48,
+    Algorithm::HS512 => 64,
+    _ => return vec![],
+  };
+  let key = match get_secret_from_file_or_input(alg, secret) {
+    (Ok(key), SecretType::Plain) => key,
+    (Ok(key), SecretType::B64) => match STANDARD.decode(key) {
+      Ok(key) => key,
+      Err(_) => return vec![],
+    },
+    _ => return vec![],
+  };
+
+  let mut findings = vec![];
+  let text = String::from_utf8_lossy(&key);
+  if COMMON_SECRETS.contains(&text.trim().to_lowercase().as_str()) {
+    findings.push(Finding::new(
+      Severity::Warning,
+      "weak-secret",
+      format!("The {alg:?} secret is a well-known secret that is easily guessed"),
+    ));
+  }
+  if key.len() < min_size {
+    findings.push(Finding::new(
+      Severity::Warning,
+      "weak-secret",
+      format!(
+        "The {alg:?} secret is {} bytes long, shorter than the {min_size} bytes required by RFC 7518",
+        key.len()
+      ),
+    ));
+  }
+  findings
+}
+
+/// maximum size of a cookie (name and value) in most browsers
+const COOKIE_SIZE_LIMIT: usize = 4096;
+/// default maximum size of a request header line in common servers and proxies (nginx, Apache)
+const HEADER_LINE_LIMIT: usize = 8192;
+/// prefix of the header line the token is usually sent in
+const AUTHORIZATION_PREFIX: &str = "Authorization: Bearer ";
+
+/// warn when the token is too large to be sent in a cookie or a request header
+pub fn check_token_size(token: &str) -> Vec<Finding> {
+  let size = token.trim().len();
+  let mut findings = vec![];
+
+  if size + AUTHORIZATION_PREFIX.len() > HEADER_LINE_LIMIT {
+    findings.push(Finding::new(
+      Severity::Warning,
+      "size",
+      format!(
+        "Token size of {} exceeds the {} header line limit of common servers and proxies",
+        format_size(size),
+        format_size(HEADER_LINE_LIMIT)
+      ),
+    ));
+  } else if size > COOKIE_SIZE_LIMIT {
+    findings.push(Finding::new(
+      Severity::Warning,
+      "size",
+      format!(
+        "Token size of {} exceeds the {} cookie limit of most browsers",
+        format_size(size),
+        format_size(COOKIE_SIZE_LIMIT)
+      ),
+    ));
+  }
+  findings
+}
+
+/// size in bytes of the token and of its `.` separated segments, named for JWS and JWE tokens
+pub fn token_size(token: &str) -> (usize, Vec<(&'static str, usize)>) {
+  let token = token.trim();
+  let segments: Vec<usize> = token.split('.').map(str::len).collect();
+  let names: &[&str] = match segments.len() {
+    _ if is_paseto(token) => &[],
+    3 => &["header", "payload", "signature"],
+    5 => &["header", "key", "iv", "ciphertext", "tag"],
+    _ => &[],
+  };
+  (token.len(), names.iter().copied().zip(segments).collect())
+}
+
+/// format a size in bytes as `123 B` or `4.5 KB`
+pub fn format_size(bytes: usize) -> String {
+  if bytes < 1024 {
+    format!("{bytes} B")
+  } else {
+    format!("{:.1} KB", bytes as f64 / 1024.0).replace(".0 KB", " KB")
+  }
+}
+
+/// read a timestamp claim as unix epoch, accepting numbers and RFC 3339 strings (UTC dates mode)
+pub fn claim_timestamp(claims: &Payload, claim: &str) -> Option<i64> {
+  match claims.0.get(claim)? {
+    Value::Number(n) => n.as_i64(),
+    Value::String(s) => DateTime::parse_from_rfc3339(s).ok().map(|d| d.timestamp()),
+    _ => None,
+  }
+}
+
+/// format seconds as a short human readable duration like `1d 2h 3m 4s`
+pub fn format_duration(secs: i64) -> String {
+  let sign = if secs < 0 { "-" } else { "" };
+  let secs = secs.unsigned_abs();
+  let parts: Vec<String> = [(86400, "d"), (3600, "h"), (60, "m"), (1, "s")]
+    .iter()
+    .scan(secs, |rest, (unit, suffix)| {
+      let value = *rest / unit;
+      *rest %= unit;
+      Some((value, suffix))
+    })
+    .filter(|(value, _)| *value > 0)
+    .map(|(value, suffix)| format!("{value}{suffix}"))
+    .collect();
+
+  if parts.is_empty() {
+    "0s".into()
+  } else {
+    format!("{sign}{}", parts.join(" "))
+  }
+}
+
+/// parse a duration like `15m`, `+1h 30m`, `-30s` or `+10 min` into seconds, the inverse of
+/// `format_duration`. Numbers without unit are seconds
+pub fn parse_duration(value: &str) -> Option<i64> {
+  let value = value.trim();
+  let (sign, mut rest) = match value.strip_prefix('-') {
+    Some(rest) => (-1, rest),
+    None => (1, value.strip_prefix('+').unwrap_or(value)),
+  };
+  let mut total: i64 = 0;
+  let mut parts = 0;
+  loop {
+    rest = rest.trim_start();
+    if rest.is_empty() {
+      break;
+    }
+    let digits = rest
+      .find(|c: char| !c.is_ascii_digit())
+      .unwrap_or(rest.len());
+    let number: i64 = rest[..digits].parse().ok()?;
+    rest = rest[digits..].trim_start();
+    let letters = rest
+      .find(|c: char| !c.is_ascii_alphabetic())
+      .unwrap_or(rest.len());
+    let unit = match &rest[..letters] {
+      "" | "s" | "sec" | "secs" | "second" | "seconds" => 1,
+      "m" | "min" | "mins" | "minute" | "minutes" => 60,
+      "h" | "hr" | "hrs" | "hour" | "hours" => 3600,
+      "d" | "day" | "days" => 86400,
+      "w" | "week" | "weeks" => 604800,
+      _ => return None,
+    };
+    rest = &rest[letters..];
+    total = total.checked_add(number.checked_mul(unit)?)?;
+    parts += 1;
+  }
+  (parts > 0).then_some(sign * total)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn payload(json: &str) -> Payload {
+    serde_json::from_str(json).unwrap()
+  }
+
+  #[test]
+  fn test_format_duration() {
+    assert_eq!(format_duration(0), "0s");
+    assert_eq!(format_duration(59), "59s");
+    assert_eq!(format_duration(3600), "1h");
+    assert_eq!(format_duration(90061), "1d 1h 1m 1s");
+    assert_eq!(format_duration(-120), "-2m");
+  }
+
+  #[test]
+  fn test_parse_duration() {
+    assert_eq!(parse_duration("15m"), Some(900));
+    assert_eq!(parse_duration("+1h 30m"), Some(5400));
+    assert_eq!(parse_duration(" -30s "), Some(-30));
+    assert_eq!(parse_duration("90"), Some(90));
+    assert_eq!(parse_duration("1w"), Some(604800));
+    assert_eq!(parse_duration("1d 1h 1m 1s"), Some(90061));
+    assert_eq!(parse_duration("+10 min"), Some(600));
+    assert_eq!(parse_duration("2 hours 5 mins"), Some(7500));
+    assert_eq!(parse_duration("1day"), Some(86400));
+    for invalid in [
+      "",
+      "+",
+      "m",
+      "15 lightyears",
+      "1.5h",
+      "99999999999999999999d",
+    ] {
+      assert_eq!(parse_duration(invalid), None, "{invalid}");
+    }
+  }
+
+  #[test]
+  fn test_check_hmac_secret() {
+    let findings = check_hmac_secret(&Algorithm::HS256, "secret");
+    assert_eq!(
+      findings,
+      vec![
+        Finding::new(
+          Severity::Warning,
+          "weak-secret",
+          "The HS256 secret is a well-known secret that is easily guessed".into()
+        ),
+        Finding::new(
+          Severity::Warning,
+          "weak-secret",
+          "The HS256 secret is 6 bytes long, shorter than the 32 bytes required by RFC 7518".into()
+        ),
+      ]
+    );
+
+    let secret = "a-random-secret-of-32-bytes-long";
+    assert!(check_hmac_secret(&Algorithm::HS256, secret).is_empty());
+    assert_eq!(
+      check_hmac_secret(&Algorithm::HS512, secret)[0].message,
+      "The HS512 secret is 32 bytes long, shorter than the 64 bytes required by RFC 7518"
+    );
+    // base64 secrets are checked decoded
+    let b64 = format!("b64:{}", STANDARD.encode([7u8; 16]));
+    assert_eq!(check_hmac_secret(&Algorithm::HS256, &b64).len(), 1);
+    assert!(check_hmac_secret(&Algorithm::RS256, "secret").is_empty());
+  }
+
+  #[test]
+  fn test_format_size() {
+    assert_eq!(format_size(0), "0 B");
+    assert_eq!(format_size(1023), "1023 B");
+    assert_eq!(format_size(4096), "4 KB");
+    assert_eq!(format_size(5300), "5.2 KB");
+  }
+
+  #[test]
+  fn test_token_size() {
+    assert_eq!(
+      token_size("abc.de.f\n"),
+      (8, vec![("header", 3), ("payload", 2), ("signature", 1)])
+    );
+    assert_eq!(token_size("a.b.c.d.e").1[3], ("ciphertext", 1));
+    assert_eq!(token_size("v4.public.abc"), (13, vec![]));
+  }
+
+  #[test]
+  fn test_check_token_size() {
+    let token = |size: usize| format!("{}.e30.sig", "a".repeat(size - 8));
+
+    assert!(check_token_size(&token(4096)).is_empty());
+    assert_eq!(
+      check_token_size(&token(5300)),
+      vec![Finding::new(
+        Severity::Warning,
+        "size",
+        "Token size of 5.2 KB exceeds the 4 KB cookie limit of most browsers".into()
+      )]
+    );
+    assert_eq!(
+      check_token_size(&token(8192))[0].message,
+      "Token size of 8 KB exceeds the 8 KB header line limit of common servers and proxies"
+    );
+    assert!(!has_errors(&check_token_size(&token(9000))));
+  }
+
+  #[test]
+  fn test_check_preset_rfc9068() {
+    let mut header = Header::new(Algorithm::RS256);
+    header.typ = Some("at+jwt".into());
+    let claims = payload(
+      r#"{"iss": "https://idp.example.com", "exp": 1700000300, "aud": ["api"], "sub": "user-1",
+      "client_id": "my-app", "iat": 1700000000, "jti": "id-1", "scope": "read write"}"#,
+    );
+    assert!(check_preset(Preset::Rfc9068, &header, &claims, 0).is_empty());
+    header.typ = Some("application/at+JWT".into());
+    assert!(check_preset(Preset::Rfc9068, &header, &claims, 0).is_empty());
+
+    header.typ = Some("JWT".into());
+    let claims =
+      payload(r#"{"iss": "https://idp.example.com", "exp": "soon", "aud": 1, "scope": ["read"]}"#);
+    let messages: Vec<String> = check_preset(Preset::Rfc9068, &header, &claims, 0)
+      .into_iter()
+      .map(|f| f.message)
+      .collect();
+    assert_eq!(
+      messages,
+      vec![
+        "`typ` is \"JWT\", access tokens must have `at+jwt`",
+        "Access token has no `sub` claim",
+        "Access token has no `client_id` claim",
+        "Access token has no `iat` claim",
+        "Access token has no `jti` claim",
+        "`exp` is not a timestamp",
+        "`aud` is not a string or an array of strings",
+        "`scope` is not a space separated string",
+      ]
+    );
+  }
+
+  #[test]
+  fn test_check_lifetime_policy_within_limits() {
+    let profile = Profile {
+      max_lifetime: Some(3600),
+      max_clock_skew: Some(60),
+      ..Profile::default()
+    };
+    let claims = payload(r#"{"iat": 1000, "exp": 4600}"#);
+
+    assert!(check_lifetime_policy(&claims, &profile, 1000).is_empty());
+    // no policy, no findings
+    assert!(check_lifetime_policy(&claims, &Profile::default(), 0).is_empty());
+  }
+
+  #[test]
+  fn test_check_lifetime_policy_exceeded() {
+    let profile = Profile {
+      max_lifetime: Some(3600),
+      max_clock_skew: Some(60),
+      ..Profile::default()
+    };
+    let claims = payload(r#"{"iat": 1000, "nbf": 1030, "exp": 90000}"#);
+
+    let findings = check_lifetime_policy(&claims, &profile, 900);
+
+    assert_eq!(
+      findings,
+      vec![
+        Finding::new(
+          Severity::Error,
+          "lifetime",
+          "Token lifetime of 1d 43m 20s exceeds the maximum of 1h".into()
+        ),
+        Finding::new(
+          Severity::Error,
+          "clock-skew",
+          "`iat` is 1m 40s in the future, more than the allowed clock skew of 1m".into()
+        ),
+        Finding::new(
+          Severity::Error,
+          "clock-skew",
+          "`nbf` is 2m 10s in the future, more than the allowed clock skew of 1m".into()
+        ),
+      ]
+    );
+    assert!(has_errors(&findings));
+  }
+
+  #[test]
+  fn test_check_lifetime_policy_with_utc_dates_and_missing_claims() {
+    let profile = Profile {
+      max_lifetime: Some(60),
+      max_clock_skew: None,
+      ..Profile::default()
+    };
+    let claims = payload(r#"{"iat": "2018-01-18T01:30:22+00:00", "exp": 1516239142}"#);
+
+    let findings = check_lifetime_policy(&claims, &profile, 0);
+    assert_eq!(findings.len(), 1);
+    assert_eq!(
+      findings[0].message,
+      "Token lifetime of 2m exceeds the maximum of 1m"
+    );
+
+    let findings = check_lifetime_policy(&payload(r#"{"iat": 1}"#), &profile, 0);
+    assert_eq!(
+      findings[0].message,
+      "Token has no `exp` claim, lifetime is unbounded"
+    );
+
+    let findings = check_lifetime_policy(&payload(r#"{"exp": 1}"#), &profile, 0);
+    assert_eq!(findings[0].severity, Severity::Warning);
+    assert!(!has_errors(&findings));
+  }
+}
@@ -0,0 +1,364 @@
+use std::{
+  io::{Read, Write},
+  process::{Output, Stdio},
+  sync::mpsc::{self, Receiver, Sender},
+  thread,
+  time::{Duration, Instant},
+};
+
+use crossterm::event::{KeyEvent, MouseEvent};
+use jsonwebtoken::Header;
+use serde_derive::Deserialize;
+use serde_json::json;
+
+use super::{
+  jwt_decoder::Payload,
+  utils::{shell_command, JWTError, JWTResult},
+  validation::{Finding, Severity},
+};
+use crate::event::Event;
+
+/// validators still running after this are killed, a hanging validator would never report
+const HOOK_TIMEOUT: Duration = Duration::from_secs(10);
+/// external command checking the decoded token, e.g. a company policy checker. It reads the
+/// decoded token as JSON on stdin and prints a JSON array of verdicts
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub struct ValidatorHook {
+  /// name of the validator, shown as the check of its findings
+  pub name: String,
+  /// command run in the system shell
+  pub command: String,
+}
+
+/// verdict printed by a validator, e.g. `{"severity": "warning", "message": "No tenant claim"}`
+#[derive(Debug, Deserialize)]
+struct Verdict {
+  #[serde(default = "default_severity")]
+  severity: Severity,
+  message: String,
+}
+
+fn default_severity() -> Severity {
+  Severity::Error
+}
+
+/// validators to run for a decoded token, as the JSON read by the validators
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HookJob {
+  hooks: Vec<ValidatorHook>,
+  input: String,
+}
+
+/// findings of the validators run by the worker for a token
+pub struct HookRun {
+  input: String,
+  findings: Vec<Finding>,
+}
+
+/// worker thread running the validators, so a slow validator doesn't freeze the UI. Results come
+/// back to the main loop as [`Event::Validated`] events
+pub struct HookRunner {
+  jobs: Sender<HookJob>,
+}
+
+impl HookRunner {
+  pub fn new(events: Sender<Event<KeyEvent, MouseEvent>>) -> JWTResult<Self> {
+    let (jobs, receiver) = mpsc::channel::<HookJob>();
+    thread::Builder::new()
+      .name("jwtui-validators".into())
+      .spawn(move || {
+        while let Ok(mut job) = receiver.recv() {
+          // only the last decoded token is worth validating
+          while let Ok(newer) = receiver.try_recv() {
+            job = newer;
+          }
+          let findings = run_hooks(&job.hooks, &job.input, HOOK_TIMEOUT);
+          let run = HookRun {
+            input: job.input,
+            findings,
+          };
+          if events.send(Event::Validated(Box::new(run))).is_err() {
+            break;
+          }
+        }
+      })
+      .map_err(|e| JWTError::Internal(format!("Unable to start the validators: {e}")))?;
+    Ok(HookRunner { jobs })
+  }
+
+  fn run(&self, job: HookJob) {
+    // the worker only stops when the main loop is gone
+    let _ = self.jobs.send(job);
+  }
+}
+
+/// findings of the validators for the last decoded token. Tokens are decoded on every tick, so the
+/// validators only run again when the token or its verification changes
+#[derive(Debug, Default)]
+pub struct HookResults {
+  input: String,
+  findings: Vec<Finding>,
+  /// the input the worker is validating
+  pending: Option<String>,
+}
+
+impl HookResults {
+  /// the findings of the validators, run by the worker when there is one. The findings of a new
+  /// token are empty until the worker is done
+  pub fn findings(
+    &mut self,
+    hooks: &[ValidatorHook],
+    header: &Header,
+    claims: &Payload,
+    signature_verified: bool,
+    runner: Option<&HookRunner>,
+  ) -> Vec<Finding> {
+    let input = json!({
+      "header": header,
+      "payload": claims,
+      "signature_verified": signature_verified,
+    })
+    .to_string();
+    if input == self.input {
+      return self.findings.clone();
+    }
+    let Some(runner) = runner else {
+      self.findings = run_hooks(hooks, &input, HOOK_TIMEOUT);
+      self.input = input;
+      return self.findings.clone();
+    };
+    if self.pending.as_ref() != Some(&input) {
+      runner.run(HookJob {
+        hooks: hooks.to_vec(),
+        input: input.clone(),
+      });
+      self.pending = Some(input);
+    }
+    vec![]
+  }
+
+  /// whether the worker is running the validators
+  pub fn is_pending(&self) -> bool {
+    self.pending.is_some()
+  }
+
+  /// keep the findings of the pending run. False for the runs of replaced tokens
+  pub fn complete(&mut self, run: HookRun) -> bool {
+    if self.pending.as_ref() != Some(&run.input) {
+      return false;
+    }
+    self.pending = None;
+    self.input = run.input;
+    self.findings = run.findings;
+    true
+  }
+}
+
+fn run_hooks(hooks: &[ValidatorHook], input: &str, timeout: Duration) -> Vec<Finding> {
+  hooks
+    .iter()
+    .flat_map(|hook| hook.run(input, timeout))
+    .collect()
+}
+
+impl ValidatorHook {
+  /// the verdicts of the validator as findings. A validator that can't run, fails without
+  /// verdicts, times out or prints something else than verdicts is an error finding
+  fn run(&self, input: &str, timeout: Duration) -> Vec<Finding> {
+    let output = match self.output(input, timeout) {
+      Ok(output) => output,
+      Err(e) => return vec![self.finding(Severity::Error, e.to_string())],
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.trim().is_empty() {
+      return match output.status.success() {
+        true => vec![],
+        false => vec![self.finding(
+          Severity::Error,
+          format!(
+            "Validator failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+          ),
+        )],
+      };
+    }
+    match serde_json::from_str::<Vec<Verdict>>(&stdout) {
+      Ok(verdicts) => verdicts
+        .into_iter()
+        .map(|verdict| self.finding(verdict.severity, verdict.message))
+        .collect(),
+      Err(e) => vec![self.finding(
+        Severity::Error,
+        format!("Invalid verdicts printed by the validator: {e}"),
+      )],
+    }
+  }
+
+  fn output(&self, input: &str, timeout: Duration) -> JWTResult<Output> {
+    let error = |e| JWTError::Internal(format!("Unable to run `{}`: {e}", self.command));
+    let mut child = shell_command(&self.command)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()
+      .map_err(error)?;
+    // a validator that doesn't read the token would block a write larger than the pipe buffer
+    if let Some(mut stdin) = child.stdin.take() {
+      let input = input.to_string();
+      thread::spawn(move || {
+        // a validator may exit without reading the token
+        let _ = stdin.write_all(input.as_bytes());
+      });
+    }
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+      match child.try_wait().map_err(error)? {
+        Some(status) => break status,
+        None if Instant::now() >= deadline => {
+          let _ = child.kill();
+          let _ = child.wait();
+          return Err(JWTError::Internal(format!(
+            "`{}` timed out after {}s",
+            self.command,
+            timeout.as_secs_f32()
+          )));
+        }
+        None => thread::sleep(Duration::from_millis(10)),
+      }
+    };
+    // processes started by the validator may keep its output open
+    let read = |pipe: Receiver<Vec<u8>>| {
+      pipe
+        .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        .unwrap_or_default()
+    };
+    Ok(Output {
+      status,
+      stdout: read(stdout),
+      stderr: read(stderr),
+    })
+  }
+
+  fn finding(&self, severity: Severity, message: String) -> Finding {
+    Finding::new(severity, &self.name, message)
+  }
+}
+
+/// the content of an output pipe of a validator, read by another thread
+fn read_pipe(pipe: Option<impl Read + Send + 'static>) -> Receiver<Vec<u8>> {
+  let (sender, receiver) = mpsc::channel();
+  if let Some(mut pipe) = pipe {
+    thread::spawn(move || {
+      let mut content = vec![];
+      let _ = pipe.read_to_end(&mut content);
+      let _ = sender.send(content);
+    });
+  }
+  receiver
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod tests {
+  use super::*;
+
+  fn hook(name: &str, command: &str) -> ValidatorHook {
+    ValidatorHook {
+      name: name.into(),
+      command: command.into(),
+    }
+  }
+
+  #[test]
+  fn test_validator_hooks() {
+    let claims: Payload = serde_json::from_str(r#"{"sub": "alice"}"#).unwrap();
+    let hooks = [
+      hook(
+        "tenant",
+        r#"grep -q '"tenant"' || echo '[{"severity": "warning", "message": "No tenant claim"}]'"#,
+      ),
+      hook(
+        "verified",
+        r#"grep -q '"signature_verified":true' || echo '[{"message": "Unverified token"}]'"#,
+      ),
+      hook("passing", "cat > /dev/null"),
+      hook("failing", "echo 'policy server down' >&2; exit 2"),
+      hook("garbage", "echo ok"),
+    ];
+    let mut results = HookResults::default();
+    let findings = results.findings(&hooks, &Header::default(), &claims, false, None);
+    assert_eq!(
+      findings,
+      [
+        Finding::new(Severity::Warning, "tenant", "No tenant claim".into()),
+        Finding::new(Severity::Error, "verified", "Unverified token".into()),
+        Finding::new(
+          Severity::Error,
+          "failing",
+          "Validator failed (exit status: 2): policy server down".into()
+        ),
+        Finding::new(
+          Severity::Error,
+          "garbage",
+          "Invalid verdicts printed by the validator: expected value at line 1 column 1".into()
+        ),
+      ]
+    );
+
+    let findings = results.findings(&hooks[1..2], &Header::default(), &claims, true, None);
+    assert!(findings.is_empty());
+  }
+
+  #[test]
+  fn test_validator_hook_timeout() {
+    // the input is larger than the pipe buffer and never read
+    let input = "x".repeat(1 << 20);
+    let started = Instant::now();
+    let findings = hook("hanging", "sleep 5").run(&input, Duration::from_millis(200));
+    assert!(started.elapsed() < Duration::from_secs(4));
+    assert_eq!(
+      findings,
+      [Finding::new(
+        Severity::Error,
+        "hanging",
+        "`sleep 5` timed out after 0.2s".into()
+      )]
+    );
+    let findings = hook("unread", "echo '[]'").run(&input, HOOK_TIMEOUT);
+    assert!(findings.is_empty());
+  }
+
+  #[test]
+  fn test_validator_hooks_in_background() {
+    let claims: Payload = serde_json::from_str(r#"{"sub": "alice"}"#).unwrap();
+    let hooks = [hook("tenant", r#"echo '[{"message": "No tenant claim"}]'"#)];
+    let (sender, receiver) = mpsc::channel();
+    let runner = HookRunner::new(sender).unwrap();
+    let mut results = HookResults::default();
+
+    let findings = results.findings(&hooks, &Header::default(), &claims, false, Some(&runner));
+    assert!(findings.is_empty());
+    assert!(results.is_pending());
+    // the job is only sent once
+    results.findings(&hooks, &Header::default(), &claims, false, Some(&runner));
+
+    let Ok(Event::Validated(run)) = receiver.recv_timeout(Duration::from_secs(5)) else {
+      panic!("expected a validated event");
+    };
+    assert!(results.complete(*run));
+    assert!(!results.is_pending());
+    let findings = results.findings(&hooks, &Header::default(), &claims, false, Some(&runner));
+    assert_eq!(
+      findings,
+      [Finding::new(
+        Severity::Error,
+        "tenant",
+        "No tenant claim".into()
+      )]
+    );
+    assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+  }
+}
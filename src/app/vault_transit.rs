@@ -0,0 +1,323 @@
+//! Keys of the transit secrets engine of HashiCorp Vault, referenced as
+//! `vault:<mount>/keys/<name>` like `vault:transit/keys/jwt-signing`. Tokens are verified with the
+//! public keys of the key versions and signed by Vault, the private key never leaves it. Requests
+//! go to `VAULT_ADDR` with `VAULT_TOKEN` or the token of the Vault CLI
+
+use std::{collections::BTreeMap, env, fs};
+
+use base64::{
+  engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+  Engine,
+};
+use serde::de::DeserializeOwned;
+use serde_derive::Deserialize;
+use serde_json::{json, Value};
+
+use super::{
+  jwt_decoder::Payload,
+  keys::{jwks, parse_key},
+  network::{send, RequestBody},
+  utils::{JWTError, JWTResult},
+};
+
+/// prefix of the keys of the Vault transit engine
+pub const VAULT_PREFIX: &str = "vault:";
+/// address of Vault when `VAULT_ADDR` isn't set, like for the Vault CLI
+const DEFAULT_VAULT_ADDR: &str = "https://127.0.0.1:8200";
+/// file of the token of `vault login` in the home directory
+const TOKEN_FILE: &str = ".vault-token";
+
+/// a key of the transit engine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransitKey<'a> {
+  /// path of the transit engine, `transit` by default
+  pub mount: &'a str,
+  pub name: &'a str,
+}
+
+/// Vault server and the token authorizing the requests
+#[derive(Debug, Clone)]
+struct VaultClient {
+  addr: String,
+  token: String,
+  /// Vault Enterprise namespace of `VAULT_NAMESPACE`
+  namespace: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Response<T> {
+  data: T,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeyInfo {
+  /// key type like `ecdsa-p256`, `ed25519` or `rsa-2048`
+  #[serde(rename = "type")]
+  key_type: String,
+  /// public keys by version
+  keys: BTreeMap<String, KeyVersion>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeyVersion {
+  /// PEM public key, or the base64 encoded raw public key for `ed25519`
+  public_key: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Signature {
+  /// `vault:v<version>:` and the signature, base64url encoded with the JWS marshaling
+  signature: String,
+}
+
+/// the transit key of a `vault:` reference
+pub fn transit_key(secret: &str) -> Option<TransitKey<'_>> {
+  let path = secret.strip_prefix(VAULT_PREFIX)?.trim().trim_matches('/');
+  let (mount, name) = path.rsplit_once("/keys/")?;
+  (!mount.is_empty() && !name.is_empty() && !name.contains('/'))
+    .then_some(TransitKey { mount, name })
+}
+
+/// the public keys of the versions of the transit key as JWKS, with `<name>:v<version>` as `kid`
+pub fn public_jwks(key: &TransitKey<'_>) -> JWTResult<String> {
+  VaultClient::from_env()?.public_jwks(key)
+}
+
+/// sign the header and claims with the latest version of the transit key, the `alg` of the header
+/// has to fit the key
+pub fn encode_vault_transit(
+  header: &Value,
+  payload: &Payload,
+  key: &TransitKey<'_>,
+) -> JWTResult<String> {
+  let message = format!(
+    "{}.{}",
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(header)?),
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(payload)?)
+  );
+  let alg = header["alg"].as_str().unwrap_or_default();
+  let signature = VaultClient::from_env()?.sign(key, alg, message.as_bytes())?;
+  Ok(format!("{message}.{signature}"))
+}
+
+impl VaultClient {
+  /// the client of `VAULT_ADDR`, `VAULT_TOKEN` or else the token of `vault login`, and
+  /// `VAULT_NAMESPACE`
+  fn from_env() -> JWTResult<Self> {
+    let var = |name| env::var(name).ok().filter(|value| !value.is_empty());
+    let token = var("VAULT_TOKEN")
+      .or_else(|| {
+        let path = dirs::home_dir()?.join(TOKEN_FILE);
+        fs::read_to_string(path)
+          .ok()
+          .map(|token| token.trim().to_string())
+      })
+      .ok_or_else(|| {
+        JWTError::Internal("Set VAULT_TOKEN or run `vault login` to use Vault keys".into())
+      })?;
+    Ok(VaultClient {
+      addr: var("VAULT_ADDR").unwrap_or_else(|| DEFAULT_VAULT_ADDR.into()),
+      token,
+      namespace: var("VAULT_NAMESPACE"),
+    })
+  }
+
+  fn public_jwks(&self, key: &TransitKey<'_>) -> JWTResult<String> {
+    let info: KeyInfo = self.send(&format!("{}/keys/{}", key.mount, key.name), None)?;
+    let keys = info
+      .keys
+      .iter()
+      .filter_map(|(version, key_version)| Some((version, key_version.public_key.as_ref()?)))
+      .map(|(version, public_key)| {
+        let mut jwk = match info.key_type.as_str() {
+          "ed25519" => {
+            let raw = STANDARD.decode(public_key).map_err(|e| {
+              JWTError::Internal(format!("Invalid ed25519 public key from Vault: {e}"))
+            })?;
+            json!({ "kty": "OKP", "crv": "Ed25519", "x": URL_SAFE_NO_PAD.encode(raw) })
+          }
+          _ => parse_key(public_key.as_bytes())?,
+        };
+        jwk["kid"] = format!("{}:v{version}", key.name).into();
+        Ok(jwk)
+      })
+      .collect::<JWTResult<Vec<Value>>>()?;
+    if keys.is_empty() {
+      return Err(JWTError::Internal(format!(
+        "The {} Vault key {:?} has no public key",
+        info.key_type, key.name
+      )));
+    }
+    Ok(jwks(keys).to_string())
+  }
+
+  /// the base64url signature of the message by Vault, marshaled like in JWS
+  fn sign(&self, key: &TransitKey<'_>, alg: &str, message: &[u8]) -> JWTResult<String> {
+    let (hash_algorithm, signature_algorithm) = match alg {
+      "RS256" => (Some("sha2-256"), Some("pkcs1v15")),
+      "RS384" => (Some("sha2-384"), Some("pkcs1v15")),
+      "RS512" => (Some("sha2-512"), Some("pkcs1v15")),
+      "PS256" => (Some("sha2-256"), Some("pss")),
+      "PS384" => (Some("sha2-384"), Some("pss")),
+      "PS512" => (Some("sha2-512"), Some("pss")),
+      "ES256" => (Some("sha2-256"), None),
+      "ES384" => (Some("sha2-384"), None),
+      "ES512" => (Some("sha2-512"), None),
+      "EdDSA" => (None, None),
+      alg => {
+        return Err(JWTError::Internal(format!(
+          "Vault transit keys can't sign {alg:?} tokens"
+        )))
+      }
+    };
+    let mut body = json!({ "input": STANDARD.encode(message), "marshaling_algorithm": "jws" });
+    if let Some(hash_algorithm) = hash_algorithm {
+      body["hash_algorithm"] = hash_algorithm.into();
+    }
+    if let Some(signature_algorithm) = signature_algorithm {
+      body["signature_algorithm"] = signature_algorithm.into();
+    }
+    let signature: Signature =
+      self.send(&format!("{}/sign/{}", key.mount, key.name), Some(&body))?;
+    // the signature is prefixed with the key version
+    match signature.signature.rsplit_once(':') {
+      Some((_, signature)) => Ok(signature.to_string()),
+      None => Ok(signature.signature),
+    }
+  }
+
+  /// send a request to the Vault API, a POST of the body when there is one, and parse its data
+  fn send<T: DeserializeOwned>(&self, path: &str, body: Option<&Value>) -> JWTResult<T> {
+    let url = format!("{}/v1/{path}", self.addr.trim_end_matches('/'));
+    let mut headers = vec![("X-Vault-Token".to_string(), self.token.clone())];
+    if let Some(namespace) = &self.namespace {
+      headers.push(("X-Vault-Namespace".into(), namespace.clone()));
+    }
+    let body = body.map(Value::to_string);
+    let response = match &body {
+      Some(body) => {
+        headers.push(("Content-Type".into(), "application/json".into()));
+        send(&url, &headers, RequestBody::Text(body))?
+      }
+      None => send(&url, &headers, RequestBody::None)?,
+    };
+    serde_json::from_slice::<Response<T>>(&response)
+      .map(|response| response.data)
+      .map_err(|e| JWTError::Internal(format!("Invalid response from {url}: {e}")))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use super::*;
+  use crate::app::network::tests::{serve_in_order, serve_once_with_request};
+
+  fn client(addr: String) -> VaultClient {
+    VaultClient {
+      addr,
+      token: "hvs.token".into(),
+      namespace: Some("team-a".into()),
+    }
+  }
+
+  #[test]
+  fn test_transit_key() {
+    assert_eq!(
+      transit_key("vault:transit/keys/jwt-signing"),
+      Some(TransitKey {
+        mount: "transit",
+        name: "jwt-signing"
+      })
+    );
+    assert_eq!(
+      transit_key("vault:/teams/a/transit/keys/jwt/").map(|key| key.mount),
+      Some("teams/a/transit")
+    );
+    assert_eq!(transit_key("vault:transit/jwt-signing"), None);
+    assert_eq!(transit_key("vault:transit/keys/"), None);
+    assert_eq!(transit_key("keyring:staging"), None);
+  }
+
+  #[test]
+  fn test_public_jwks() {
+    let pem = fs::read_to_string("./test_data/test_rsa_public_key.pem").unwrap();
+    let body = json!({
+      "data": {
+        "type": "rsa-2048",
+        "latest_version": 2,
+        "keys": {
+          "1": { "public_key": pem, "creation_time": "2024-01-01T00:00:00Z" },
+          "2": { "public_key": pem, "creation_time": "2024-06-01T00:00:00Z" },
+        },
+      }
+    });
+    let (url, request) = serve_once_with_request("200 OK", body.to_string().into_bytes());
+    let key = transit_key("vault:transit/keys/jwt-signing").unwrap();
+    let jwks: Value = serde_json::from_str(&client(url).public_jwks(&key).unwrap()).unwrap();
+    assert_eq!(jwks["keys"][0]["kty"], "RSA");
+    assert_eq!(jwks["keys"][0]["kid"], "jwt-signing:v1");
+    assert_eq!(jwks["keys"][1]["kid"], "jwt-signing:v2");
+    let request = request.recv().unwrap().to_lowercase();
+    assert!(request.starts_with("get /v1/transit/keys/jwt-signing http/1.1"));
+    assert!(request.contains("x-vault-token: hvs.token"));
+    assert!(request.contains("x-vault-namespace: team-a"));
+  }
+
+  #[test]
+  fn test_public_jwks_ed25519() {
+    let body = json!({
+      "data": { "type": "ed25519", "keys": { "1": { "public_key": STANDARD.encode([7; 32]) } } }
+    });
+    let (url, _) = serve_once_with_request("200 OK", body.to_string().into_bytes());
+    let key = transit_key("vault:transit/keys/ed").unwrap();
+    let jwks: Value = serde_json::from_str(&client(url).public_jwks(&key).unwrap()).unwrap();
+    assert_eq!(
+      jwks["keys"][0],
+      json!({ "kty": "OKP", "crv": "Ed25519", "x": URL_SAFE_NO_PAD.encode([7; 32]), "kid": "ed:v1" })
+    );
+
+    // symmetric keys have no public key
+    let body = json!({ "data": { "type": "aes256-gcm96", "keys": { "1": 1704067200 } } });
+    let (url, _) = serve_once_with_request("200 OK", body.to_string().into_bytes());
+    assert!(client(url).public_jwks(&key).is_err());
+  }
+
+  #[test]
+  fn test_sign() {
+    let (url, requests) = serve_in_order(vec![
+      (
+        "200 OK",
+        br#"{"data": {"signature": "vault:v2:c2lnbmF0dXJl", "key_version": 2}}"#.to_vec(),
+      ),
+      (
+        "403 Forbidden",
+        br#"{"errors": ["1 error occurred:\n\t* permission denied\n\n"]}"#.to_vec(),
+      ),
+    ]);
+    let key = transit_key("vault:transit/keys/jwt-signing").unwrap();
+    let client = client(url);
+    assert_eq!(client.sign(&key, "PS256", b"a.b").unwrap(), "c2lnbmF0dXJl");
+    let request = requests.recv().unwrap();
+    assert!(request.starts_with("POST /v1/transit/sign/jwt-signing HTTP/1.1"));
+    let (_, body) = request.split_once("\r\n\r\n").unwrap();
+    assert_eq!(
+      serde_json::from_str::<Value>(body).unwrap(),
+      json!({
+        "input": STANDARD.encode(b"a.b"),
+        "marshaling_algorithm": "jws",
+        "hash_algorithm": "sha2-256",
+        "signature_algorithm": "pss",
+      })
+    );
+
+    let error = client.sign(&key, "RS256", b"a.b").unwrap_err();
+    assert!(error.to_string().contains("permission denied"));
+    assert!(client
+      .sign(&key, "HS256", b"a.b")
+      .unwrap_err()
+      .to_string()
+      .contains("can't sign \"HS256\" tokens"));
+  }
+}
@@ -0,0 +1,161 @@
+//! Signatures of asymmetric algorithms are verified by a worker thread, as parsing RSA and EC keys
+//! on every tick makes typing in the token field lag. Results come back to the main loop as
+//! [`Event::Verified`] events
+
+use std::{
+  sync::mpsc::{self, Sender},
+  thread,
+};
+
+use crossterm::event::{KeyEvent, MouseEvent};
+
+use super::{
+  jwt_decoder::{decode_before_verification, verify_jwt, DecodeArgs, VerifiedOutput},
+  utils::{JWTError, JWTResult},
+};
+use crate::event::Event;
+
+/// a token to verify with the key source or the resolved secrets of the decoder. Not `Debug` as
+/// it holds the secrets
+#[derive(Clone, PartialEq, Eq)]
+pub struct VerifyJob {
+  pub(super) args: DecodeArgs,
+  pub(super) secrets: Vec<JWTResult<String>>,
+}
+
+/// a job verified by the worker and its result
+pub struct Verification {
+  job: VerifyJob,
+  output: VerifiedOutput,
+}
+
+/// worker thread verifying the jobs in order, skipping the jobs replaced by newer ones
+pub struct Verifier {
+  jobs: Sender<VerifyJob>,
+}
+
+impl Verifier {
+  pub fn new(events: Sender<Event<KeyEvent, MouseEvent>>) -> JWTResult<Self> {
+    let (jobs, receiver) = mpsc::channel::<VerifyJob>();
+    thread::Builder::new()
+      .name("jwtui-verifier".into())
+      .spawn(move || {
+        while let Ok(mut job) = receiver.recv() {
+          // only the last token typed is worth verifying
+          while let Ok(newer) = receiver.try_recv() {
+            job = newer;
+          }
+          let output = verify_jwt(&job.args, &job.secrets);
+          let verification = Verification { job, output };
+          if events
+            .send(Event::Verified(Box::new(verification)))
+            .is_err()
+          {
+            break;
+          }
+        }
+      })
+      .map_err(|e| JWTError::Internal(format!("Unable to start the verifier: {e}")))?;
+    Ok(Verifier { jobs })
+  }
+
+  fn verify(&self, job: VerifyJob) {
+    // the worker only stops when the main loop is gone
+    let _ = self.jobs.send(job);
+  }
+}
+
+/// the last verification of the worker for a decoder and the job waiting for one
+#[derive(Default)]
+pub struct Verifications {
+  pending: Option<VerifyJob>,
+  done: Option<Verification>,
+}
+
+impl Verifications {
+  /// the result of the job when the worker verified it, or else the token decoded without
+  /// verification until the worker is done
+  pub fn output(&mut self, job: VerifyJob, verifier: &Verifier) -> VerifiedOutput {
+    if let Some(done) = self.done.as_ref().filter(|done| done.job == job) {
+      return done.output.clone();
+    }
+    let unverified = decode_before_verification(&job.args);
+    if self.pending.as_ref() != Some(&job) {
+      verifier.verify(job.clone());
+      self.pending = Some(job);
+    }
+    unverified
+  }
+
+  /// whether the worker is verifying a job
+  pub fn is_pending(&self) -> bool {
+    self.pending.is_some()
+  }
+
+  /// keep the verification of the pending job. False for the verifications of replaced jobs
+  pub fn complete(&mut self, verification: Verification) -> bool {
+    if self.pending.as_ref() != Some(&verification.job) {
+      return false;
+    }
+    self.pending = None;
+    self.done = Some(verification);
+    true
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+
+  use super::*;
+  use crate::app::{
+    jwt_decoder::{decode_jwt_token, Payload},
+    App,
+  };
+
+  #[test]
+  fn test_verify_in_background() {
+    let key =
+      EncodingKey::from_rsa_pem(&std::fs::read("./test_data/test_rsa_private_key.pem").unwrap())
+        .unwrap();
+    let claims: Payload = serde_json::from_str(r#"{"sub": "1234"}"#).unwrap();
+    let token = encode(&Header::new(Algorithm::RS256), &claims, &key).unwrap();
+    let (sender, receiver) = mpsc::channel();
+    let mut app = App::new(
+      Some(token),
+      "@./test_data/test_rsa_public_jwks_without_kid.json".into(),
+    );
+    app.verifier = Some(Verifier::new(sender).unwrap());
+
+    // the claims are shown while the signature is verified
+    decode_jwt_token(&mut app, false);
+    assert!(app.data.decoder.is_decoded());
+    assert!(!app.data.decoder.signature_verified);
+    assert_eq!(app.data.error, "Verifying the signature...");
+    assert!(app.is_loading());
+    // the job is only sent once
+    decode_jwt_token(&mut app, false);
+
+    let Ok(Event::Verified(verification)) = receiver.recv_timeout(Duration::from_secs(5)) else {
+      panic!("expected a verification event");
+    };
+    app.handle_verification(*verification);
+    assert!(!app.is_loading());
+    assert!(app.data.decoder.signature_verified);
+    assert_eq!(app.data.decoder.verified_jwk, Some("#3".into()));
+    assert_eq!(app.data.error, "");
+    assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+
+    // verifications of replaced jobs are dropped
+    let verifications = &mut app.data.decoder.verifications;
+    let args = verifications.done.as_ref().unwrap().job.args.clone();
+    let output = verify_jwt(&args, &[]);
+    let job = VerifyJob {
+      args,
+      secrets: vec![],
+    };
+    assert!(!verifications.complete(Verification { job, output }));
+  }
+}
@@ -0,0 +1,465 @@
+use std::{fs, path::Path};
+
+use base64::{
+  engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+  Engine,
+};
+use chrono::{TimeZone, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, Header};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use x509_parser::{
+  oid_registry::{
+    OID_EC_P256, OID_KEY_TYPE_EC_PUBLIC_KEY, OID_NIST_EC_P384, OID_PKCS1_RSAENCRYPTION,
+    OID_SIG_ED25519,
+  },
+  pem::Pem,
+  prelude::{parse_x509_certificate, X509Certificate},
+  x509::SubjectPublicKeyInfo,
+};
+
+use super::{
+  keys::parse_key,
+  network::fetch,
+  utils::{JWTError, JWTResult},
+};
+
+/// Summary of a certificate from the `x5c` header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateInfo {
+  pub subject: String,
+  pub issuer: String,
+  pub not_before: i64,
+  pub not_after: i64,
+  pub key_type: String,
+}
+
+impl CertificateInfo {
+  fn new(cert: &X509Certificate<'_>) -> Self {
+    CertificateInfo {
+      subject: cert.subject().to_string(),
+      issuer: cert.issuer().to_string(),
+      not_before: cert.validity().not_before.timestamp(),
+      not_after: cert.validity().not_after.timestamp(),
+      key_type: key_type(cert.public_key()),
+    }
+  }
+
+  pub fn is_valid_at(&self, now: i64) -> bool {
+    self.not_before <= now && now <= self.not_after
+  }
+}
+
+/// decode the base64 DER certificates of an `x5c` header
+pub fn decode_x5c(x5c: &[String]) -> JWTResult<Vec<Vec<u8>>> {
+  x5c
+    .iter()
+    .enumerate()
+    .map(|(i, cert)| {
+      STANDARD
+        .decode(cert)
+        .map_err(|e| JWTError::Internal(format!("Invalid base64 in x5c certificate {i}: {e}")))
+    })
+    .collect()
+}
+
+fn parse_certificate(der: &[u8]) -> JWTResult<X509Certificate<'_>> {
+  parse_x509_certificate(der)
+    .map(|(_, cert)| cert)
+    .map_err(|e| JWTError::Internal(format!("Invalid x5c certificate: {e}")))
+}
+
+/// parse the certificate chain from the `x5c` header, leaf certificate first
+pub fn parse_x5c(header: &Header) -> JWTResult<Vec<CertificateInfo>> {
+  let chain = header_x5c(header)?;
+  decode_x5c(chain)?
+    .iter()
+    .map(|der| parse_certificate(der).map(|cert| CertificateInfo::new(&cert)))
+    .collect()
+}
+
+/// render the certificate chain as text for the certificate popup
+pub fn describe_x5c(header: &Header, now: i64) -> JWTResult<String> {
+  let certs = parse_x5c(header)?;
+  let last = certs.len().saturating_sub(1);
+  let lines: Vec<String> = certs
+    .iter()
+    .enumerate()
+    .map(|(i, cert)| {
+      let position = match i {
+        0 => " (leaf)",
+        _ if i == last => " (root/intermediate)",
+        _ => " (intermediate)",
+      };
+      let validity = if cert.is_valid_at(now) {
+        "valid"
+      } else {
+        "not valid now"
+      };
+      format!(
+        "Certificate {}{}\n  Subject:    {}\n  Issuer:     {}\n  Not before: {}\n  Not after:  {} ({})\n  Key type:   {}",
+        i + 1,
+        position,
+        cert.subject,
+        cert.issuer,
+        format_time(cert.not_before),
+        format_time(cert.not_after),
+        validity,
+        cert.key_type,
+      )
+    })
+    .collect();
+  Ok(lines.join("\n\n"))
+}
+
+/// build a decoding key from the public key of the leaf certificate in the `x5c` header
+pub fn decoding_key_from_x5c(alg: &Algorithm, header: Option<&Header>) -> JWTResult<DecodingKey> {
+  let header =
+    header.ok_or_else(|| JWTError::Internal("Invalid jwt header for x5c verification".into()))?;
+  let chain = decode_x5c(header_x5c(header)?)?;
+  decoding_key_from_certificate(alg, &chain[0], "x5c leaf")
+}
+
+/// build a decoding key from the public key of a DER encoded certificate
+pub fn decoding_key_from_certificate(
+  alg: &Algorithm,
+  der: &[u8],
+  name: &str,
+) -> JWTResult<DecodingKey> {
+  let cert = parse_certificate(der)?;
+  let spki = cert.public_key();
+  // the subject public key bits are in the raw format expected for DER keys
+  let key = spki.subject_public_key.data.as_ref();
+  let oid = &spki.algorithm.algorithm;
+
+  match alg {
+    Algorithm::RS256
+    | Algorithm::RS384
+    | Algorithm::RS512
+    | Algorithm::PS256
+    | Algorithm::PS384
+    | Algorithm::PS512
+      if *oid == OID_PKCS1_RSAENCRYPTION =>
+    {
+      Ok(DecodingKey::from_rsa_der(key))
+    }
+    Algorithm::ES256 | Algorithm::ES384 if *oid == OID_KEY_TYPE_EC_PUBLIC_KEY => {
+      Ok(DecodingKey::from_ec_der(key))
+    }
+    Algorithm::EdDSA if *oid == OID_SIG_ED25519 => Ok(DecodingKey::from_ed_der(key)),
+    _ => Err(JWTError::Internal(format!(
+      "The {name} certificate key ({}) can't be used for {alg:?}",
+      key_type(spki)
+    ))),
+  }
+}
+
+/// read certificates from PEM (one or more `CERTIFICATE` blocks) or a single DER certificate
+pub fn parse_certificates(data: &[u8]) -> JWTResult<Vec<Vec<u8>>> {
+  let certs: Vec<Vec<u8>> = Pem::iter_from_buffer(data)
+    .filter_map(|pem| pem.ok())
+    .filter(|pem| pem.label == "CERTIFICATE")
+    .map(|pem| pem.contents)
+    .collect();
+
+  if !certs.is_empty() {
+    return Ok(certs);
+  }
+  parse_certificate(data)?;
+  Ok(vec![data.to_vec()])
+}
+
+/// the `x5c` chain (base64 DER, leaf certificate first) and `x5t#S256` thumbprint (base64url
+/// SHA-256 of the leaf certificate) headers of a certificate chain
+pub fn certificate_headers(chain: &[Vec<u8>]) -> JWTResult<(Vec<String>, String)> {
+  let leaf = chain
+    .first()
+    .ok_or_else(|| JWTError::Internal("No certificate found".into()))?;
+  for cert in chain {
+    parse_certificate(cert)?;
+  }
+  let x5c = chain.iter().map(|cert| STANDARD.encode(cert)).collect();
+  Ok((x5c, URL_SAFE_NO_PAD.encode(Sha256::digest(leaf))))
+}
+
+/// the public key of the certificate as JWK
+pub fn certificate_jwk(der: &[u8]) -> JWTResult<Value> {
+  parse_key(parse_certificate(der)?.public_key().raw)
+}
+
+/// fetch the certificate chain referenced by an `x5u` header URL, leaf certificate first
+pub fn fetch_x5u(url: &str) -> JWTResult<Vec<Vec<u8>>> {
+  if !url.starts_with("https://") && !url.starts_with("http://") {
+    return Err(JWTError::Internal(format!("Unsupported x5u URL {url:?}")));
+  }
+  parse_certificates(&fetch(url)?)
+}
+
+/// load trusted root certificates from a PEM/DER CA bundle, or from the system root store
+pub fn load_trust_roots(ca_bundle: Option<&Path>) -> JWTResult<Vec<Vec<u8>>> {
+  match ca_bundle {
+    Some(path) => {
+      let data = fs::read(path).map_err(|e| {
+        JWTError::Internal(format!("Unable to read CA bundle {}: {e}", path.display()))
+      })?;
+      parse_certificates(&data)
+    }
+    None => {
+      let result = rustls_native_certs::load_native_certs();
+      if result.certs.is_empty() {
+        return Err(JWTError::Internal(format!(
+          "Unable to load the system root certificates: {:?}",
+          result.errors
+        )));
+      }
+      Ok(result.certs.iter().map(|cert| cert.to_vec()).collect())
+    }
+  }
+}
+
+/// validate the `x5c` certificate chain against the trusted roots.
+/// Returns the subject of the trust anchor the chain terminates in.
+pub fn validate_x5c_chain(header: &Header, roots: &[Vec<u8>], now: i64) -> JWTResult<String> {
+  let ders = decode_x5c(header_x5c(header)?)?;
+  let chain = ders
+    .iter()
+    .map(|der| parse_certificate(der))
+    .collect::<JWTResult<Vec<_>>>()?;
+
+  for (i, cert) in chain.iter().enumerate() {
+    if !CertificateInfo::new(cert).is_valid_at(now) {
+      return Err(JWTError::Internal(format!(
+        "Certificate {} ({}) is expired or not yet valid",
+        i + 1,
+        cert.subject()
+      )));
+    }
+    if let Some(issuer) = chain.get(i + 1) {
+      verify_issued_by(cert, issuer)
+        .map_err(|e| JWTError::Internal(format!("Certificate {}: {e}", i + 1)))?;
+    }
+  }
+
+  let last = chain.last().unwrap();
+  let last_der = ders.last().unwrap();
+
+  // the chain either ends with a trusted root or is issued by one
+  roots
+    .iter()
+    .filter_map(|der| parse_certificate(der).ok().map(|root| (der, root)))
+    .find(|(der, root)| *der == last_der || verify_issued_by(last, root).is_ok())
+    .map(|(_, root)| root.subject().to_string())
+    .ok_or_else(|| {
+      JWTError::Internal(format!(
+        "The x5c chain is not issued by a trusted root (issuer: {})",
+        last.issuer()
+      ))
+    })
+}
+
+/// check that `cert` names `issuer` as its issuer and is signed by its key
+fn verify_issued_by(
+  cert: &X509Certificate<'_>,
+  issuer: &X509Certificate<'_>,
+) -> Result<(), String> {
+  if cert.issuer() != issuer.subject() {
+    return Err(format!(
+      "issuer {} does not match the next certificate {}",
+      cert.issuer(),
+      issuer.subject()
+    ));
+  }
+  cert
+    .verify_signature(Some(issuer.public_key()))
+    .map_err(|e| format!("signature can't be verified with the issuer key: {e}"))
+}
+
+fn header_x5c(header: &Header) -> JWTResult<&Vec<String>> {
+  match header.x5c.as_ref() {
+    Some(chain) if !chain.is_empty() => Ok(chain),
+    _ => Err(JWTError::Internal(
+      "The JWT header has no `x5c` certificate chain".into(),
+    )),
+  }
+}
+
+fn key_type(spki: &SubjectPublicKeyInfo<'_>) -> String {
+  let oid = &spki.algorithm.algorithm;
+  if *oid == OID_PKCS1_RSAENCRYPTION {
+    match spki.parsed() {
+      Ok(key) => format!("RSA {} bits", key.key_size()),
+      Err(_) => "RSA".into(),
+    }
+  } else if *oid == OID_KEY_TYPE_EC_PUBLIC_KEY {
+    let curve = spki
+      .algorithm
+      .parameters
+      .as_ref()
+      .and_then(|p| p.as_oid().ok());
+    match curve {
+      Some(c) if c == OID_EC_P256 => "EC P-256".into(),
+      Some(c) if c == OID_NIST_EC_P384 => "EC P-384".into(),
+      Some(c) => format!("EC {c}"),
+      None => "EC".into(),
+    }
+  } else if *oid == OID_SIG_ED25519 {
+    "Ed25519".into()
+  } else {
+    format!("Unknown ({oid})")
+  }
+}
+
+fn format_time(ts: i64) -> String {
+  Utc
+    .timestamp_opt(ts, 0)
+    .single()
+    .map_or_else(|| ts.to_string(), |t| t.to_rfc3339())
+}
+
+#[cfg(test)]
+mod tests {
+  use jsonwebtoken::decode_header;
+
+  use super::*;
+
+  fn x5c_header() -> Header {
+    let token = std::fs::read_to_string("./test_data/test_x5c_token.txt").unwrap();
+    decode_header(token.trim()).unwrap()
+  }
+
+  #[test]
+  fn test_parse_x5c() {
+    let certs = parse_x5c(&x5c_header()).unwrap();
+
+    assert_eq!(certs.len(), 2);
+    assert_eq!(certs[0].subject, "CN=jwt-ui test signer, O=jwt-ui");
+    assert_eq!(certs[0].issuer, "CN=JWT UI Test CA, O=jwt-ui");
+    assert_eq!(certs[0].key_type, "RSA 2048 bits");
+    assert_eq!(certs[1].subject, "CN=JWT UI Test CA, O=jwt-ui");
+    assert!(certs[0].is_valid_at(certs[0].not_before));
+    assert!(!certs[0].is_valid_at(certs[0].not_after + 1));
+  }
+
+  #[test]
+  fn test_describe_x5c() {
+    let header = x5c_header();
+    let certs = parse_x5c(&header).unwrap();
+    let text = describe_x5c(&header, certs[0].not_before).unwrap();
+
+    assert!(text.starts_with("Certificate 1 (leaf)\n  Subject:    CN=jwt-ui test signer"));
+    assert!(text.contains("Certificate 2 (root/intermediate)"));
+    assert!(text.contains("(valid)"));
+  }
+
+  #[test]
+  fn test_parse_certificates() {
+    let pem = std::fs::read("./test_data/test_rsa_cert.pem").unwrap();
+    let mut chain = pem.clone();
+    chain.extend(std::fs::read("./test_data/test_ca_cert.pem").unwrap());
+
+    let certs = parse_certificates(&pem).unwrap();
+    assert_eq!(certs.len(), 1);
+    // DER input
+    assert_eq!(parse_certificates(&certs[0]).unwrap(), certs);
+    assert_eq!(parse_certificates(&chain).unwrap().len(), 2);
+    assert!(parse_certificates(b"not a certificate").is_err());
+  }
+
+  #[test]
+  fn test_certificate_headers() {
+    let mut pem = std::fs::read("./test_data/test_rsa_cert.pem").unwrap();
+    pem.extend(std::fs::read("./test_data/test_ca_cert.pem").unwrap());
+    let chain = parse_certificates(&pem).unwrap();
+
+    let (x5c, x5t) = certificate_headers(&chain).unwrap();
+    assert_eq!(decode_x5c(&x5c).unwrap(), chain);
+    assert_eq!(x5t, "ZzrdHhw2y0j4Bs4kpXSMQgxw_Xhjb75-FipFO4FCpXI");
+    assert!(certificate_headers(&[]).is_err());
+
+    let jwk = certificate_jwk(&chain[0]).unwrap();
+    let key = parse_key(&std::fs::read("./test_data/test_rsa_private_key.pem").unwrap()).unwrap();
+    assert_eq!(jwk["n"], key["n"]);
+  }
+
+  #[test]
+  fn test_fetch_x5u() {
+    let pem = std::fs::read("./test_data/test_rsa_cert.pem").unwrap();
+    let url = crate::app::network::tests::serve_once("200 OK", pem);
+
+    let chain = fetch_x5u(&url).unwrap();
+    assert_eq!(chain.len(), 1);
+    assert!(decoding_key_from_certificate(&Algorithm::RS256, &chain[0], "x5u").is_ok());
+    assert_eq!(
+      fetch_x5u("file:///etc/passwd").err().unwrap().to_string(),
+      "Unsupported x5u URL \"file:///etc/passwd\""
+    );
+  }
+
+  #[test]
+  fn test_validate_x5c_chain() {
+    let header = x5c_header();
+    let certs = parse_x5c(&header).unwrap();
+    let now = certs[0].not_before;
+    let roots = load_trust_roots(Some(Path::new("./test_data/test_ca_cert.pem"))).unwrap();
+
+    assert_eq!(
+      validate_x5c_chain(&header, &roots, now).unwrap(),
+      "CN=JWT UI Test CA, O=jwt-ui"
+    );
+
+    // chain without the root, issued by a trusted root
+    let mut leaf_only = header.clone();
+    leaf_only.x5c = Some(vec![header.x5c.as_ref().unwrap()[0].clone()]);
+    assert!(validate_x5c_chain(&leaf_only, &roots, now).is_ok());
+
+    assert_eq!(
+      validate_x5c_chain(&header, &[], now)
+        .unwrap_err()
+        .to_string(),
+      "The x5c chain is not issued by a trusted root (issuer: CN=JWT UI Test CA, O=jwt-ui)"
+    );
+    assert_eq!(
+      validate_x5c_chain(&header, &roots, certs[0].not_after + 1)
+        .unwrap_err()
+        .to_string(),
+      "Certificate 1 (CN=jwt-ui test signer, O=jwt-ui) is expired or not yet valid"
+    );
+
+    // chain in the wrong order
+    let mut reversed = header.clone();
+    reversed.x5c.as_mut().unwrap().reverse();
+    assert!(validate_x5c_chain(&reversed, &roots, now)
+      .unwrap_err()
+      .to_string()
+      .starts_with("Certificate 1: issuer CN=JWT UI Test CA, O=jwt-ui does not match"));
+  }
+
+  #[test]
+  fn test_load_trust_roots_missing_bundle() {
+    assert!(load_trust_roots(Some(Path::new("nonexistent.pem")))
+      .unwrap_err()
+      .to_string()
+      .starts_with("Unable to read CA bundle nonexistent.pem"));
+  }
+
+  #[test]
+  fn test_decoding_key_from_x5c() {
+    let header = x5c_header();
+
+    assert!(decoding_key_from_x5c(&Algorithm::RS256, Some(&header)).is_ok());
+    assert_eq!(
+      decoding_key_from_x5c(&Algorithm::ES256, Some(&header))
+        .err()
+        .unwrap()
+        .to_string(),
+      "The x5c leaf certificate key (RSA 2048 bits) can't be used for ES256"
+    );
+    assert_eq!(
+      decoding_key_from_x5c(&Algorithm::RS256, Some(&Header::default()))
+        .err()
+        .unwrap()
+        .to_string(),
+      "The JWT header has no `x5c` certificate chain"
+    );
+  }
+}
@@ -0,0 +1,113 @@
+//! Detection of the terminal background color, to start with the dark or light theme
+
+use std::env;
+
+/// how long the terminal gets to answer the background color query
+#[cfg(unix)]
+const QUERY_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// whether the background of the terminal is light, from its answer to the OSC 11 query or else
+/// the `COLORFGBG` variable. None when unknown. Raw mode must be enabled so the answer isn't echoed
+pub fn detect_light_background() -> Option<bool> {
+  query_background()
+    .and_then(|answer| parse_osc11_answer(&answer))
+    .or_else(|| parse_colorfgbg(&env::var("COLORFGBG").ok()?))
+}
+
+/// ask the terminal for its background color, terminals without OSC 11 support don't answer
+#[cfg(unix)]
+fn query_background() -> Option<String> {
+  use std::{
+    fs::OpenOptions,
+    io::{Read, Write},
+    time::Instant,
+  };
+
+  use rustix::event::{poll, PollFd, PollFlags};
+
+  // the terminal rather than STDIN, which may be a pipe
+  let mut tty = OpenOptions::new()
+    .read(true)
+    .write(true)
+    .open("/dev/tty")
+    .ok()?;
+  tty.write_all(b"\x1b]11;?\x1b\\").ok()?;
+  tty.flush().ok()?;
+
+  let deadline = Instant::now() + QUERY_TIMEOUT;
+  let mut answer = vec![];
+  let mut buf = [0; 64];
+  while !(answer.ends_with(b"\x07") || answer.ends_with(b"\x1b\\")) {
+    let timeout = deadline
+      .saturating_duration_since(Instant::now())
+      .as_millis() as i32;
+    let mut fds = [PollFd::new(&tty, PollFlags::IN)];
+    if timeout == 0 || poll(&mut fds, timeout).ok()? == 0 {
+      break;
+    }
+    let read = tty.read(&mut buf).ok()?;
+    if read == 0 {
+      break;
+    }
+    answer.extend_from_slice(&buf[..read]);
+  }
+  String::from_utf8(answer).ok()
+}
+
+#[cfg(not(unix))]
+fn query_background() -> Option<String> {
+  None
+}
+
+/// light when the relative luminance of the `rgb:RRRR/GGGG/BBBB` color of the answer is above half
+fn parse_osc11_answer(answer: &str) -> Option<bool> {
+  let rgb = &answer[answer.find("rgb:")? + 4..];
+  let rgb = rgb.trim_end_matches(['\x07', '\x1b', '\\']);
+  let channels = rgb
+    .split('/')
+    .map(|hex| {
+      let max = 16f64.powi(hex.len() as i32) - 1.0;
+      u32::from_str_radix(hex, 16).ok().map(|v| v as f64 / max)
+    })
+    .collect::<Option<Vec<f64>>>()?;
+  let [r, g, b] = channels[..] else {
+    return None;
+  };
+  Some(0.2126 * r + 0.7152 * g + 0.0722 * b > 0.5)
+}
+
+/// `COLORFGBG` is `<foreground>;<background>` with ANSI color numbers, 7 and 9-15 are light
+fn parse_colorfgbg(value: &str) -> Option<bool> {
+  let background: u8 = value.rsplit(';').next()?.parse().ok()?;
+  Some(background == 7 || background >= 9)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_osc11_answer() {
+    assert_eq!(
+      parse_osc11_answer("\x1b]11;rgb:ffff/ffff/ffff\x1b\\"),
+      Some(true)
+    );
+    assert_eq!(
+      parse_osc11_answer("\x1b]11;rgb:2323/3232/3737\x07"),
+      Some(false)
+    );
+    // solarized light with 2 digits per channel
+    assert_eq!(parse_osc11_answer("\x1b]11;rgb:fd/f6/e3\x07"), Some(true));
+    assert_eq!(parse_osc11_answer(""), None);
+    assert_eq!(parse_osc11_answer("\x1b]11;rgb:zz/00/00\x07"), None);
+    assert_eq!(parse_osc11_answer("\x1b]11;rgb:00/00\x07"), None);
+  }
+
+  #[test]
+  fn test_parse_colorfgbg() {
+    assert_eq!(parse_colorfgbg("15;0"), Some(false));
+    assert_eq!(parse_colorfgbg("0;15"), Some(true));
+    assert_eq!(parse_colorfgbg("0;default;7"), Some(true));
+    assert_eq!(parse_colorfgbg("15;default"), None);
+  }
+}
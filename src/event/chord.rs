@@ -0,0 +1,39 @@
+use std::time::{Duration, Instant};
+
+use super::Key;
+
+/// first key of a two-key chord, waiting for the second key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingKey {
+  pub key: Key,
+  pressed_at: Instant,
+}
+
+impl PendingKey {
+  /// how long the first key of a chord waits for the second key before it is handled on its own
+  pub const TIMEOUT: Duration = Duration::from_secs(1);
+
+  pub fn new(key: Key) -> Self {
+    Self {
+      key,
+      pressed_at: Instant::now(),
+    }
+  }
+
+  /// whether the second key didn't come in time
+  pub fn is_expired(&self, now: Instant) -> bool {
+    now.saturating_duration_since(self.pressed_at) >= Self::TIMEOUT
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_pending_key_expires() {
+    let pending = PendingKey::new(Key::Char('g'));
+    assert!(!pending.is_expired(Instant::now()));
+    assert!(pending.is_expired(Instant::now() + PendingKey::TIMEOUT));
+  }
+}
@@ -1,6 +1,10 @@
 //  adapted from tui-rs/examples/crossterm_demo.rs
 use std::{
-  sync::mpsc,
+  io,
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    mpsc, Arc,
+  },
   thread,
   time::{Duration, Instant},
 };
@@ -8,6 +12,7 @@ use std::{
 use crossterm::event::{self, Event as CEvent, KeyEvent, MouseEvent};
 
 use super::Key;
+use crate::app::{dispatch::NetworkResponse, validator_hooks::HookRun, verifier::Verification};
 
 #[derive(Debug, Clone, Copy)]
 /// Configuration for event handling.
@@ -32,8 +37,20 @@ pub enum Event<I, J> {
   /// An input event occurred.
   Input(I),
   MouseInput(J),
+  /// The terminal was resized to the width and height
+  Resize(u16, u16),
+  /// Text pasted in the terminal with bracketed paste, delivered at once instead of key by key
+  Paste(String),
   /// An tick event occurred.
   Tick,
+  /// A network request sent in the background completed
+  Network(Box<NetworkResponse>),
+  /// A signature verified in the background
+  Verified(Box<Verification>),
+  /// The validator hooks run in the background for a token
+  Validated(Box<HookRun>),
+  /// The process was asked to terminate by a signal, the terminal must be restored before exiting
+  Terminate,
 }
 
 /// A small event handler that wrap crossterm input and tick event. Each event
@@ -41,7 +58,9 @@ pub enum Event<I, J> {
 pub struct Events {
   rx: mpsc::Receiver<Event<KeyEvent, MouseEvent>>,
   // Need to be kept around to prevent disposing the sender side.
-  _tx: mpsc::Sender<Event<KeyEvent, MouseEvent>>,
+  tx: mpsc::Sender<Event<KeyEvent, MouseEvent>>,
+  /// milliseconds between ticks, read by the event thread on every loop
+  tick_rate: Arc<AtomicU64>,
 }
 
 impl Events {
@@ -57,34 +76,84 @@ impl Events {
   pub fn with_config(config: EventConfig) -> Events {
     let (tx, rx) = mpsc::channel();
 
-    let tick_rate = config.tick_rate;
+    let tick_rate = Arc::new(AtomicU64::new(config.tick_rate.as_millis() as u64));
 
     let event_tx = tx.clone();
+    let thread_tick_rate = tick_rate.clone();
     thread::spawn(move || {
       let mut last_tick = Instant::now();
+      // terminals without focus reporting never send focus events, so they always tick
+      let mut focused = true;
       loop {
-        let timeout = tick_rate
-          .checked_sub(last_tick.elapsed())
-          .unwrap_or_else(|| Duration::from_secs(0));
-        // poll for tick rate duration, if no event, sent tick event.
-        if event::poll(timeout).unwrap() {
+        let tick_rate = Duration::from_millis(thread_tick_rate.load(Ordering::Relaxed));
+        // poll for tick rate duration, if no event, sent tick event. Without the focus, wait for
+        // the next event instead, as there is nobody to show the ticks to
+        let ready = !focused || {
+          let timeout = tick_rate
+            .checked_sub(last_tick.elapsed())
+            .unwrap_or_else(|| Duration::from_secs(0));
+          event::poll(timeout).unwrap()
+        };
+        if ready {
           let e = event::read().unwrap();
           match e {
             CEvent::Key(key_event) => handle_key_event(&event_tx, key_event),
             CEvent::Mouse(mouse_event) => {
               event_tx.send(Event::MouseInput(mouse_event)).unwrap();
             }
-            _ => {}
+            CEvent::Paste(text) => event_tx.send(Event::Paste(text)).unwrap(),
+            CEvent::Resize(width, height) => event_tx.send(Event::Resize(width, height)).unwrap(),
+            // the tick is late when the focus comes back, so it is sent right away
+            CEvent::FocusGained => focused = true,
+            CEvent::FocusLost => focused = false,
           }
         }
-        if last_tick.elapsed() >= tick_rate {
+        if focused && last_tick.elapsed() >= tick_rate {
           event_tx.send(Event::Tick).unwrap();
           last_tick = Instant::now();
         }
       }
     });
 
-    Events { rx, _tx: tx }
+    Events { rx, tx, tick_rate }
+  }
+
+  /// Change the milliseconds between ticks, from the next tick.
+  pub fn set_tick_rate(&self, tick_rate: u64) {
+    self.tick_rate.store(tick_rate, Ordering::Relaxed);
+  }
+
+  /// Sender of events from other threads, like the responses of network requests.
+  pub fn sender(&self) -> mpsc::Sender<Event<KeyEvent, MouseEvent>> {
+    self.tx.clone()
+  }
+
+  /// Turn SIGTERM, SIGHUP and SIGINT into [`Event::Terminate`] events, so killing the app from
+  /// another terminal doesn't leave the shell in raw mode and the alternate screen.
+  #[cfg(unix)]
+  pub fn forward_signals(&self) -> io::Result<()> {
+    use signal_hook::{
+      consts::{SIGHUP, SIGINT, SIGTERM},
+      iterator::Signals,
+    };
+
+    let mut signals = Signals::new([SIGTERM, SIGHUP, SIGINT])?;
+    let event_tx = self.tx.clone();
+    thread::spawn(move || {
+      for _ in signals.forever() {
+        // the main loop is gone when the app already quits
+        if event_tx.send(Event::Terminate).is_err() {
+          break;
+        }
+      }
+    });
+    Ok(())
+  }
+
+  /// No signals to forward outside of unix.
+  #[cfg(not(unix))]
+  pub fn forward_signals(&self) -> io::Result<()> {
+    Ok(())
   }
 
   /// Attempts to read an event.
@@ -128,6 +128,16 @@ impl From<event::KeyEvent> for Key {
         modifiers: event::KeyModifiers::CONTROL,
         ..
       } => Key::CtrlK(KeyCode::Delete),
+      event::KeyEvent {
+        code: KeyCode::Up,
+        modifiers: event::KeyModifiers::CONTROL,
+        ..
+      } => Key::CtrlK(KeyCode::Up),
+      event::KeyEvent {
+        code: KeyCode::Down,
+        modifiers: event::KeyModifiers::CONTROL,
+        ..
+      } => Key::CtrlK(KeyCode::Down),
       event::KeyEvent {
         code: KeyCode::Esc, ..
       } => Key::Esc,
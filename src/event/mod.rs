@@ -1,7 +1,9 @@
+mod chord;
 mod events;
 mod key;
 
 pub use self::{
+  chord::PendingKey,
   events::{Event, Events},
   key::Key,
 };
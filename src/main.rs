@@ -1,43 +1,137 @@
 #![warn(rust_2018_idioms)]
 mod app;
+mod background;
 mod banner;
 mod event;
 mod handlers;
+mod screen_reader;
 mod ui;
 
 use std::{
+  collections::BTreeMap,
+  env,
   error::Error,
-  io::{self, stdout, Stdout, Write},
+  fs,
+  io::{self, stdout, Read, Stdout, Write},
   panic::{self, PanicHookInfo},
+  path::PathBuf,
+  process,
+  str::FromStr,
+  sync::Mutex,
+  time::Instant,
 };
 
-use app::{jwt_decoder::print_decoded_token, App};
+use app::{
+  audit::{audit_token, print_audit_report},
+  bookmarks::Bookmarks,
+  config::{
+    default_bookmarks_path, default_history_path, default_session_path, default_workspace_dir,
+    Config, Profile,
+  },
+  dispatch::Dispatcher,
+  history::{HistorySettings, TokenHistory},
+  jwt_decoder::print_decoded_token,
+  jwt_encoder::{encode_with_options, parse_key_value, read_payload, EncodeOptions, ManagedClaims},
+  keys::{
+    build_jwks, convert_key, generate_key_pair, jwks, write_key_file, KeyEncoding, KeyFormat,
+    KidSource, DEFAULT_RSA_BITS,
+  },
+  models::StatefulTable,
+  network::{set_offline, set_proxy, set_tls, TlsOptions},
+  oauth::{open_browser, Grant, OAuthClient, OAuthSession, SIGN_IN_TIMEOUT},
+  secrets::{secret_from_env, EnvSecret, SecretStore, COMMAND_PREFIX},
+  session::Session,
+  templates::{all_templates, Template},
+  theme::Theme,
+  token_url::{parse_header, TokenRequest},
+  utils::{run_command, JWTError, JWTResult},
+  validation::{has_errors, Preset},
+  validator_hooks::HookRunner,
+  verifier::Verifier,
+  workspace::{workspace_path, Workspace},
+  App, RouteId, DEFAULT_TICK_RATE,
+};
 use banner::BANNER;
-use clap::Parser;
+use chrono::Utc;
+use clap::{ArgGroup, Parser, Subcommand};
 use crossterm::{
-  event::DisableMouseCapture,
+  event::{
+    DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+    EnableFocusChange,
+  },
   execute,
   terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use event::Key;
-use ratatui::{
-  backend::{Backend, CrosstermBackend},
-  Terminal,
-};
+use jsonwebtoken::Algorithm;
+use ratatui::{backend::CrosstermBackend, Terminal};
+use serde_json::Value;
 
 use crate::app::jwt_decoder::decode_jwt_token;
 
 /// JWT UI
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None, before_help = BANNER)]
+#[command(group = ArgGroup::new("oauth").multiple(true).args(["grant", "oauth_client", "introspection_endpoint"]))]
 pub struct Cli {
+  #[command(subcommand)]
+  pub command: Option<Command>,
   /// JWT token to decode [mandatory for stdout mode, optional for TUI mode].
   #[clap(index = 1)]
   #[clap(value_parser)]
   pub token: Option<String>,
-  /// Secret for validating the JWT. Can be text, file path (beginning with @) or base64 encoded string (beginning with b64:).
-  #[arg(short = 'S', long, value_parser, default_value = "")]
-  pub secret: String,
+  /// More tokens, listed with the first one to pick the token to decode [TUI mode only].
+  #[clap(index = 2, conflicts_with_all = ["stdout", "json", "screen_reader"])]
+  #[clap(value_parser)]
+  pub more_tokens: Vec<String>,
+  /// URL of an endpoint returning the token to decode, e.g. a test token endpoint. The token is the `access_token`, `id_token`, `token` or `jwt` field of a JSON response, the `--token-field` or the whole response.
+  #[arg(long, value_parser, conflicts_with = "token")]
+  pub token_url: Option<String>,
+  /// Header of the `--token-url` request, as `Name: Value`. Repeat the flag for several headers.
+  #[arg(long, value_parser = parse_header, requires = "token_url")]
+  pub token_header: Vec<(String, String)>,
+  /// Body of the `--token-url` request, sent with a POST instead of a GET.
+  #[arg(long, value_parser, requires = "token_url")]
+  pub token_body: Option<String>,
+  /// Dot separated path of the token in the JSON response of `--token-url` (e.g. `data.token`).
+  #[arg(long, value_parser, requires = "token_url")]
+  pub token_field: Option<String>,
+  /// Request a token with this OAuth grant and decode its access token. `R` requests a new token in the decoder.
+  #[arg(long, value_enum, conflicts_with_all = ["token", "token_url"])]
+  pub grant: Option<Grant>,
+  /// Name of the OAuth client of the config used by `--grant` and `I`, the flags below override its settings.
+  #[arg(long, value_parser)]
+  pub oauth_client: Option<String>,
+  /// Token endpoint URL of the OAuth client.
+  #[arg(long, value_parser, requires = "oauth")]
+  pub token_endpoint: Option<String>,
+  /// Client id of the OAuth client.
+  #[arg(long, value_parser, requires = "oauth")]
+  pub client_id: Option<String>,
+  /// Client secret of the OAuth client: text or OS keyring entry (beginning with keyring:) or command printing it (beginning with cmd:).
+  #[arg(long, value_parser, requires = "oauth")]
+  pub client_secret: Option<String>,
+  /// Space separated scopes requested by `--grant`.
+  #[arg(long, value_parser, requires = "oauth")]
+  pub scope: Option<String>,
+  /// Device authorization endpoint URL of the OAuth client, for `--grant device-code`.
+  #[arg(long, value_parser, requires = "oauth")]
+  pub device_endpoint: Option<String>,
+  /// Authorization endpoint URL of the OAuth client, for `--grant authorization-code`.
+  #[arg(long, value_parser, requires = "oauth")]
+  pub authorization_endpoint: Option<String>,
+  /// Local redirect URI the sign in comes back to, for `--grant authorization-code`. Defaults to http://localhost:8400/callback.
+  #[arg(long, value_parser, requires = "oauth")]
+  pub redirect_uri: Option<String>,
+  /// Token introspection endpoint URL of the OAuth client. `I` sends the decoded token to it and compares the response with the claims.
+  #[arg(long, value_parser)]
+  pub introspection_endpoint: Option<String>,
+  /// Secret for validating the JWT. Can be text, file path (beginning with @), base64 encoded string (beginning with b64:) OS keyring entry (beginning with keyring:), Cloud KMS key version (beginning with gcpkms:) Azure Key Vault key (beginning with azurekv:) or Vault transit key (beginning with vault:). Repeat the flag or separate secrets with commas to try several secrets in order. Defaults to $JWT_UI_SECRET, or the JWKS fetched from $JWT_UI_JWKS_URL.
+  #[arg(short = 'S', long, value_parser)]
+  pub secret: Vec<String>,
+  /// Command printing the secret for validating the JWT (e.g. `op read op://vault/jwt/secret`). Tried after the `--secret` values.
+  #[arg(long, value_parser)]
+  pub secret_cmd: Option<String>,
   /// Print to STDOUT instead of starting the CLI in TUI mode.
   #[arg(short, long, value_parser, default_value_t = false)]
   pub stdout: bool,
@@ -48,48 +142,605 @@ pub struct Cli {
   #[arg(short, long, value_parser, default_value_t = false)]
   pub json: bool,
   /// Set the tick rate (milliseconds): the lower the number the higher the FPS. Must be less than 1000.
-  #[arg(short, long, value_parser, default_value_t = 250)]
-  pub tick_rate: u64,
+  /// Defaults to the `tick_rate` of the config, or 250. Change it while running with `+` and `-`.
+  #[arg(short, long, value_parser)]
+  pub tick_rate: Option<u64>,
   /// Disable mouse capture in order to copy individual text.
   #[arg(short, long, value_parser, default_value_t = false)]
   pub disable_mouse_capture: bool,
+  /// Start the decoder and encoder views in compact layout (without inner borders and hints).
+  #[arg(short, long, value_parser, default_value_t = false)]
+  pub compact: bool,
+  /// Use vim keys: `gg`/`G` to jump to the top/bottom of a block, `i` to edit and `:` commands.
+  #[arg(long, value_parser, default_value_t = false)]
+  pub vim: bool,
+  /// Color theme: dark, light, solarized-dark, solarized-light, gruvbox, dracula, monochrome or plain [default: dark or light depending on the terminal background, monochrome when NO_COLOR is set].
+  #[arg(long, value_parser = parse_theme)]
+  pub theme: Option<Theme>,
+  /// Plain mode for limited terminals and colorblind users: no colors, ASCII borders and text markers for the active block and verification results. Same as `--theme plain`.
+  #[arg(long, value_parser, default_value_t = false, conflicts_with = "theme")]
+  pub plain: bool,
+  /// Restore the token, secret references, encoder and tab of the last session, and save them on quit. Secrets entered as text are never saved.
+  #[arg(long, value_parser, default_value_t = false)]
+  pub session: bool,
+  /// Open a workspace saved with `W`: the name of a workspace of <config dir>/jwt-ui/workspaces or the path of a workspace file shared by a teammate. The token and secret of the arguments are kept.
+  #[arg(long, value_parser, conflicts_with = "session")]
+  pub workspace: Option<String>,
+  /// Linear mode for screen readers: read tokens and commands line by line and print the results as plain text, without the full screen UI.
+  #[arg(long, value_parser, default_value_t = false)]
+  pub screen_reader: bool,
+  /// Path to the config file [default: <config dir>/jwt-ui/config.toml].
+  #[arg(long, value_parser)]
+  pub config: Option<PathBuf>,
+  /// Name of the config profile whose token policy is applied to decoded tokens.
+  #[arg(short, long, value_parser)]
+  pub profile: Option<String>,
+  /// Built-in checks of a token profile applied to decoded tokens, in addition to the policy of the profile (`rfc9068` for OAuth access tokens, `jwt-svid` for SPIFFE JWT-SVIDs).
+  #[arg(long, value_parser)]
+  pub preset: Option<Preset>,
+  /// Validate the `x5c` certificate chain of the JWT against the trusted root certificates.
+  #[arg(long, value_parser, default_value_t = false)]
+  pub validate_chain: bool,
+  /// PEM or DER file with the trusted root certificates used instead of the system root store.
+  #[arg(long, value_parser)]
+  pub ca_bundle: Option<PathBuf>,
+  /// Proxy URL of the JWKS, x5u, token and OAuth requests (e.g. `http://proxy.corp:3128`), instead of the `HTTPS_PROXY` or `HTTP_PROXY` environment variable. Hosts of `NO_PROXY` are not proxied.
+  #[arg(long, value_parser)]
+  pub proxy: Option<String>,
+  /// PEM or DER CA certificate of the JWKS, x5u, token and OAuth servers, e.g. of an internal IdP with a private CA. Trusted in addition to the bundled Mozilla root certificates.
+  #[arg(long, value_parser)]
+  pub ca_cert: Option<PathBuf>,
+  /// PEM client certificate sent to the servers of the network requests (mTLS).
+  #[arg(long, value_parser, requires = "client_key")]
+  pub client_cert: Option<PathBuf>,
+  /// PEM private key of the `--client-cert` client certificate.
+  #[arg(long, value_parser, requires = "client_cert")]
+  pub client_key: Option<PathBuf>,
+  /// Accept any server certificate of the network requests, including self-signed and expired ones. Only for testing: responses can be tampered with.
+  #[arg(long, value_parser, default_value_t = false)]
+  pub insecure_skip_tls_verify: bool,
+  /// Never access the network: JWKS URLs, x5u certificates, introspection and OAuth requests fail with an error instead, and `--token-url` and `--grant` are rejected.
+  #[arg(long, value_parser, default_value_t = false, conflicts_with_all = ["token_url", "grant"])]
+  pub offline: bool,
+  /// Command printing the passphrase of an encrypted private key used by the encoder (e.g. `pass show jwt/key`).
+  #[arg(long, value_parser)]
+  pub passphrase_cmd: Option<String>,
+  /// Claims loaded into the encoder payload: JSON text, a file path (beginning with @) or @- for STDIN.
+  #[arg(short = 'P', long, value_parser)]
+  pub payload: Option<String>,
+  /// Allow the encoder to create unsigned `alg: none` tokens with an empty signature, to test that services reject them.
+  #[arg(long, value_parser, default_value_t = false)]
+  pub insecure_none: bool,
+  /// File the decode, verify and network events are appended to. Tokens and secrets are never logged.
+  #[arg(long, value_parser)]
+  pub log_file: Option<PathBuf>,
+  /// Verbosity of the log file: error, warn, info, debug or trace.
+  #[arg(long, value_parser, default_value = "info")]
+  pub log_level: tracing::Level,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+  /// Run security checks on a JWT and print a scored report. Exits with code 3 when high or critical issues are found.
+  Audit {
+    /// JWT token to audit.
+    #[clap(value_parser)]
+    token: String,
+    /// Print the report as JSON.
+    #[arg(short, long, value_parser, default_value_t = false)]
+    json: bool,
+  },
+  /// Encode and sign a JWT and print it, without starting the UI.
+  Encode {
+    /// Signing algorithm of the token.
+    #[arg(short = 'A', long, value_parser, default_value = "HS256")]
+    alg: String,
+    /// Secret to sign the JWT with. Can be text, file path (beginning with @), base64 encoded string (beginning with b64:), OS keyring entry (beginning with keyring:) or command (beginning with cmd:).
+    #[arg(short = 'S', long, value_parser, default_value = "")]
+    secret: String,
+    /// Header parameter as KEY=VALUE (e.g. `kid=key-1`), can be repeated.
+    #[arg(short = 'H', long = "header", value_parser = parse_key_value)]
+    header: Vec<(String, Value)>,
+    /// Claim as KEY=VALUE, values that are valid JSON keep their type (e.g. `admin=true`), can be repeated.
+    #[arg(short = 'P', long = "claim", value_parser = parse_key_value)]
+    claims: Vec<(String, Value)>,
+    /// Claims the `--claim` values are added to: JSON text, a file path (beginning with @) or @- for STDIN.
+    #[arg(long, value_parser)]
+    payload: Option<String>,
+    /// Expiration as timestamp or relative to now (e.g. `+1h` or `+10 min`).
+    #[arg(long, value_parser, allow_hyphen_values = true)]
+    exp: Option<String>,
+    /// Not before as timestamp or relative to now (e.g. `-5m`).
+    #[arg(long, value_parser, allow_hyphen_values = true)]
+    nbf: Option<String>,
+    /// Set `iat` to the current time.
+    #[arg(long, value_parser, default_value_t = false)]
+    iat: bool,
+    /// Set `jti` to a new UUID.
+    #[arg(long, value_parser, default_value_t = false)]
+    jti: bool,
+    /// Command printing the passphrase of an encrypted private key.
+    #[arg(long, value_parser)]
+    passphrase_cmd: Option<String>,
+    /// Allow unsigned `alg: none` tokens with an empty signature.
+    #[arg(long, value_parser, default_value_t = false)]
+    insecure_none: bool,
+  },
+  /// Manage signing keys.
+  Keys {
+    #[command(subcommand)]
+    command: KeysCommand,
+  },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum KeysCommand {
+  /// Generate a private/public key pair for testing asymmetric tokens.
+  Generate {
+    /// Signing algorithm the key is for: RS256, RS384, RS512, PS256, PS384, PS512, ES256 or EdDSA.
+    #[arg(short, long, value_parser = Algorithm::from_str)]
+    alg: Algorithm,
+    /// Size of RSA keys in bits.
+    #[arg(short, long, value_parser, default_value_t = DEFAULT_RSA_BITS)]
+    bits: usize,
+    /// Key representation to print or write.
+    #[arg(short, long, value_enum, default_value_t = KeyFormat::Pem)]
+    format: KeyFormat,
+    /// Write the keys to files starting with this path (e.g. `./key` writes `key.pem` and `key.pub.pem`) instead of printing them.
+    #[arg(short, long, value_parser)]
+    out: Option<PathBuf>,
+    /// Also print the JWKS with the public key, to host on a JWKS endpoint.
+    #[arg(long, value_parser, default_value_t = false)]
+    jwks: bool,
+  },
+  /// Convert a key between PEM/DER and JWK, inferring the key type (RSA, P-256, P-384, secp256k1 or Ed25519).
+  Convert {
+    /// PEM, DER or JWK key file to convert, `-` reads from STDIN.
+    #[clap(value_parser)]
+    input: PathBuf,
+    /// Encoding of the converted key [default: pem for JWK input, jwk otherwise].
+    #[arg(short, long, value_enum)]
+    to: Option<KeyEncoding>,
+    /// Only output the public key of a private key.
+    #[arg(short, long, value_parser, default_value_t = false)]
+    public: bool,
+    /// Write the converted key to this file instead of STDOUT.
+    #[arg(short, long, value_parser)]
+    out: Option<PathBuf>,
+  },
+  /// Build a JWKS document from public (or private) key files, to host on a JWKS endpoint.
+  Jwks {
+    /// PEM, DER or JWK key files. Only the public part of private keys is published.
+    #[clap(value_parser, required = true)]
+    keys: Vec<PathBuf>,
+    /// How the `kid` of each key is computed.
+    #[arg(short, long, value_enum, default_value_t = KidSource::Thumbprint)]
+    kid_from: KidSource,
+    /// Write the JWKS to this file instead of STDOUT.
+    #[arg(short, long, value_parser)]
+    out: Option<PathBuf>,
+  },
+}
+
+impl Cli {
+  /// the secrets from the arguments, as entered in the secret input of the UI
+  fn secret(&self) -> String {
+    let mut secrets = self.secret.clone();
+    if let Some(cmd) = &self.secret_cmd {
+      // `cmd:` takes the rest of the input, so it goes last
+      secrets.push(format!("{COMMAND_PREFIX}{cmd}"));
+    }
+    secrets.join(",")
+  }
+
+  /// the default secret from the environment, when no secret is given on the command line
+  fn env_secret(&self) -> Option<EnvSecret> {
+    if !self.secret.is_empty() || self.secret_cmd.is_some() {
+      return None;
+    }
+    match secret_from_env() {
+      Ok(secret) => secret,
+      Err(e) => {
+        eprintln!("{e}");
+        process::exit(1);
+      }
+    }
+  }
+
+  /// the request fetching the token of `--token-url`
+  fn token_request(&self) -> Option<TokenRequest> {
+    self.token_url.as_ref().map(|url| TokenRequest {
+      url: url.clone(),
+      headers: self.token_header.clone(),
+      body: self.token_body.clone(),
+      field: self.token_field.clone(),
+    })
+  }
+
+  /// request a token with `--grant`, from the OAuth client of the config and the flags
+  fn oauth_session(
+    &self,
+    clients: &BTreeMap<String, OAuthClient>,
+  ) -> JWTResult<Option<OAuthSession>> {
+    if self.grant.is_none() && self.oauth_client.is_none() && self.introspection_endpoint.is_none()
+    {
+      return Ok(None);
+    }
+    let mut client = match &self.oauth_client {
+      Some(name) => clients
+        .get(name)
+        .cloned()
+        .ok_or_else(|| JWTError::Internal(format!("OAuth client {name:?} not found in config")))?,
+      None => OAuthClient::default(),
+    };
+    if let Some(url) = &self.token_endpoint {
+      client.token_url = url.clone();
+    }
+    if let Some(client_id) = &self.client_id {
+      client.client_id = client_id.clone();
+    }
+    if let Some(secret) = &self.client_secret {
+      client.client_secret = Some(secret.clone());
+    }
+    if let Some(scope) = &self.scope {
+      client.scope = Some(scope.clone());
+    }
+    if let Some(url) = &self.device_endpoint {
+      client.device_authorization_url = Some(url.clone());
+    }
+    if let Some(url) = &self.authorization_endpoint {
+      client.authorization_url = Some(url.clone());
+    }
+    if let Some(uri) = &self.redirect_uri {
+      client.redirect_uri = Some(uri.clone());
+    }
+    if let Some(url) = &self.introspection_endpoint {
+      client.introspection_url = Some(url.clone());
+    }
+    let Some(grant) = self.grant else {
+      return Ok(Some(OAuthSession {
+        client,
+        grant: None,
+        tokens: None,
+      }));
+    };
+    let tokens = match grant {
+      Grant::DeviceCode => {
+        let authorization = client.device_authorization()?;
+        eprintln!("{}", authorization.instructions());
+        eprintln!("Waiting for the sign in...");
+        client.poll_device_token(&authorization)?
+      }
+      Grant::AuthorizationCode => {
+        let request = client.authorization_request()?;
+        let listener = request.listen()?;
+        eprintln!("Sign in with your browser, or open {}", request.url);
+        if let Err(e) = open_browser(&request.url) {
+          eprintln!("{e}");
+        }
+        eprintln!("Waiting for the sign in...");
+        let code = request.wait_for_code(&listener, SIGN_IN_TIMEOUT)?;
+        client.exchange_code(&request, &code)?
+      }
+      _ => client.request_token(grant)?,
+    };
+    Ok(Some(OAuthSession {
+      client,
+      grant: Some(grant),
+      tokens: Some(tokens),
+    }))
+  }
+
+  /// create the app with the token and secrets from the arguments
+  fn app(&self) -> App {
+    let env_secret = self.env_secret();
+    let secret = match &env_secret {
+      Some(env_secret) => env_secret.value.clone(),
+      None => self.secret(),
+    };
+    let mut app = App::new(self.token.clone(), secret);
+    app.data.decoder.env_secret = env_secret;
+    // secret commands may ask for input (e.g. to unlock a password manager), run them before raw mode
+    app.secrets.run_commands(&self.secret());
+    app
+      .secrets
+      .fetch_secrets(app.data.decoder.secret.input.value());
+    app
+  }
 }
 
+/// Exit code used in stdout mode when the decoded token violates the profile policy
+const POLICY_VIOLATION_EXIT_CODE: i32 = 3;
+
 type Result<T> = std::result::Result<T, Box<dyn Error>>;
 
+fn parse_theme(name: &str) -> std::result::Result<Theme, String> {
+  Theme::from_str(name).map_err(|e| e.to_string())
+}
+
 fn main() -> Result<()> {
   panic::set_hook(Box::new(|info| {
     panic_hook(info);
   }));
 
   // parse CLI arguments
-  let cli = Cli::parse();
+  let mut cli = Cli::parse();
+
+  if let Err(e) = init_logging(&cli) {
+    eprintln!("Unable to open the log file: {e}");
+    process::exit(1);
+  }
+  if let Err(e) = cli.proxy.as_deref().map(set_proxy).transpose() {
+    eprintln!("{e}");
+    process::exit(1);
+  }
+  if let Err(e) = set_tls(&TlsOptions {
+    ca_cert: cli.ca_cert.clone(),
+    client_cert: cli.client_cert.clone(),
+    client_key: cli.client_key.clone(),
+    insecure_skip_verify: cli.insecure_skip_tls_verify,
+  }) {
+    eprintln!("{e}");
+    process::exit(1);
+  }
+  if cli.offline {
+    set_offline();
+  }
+
+  if let Some(command) = &cli.command {
+    run_command_line(command);
+    return Ok(());
+  }
+
+  if let Some(request) = cli.token_request() {
+    match request.fetch() {
+      Ok(token) => cli.token = Some(token),
+      Err(e) => {
+        eprintln!("{e}");
+        process::exit(1);
+      }
+    }
+  }
 
-  if cli.tick_rate >= 1000 {
+  let (mut profile, templates, history, oauth, tick_rate) = match Config::load(cli.config.clone())
+    .and_then(|config| {
+      Ok((
+        config.profile(cli.profile.as_deref())?,
+        config.templates,
+        config.history,
+        cli.oauth_session(&config.oauth)?,
+        config.tick_rate,
+      ))
+    }) {
+    Ok(config) => config,
+    Err(e) => {
+      eprintln!("{e}");
+      process::exit(1);
+    }
+  };
+  if let Some(tokens) = oauth.as_ref().and_then(|session| session.tokens.as_ref()) {
+    cli.token = Some(tokens.access_token.clone());
+  }
+  if cli.preset.is_some() {
+    profile.preset = cli.preset;
+  }
+  cli.tick_rate = cli.tick_rate.or(tick_rate);
+  if cli.tick_rate.is_some_and(|tick_rate| tick_rate >= 1000) {
     panic!("Tick rate must be below 1000");
   }
 
   if (cli.stdout || cli.json) && cli.token.is_some() {
-    to_stdout(cli);
+    to_stdout(cli, profile);
+  } else if cli.screen_reader {
+    let mut app = cli.app();
+    app.profile = profile;
+    app.ca_bundle = cli.ca_bundle.clone();
+    app.data.decoder.validate_chain = cli.validate_chain;
+    screen_reader::run(
+      &mut app,
+      cli.no_verify,
+      io::stdin().lock(),
+      &mut io::stdout(),
+    )?;
   } else {
+    let passphrase = match cli.passphrase_cmd.as_deref().map(run_command).transpose() {
+      Ok(passphrase) => passphrase,
+      Err(e) => {
+        eprintln!("{e}");
+        process::exit(1);
+      }
+    };
+    let payload = match cli.payload.as_deref().map(read_payload).transpose() {
+      Ok(payload) => payload,
+      Err(e) => {
+        eprintln!("{e}");
+        process::exit(1);
+      }
+    };
     // The UI must run in the "main" thread
-    start_ui(cli)?;
+    start_ui(
+      cli, profile, &templates, history, oauth, passphrase, payload,
+    )?;
   }
 
   Ok(())
 }
 
-fn to_stdout(cli: Cli) {
-  let mut app = App::new(cli.token.clone(), cli.secret.clone());
+/// log the events of the app to the log file, nothing is logged without one
+fn init_logging(cli: &Cli) -> io::Result<()> {
+  let Some(path) = cli.log_file.as_ref() else {
+    return Ok(());
+  };
+  let file = fs::OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(path)?;
+  tracing_subscriber::fmt()
+    .with_writer(Mutex::new(file))
+    .with_ansi(false)
+    .with_max_level(cli.log_level)
+    .init();
+  Ok(())
+}
+
+fn to_stdout(cli: Cli, profile: Profile) {
+  let mut app = cli.app();
+  app.profile = profile;
+  app.ca_bundle = cli.ca_bundle.clone();
+  app.data.decoder.validate_chain = cli.validate_chain;
   // print decoded result to stdout
   decode_jwt_token(&mut app, cli.no_verify);
   if app.data.error.is_empty() && app.data.decoder.is_decoded() {
-    print_decoded_token(app.data.decoder.get_decoded().as_ref().unwrap(), cli.json);
+    print_decoded_token(&app.data.decoder, cli.json);
+    if has_errors(&app.data.decoder.findings) {
+      process::exit(POLICY_VIOLATION_EXIT_CODE);
+    }
   } else {
     println!("{}", app.data.error);
   }
 }
 
+fn run_command_line(command: &Command) {
+  match command {
+    Command::Audit { token, json } => match audit_token(token) {
+      Ok(report) => {
+        print_audit_report(&report, *json);
+        if report.has_severe_findings() {
+          process::exit(POLICY_VIOLATION_EXIT_CODE);
+        }
+      }
+      Err(e) => {
+        eprintln!("{e}");
+        process::exit(1);
+      }
+    },
+    Command::Encode {
+      alg,
+      secret,
+      header,
+      claims,
+      payload,
+      exp,
+      nbf,
+      iat,
+      jti,
+      passphrase_cmd,
+      insecure_none,
+    } => {
+      let mut secrets = SecretStore::default();
+      secrets.run_commands(secret);
+      secrets.fetch_keyring_entries(secret);
+      secrets.fetch_queued();
+      let token = secrets.resolve_for_signing(secret).and_then(|secret| {
+        let options = EncodeOptions {
+          alg: alg.clone(),
+          secret: secret.into_owned(),
+          header: header.clone(),
+          payload: payload.clone(),
+          claims: claims.clone(),
+          exp: exp.clone(),
+          nbf: nbf.clone(),
+          managed_claims: ManagedClaims {
+            iat: *iat,
+            jti: *jti,
+            ..ManagedClaims::default()
+          },
+          passphrase: passphrase_cmd.as_deref().map(run_command).transpose()?,
+          insecure_none: *insecure_none,
+        };
+        encode_with_options(&options, Utc::now().timestamp())
+      });
+      match token {
+        Ok(token) => println!("{token}"),
+        Err(e) => {
+          eprintln!("{e}");
+          process::exit(1);
+        }
+      }
+    }
+    Command::Keys {
+      command:
+        KeysCommand::Generate {
+          alg,
+          bits,
+          format,
+          out,
+          jwks: print_jwks,
+        },
+    } => {
+      let result = generate_key_pair(*alg, *bits).and_then(|pair| {
+        match out {
+          Some(prefix) => {
+            for path in pair.write(*format, prefix)? {
+              println!("Wrote {}", path.display());
+            }
+          }
+          None => pair.print(*format),
+        }
+        if *print_jwks {
+          let jwks = jwks(vec![pair.public_jwk]);
+          println!("{}", serde_json::to_string_pretty(&jwks).unwrap());
+        }
+        Ok(())
+      });
+      if let Err(e) = result {
+        eprintln!("{e}");
+        process::exit(1);
+      }
+    }
+    Command::Keys {
+      command:
+        KeysCommand::Convert {
+          input,
+          to,
+          public,
+          out,
+        },
+    } => {
+      let input = match input.to_str() {
+        Some("-") => {
+          let mut buf = vec![];
+          io::stdin().read_to_end(&mut buf).map(|_| buf)
+        }
+        _ => fs::read(input),
+      };
+      let result = input
+        .map_err(JWTError::from)
+        .and_then(|input| convert_key(&input, *to, *public))
+        .and_then(|(key, private)| match out {
+          Some(path) => write_key_file(path, &key, private),
+          None => Ok(io::stdout().write_all(&key)?),
+        });
+      if let Err(e) = result {
+        eprintln!("{e}");
+        process::exit(1);
+      }
+    }
+    Command::Keys {
+      command: KeysCommand::Jwks {
+        keys,
+        kid_from,
+        out,
+      },
+    } => {
+      let result = build_jwks(keys, *kid_from).and_then(|jwks| {
+        let jwks = format!("{}\n", serde_json::to_string_pretty(&jwks)?);
+        match out {
+          Some(path) => write_key_file(path, jwks.as_bytes(), false),
+          None => {
+            print!("{jwks}");
+            Ok(())
+          }
+        }
+      });
+      if let Err(e) = result {
+        eprintln!("{e}");
+        process::exit(1);
+      }
+    }
+  }
+}
+
 /// Enable mouse capture, but don't enable capture of all the mouse movements, doing so will improve performance, and is part of the fix for the weird mouse event output bug
 pub fn enable_mouse_capture() -> Result<()> {
   Ok(
@@ -104,13 +755,98 @@ pub fn enable_mouse_capture() -> Result<()> {
   )
 }
 
-fn start_ui(cli: Cli) -> Result<()> {
+fn start_ui(
+  cli: Cli,
+  profile: Profile,
+  templates: &BTreeMap<String, Template>,
+  history: HistorySettings,
+  oauth: Option<OAuthSession>,
+  passphrase: Option<String>,
+  payload: Option<Vec<String>>,
+) -> Result<()> {
+  let mut app = cli.app();
+  app.data.decoder.compact = cli.compact;
+  app.data.encoder.compact = cli.compact;
+  app.vim_mode = cli.vim;
+  app.tick_rate = cli.tick_rate.unwrap_or(DEFAULT_TICK_RATE);
+  app.profile = profile;
+  app.templates = StatefulTable::with_items(all_templates(templates));
+  app.ca_bundle = cli.ca_bundle.clone();
+  app.data.decoder.validate_chain = cli.validate_chain;
+  app.data.encoder.passphrase = passphrase;
+  app.data.encoder.insecure_none = cli.insecure_none;
+  if let Some(session) = oauth {
+    if let Some(tokens) = &session.tokens {
+      app.notify(tokens.expiry());
+    }
+    app.oauth = Some(session);
+    app.open_oauth_tokens();
+  }
+  match TokenHistory::load(history, default_history_path()) {
+    Ok(token_history) => app.token_history = token_history,
+    Err(e) => app.handle_error(e),
+  }
+  match Bookmarks::load(default_bookmarks_path()) {
+    Ok(bookmarks) => app.bookmarks = bookmarks,
+    Err(e) => app.handle_error(e),
+  }
+  let session_path = default_session_path().filter(|_| cli.session);
+  if let Some(path) = session_path.as_deref() {
+    match Session::load(path) {
+      Ok(Some(session)) => session.restore(&mut app),
+      Ok(None) => {}
+      Err(e) => app.handle_error(e),
+    }
+  }
+  let mut workspace_theme = false;
+  if let Some(name) = cli.workspace.as_deref() {
+    let workspace = workspace_path(name, default_workspace_dir().as_deref())
+      .and_then(|path| Workspace::load(&path))
+      .and_then(|workspace| workspace.restore(&mut app));
+    match workspace {
+      Ok(_) => workspace_theme = true,
+      Err(e) => app.handle_error(e),
+    }
+  }
+  if let Some(payload) = payload {
+    app.data.encoder.payload.input = payload.into();
+    if cli.token.is_none() {
+      app.route_encoder();
+    }
+  }
+  if app.data.decoder.encoded.input.value().is_empty()
+    && app.get_current_route().id == RouteId::Decoder
+  {
+    app.open_intro();
+  }
+  if !cli.more_tokens.is_empty() {
+    let tokens: Vec<String> = cli.token.iter().chain(&cli.more_tokens).cloned().collect();
+    app.open_token_picker(&tokens);
+  }
   // see https://docs.rs/crossterm/0.17.7/crossterm/terminal/#raw-mode
   enable_raw_mode()?;
+  // the background is queried before the events are read so its answer isn't taken for keys
+  app.theme = match (cli.plain, cli.theme) {
+    (true, _) => Theme::Plain,
+    (_, Some(theme)) => theme,
+    _ if workspace_theme => app.theme,
+    // see https://no-color.org
+    _ if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) => Theme::Monochrome,
+    _ => match background::detect_light_background() {
+      Some(true) => Theme::Light,
+      _ => Theme::Dark,
+    },
+  };
   // Terminal initialization
   let mut stdout = stdout();
   // not capturing mouse to make text select/copy possible
-  execute!(stdout, EnterAlternateScreen)?;
+  // focus changes pause the ticks while the terminal is in the background
+  execute!(
+    stdout,
+    EnterAlternateScreen,
+    EnableBracketedPaste,
+    EnableFocusChange
+  )?;
   if !cli.disable_mouse_capture {
     enable_mouse_capture()?;
   }
@@ -120,20 +856,28 @@ fn start_ui(cli: Cli) -> Result<()> {
   terminal.clear()?;
   terminal.hide_cursor()?;
   // custom events
-  let events = event::Events::new(cli.tick_rate);
+  let events = event::Events::new(app.tick_rate);
+  events.forward_signals()?;
+  match Dispatcher::new(events.sender()) {
+    Ok(network) => app.network = Some(network),
+    Err(e) => app.handle_error(e),
+  }
+  match Verifier::new(events.sender()) {
+    Ok(verifier) => app.verifier = Some(verifier),
+    Err(e) => app.handle_error(e),
+  }
+  match HookRunner::new(events.sender()) {
+    Ok(runner) => app.hook_runner = Some(runner),
+    Err(e) => app.handle_error(e),
+  }
+
+  // the size is then updated by the resize events
+  let size = terminal.size()?;
+  app.size.width = size.width;
+  app.size.height = size.height;
 
-  let mut app = App::new(cli.token.clone(), cli.secret.clone());
   // main UI loop
   loop {
-    // Get the size of the screen on each loop to account for resize event
-    if let Ok(size) = terminal.backend().size() {
-      // Reset the size if the terminal was resized
-      if app.size.as_size() != size {
-        app.size.width = size.width;
-        app.size.height = size.height;
-      }
-    };
-
     // draw the UI layout
     terminal.draw(|f| ui::draw(f, &mut app))?;
 
@@ -151,19 +895,42 @@ fn start_ui(cli: Cli) -> Result<()> {
       }
       // handle mouse events
       event::Event::MouseInput(mouse) => handlers::handle_mouse_events(mouse, &mut app),
+      // handle pasted text
+      event::Event::Paste(text) => handlers::handle_paste_events(&text, &mut app),
+      // handle terminal resizes
+      event::Event::Resize(width, height) => {
+        app.size.width = width;
+        app.size.height = height;
+      }
       // handle tick events
       event::Event::Tick => {
+        handlers::handle_chord_timeout(&mut app, Instant::now());
         app.on_tick();
       }
+      // handle the responses of the network requests
+      event::Event::Network(response) => app.handle_network_response(*response),
+      // handle the signatures verified in the background
+      event::Event::Verified(verification) => app.handle_verification(*verification),
+      // handle the findings of the validators run in the background
+      event::Event::Validated(run) => app.handle_hook_run(*run),
+      // quit like with CTRL + C when killed
+      event::Event::Terminate => break,
     }
     if app.should_quit {
       break;
     }
+    events.set_tick_rate(app.tick_rate);
   }
 
   terminal.show_cursor()?;
   shutdown(terminal)?;
 
+  if let Some(path) = session_path.as_deref() {
+    if let Err(e) = Session::capture(&app).save(path) {
+      eprintln!("Unable to save the session: {e}");
+    }
+  }
+
   Ok(())
 }
 
@@ -173,7 +940,9 @@ fn shutdown(mut terminal: Terminal<CrosstermBackend<Stdout>>) -> io::Result<()>
   execute!(
     terminal.backend_mut(),
     LeaveAlternateScreen,
-    DisableMouseCapture
+    DisableMouseCapture,
+    DisableBracketedPaste,
+    DisableFocusChange
   )?;
   terminal.show_cursor()?;
   Ok(())
@@ -201,6 +970,8 @@ fn panic_hook(info: &PanicHookInfo<'_>) {
     io::stdout(),
     LeaveAlternateScreen,
     DisableMouseCapture,
+    DisableBracketedPaste,
+    DisableFocusChange,
     Print(format!(
       "thread '<unnamed>' panicked at '{}', {}\n\r{}",
       msg, location, stacktrace
@@ -219,6 +990,13 @@ fn panic_hook(info: &PanicHookInfo<'_>) {
 
   let file_path = handle_dump(&meta, info);
   disable_raw_mode().unwrap();
-  execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture).unwrap();
+  execute!(
+    io::stdout(),
+    LeaveAlternateScreen,
+    DisableMouseCapture,
+    DisableBracketedPaste,
+    DisableFocusChange
+  )
+  .unwrap();
   print_msg(file_path, &meta).expect("human-panic: printing error message to console failed");
 }
@@ -1,117 +1,223 @@
 use ratatui::{
   layout::{Constraint, Rect},
+  style::Modifier,
   text::Text,
-  widgets::{Block, Borders, Paragraph, Wrap},
+  widgets::{Block, Clear, Paragraph, Row, Table, Wrap},
   Frame,
 };
 
-use super::utils::{
-  get_input_style, get_selectable_block, horizontal_chunks, render_input_widget, style_default,
-  style_primary, vertical_chunks, vertical_chunks_with_margin,
+use super::{
+  utils::{
+    bordered_block, centered_rect, draw_validation_block, get_input_style, get_selectable_block,
+    horizontal_chunks, layout_block_with_line, render_input_widget, split_sides, style_default,
+    style_failure, style_highlight, style_main_background, style_primary, title_with_dual_style,
+    use_compact_layout, use_minimal_layout, vertical_chunks, vertical_chunks_with_margin,
+    MAX_VALIDATION_LINES,
+  },
+  HIGHLIGHT,
+};
+use crate::app::{
+  jwt_encoder::json_syntax_error, relaxed_json::SyntaxError, theme::Theme, ActiveBlock, App, Route,
+  RouteId, TextAreaInput,
 };
-use crate::app::{ActiveBlock, App, Route, RouteId, TextAreaInput};
 
 pub fn draw_encoder(f: &mut Frame<'_>, app: &mut App, area: Rect) {
-  let chunks = horizontal_chunks(
-    vec![Constraint::Percentage(50), Constraint::Percentage(50)],
-    area,
-  );
-  draw_left_side(f, app, chunks[0]);
-  draw_right_side(f, app, chunks[1]);
+  let compact = use_compact_layout(app.data.encoder.compact, area);
+  if use_minimal_layout(area) {
+    draw_selected_block(f, app, area);
+  } else {
+    let chunks = split_sides(app.data.encoder.splits.left, area);
+    draw_left_side(f, app, chunks[0], compact);
+    draw_right_side(f, app, chunks[1], compact);
+  }
+
+  if app.get_current_route().active_block == ActiveBlock::EncoderTemplates {
+    draw_templates_popup(f, app, area);
+  }
+}
+
+/// only the selected block fits in the view, the hidden blocks can't be clicked
+fn draw_selected_block(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+  app
+    .block_map
+    .retain(|route, _| route.id != RouteId::Encoder);
+  match *app.data.encoder.blocks.get_active_block() {
+    ActiveBlock::EncoderPayload => draw_payload_block(f, app, area, true),
+    ActiveBlock::EncoderSecret => draw_secret_block(f, app, area, true),
+    ActiveBlock::EncoderToken => draw_token_block(f, app, area, true),
+    _ => draw_header_block(f, app, area, true),
+  }
 }
 
-fn draw_left_side(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+fn draw_left_side(f: &mut Frame<'_>, app: &mut App, area: Rect, compact: bool) {
+  let header = app.data.encoder.splits.header;
   let chunks = vertical_chunks(
-    vec![Constraint::Percentage(40), Constraint::Percentage(60)],
+    vec![
+      Constraint::Percentage(header),
+      Constraint::Percentage(100 - header),
+    ],
     area,
   );
 
-  draw_header_block(f, app, chunks[0]);
-  draw_payload_block(f, app, chunks[1]);
+  draw_header_block(f, app, chunks[0], compact);
+  draw_payload_block(f, app, chunks[1], compact);
 }
 
-fn draw_right_side(f: &mut Frame<'_>, app: &mut App, area: Rect) {
-  let chunks = vertical_chunks(
-    vec![Constraint::Percentage(30), Constraint::Percentage(70)],
-    area,
-  );
+fn draw_right_side(f: &mut Frame<'_>, app: &mut App, area: Rect, compact: bool) {
+  let constraints = if compact {
+    // secret only needs a single line in compact mode
+    vec![Constraint::Length(3), Constraint::Min(3)]
+  } else {
+    vec![Constraint::Percentage(30), Constraint::Percentage(70)]
+  };
+  let chunks = vertical_chunks(constraints, area);
+
+  draw_secret_block(f, app, chunks[0], compact);
+
+  let token_area = if app.data.encoder.preview {
+    let preview_chunks = vertical_chunks(
+      vec![Constraint::Percentage(50), Constraint::Percentage(50)],
+      chunks[1],
+    );
+    draw_preview_block(f, app, preview_chunks[1]);
+    preview_chunks[0]
+  } else {
+    chunks[1]
+  };
 
-  draw_secret_block(f, app, chunks[0]);
-  draw_token_block(f, app, chunks[1]);
+  let findings = app.data.encoder.findings.len() as u16;
+  if findings == 0 {
+    draw_token_block(f, app, token_area, compact);
+  } else {
+    let token_chunks = vertical_chunks(
+      vec![
+        Constraint::Min(0),
+        Constraint::Length(findings.min(MAX_VALIDATION_LINES) + 2),
+      ],
+      token_area,
+    );
+    draw_token_block(f, app, token_chunks[0], compact);
+    draw_validation_block(f, &app.data.encoder.findings, app.theme, token_chunks[1]);
+  }
 }
 
-fn draw_header_block(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+fn draw_header_block(f: &mut Frame<'_>, app: &mut App, area: Rect, compact: bool) {
   app.update_block_map(get_route(ActiveBlock::EncoderHeader), area);
 
+  let error = json_syntax_error(&app.data.encoder.header.input.lines().join("\n"));
   let block = get_selectable_block(
-    "Header: Algorithm & Token Type",
+    &json_block_title("Header: Algorithm & Token Type".into(), error.as_ref()),
     *app.data.encoder.blocks.get_active_block() == ActiveBlock::EncoderHeader,
     Some(&app.data.encoder.header.input_mode),
-    app.light_theme,
+    app.theme,
+    compact,
   );
 
   f.render_widget(block, area);
 
-  render_text_area_widget(f, area, &mut app.data.encoder.header, app.light_theme);
+  let input_area = render_text_area_widget(
+    f,
+    area,
+    &mut app.data.encoder.header,
+    error.as_ref(),
+    app.theme,
+    compact,
+  );
+  app.update_input_map(ActiveBlock::EncoderHeader, input_area);
 }
 
-fn draw_payload_block(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+fn draw_payload_block(f: &mut Frame<'_>, app: &mut App, area: Rect, compact: bool) {
   app.update_block_map(get_route(ActiveBlock::EncoderPayload), area);
 
+  let title = match app.data.encoder.managed_claims.describe() {
+    Some(claims) => format!("Payload: Claims | {claims}"),
+    None => "Payload: Claims".into(),
+  };
+  let error = json_syntax_error(&app.data.encoder.payload.input.lines().join("\n"));
   let block = get_selectable_block(
-    "Payload: Claims",
+    &json_block_title(title, error.as_ref()),
     *app.data.encoder.blocks.get_active_block() == ActiveBlock::EncoderPayload,
     Some(&app.data.encoder.payload.input_mode),
-    app.light_theme,
+    app.theme,
+    compact,
   );
   f.render_widget(block, area);
 
-  render_text_area_widget(f, area, &mut app.data.encoder.payload, app.light_theme);
+  let input_area = render_text_area_widget(
+    f,
+    area,
+    &mut app.data.encoder.payload,
+    error.as_ref(),
+    app.theme,
+    compact,
+  );
+  app.update_input_map(ActiveBlock::EncoderPayload, input_area);
 }
 
-fn draw_secret_block(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+fn draw_secret_block(f: &mut Frame<'_>, app: &mut App, area: Rect, compact: bool) {
   app.update_block_map(get_route(ActiveBlock::EncoderSecret), area);
 
   let block = get_selectable_block(
     "Signing Secret",
     *app.data.encoder.blocks.get_active_block() == ActiveBlock::EncoderSecret,
     Some(&app.data.encoder.secret.input_mode),
-    app.light_theme,
+    app.theme,
+    compact,
   );
 
   f.render_widget(block, area);
 
+  if compact {
+    let chunks = vertical_chunks_with_margin(vec![Constraint::Min(1)], area, 1);
+    let input_area = render_input_widget(f, chunks[0], &app.data.encoder.secret, app.theme, true);
+    app.update_input_map(ActiveBlock::EncoderSecret, input_area);
+    return;
+  }
+
   let chunks =
     vertical_chunks_with_margin(vec![Constraint::Length(1), Constraint::Min(2)], area, 1);
 
   let mut text = Text::from(
     "Prepend 'b64:' for base64 encoded secret. Prepend '@' for file path (.pem, .pk8, .der, .json)",
   );
-  text = text.patch_style(style_default(app.light_theme));
+  text = text.patch_style(style_default(app.theme));
   let paragraph = Paragraph::new(text).block(Block::default());
 
   f.render_widget(paragraph, chunks[0]);
 
-  render_input_widget(f, chunks[1], &app.data.encoder.secret, app.light_theme);
+  let input_area = render_input_widget(f, chunks[1], &app.data.encoder.secret, app.theme, false);
+  app.update_input_map(ActiveBlock::EncoderSecret, input_area);
 }
 
-fn draw_token_block(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+fn draw_token_block(f: &mut Frame<'_>, app: &mut App, area: Rect, compact: bool) {
   app.update_block_map(get_route(ActiveBlock::EncoderToken), area);
 
   let block = get_selectable_block(
     "Encoded Token",
     *app.data.encoder.blocks.get_active_block() == ActiveBlock::EncoderToken,
     None,
-    app.light_theme,
+    app.theme,
+    compact,
   );
 
   f.render_widget(block, area);
 
   let chunks = vertical_chunks_with_margin(vec![Constraint::Min(2)], area, 1);
 
-  let encoded = app.data.encoder.encoded.get_txt();
-  let mut txt = Text::from(encoded.clone());
-  txt = txt.patch_style(style_primary(app.light_theme));
+  // the token wraps over the lines of the block, don't scroll past its last line
+  let lines = app
+    .data
+    .encoder
+    .encoded
+    .get_txt()
+    .chars()
+    .count()
+    .div_ceil(chunks[0].width.max(1) as usize) as u16;
+  let encoded_txt = &mut app.data.encoder.encoded;
+  encoded_txt.offset = encoded_txt
+    .offset
+    .min(lines.saturating_sub(chunks[0].height));
+  let txt = Text::from(app.data.encoder.encoded.get_txt()).patch_style(style_primary(app.theme));
 
   let paragraph = Paragraph::new(txt)
     .block(Block::default())
@@ -120,22 +226,118 @@ fn draw_token_block(f: &mut Frame<'_>, app: &mut App, area: Rect) {
   f.render_widget(paragraph, chunks[0]);
 }
 
+fn draw_preview_block(f: &mut Frame<'_>, app: &App, area: Rect) {
+  let title = title_with_dual_style(" Decoded Token ".into(), "| <v> hide ".into());
+  let text = Text::from(app.data.encoder.decoded_preview().join("\n"));
+  let paragraph = Paragraph::new(text.patch_style(style_primary(app.theme)))
+    .block(layout_block_with_line(title, app.theme, false))
+    .wrap(Wrap { trim: false });
+  f.render_widget(paragraph, area);
+}
+
+fn draw_templates_popup(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+  let area = centered_rect(80, 80, area);
+  let chunks = horizontal_chunks(
+    vec![Constraint::Percentage(50), Constraint::Percentage(50)],
+    area,
+  );
+
+  let title = title_with_dual_style(" Templates ".into(), "| <enter> load ".into());
+  let rows = app
+    .templates
+    .items
+    .iter()
+    .map(|template| Row::new(vec![template.name.clone()]).style(style_primary(app.theme)));
+  let table = Table::new(rows, [Constraint::Min(10)])
+    .style(style_main_background(app.theme))
+    .block(layout_block_with_line(title, app.theme, true))
+    .row_highlight_style(style_highlight())
+    .highlight_symbol(HIGHLIGHT);
+
+  let preview = app
+    .templates
+    .state
+    .selected()
+    .and_then(|i| app.templates.items.get(i))
+    .map(|template| {
+      let header = template.header_lines(&app.data.encoder.header_json());
+      [header, vec![String::new()], template.payload_lines()]
+        .concat()
+        .join("\n")
+    })
+    .unwrap_or_default();
+  let paragraph = Paragraph::new(Text::from(preview).patch_style(style_primary(app.theme)))
+    .style(style_main_background(app.theme))
+    .block(layout_block_with_line(
+      title_with_dual_style(" Header & Payload ".into(), String::new()),
+      app.theme,
+      false,
+    ))
+    .wrap(Wrap { trim: false });
+
+  f.render_widget(Clear, area);
+  f.render_stateful_widget(table, chunks[0], &mut app.templates.state);
+  f.render_widget(paragraph, chunks[1]);
+}
+
 // Utility methods
+fn json_block_title(title: String, error: Option<&SyntaxError>) -> String {
+  match error {
+    Some(e) => format!("{title} | invalid JSON at {}:{}", e.line, e.column),
+    None => title,
+  }
+}
+
 fn render_text_area_widget(
   f: &mut Frame<'_>,
   area: Rect,
   text_input: &mut TextAreaInput<'_>,
-  light_theme: bool,
-) {
+  error: Option<&SyntaxError>,
+  theme: Theme,
+  compact: bool,
+) -> Rect {
   let chunks = vertical_chunks_with_margin(vec![Constraint::Min(2)], area, 1);
   let mut textarea = text_input.input.clone();
-  textarea.set_block(
+  let block = if compact {
     Block::default()
-      .borders(Borders::ALL)
-      .style(get_input_style(&text_input.input_mode, light_theme)),
-  );
+  } else {
+    bordered_block(theme)
+  };
+  let inner = block.inner(chunks[0]);
+  textarea.set_block(block.style(get_input_style(&text_input.input_mode, theme)));
 
   f.render_widget(&textarea, chunks[0]);
+
+  if let Some(e) = error {
+    highlight_json_error(f, inner, text_input, e, theme);
+  }
+  inner
+}
+
+/// highlight the line of the syntax error and the position it was found at
+fn highlight_json_error(
+  f: &mut Frame<'_>,
+  area: Rect,
+  text_input: &TextAreaInput<'_>,
+  error: &SyntaxError,
+  theme: Theme,
+) {
+  let row = error.line.saturating_sub(1) as u16;
+  let col = error.column.saturating_sub(1) as u16;
+  let (top_row, top_col) = text_input.scroll_top(area);
+  if row < top_row || row >= top_row + area.height {
+    return;
+  }
+  let y = area.y + row - top_row;
+  let style = style_failure(theme);
+  f.buffer_mut()
+    .set_style(Rect::new(area.x, y, area.width, 1), style);
+  if col >= top_col && col < top_col + area.width {
+    f.buffer_mut().set_style(
+      Rect::new(area.x + col - top_col, y, 1, 1),
+      style.add_modifier(Modifier::REVERSED),
+    );
+  }
 }
 
 fn get_route(active_block: ActiveBlock) -> Route {
@@ -163,7 +365,7 @@ mod tests {
 
   #[test]
   fn test_draw_encoder() {
-    let mut app = App::new(None, "secret".into());
+    let mut app = App::new(None, "a-string-secret-at-least-256-bits-long".into());
 
     app.data.encoder.payload.input = vec![
       "{",
@@ -192,14 +394,14 @@ mod tests {
       r#"┌ Header: Algorithm & Token Type (<enter> edit | ┐┌ Signing Secret ────────────────────────────────┐"#,
       r#"│┌──────────────────────────────────────────────┐││Prepend 'b64:' for base64 encoded secret. Prepen│"#,
       r#"││{                                             │││┌──────────────────────────────────────────────┐│"#,
-      r#"││  "alg": "HS256",                             ││││secret                                        ││"#,
+      r#"││  "alg": "HS256",                             ││││••••••••••••••••••••••••••••••••••••••        ││"#,
       r#"││  "typ": "JWT"                                │││└──────────────────────────────────────────────┘│"#,
       r#"││}                                             ││└────────────────────────────────────────────────┘"#,
       r#"│└──────────────────────────────────────────────┘│┌ Encoded Token ─────────────────────────────────┐"#,
       r#"└────────────────────────────────────────────────┘│eyJ0eXAiOiJKV1QiLCJhbGciOiJIUzI1NiJ9.eyJhZG1pbiI│"#,
       r#"┌ Payload: Claims ───────────────────────────────┐│6dHJ1ZSwiaWF0IjoxNTE2MjM5MDIyLCJuYW1lIjoiSm9obiB│"#,
-      r#"│┌──────────────────────────────────────────────┐││Eb2UiLCJzdWIiOiIxMjM0NTY3ODkwIn0.g7Ern-srhIi_7ZX│"#,
-      r#"││{                                             │││qrl6uyey7xxWJjr-LTn4p2Nv-DOY                    │"#,
+      r#"│┌──────────────────────────────────────────────┐││Eb2UiLCJzdWIiOiIxMjM0NTY3ODkwIn0.v0ZSU_jZMt5vXEx│"#,
+      r#"││{                                             │││A5otw2zH2wPgDgb1_RAKOoYT-dto                    │"#,
       r#"││  "sub": "1234567890",                        │││                                                │"#,
       r#"││  "name": "John Doe",                         │││                                                │"#,
       r#"││  "admin": true,                              │││                                                │"#,
@@ -270,4 +472,125 @@ mod tests {
 
     terminal.backend().assert_buffer(&expected);
   }
+
+  #[test]
+  fn test_draw_encoder_templates_popup() {
+    let mut app = App::new(None, "a-string-secret-at-least-256-bits-long".into());
+    app.push_navigation_stack(RouteId::Encoder, ActiveBlock::EncoderTemplates);
+    app.templates.state.select(Some(2));
+
+    let backend = TestBackend::new(100, 30);
+    let mut terminal = Terminal::new(backend).unwrap();
+    terminal
+      .draw(|f| {
+        draw_encoder(f, &mut app, f.area());
+      })
+      .unwrap();
+
+    let buffer = terminal.backend().buffer();
+    let line = |row: u16| -> String {
+      (10..90)
+        .map(|col| buffer[(col, row)].symbol().to_string())
+        .collect()
+    };
+    assert!(line(3).starts_with("┌ Templates | <enter> load "));
+    assert!(line(3).contains("┌ Header & Payload "));
+    assert!(line(4).contains("Auth0 access token"));
+    assert!(line(6).contains("=> Keycloak access token"));
+    assert!(line(5).contains(r#""alg": "HS256","#));
+    assert!((4..27)
+      .map(line)
+      .any(|l| l.contains(r#""preferred_username": "john.doe","#)));
+  }
+
+  #[test]
+  fn test_draw_encoder_json_error() {
+    let mut app = App::new(None, "a-string-secret-at-least-256-bits-long".into());
+    app.data.encoder.payload.input = vec!["{", r#"  "sub" "1234","#, "}"].into();
+    app.push_navigation_stack(RouteId::Encoder, ActiveBlock::EncoderPayload);
+
+    let backend = TestBackend::new(100, 20);
+    let mut terminal = Terminal::new(backend).unwrap();
+    terminal
+      .draw(|f| {
+        draw_encoder(f, &mut app, f.area());
+      })
+      .unwrap();
+
+    let buffer = terminal.backend().buffer();
+    let line = |row: u16| -> String {
+      (0..50)
+        .map(|col| buffer[(col, row)].symbol().to_string())
+        .collect()
+    };
+    assert!(line(0).starts_with("┌ Header: Algorithm & Token Type (<enter> edit"));
+    assert!(line(8).starts_with("┌ Payload: Claims | invalid JSON at 2:9 "));
+    let failure = style_failure(Theme::Dark).fg;
+    assert_eq!(buffer[(2, 10)].fg, COLOR_WHITE);
+    assert_eq!(buffer[(10, 11)].symbol(), "\"");
+    assert_eq!(buffer[(10, 11)].fg, failure.unwrap());
+    assert!(buffer[(10, 11)].modifier.contains(Modifier::REVERSED));
+    assert_eq!(buffer[(2, 11)].fg, failure.unwrap());
+    assert!(!buffer[(2, 11)].modifier.contains(Modifier::REVERSED));
+  }
+
+  #[test]
+  fn test_draw_encoder_token_scroll() {
+    let mut app = App::new(None, "a-string-secret-at-least-256-bits-long".into());
+    app.data.encoder.payload.input = vec![format!(r#"{{"data": "{}"}}"#, "a".repeat(800))].into();
+    app.route_encoder();
+    app.on_tick();
+    app.data.encoder.encoded.offset = 100;
+
+    let backend = TestBackend::new(100, 20);
+    let mut terminal = Terminal::new(backend).unwrap();
+    terminal
+      .draw(|f| {
+        draw_encoder(f, &mut app, f.area());
+      })
+      .unwrap();
+
+    // the token doesn't scroll past its last line
+    let token = app.data.encoder.encoded.get_txt();
+    let lines = token.len().div_ceil(48) as u16;
+    assert_eq!(app.data.encoder.encoded.offset, lines - 12);
+    let buffer = terminal.backend().buffer();
+    let last_line: String = (51..99).map(|col| buffer[(col, 18)].symbol()).collect();
+    assert!(token.ends_with(last_line.trim_end()));
+  }
+
+  #[test]
+  fn test_draw_encoder_preview() {
+    let mut app = App::new(None, "a-string-secret-at-least-256-bits-long".into());
+    app.data.encoder.payload.input = vec![r#"{"sub": "1234", "exp": "+1h"}"#].into();
+    app.data.encoder.preview = true;
+    app.route_encoder();
+    app.on_tick();
+
+    let backend = TestBackend::new(100, 30);
+    let mut terminal = Terminal::new(backend).unwrap();
+    terminal
+      .draw(|f| {
+        draw_encoder(f, &mut app, f.area());
+      })
+      .unwrap();
+
+    let buffer = terminal.backend().buffer();
+    let lines: Vec<String> = (0..30)
+      .map(|row| {
+        (50..100)
+          .map(|col| buffer[(col, row)].symbol().to_string())
+          .collect()
+      })
+      .collect();
+    let preview = lines
+      .iter()
+      .position(|l| l.starts_with("┌ Decoded Token | <v> hide "))
+      .unwrap();
+    assert!(lines[preview + 1].starts_with("│{"));
+    assert!(lines[preview + 2].contains(r#""alg": "HS256","#));
+    // the relative exp is signed as a timestamp
+    let exp = lines.iter().find(|l| l.contains(r#""exp": "#)).unwrap();
+    assert!(!exp.contains("+1h"));
+  }
 }
@@ -1,20 +1,30 @@
 use ratatui::{
   layout::{Constraint, Rect},
-  widgets::{Row, Table},
+  text::Line,
+  widgets::{Clear, Row, Table},
   Frame,
 };
 
 use super::{
   utils::{
-    layout_block_with_line, style_highlight, style_primary, style_secondary, title_with_dual_style,
-    vertical_chunks,
+    highlight_matches, layout_block_with_line, render_input_widget, style_highlight,
+    style_main_background, style_primary, style_secondary, title_with_dual_style, vertical_chunks,
   },
   HIGHLIGHT,
 };
 use crate::app::App;
 
+/// popup with the filter input above the keybindings matching it
 pub fn draw_help(f: &mut Frame<'_>, app: &mut App, area: Rect) {
-  let chunks = vertical_chunks(vec![Constraint::Percentage(100)], area);
+  let title = title_with_dual_style(" Help ".into(), "| type to filter | close <esc> ".into());
+  let block = layout_block_with_line(title, app.theme, true);
+  let chunks = vertical_chunks(
+    vec![Constraint::Length(3), Constraint::Min(0)],
+    block.inner(area),
+  );
+  f.render_widget(Clear, area);
+  f.render_widget(block.style(style_main_background(app.theme)), area);
+  render_input_widget(f, chunks[0], &app.help_filter, app.theme, false);
 
   // Create a one-column table to avoid flickering due to non-determinism when
   // resolving constraints on widths of table columns.
@@ -30,143 +40,112 @@ pub fn draw_help(f: &mut Frame<'_>, app: &mut App, area: Rect) {
     .iter()
     .map(format_row)
     .collect::<Vec<Vec<String>>>();
-  let help_docs = &help_docs[0_usize..];
 
-  let rows = help_docs
-    .iter()
-    .map(|item| Row::new(item.clone()).style(style_primary(app.light_theme)));
-
-  let title = title_with_dual_style(" Help ".into(), "| close <esc> ".into());
+  let filter = app.help_filter.input.value();
+  let rows = help_docs.iter().map(|item| {
+    let cells = item
+      .iter()
+      .map(|cell| highlight_matches(Line::from(cell.clone()), filter));
+    Row::new(cells).style(style_primary(app.theme))
+  });
 
   let help_menu = Table::new(rows, [Constraint::Percentage(100)])
     .header(
       Row::new(header)
-        .style(style_secondary(app.light_theme))
+        .style(style_secondary(app.theme))
         .bottom_margin(0),
     )
-    .block(layout_block_with_line(title, app.light_theme, true))
     .row_highlight_style(style_highlight())
     .highlight_symbol(HIGHLIGHT);
-  f.render_stateful_widget(help_menu, chunks[0], &mut app.help_docs.state);
+  f.render_stateful_widget(help_menu, chunks[1], &mut app.help_docs.state);
 }
 
 #[cfg(test)]
 mod tests {
-  use ratatui::{
-    backend::TestBackend,
-    buffer::Buffer,
-    layout::Position,
-    style::{Modifier, Style},
-    Terminal,
-  };
+  use ratatui::{backend::TestBackend, layout::Position, style::Modifier, Terminal};
 
   use super::*;
-  use crate::ui::utils::{COLOR_CYAN, COLOR_WHITE, COLOR_YELLOW};
+  use crate::ui::utils::{COLOR_CYAN, COLOR_YELLOW};
 
-  #[test]
-  fn test_draw_help() {
-    let backend = TestBackend::new(110, 7);
+  fn render(app: &mut App) -> Terminal<TestBackend> {
+    let backend = TestBackend::new(110, 10);
     let mut terminal = Terminal::new(backend).unwrap();
-
     terminal
       .draw(|f| {
         let size = f.area();
-        let mut app = App::default();
-        draw_help(f, &mut app, size);
+        draw_help(f, app, size);
       })
       .unwrap();
+    terminal
+  }
+
+  fn lines(terminal: &Terminal<TestBackend>) -> Vec<String> {
+    let buffer = terminal.backend().buffer();
+    (0..buffer.area.height)
+      .map(|y| {
+        (0..buffer.area.width)
+          .map(|x| buffer[(x, y)].symbol())
+          .collect::<String>()
+      })
+      .collect()
+  }
 
-    let mut expected = Buffer::with_lines(vec![
-        "┌ Help | close <esc> ────────────────────────────────────────────────────────────────────────────────────────┐",
+  #[test]
+  fn test_draw_help() {
+    let mut app = App::default();
+    app.open_help();
+    let terminal = render(&mut app);
+    assert_eq!(
+      lines(&terminal),
+      [
+        "┌ Help | type to filter | close <esc> ───────────────────────────────────────────────────────────────────────┐",
+        "│┌──────────────────────────────────────────────────────────────────────────────────────────────────────────┐│",
+        "││                                                                                                          ││",
+        "│└──────────────────────────────────────────────────────────────────────────────────────────────────────────┘│",
         "│   Key                                               Action                                            Conte│",
         "│=> <Ctrl+c> | <q>                                    Quit                                              Gener│",
         "│   <Esc>                                             Close child page/Go back/Stop editing             Gener│",
-        "│   <?>                                               Help page                                         Gener│",
+        "│   <?>                                               Help popup, type to filter the keybindings        Gener│",
         "│   <Ctrl+r>                                          Refresh UI                                        Gener│",
         "└────────────────────────────────────────────────────────────────────────────────────────────────────────────┘",
-      ]);
-    // set row styles
-    // First row heading style
-    for col in 0..=109 {
-      match col {
-        0 | 7..=109 => {
-          expected
-            .cell_mut(Position::new(col, 0))
-            .unwrap()
-            .set_style(Style::default().fg(COLOR_YELLOW));
-        }
-        1..=6 => {
-          expected.cell_mut(Position::new(col, 0)).unwrap().set_style(
-            Style::default()
-              .fg(COLOR_YELLOW)
-              .add_modifier(Modifier::BOLD),
-          );
-        }
-        _ => {
-          expected.cell_mut(Position::new(col, 0)).unwrap().set_style(
-            Style::default()
-              .fg(COLOR_WHITE)
-              .add_modifier(Modifier::BOLD),
-          );
-        }
-      }
-    }
-
-    // second row table headings
-    for col in 0..=109 {
-      expected
-        .cell_mut(Position::new(col, 1))
-        .unwrap()
-        .set_style(Style::default().fg(COLOR_YELLOW));
-    }
-
-    // first table data row style
-    for col in 0..=109 {
-      match col {
-        1..=108 => {
-          expected.cell_mut(Position::new(col, 2)).unwrap().set_style(
-            Style::default()
-              .fg(COLOR_CYAN)
-              .add_modifier(Modifier::REVERSED),
-          );
-        }
-        _ => {
-          expected
-            .cell_mut(Position::new(col, 2))
-            .unwrap()
-            .set_style(Style::default().fg(COLOR_YELLOW));
-        }
-      }
-    }
-
-    // rows
-    for row in 3..=5 {
-      for col in 0..=109 {
-        match col {
-          1..=108 => {
-            expected
-              .cell_mut(Position::new(col, row))
-              .unwrap()
-              .set_style(Style::default().fg(COLOR_CYAN));
-          }
-          _ => {
-            expected
-              .cell_mut(Position::new(col, row))
-              .unwrap()
-              .set_style(Style::default().fg(COLOR_YELLOW));
-          }
-        }
-      }
-    }
-
-    // last row
-    for col in 0..=109 {
-      expected
-        .cell_mut(Position::new(col, 6))
-        .unwrap()
-        .set_style(Style::default().fg(COLOR_YELLOW));
-    }
+      ]
+    );
+    let buffer = terminal.backend().buffer();
+    assert_eq!(buffer[(1, 4)].fg, COLOR_YELLOW);
+    assert_eq!(
+      (buffer[(1, 5)].fg, buffer[(1, 5)].modifier),
+      (COLOR_CYAN, Modifier::REVERSED)
+    );
+    assert_eq!(
+      (buffer[(4, 6)].fg, buffer[(4, 6)].modifier),
+      (COLOR_CYAN, Modifier::empty())
+    );
+  }
 
-    terminal.backend().assert_buffer(&expected);
+  #[test]
+  fn test_draw_help_filtered() {
+    let mut app = App::default();
+    app.open_help();
+    app.help_filter.insert_str("toggle theme");
+    app.filter_help_docs();
+    let terminal = render(&mut app);
+    let rows = lines(&terminal);
+    assert!(rows[2].starts_with("││toggle theme "));
+    assert!(rows[5].starts_with("│=> <t>") && rows[5].contains("Toggle theme"));
+    assert!(rows[6].starts_with("│   <Space> <t>") && rows[6].contains("Toggle theme"));
+    assert!(rows[7].trim_matches(['│', ' ']).is_empty());
+    // the matches of the filter are highlighted in the rows that aren't selected
+    app.help_docs.state.select(None);
+    let mut terminal = render(&mut app);
+    let column = lines(&terminal)[5].find("Toggle theme").unwrap() as u16 - 2;
+    let buffer = terminal.backend().buffer();
+    assert!(buffer[(column, 5)].modifier.contains(Modifier::REVERSED));
+    assert!(!buffer[(column - 1, 5)]
+      .modifier
+      .contains(Modifier::REVERSED));
+    assert_eq!(
+      terminal.get_cursor_position().unwrap(),
+      Position::new(14, 2)
+    );
   }
 }
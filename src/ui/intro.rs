@@ -0,0 +1,163 @@
+use ratatui::{
+  layout::{Constraint, Rect},
+  text::{Line, Span},
+  widgets::{Paragraph, Row, Table, Wrap},
+  Frame,
+};
+
+use super::{
+  utils::{
+    centered_rect, layout_block_with_line, style_default, style_highlight, style_primary,
+    style_secondary, title_with_dual_style, vertical_chunks, vertical_chunks_with_margin,
+  },
+  HIGHLIGHT,
+};
+use crate::app::{
+  key_binding::{KeyBinding, DEFAULT_KEYBINDING},
+  App, INTRO_RECENT_TOKENS,
+};
+
+/// quick actions of the landing screen, with their key
+fn quick_actions() -> [(String, &'static str); 5] {
+  let action = |binding: &KeyBinding| (binding.key.to_string(), binding.desc);
+  [
+    action(&DEFAULT_KEYBINDING.intro_paste),
+    action(&DEFAULT_KEYBINDING.intro_open_file),
+    action(&DEFAULT_KEYBINDING.intro_encoder),
+    ("<enter>".into(), "Decode the selected recent token"),
+    ("<esc>".into(), DEFAULT_KEYBINDING.intro_decoder.desc),
+  ]
+}
+
+/// landing screen shown instead of the empty decoder, with the quick actions and recent tokens
+pub fn draw_intro(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+  let theme = app.theme;
+  let Some(intro) = app.intro.as_mut() else {
+    return;
+  };
+  let area = centered_rect(80, 90, area);
+  let title = title_with_dual_style(
+    " Welcome to JWT UI ".into(),
+    "| <tab> switch tabs | <?> help | <q> quit ".into(),
+  );
+  let block = layout_block_with_line(title, theme, true);
+  let chunks = vertical_chunks_with_margin(
+    vec![
+      Constraint::Length(3),
+      Constraint::Length(quick_actions().len() as u16 + 1),
+      Constraint::Length(1),
+      Constraint::Min(0),
+    ],
+    block.inner(area),
+    1,
+  );
+  f.render_widget(block, area);
+
+  let welcome = Paragraph::new(
+    "Decode, verify and encode JSON Web Tokens. Start with one of the actions below.",
+  )
+  .style(style_default(theme))
+  .wrap(Wrap { trim: true });
+  f.render_widget(welcome, chunks[0]);
+
+  let actions: Vec<Line<'_>> = quick_actions()
+    .into_iter()
+    .map(|(key, action)| {
+      Line::from(vec![
+        Span::styled(format!("{key:9}"), style_secondary(theme)),
+        Span::styled(action, style_primary(theme)),
+      ])
+    })
+    .collect();
+  f.render_widget(Paragraph::new(actions), chunks[1]);
+
+  f.render_widget(
+    Paragraph::new("Recent tokens").style(style_secondary(theme)),
+    chunks[2],
+  );
+  if intro.recent.items.is_empty() {
+    let hint = Paragraph::new(
+      "No recent tokens. Enable the token history with `[history] enabled = true` in the config file to list the last decoded tokens here.",
+    )
+    .style(style_default(theme))
+    .wrap(Wrap { trim: true });
+    f.render_widget(hint, chunks[3]);
+    return;
+  }
+  let rows = intro.recent.items.iter().map(|entry| {
+    Row::new(vec![
+      entry.decoded_at_text(),
+      entry.iss.clone().unwrap_or_default(),
+      entry.sub.clone().unwrap_or_default(),
+    ])
+    .style(style_primary(theme))
+  });
+  let table = Table::new(
+    rows,
+    [
+      Constraint::Length(17),
+      Constraint::Percentage(50),
+      Constraint::Percentage(50),
+    ],
+  )
+  .row_highlight_style(style_highlight())
+  .highlight_symbol(HIGHLIGHT);
+  let area = vertical_chunks(
+    vec![
+      Constraint::Length(INTRO_RECENT_TOKENS as u16),
+      Constraint::Min(0),
+    ],
+    chunks[3],
+  )[0];
+  f.render_stateful_widget(table, area, &mut intro.recent.state);
+}
+
+#[cfg(test)]
+mod tests {
+  use ratatui::{backend::TestBackend, Terminal};
+
+  use super::*;
+  use crate::app::history::HistoryEntry;
+
+  fn draw_lines(app: &mut App) -> Vec<String> {
+    let backend = TestBackend::new(100, 24);
+    let mut terminal = Terminal::new(backend).unwrap();
+    terminal
+      .draw(|f| {
+        draw_intro(f, app, f.area());
+      })
+      .unwrap();
+    let buffer = terminal.backend().buffer();
+    (0..24)
+      .map(|row| {
+        (0..100)
+          .map(|col| buffer[(col, row)].symbol().to_string())
+          .collect::<String>()
+          .trim_end()
+          .to_string()
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_draw_intro() {
+    let mut app = App::new(None, String::new());
+    app.open_intro();
+    let lines = draw_lines(&mut app);
+    assert!(lines[1].contains(" Welcome to JWT UI "));
+    assert!(lines[6].contains("│ <p>      Paste a copied token, or paste it in the terminal"));
+    assert!(lines[12].contains("│ Recent tokens"));
+    assert!(lines[13].contains("No recent tokens. Enable the token history"));
+
+    app.token_history.entries = vec![HistoryEntry {
+      token: "first.token.sig".into(),
+      iss: Some("https://accounts.google.com".into()),
+      sub: Some("alice".into()),
+      decoded_at: 1_700_000_000,
+      redacted: false,
+    }];
+    app.open_intro();
+    let lines = draw_lines(&mut app);
+    assert!(lines[13].contains("=> 2023-11-14 22:13  https://accounts.google.com alice"));
+  }
+}
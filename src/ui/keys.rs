@@ -0,0 +1,178 @@
+use ratatui::{
+  layout::{Constraint, Rect},
+  text::{Line, Text},
+  widgets::{Paragraph, Row, Table, Wrap},
+  Frame,
+};
+
+use super::{
+  utils::{
+    layout_block_with_line, style_highlight, style_primary, style_secondary, title_with_dual_style,
+    vertical_chunks,
+  },
+  HIGHLIGHT,
+};
+use crate::app::{key_store::StoredKey, App};
+
+pub fn draw_keys(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+  let chunks = vertical_chunks(vec![Constraint::Min(0), Constraint::Length(6)], area);
+  draw_keys_table(f, app, chunks[0]);
+  draw_key_details(f, app, chunks[1]);
+}
+
+fn draw_keys_table(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+  let title = title_with_dual_style(
+    " Keys ".into(),
+    "| <i> import | <g> generate | <v> verify | <s> sign ".into(),
+  );
+  let block = layout_block_with_line(title, app.theme, true);
+
+  if app.data.keys.keys.items.is_empty() {
+    let dir = app
+      .data
+      .keys
+      .dir
+      .as_ref()
+      .map(|dir| format!(" in {}", dir.display()))
+      .unwrap_or_default();
+    let paragraph = Paragraph::new(format!(
+      "No keys{dir}, press <i> to import a key file or <g> to generate a key pair"
+    ))
+    .style(style_primary(app.theme))
+    .block(block)
+    .wrap(Wrap { trim: true });
+    f.render_widget(paragraph, area);
+    return;
+  }
+
+  let rows = app.data.keys.keys.items.iter().map(|key| {
+    Row::new(vec![
+      key.name.clone(),
+      key.key_type.clone(),
+      key.size.clone(),
+      visibility(key).into(),
+    ])
+    .style(style_primary(app.theme))
+  });
+  let table = Table::new(
+    rows,
+    [
+      Constraint::Min(20),
+      Constraint::Length(8),
+      Constraint::Length(12),
+      Constraint::Length(8),
+    ],
+  )
+  .header(Row::new(vec!["Name", "Type", "Size", "Kind"]).style(style_secondary(app.theme)))
+  .block(block)
+  .row_highlight_style(style_highlight())
+  .highlight_symbol(HIGHLIGHT);
+  f.render_stateful_widget(table, area, &mut app.data.keys.keys.state);
+}
+
+fn draw_key_details(f: &mut Frame<'_>, app: &App, area: Rect) {
+  let title = title_with_dual_style(" Details ".into(), String::new());
+  let text = match app.data.keys.selected() {
+    Some(key) => Text::from(vec![
+      Line::from(format!("Path: {}", key.path.display())),
+      Line::from(format!(
+        "Type: {} {} ({})",
+        key.key_type,
+        key.size,
+        visibility(key)
+      )),
+      Line::from(format!(
+        "Thumbprint: {}",
+        key.thumbprint.as_deref().unwrap_or("-")
+      )),
+      Line::from(format!("Algorithm: {}", key.default_alg().unwrap_or("-"))),
+    ]),
+    None => Text::default(),
+  };
+  let paragraph = Paragraph::new(text)
+    .style(style_primary(app.theme))
+    .block(layout_block_with_line(title, app.theme, false))
+    .wrap(Wrap { trim: false });
+  f.render_widget(paragraph, area);
+}
+
+fn visibility(key: &StoredKey) -> &'static str {
+  match (key.thumbprint.is_some(), key.private) {
+    (false, _) => "secret",
+    (true, true) => "private",
+    (true, false) => "public",
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::PathBuf;
+
+  use ratatui::{backend::TestBackend, Terminal};
+
+  use super::*;
+  use crate::app::{key_store::KeyStore, models::StatefulTable};
+
+  fn render(app: &mut App) -> Vec<String> {
+    let backend = TestBackend::new(80, 12);
+    let mut terminal = Terminal::new(backend).unwrap();
+    terminal
+      .draw(|f| {
+        let size = f.area();
+        draw_keys(f, app, size);
+      })
+      .unwrap();
+    let buffer = terminal.backend().buffer();
+    (0..buffer.area.height)
+      .map(|y| {
+        (0..buffer.area.width)
+          .map(|x| buffer[(x, y)].symbol())
+          .collect::<String>()
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_draw_keys_empty() {
+    let mut app = App::default();
+    app.data.keys = KeyStore::new(Some(PathBuf::from("/keys")));
+
+    let lines = render(&mut app);
+    assert!(lines[0].starts_with("┌ Keys | <i> import | <g> generate | <v> verify | <s> sign "));
+    assert!(lines[1].contains("No keys in /keys, press <i> to import a key file"));
+  }
+
+  #[test]
+  fn test_draw_keys() {
+    let mut app = App::default();
+    app.data.keys.keys = StatefulTable::with_items(vec![
+      StoredKey {
+        name: "hmac.secret".into(),
+        path: PathBuf::from("/keys/hmac.secret"),
+        key_type: "Secret".into(),
+        size: "32 bytes".into(),
+        private: true,
+        thumbprint: None,
+      },
+      StoredKey {
+        name: "rsa.pub.pem".into(),
+        path: PathBuf::from("/keys/rsa.pub.pem"),
+        key_type: "RSA".into(),
+        size: "2048 bits".into(),
+        private: false,
+        thumbprint: Some("NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs".into()),
+      },
+    ]);
+    app.data.keys.keys.state.select(Some(1));
+
+    let lines = render(&mut app);
+    assert!(lines[1].contains("Name") && lines[1].contains("Kind"));
+    assert!(lines[2].contains("hmac.secret") && lines[2].contains("secret"));
+    assert!(lines[3].starts_with("│=> rsa.pub.pem"));
+    assert!(lines[3].contains("2048 bits") && lines[3].contains("public"));
+    assert!(lines[7].contains("Path: /keys/rsa.pub.pem"));
+    assert!(lines[8].contains("Type: RSA 2048 bits (public)"));
+    assert!(lines[9].contains("Thumbprint: NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"));
+    assert!(lines[10].contains("Algorithm: RS256"));
+  }
+}
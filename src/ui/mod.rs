@@ -1,13 +1,16 @@
 mod decoder;
 mod encoder;
 mod help;
+mod intro;
+mod keys;
+mod utilities;
 pub mod utils;
 
 use ratatui::{
   layout::{Alignment, Constraint, Rect},
   style::Modifier,
   text::{Line, Span, Text},
-  widgets::{Block, Borders, Paragraph, Tabs, Wrap},
+  widgets::{Block, Clear, Paragraph, Row, Table, Tabs, Wrap},
   Frame,
 };
 
@@ -15,17 +18,26 @@ use self::{
   decoder::draw_decoder,
   encoder::draw_encoder,
   help::draw_help,
+  intro::draw_intro,
+  keys::draw_keys,
+  utilities::draw_utilities,
   utils::{
-    horizontal_chunks_with_margin, style_default, style_failure, style_header, style_header_text,
-    style_help, style_main_background, style_primary, style_secondary, vertical_chunks,
+    bordered_block, centered_rect, highlight_json_line, horizontal_chunks_with_margin,
+    layout_block_with_line, mask_char, render_input_widget, style_default, style_failure,
+    style_header, style_header_text, style_help, style_highlight, style_main_background,
+    style_primary, style_secondary, style_success, style_warning, title_with_dual_style,
+    vertical_chunks,
   },
 };
-use crate::app::{App, RouteId};
+use crate::app::{
+  token_diff::{Change, TokenPart},
+  App, RouteId, COPY_HISTORY_SIZE,
+};
 
 pub static HIGHLIGHT: &str = "=> ";
 
 pub fn draw(f: &mut Frame<'_>, app: &mut App) {
-  let block = Block::default().style(style_main_background(app.light_theme));
+  let block = Block::default().style(style_main_background(app.theme));
   f.render_widget(block, f.area());
 
   let chunks = if !app.data.error.is_empty() {
@@ -58,32 +70,74 @@ pub fn draw(f: &mut Frame<'_>, app: &mut App) {
 
   let main_chunk = chunks[chunks.len() - 1];
 
-  match app.get_current_route().id {
-    RouteId::Help => {
-      draw_help(f, app, main_chunk);
-    }
+  // the help is drawn over the view it was opened from
+  match app.get_background_route().id {
+    RouteId::Help => { /* never in the background */ }
+    RouteId::Decoder if app.intro.is_some() => draw_intro(f, app, main_chunk),
     RouteId::Decoder => {
       draw_decoder(f, app, main_chunk);
     }
     RouteId::Encoder => {
       draw_encoder(f, app, main_chunk);
     }
+    RouteId::Keys => {
+      draw_keys(f, app, main_chunk);
+    }
+    RouteId::Utilities => {
+      draw_utilities(f, app, main_chunk);
+    }
+  }
+
+  if app.get_current_route().id == RouteId::Help {
+    draw_help(f, app, centered_rect(80, 90, main_chunk));
+  }
+  draw_toasts(f, app, main_chunk);
+  if app.confirm.is_some() {
+    draw_confirmation_popup(f, app, f.area());
+  }
+  if app.prompt.is_some() {
+    draw_prompt_popup(f, app, f.area());
+  }
+  if app.theme_picker.is_some() {
+    draw_theme_picker(f, app, f.area());
+  }
+  if app.show_copy_history {
+    draw_copy_history(f, app, f.area());
+  }
+  if app.history_picker.is_some() {
+    draw_history_picker(f, app, f.area());
+  }
+  if app.bookmark_picker.is_some() {
+    draw_bookmark_picker(f, app, f.area());
+  }
+  if app.token_picker.is_some() {
+    draw_token_picker(f, app, f.area());
+  }
+  if app.token_diff.is_some() {
+    draw_token_diff(f, app, f.area());
+  }
+  if app.introspection.is_some() {
+    draw_introspection(f, app, f.area());
   }
 }
 
 fn draw_app_title(f: &mut Frame<'_>, app: &App, area: Rect) {
   let title = Paragraph::new(Span::styled(
     app.title,
-    style_header_text(app.light_theme).add_modifier(Modifier::BOLD),
+    style_header_text(app.theme).add_modifier(Modifier::BOLD),
   ))
   .style(style_header())
   .block(Block::default())
   .alignment(Alignment::Left);
   f.render_widget(title, area);
 
-  let text = format!("v{} with ♥ from Auth0 by Okta ", env!("CARGO_PKG_VERSION"),);
+  let text = format!(
+    "tick {}ms | v{} with ♥ from Auth0 by Okta ",
+    app.tick_rate,
+    env!("CARGO_PKG_VERSION"),
+  );
 
-  let meta = Paragraph::new(Span::styled(text, style_header_text(app.light_theme)))
+  let meta = Paragraph::new(Span::styled(text, style_header_text(app.theme)))
     .style(style_header())
     .block(Block::default())
     .alignment(Alignment::Right);
@@ -98,11 +152,11 @@ fn draw_app_header(f: &mut Frame<'_>, app: &App, area: Rect) {
     .main_tabs
     .items
     .iter()
-    .map(|t| Line::from(Span::styled(&t.title, style_default(app.light_theme))))
+    .map(|t| Line::from(Span::styled(&t.title, style_default(app.theme))))
     .collect();
   let tabs = Tabs::new(titles)
-    .block(Block::default().borders(Borders::ALL))
-    .highlight_style(style_secondary(app.light_theme))
+    .block(bordered_block(app.theme))
+    .highlight_style(style_secondary(app.theme))
     .select(app.main_tabs.index);
 
   f.render_widget(tabs, area);
@@ -110,33 +164,393 @@ fn draw_app_header(f: &mut Frame<'_>, app: &App, area: Rect) {
 }
 
 fn draw_header_text(f: &mut Frame<'_>, app: &App, area: Rect) {
-  let text: Vec<Line<'_>> = match app.get_current_route().id {
+  let text: Vec<Line<'_>> = match app.get_background_route().id {
     RouteId::Decoder => vec![Line::from(
-      "<?> help | <tab> switch tabs | <←→>, <click> select block | <u> toggle UTC dates | <↑↓> scroll ",
+      "<?> help | <tab> switch tabs | <←→>, <click> select block | <u> toggle UTC dates | <o> edit in encoder | </> search | <↑↓> scroll ",
     )],
     RouteId::Encoder => vec![Line::from(
-      "<?> help | <tab> switch tabs | <←→>, <click> select block | <↑↓> scroll ",
+      "<?> help | <tab> switch tabs | <←→>, <click> select block | <i> auto iat | <x> exp | <n> nbf | <u> jti | <o> nonce | <p> templates | <a> auto kid ",
+    )],
+    RouteId::Keys => vec![Line::from(
+      "<?> help | <tab> switch tabs | <i> import | <g> generate | <↑↓> select ",
+    )],
+    RouteId::Utilities => vec![Line::from(
+      "<?> help | <tab> switch tabs | <e> edit | <↑↓> select | <c> copy ",
     )],
     RouteId::Help => vec![],
   };
   let paragraph = Paragraph::new(text)
-    .style(style_help(app.light_theme))
+    .style(style_help(app.theme))
     .block(Block::default())
     .alignment(Alignment::Right);
   f.render_widget(paragraph, area);
 }
 
+fn draw_confirmation_popup(f: &mut Frame<'_>, app: &App, area: Rect) {
+  let Some(confirmation) = app.confirm.as_ref() else {
+    return;
+  };
+  let area = vertical_chunks(
+    vec![
+      Constraint::Min(0),
+      Constraint::Length(5),
+      Constraint::Min(0),
+    ],
+    centered_rect(60, 100, area),
+  )[1];
+  let title = title_with_dual_style(" Confirm ".into(), "| <y> yes | <n> no ".into());
+
+  let mut text = Text::from(confirmation.message.clone());
+  text = text.patch_style(style_primary(app.theme));
+
+  let paragraph = Paragraph::new(text)
+    .style(style_main_background(app.theme))
+    .block(layout_block_with_line(title, app.theme, true))
+    .wrap(Wrap { trim: true });
+
+  f.render_widget(Clear, area);
+  f.render_widget(paragraph, area);
+}
+
+/// themes with a sample of their colors, the selected theme is previewed on the whole UI
+fn draw_theme_picker(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+  let theme = app.theme;
+  let Some(picker) = app.theme_picker.as_mut() else {
+    return;
+  };
+  let area = vertical_chunks(
+    vec![
+      Constraint::Min(0),
+      Constraint::Length(picker.themes.items.len() as u16 + 3),
+      Constraint::Min(0),
+    ],
+    centered_rect(60, 100, area),
+  )[1];
+  let title = title_with_dual_style(" Themes ".into(), "| <enter> pick | <esc> cancel ".into());
+
+  let rows = picker.themes.items.iter().map(|item| {
+    let sample = highlight_json_line("\"sub\": \"1234\", \"iat\": 1, \"ok\": true", *item)
+      .patch_style(style_main_background(*item));
+    Row::new(vec![Line::from(item.name()), sample]).style(style_primary(theme))
+  });
+  let table = Table::new(rows, [Constraint::Length(16), Constraint::Min(0)])
+    .header(Row::new(vec!["Name", "Sample"]).style(style_secondary(theme)))
+    .block(layout_block_with_line(title, theme, true).style(style_main_background(theme)))
+    .row_highlight_style(style_highlight())
+    .highlight_symbol(HIGHLIGHT);
+
+  f.render_widget(Clear, area);
+  f.render_stateful_widget(table, area, &mut picker.themes.state);
+}
+
+/// the last copied values, newest first. Only the first line of multi-line values is shown
+fn draw_copy_history(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+  let theme = app.theme;
+  let area = vertical_chunks(
+    vec![
+      Constraint::Min(0),
+      Constraint::Length(COPY_HISTORY_SIZE as u16 + 2),
+      Constraint::Min(0),
+    ],
+    centered_rect(80, 100, area),
+  )[1];
+  let title = title_with_dual_style(
+    " Copy history ".into(),
+    "| <enter> copy again | <esc> close ".into(),
+  );
+
+  let rows = app.copy_history.items.iter().map(|item| {
+    let mut lines = item.content.lines();
+    let first = match item.masked {
+      true => mask_char(theme).repeat(8),
+      false => lines.next().unwrap_or_default().trim().to_string(),
+    };
+    let value = match lines.count() {
+      more if more > 0 && !item.masked => format!("{first} (+{more} lines)"),
+      _ => first,
+    };
+    Row::new(vec![value]).style(style_primary(theme))
+  });
+  let table = Table::new(rows, [Constraint::Min(0)])
+    .block(layout_block_with_line(title, theme, true).style(style_main_background(theme)))
+    .row_highlight_style(style_highlight())
+    .highlight_symbol(HIGHLIGHT);
+
+  f.render_widget(Clear, area);
+  f.render_stateful_widget(table, area, &mut app.copy_history.state);
+}
+
+/// header fields and claims changed since the token of the history, header fields first
+fn draw_token_diff(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+  let theme = app.theme;
+  let Some(diff) = app.token_diff.as_mut() else {
+    return;
+  };
+  let area = centered_rect(80, 60, area);
+  let title = title_with_dual_style(
+    format!(" Changes since the token decoded {} ", diff.decoded_at),
+    "| <esc> close ".into(),
+  );
+
+  let rows = diff.changes.items.iter().map(|change| {
+    let part = match change.part {
+      TokenPart::Header => "header",
+      TokenPart::Payload => "payload",
+    };
+    let style = match change.change {
+      Change::Added(_) => style_success(theme),
+      Change::Removed(_) => style_failure(theme),
+      Change::Changed(..) => style_warning(theme),
+    };
+    Row::new(vec![
+      part.to_string(),
+      change.name.clone(),
+      change.describe(),
+    ])
+    .style(style)
+  });
+  let table = Table::new(
+    rows,
+    [
+      Constraint::Length(8),
+      Constraint::Percentage(25),
+      Constraint::Percentage(75),
+    ],
+  )
+  .header(Row::new(vec!["Part", "Field", "Change"]).style(style_secondary(theme)))
+  .block(layout_block_with_line(title, theme, true).style(style_main_background(theme)))
+  .row_highlight_style(style_highlight())
+  .highlight_symbol(HIGHLIGHT);
+
+  f.render_widget(Clear, area);
+  f.render_stateful_widget(table, area, &mut diff.changes.state);
+}
+
+/// members of the introspection response next to the claims of the decoded token, the ones the
+/// server disagrees on highlighted
+fn draw_introspection(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+  let theme = app.theme;
+  let Some(introspection) = app.introspection.as_mut() else {
+    return;
+  };
+  let area = centered_rect(80, 60, area);
+  let status = if introspection.active {
+    "active"
+  } else {
+    "not active"
+  };
+  let title = title_with_dual_style(
+    format!(" Introspection: the token is {status} "),
+    "| <esc> close ".into(),
+  );
+
+  let rows = introspection.claims.items.iter().map(|claim| {
+    let style = if claim.is_discrepancy() {
+      style_failure(theme)
+    } else if claim.decoded.is_some() {
+      style_success(theme)
+    } else {
+      style_primary(theme)
+    };
+    Row::new(vec![
+      claim.name.clone(),
+      claim.introspected.to_string(),
+      claim
+        .decoded
+        .as_ref()
+        .map(|value| value.to_string())
+        .unwrap_or_default(),
+    ])
+    .style(style)
+  });
+  let table = Table::new(
+    rows,
+    [
+      Constraint::Percentage(20),
+      Constraint::Percentage(40),
+      Constraint::Percentage(40),
+    ],
+  )
+  .header(Row::new(vec!["Claim", "Introspection", "Decoded"]).style(style_secondary(theme)))
+  .block(layout_block_with_line(title, theme, true).style(style_main_background(theme)))
+  .row_highlight_style(style_highlight())
+  .highlight_symbol(HIGHLIGHT);
+
+  f.render_widget(Clear, area);
+  f.render_stateful_widget(table, area, &mut introspection.claims.state);
+}
+
+/// bookmarks by name, secrets entered as text are masked
+fn draw_bookmark_picker(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+  let theme = app.theme;
+  let Some(picker) = app.bookmark_picker.as_mut() else {
+    return;
+  };
+  let area = centered_rect(80, 60, area);
+  let title = title_with_dual_style(
+    " Bookmarks ".into(),
+    "| <enter> use | <a> bookmark the secret | <d> remove | <esc> close ".into(),
+  );
+
+  let rows = picker.bookmarks.items.iter().map(|bookmark| {
+    let value = match bookmark.is_secret() {
+      true => mask_char(theme).repeat(8),
+      false => bookmark.value.clone(),
+    };
+    Row::new(vec![bookmark.name.clone(), value]).style(style_primary(theme))
+  });
+  let table = Table::new(
+    rows,
+    [Constraint::Percentage(30), Constraint::Percentage(70)],
+  )
+  .header(Row::new(vec!["Name", "Secret"]).style(style_secondary(theme)))
+  .block(layout_block_with_line(title, theme, true).style(style_main_background(theme)))
+  .row_highlight_style(style_highlight())
+  .highlight_symbol(HIGHLIGHT);
+
+  f.render_widget(Clear, area);
+  f.render_stateful_widget(table, area, &mut picker.bookmarks.state);
+}
+
+/// recorded tokens matching the filter, newest first
+fn draw_history_picker(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+  let theme = app.theme;
+  let Some(picker) = app.history_picker.as_mut() else {
+    return;
+  };
+  let area = centered_rect(80, 70, area);
+  let title = title_with_dual_style(
+    " Token history ".into(),
+    "| type to filter | <enter> decode | <tab> diff with the decoded token | <esc> close ".into(),
+  );
+  let block = layout_block_with_line(title, theme, true).style(style_main_background(theme));
+  let chunks = vertical_chunks(
+    vec![Constraint::Length(3), Constraint::Min(0)],
+    block.inner(area),
+  );
+
+  let rows = picker.entries.items.iter().map(|entry| {
+    Row::new(vec![
+      entry.decoded_at_text(),
+      entry.iss.clone().unwrap_or_default(),
+      entry.sub.clone().unwrap_or_default(),
+    ])
+    .style(style_primary(theme))
+  });
+  let table = Table::new(
+    rows,
+    [
+      Constraint::Length(17),
+      Constraint::Percentage(50),
+      Constraint::Percentage(50),
+    ],
+  )
+  .header(Row::new(vec!["Decoded", "Issuer", "Subject"]).style(style_secondary(theme)))
+  .row_highlight_style(style_highlight())
+  .highlight_symbol(HIGHLIGHT);
+
+  f.render_widget(Clear, area);
+  f.render_widget(block, area);
+  render_input_widget(f, chunks[0], &picker.filter, theme, false);
+  f.render_stateful_widget(table, chunks[1], &mut picker.entries.state);
+}
+
+/// tokens given on the command line or found by a scan, in their order
+fn draw_token_picker(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+  let theme = app.theme;
+  let Some(picker) = app.token_picker.as_mut() else {
+    return;
+  };
+  let area = centered_rect(80, 60, area);
+  let title = title_with_dual_style(
+    format!(" Tokens ({}) ", picker.tokens.items.len()),
+    "| <enter> decode | <esc> close ".into(),
+  );
+
+  let rows = picker.tokens.items.iter().enumerate().map(|(i, scanned)| {
+    Row::new(vec![
+      (i + 1).to_string(),
+      scanned.kind.clone(),
+      scanned.preview.clone(),
+    ])
+    .style(style_primary(theme))
+  });
+  let table = Table::new(
+    rows,
+    [
+      Constraint::Length(4),
+      Constraint::Percentage(25),
+      Constraint::Percentage(75),
+    ],
+  )
+  .header(Row::new(vec!["#", "Token", "Claims"]).style(style_secondary(theme)))
+  .block(layout_block_with_line(title, theme, true).style(style_main_background(theme)))
+  .row_highlight_style(style_highlight())
+  .highlight_symbol(HIGHLIGHT);
+
+  f.render_widget(Clear, area);
+  f.render_stateful_widget(table, area, &mut picker.tokens.state);
+}
+
+/// toasts stacked in the bottom right corner of the view, newest at the bottom
+fn draw_toasts(f: &mut Frame<'_>, app: &App, area: Rect) {
+  let mut bottom = area.bottom();
+  for toast in app.toasts.iter().rev() {
+    let width = (toast.message.chars().count() as u16 + 4).min(area.width);
+    if bottom < area.y + 3 || width < 5 {
+      break;
+    }
+    let rect = Rect::new(area.right() - width, bottom - 3, width, 3);
+    let paragraph = Paragraph::new(toast.message.clone())
+      .style(style_main_background(app.theme).patch(style_success(app.theme)))
+      .block(bordered_block(app.theme));
+    f.render_widget(Clear, rect);
+    f.render_widget(paragraph, rect);
+    bottom -= 3;
+  }
+}
+
+fn draw_prompt_popup(f: &mut Frame<'_>, app: &App, area: Rect) {
+  let Some(prompt) = app.prompt.as_ref() else {
+    return;
+  };
+  let area = vertical_chunks(
+    vec![
+      Constraint::Min(0),
+      Constraint::Length(7),
+      Constraint::Min(0),
+    ],
+    centered_rect(60, 100, area),
+  )[1];
+  let title = title_with_dual_style(" Input ".into(), "| <enter> submit | <esc> cancel ".into());
+
+  let block = layout_block_with_line(title, app.theme, true);
+  let chunks = vertical_chunks(
+    vec![Constraint::Length(2), Constraint::Length(3)],
+    block.inner(area),
+  );
+
+  let mut text = Text::from(prompt.message.clone());
+  text = text.patch_style(style_primary(app.theme));
+  let paragraph = Paragraph::new(text)
+    .style(style_main_background(app.theme))
+    .wrap(Wrap { trim: true });
+
+  f.render_widget(Clear, area);
+  f.render_widget(block.style(style_main_background(app.theme)), area);
+  f.render_widget(paragraph, chunks[0]);
+  render_input_widget(f, chunks[1], &prompt.input, app.theme, false);
+}
+
 fn draw_app_error(f: &mut Frame<'_>, app: &App, size: Rect) {
-  let block = Block::default()
+  let block = bordered_block(app.theme)
     .title(" Error ")
-    .style(style_failure(app.light_theme))
-    .borders(Borders::ALL);
+    .style(style_failure(app.theme));
 
-  let mut text = Text::from(app.data.error.clone());
-  text = text.patch_style(style_failure(app.light_theme));
+  let text = Text::from(app.data.error.as_str()).patch_style(style_failure(app.theme));
 
   let paragraph = Paragraph::new(text)
-    .style(style_primary(app.light_theme))
+    .style(style_primary(app.theme))
     .block(block)
     .wrap(Wrap { trim: true });
   f.render_widget(paragraph, size);
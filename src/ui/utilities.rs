@@ -0,0 +1,98 @@
+use ratatui::{
+  layout::{Constraint, Rect},
+  widgets::{Row, Table},
+  Frame,
+};
+
+use super::{
+  utils::{
+    get_selectable_block, layout_block_with_line, render_input_widget, style_highlight,
+    style_primary, style_secondary, title_with_dual_style, vertical_chunks,
+    vertical_chunks_with_margin,
+  },
+  HIGHLIGHT,
+};
+use crate::app::{ActiveBlock, App};
+
+pub fn draw_utilities(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+  let chunks = vertical_chunks(vec![Constraint::Length(3), Constraint::Min(0)], area);
+  draw_input(f, app, chunks[0]);
+  draw_conversions(f, app, chunks[1]);
+}
+
+fn draw_input(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+  let block = get_selectable_block(
+    "Base64url, URL encoded text, UNIX time, date or +/- duration",
+    true,
+    Some(&app.data.utilities.input.input_mode),
+    app.theme,
+    false,
+  );
+  f.render_widget(block, area);
+
+  let chunks = vertical_chunks_with_margin(vec![Constraint::Min(1)], area, 1);
+  let input_area = render_input_widget(f, chunks[0], &app.data.utilities.input, app.theme, true);
+  app.update_input_map(ActiveBlock::Utilities, input_area);
+}
+
+fn draw_conversions(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+  let title = title_with_dual_style(" Conversions ".into(), "| <c> copy ".into());
+  let rows = app
+    .data
+    .utilities
+    .conversions
+    .items
+    .iter()
+    .map(|conversion| {
+      Row::new(vec![conversion.name, conversion.value.as_str()]).style(style_primary(app.theme))
+    });
+  let table = Table::new(rows, [Constraint::Length(18), Constraint::Min(20)])
+    .header(Row::new(vec!["Conversion", "Value"]).style(style_secondary(app.theme)))
+    .block(layout_block_with_line(title, app.theme, false))
+    .row_highlight_style(style_highlight())
+    .highlight_symbol(HIGHLIGHT);
+  f.render_stateful_widget(table, area, &mut app.data.utilities.conversions.state);
+}
+
+#[cfg(test)]
+mod tests {
+  use ratatui::{backend::TestBackend, Terminal};
+  use tui_input::Input;
+
+  use super::*;
+
+  fn render(app: &mut App) -> Vec<String> {
+    let backend = TestBackend::new(80, 10);
+    let mut terminal = Terminal::new(backend).unwrap();
+    terminal
+      .draw(|f| {
+        let size = f.area();
+        draw_utilities(f, app, size);
+      })
+      .unwrap();
+    let buffer = terminal.backend().buffer();
+    (0..buffer.area.height)
+      .map(|y| {
+        (0..buffer.area.width)
+          .map(|x| buffer[(x, y)].symbol())
+          .collect::<String>()
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_draw_utilities() {
+    let mut app = App::default();
+    app.data.utilities.input.input = Input::new("eyJhbGciOiJIUzI1NiJ9".into());
+    app.data.utilities.update(1_717_243_200);
+
+    let lines = render(&mut app);
+    assert!(lines[0].starts_with("┌ Base64url, URL encoded text, UNIX time"));
+    assert!(lines[1].contains("eyJhbGciOiJIUzI1NiJ9"));
+    assert!(lines[3].starts_with("┌ Conversions | <c> copy "));
+    assert!(lines[4].contains("Conversion") && lines[4].contains("Value"));
+    assert!(lines[5].starts_with("│=> Base64url decoded"));
+    assert!(lines[5].contains(r#"{"alg":"HS256"}"#));
+    assert!(lines[6].contains("Base64url encoded"));
+  }
+}
@@ -3,12 +3,17 @@ use std::{collections::BTreeMap, rc::Rc};
 use ratatui::{
   layout::{Constraint, Direction, Layout, Position, Rect},
   style::{Color, Modifier, Style},
-  text::{Line, Span},
+  symbols::border,
+  text::{Line, Span, Text},
   widgets::{Block, Borders, Paragraph, Wrap},
   Frame,
 };
 
-use crate::app::{InputMode, TextInput};
+use crate::app::{
+  theme::Theme,
+  validation::{has_errors, Finding, Severity},
+  InputMode, TextInput,
+};
 
 // Utils
 
@@ -30,6 +35,15 @@ pub const COLOR_BLUE: Color = Color::Rgb(0, 82, 163);
 pub const COLOR_GREEN_DARK: Color = Color::Rgb(20, 97, 73);
 pub const COLOR_RED_DARK: Color = Color::Rgb(173, 25, 20);
 pub const COLOR_ORANGE_DARK: Color = Color::Rgb(184, 49, 15);
+pub const COLOR_YELLOW_DARK: Color = Color::Rgb(133, 100, 4);
+
+/// views shorter than this are always drawn using the compact layout
+pub const COMPACT_HEIGHT_THRESHOLD: u16 = 16;
+/// views narrower than this stack their sides instead of showing them side by side
+pub const NARROW_WIDTH_THRESHOLD: u16 = 80;
+/// views narrower or shorter than this only show the selected block
+pub const MINIMAL_WIDTH_THRESHOLD: u16 = 40;
+pub const MINIMAL_HEIGHT_THRESHOLD: u16 = 8;
 
 #[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Styles {
@@ -43,79 +57,404 @@ pub enum Styles {
   Secondary,
   Help,
   Background,
+  JsonKey,
+  JsonString,
+  JsonNumber,
+  JsonLiteral,
+  JsonTimestamp,
 }
 
-pub fn theme_styles(light: bool) -> BTreeMap<Styles, Style> {
-  if light {
-    BTreeMap::from([
-      (Styles::Default, Style::default().fg(COLOR_GRAY)),
-      (Styles::Header, Style::default().fg(COLOR_DARK_GRAY)),
-      (Styles::Logo, Style::default().fg(COLOR_GREEN_DARK)),
-      (Styles::Failure, Style::default().fg(COLOR_RED_DARK)),
-      (Styles::Warning, Style::default().fg(COLOR_ORANGE_DARK)),
-      (Styles::Success, Style::default().fg(COLOR_GREEN_DARK)),
-      (Styles::Primary, Style::default().fg(COLOR_BLUE)),
-      (Styles::Secondary, Style::default().fg(COLOR_MAGENTA_DARK)),
-      (Styles::Help, Style::default().fg(COLOR_BLUE)),
-      (
-        Styles::Background,
-        Style::default().bg(COLOR_WHITE).fg(COLOR_GRAY),
-      ),
-    ])
-  } else {
-    BTreeMap::from([
-      (Styles::Default, Style::default().fg(COLOR_WHITE)),
-      (Styles::Header, Style::default().fg(COLOR_DARK_GRAY)),
-      (Styles::Logo, Style::default().fg(COLOR_GREEN)),
-      (Styles::Failure, Style::default().fg(COLOR_RED)),
-      (Styles::Warning, Style::default().fg(COLOR_ORANGE)),
-      (Styles::Success, Style::default().fg(COLOR_GREEN)),
-      (Styles::Primary, Style::default().fg(COLOR_CYAN)),
-      (Styles::Secondary, Style::default().fg(COLOR_YELLOW)),
-      (Styles::Help, Style::default().fg(COLOR_LIGHT_BLUE)),
-      (
-        Styles::Background,
-        Style::default().bg(COLOR_TEAL).fg(COLOR_WHITE),
-      ),
-    ])
+/// colors of a theme, the background style uses the default color for the text
+struct Palette {
+  default: Color,
+  header: Color,
+  logo: Color,
+  failure: Color,
+  warning: Color,
+  success: Color,
+  primary: Color,
+  secondary: Color,
+  help: Color,
+  background: Color,
+  json_key: Color,
+  json_string: Color,
+  json_number: Color,
+  json_literal: Color,
+  json_timestamp: Color,
+}
+
+fn palette(theme: Theme) -> Palette {
+  match theme {
+    Theme::Dark => Palette {
+      default: COLOR_WHITE,
+      header: COLOR_DARK_GRAY,
+      logo: COLOR_GREEN,
+      failure: COLOR_RED,
+      warning: COLOR_ORANGE,
+      success: COLOR_GREEN,
+      primary: COLOR_CYAN,
+      secondary: COLOR_YELLOW,
+      help: COLOR_LIGHT_BLUE,
+      background: COLOR_TEAL,
+      json_key: COLOR_CYAN,
+      json_string: COLOR_GREEN,
+      json_number: COLOR_ORANGE,
+      json_literal: COLOR_MAGENTA,
+      json_timestamp: COLOR_YELLOW,
+    },
+    Theme::Light => Palette {
+      default: COLOR_GRAY,
+      header: COLOR_DARK_GRAY,
+      logo: COLOR_GREEN_DARK,
+      failure: COLOR_RED_DARK,
+      warning: COLOR_ORANGE_DARK,
+      success: COLOR_GREEN_DARK,
+      primary: COLOR_BLUE,
+      secondary: COLOR_MAGENTA_DARK,
+      help: COLOR_BLUE,
+      background: COLOR_WHITE,
+      json_key: COLOR_BLUE,
+      json_string: COLOR_GREEN_DARK,
+      json_number: COLOR_ORANGE_DARK,
+      json_literal: COLOR_MAGENTA_DARK,
+      json_timestamp: COLOR_YELLOW_DARK,
+    },
+    Theme::SolarizedDark | Theme::SolarizedLight => {
+      let (default, background) = match theme {
+        Theme::SolarizedDark => (Color::Rgb(131, 148, 150), Color::Rgb(0, 43, 54)),
+        _ => (Color::Rgb(101, 123, 131), Color::Rgb(253, 246, 227)),
+      };
+      Palette {
+        default,
+        header: COLOR_DARK_GRAY,
+        logo: Color::Rgb(133, 153, 0),
+        failure: Color::Rgb(220, 50, 47),
+        warning: Color::Rgb(203, 75, 22),
+        success: Color::Rgb(133, 153, 0),
+        primary: Color::Rgb(42, 161, 152),
+        secondary: Color::Rgb(181, 137, 0),
+        help: Color::Rgb(38, 139, 210),
+        background,
+        json_key: Color::Rgb(38, 139, 210),
+        json_string: Color::Rgb(133, 153, 0),
+        json_number: Color::Rgb(203, 75, 22),
+        json_literal: Color::Rgb(108, 113, 196),
+        json_timestamp: Color::Rgb(181, 137, 0),
+      }
+    }
+    Theme::Gruvbox => Palette {
+      default: Color::Rgb(235, 219, 178),
+      header: COLOR_DARK_GRAY,
+      logo: Color::Rgb(184, 187, 38),
+      failure: Color::Rgb(251, 73, 52),
+      warning: Color::Rgb(254, 128, 25),
+      success: Color::Rgb(184, 187, 38),
+      primary: Color::Rgb(142, 192, 124),
+      secondary: Color::Rgb(250, 189, 47),
+      help: Color::Rgb(131, 165, 152),
+      background: Color::Rgb(40, 40, 40),
+      json_key: Color::Rgb(131, 165, 152),
+      json_string: Color::Rgb(184, 187, 38),
+      json_number: Color::Rgb(254, 128, 25),
+      json_literal: Color::Rgb(211, 134, 155),
+      json_timestamp: Color::Rgb(250, 189, 47),
+    },
+    Theme::Dracula => Palette {
+      default: Color::Rgb(248, 248, 242),
+      header: COLOR_DARK_GRAY,
+      logo: Color::Rgb(80, 250, 123),
+      failure: Color::Rgb(255, 85, 85),
+      warning: Color::Rgb(255, 184, 108),
+      success: Color::Rgb(80, 250, 123),
+      primary: Color::Rgb(139, 233, 253),
+      secondary: Color::Rgb(255, 121, 198),
+      help: Color::Rgb(189, 147, 249),
+      background: Color::Rgb(40, 42, 54),
+      json_key: Color::Rgb(139, 233, 253),
+      json_string: Color::Rgb(241, 250, 140),
+      json_number: Color::Rgb(255, 184, 108),
+      json_literal: Color::Rgb(189, 147, 249),
+      json_timestamp: Color::Rgb(255, 121, 198),
+    },
+    // the colors of the terminal, the styles are told apart by modifiers
+    Theme::Monochrome | Theme::Plain => Palette {
+      default: Color::Reset,
+      header: Color::Reset,
+      logo: Color::Reset,
+      failure: Color::Reset,
+      warning: Color::Reset,
+      success: Color::Reset,
+      primary: Color::Reset,
+      secondary: Color::Reset,
+      help: Color::Reset,
+      background: Color::Reset,
+      json_key: Color::Reset,
+      json_string: Color::Reset,
+      json_number: Color::Reset,
+      json_literal: Color::Reset,
+      json_timestamp: Color::Reset,
+    },
   }
 }
 
-pub fn style_header_text(light: bool) -> Style {
-  *theme_styles(light).get(&Styles::Header).unwrap()
+pub fn theme_styles(theme: Theme) -> BTreeMap<Styles, Style> {
+  let colors = palette(theme);
+  let mut styles = BTreeMap::from([
+    (Styles::Default, Style::default().fg(colors.default)),
+    (Styles::Header, Style::default().fg(colors.header)),
+    (Styles::Logo, Style::default().fg(colors.logo)),
+    (Styles::Failure, Style::default().fg(colors.failure)),
+    (Styles::Warning, Style::default().fg(colors.warning)),
+    (Styles::Success, Style::default().fg(colors.success)),
+    (Styles::Primary, Style::default().fg(colors.primary)),
+    (Styles::Secondary, Style::default().fg(colors.secondary)),
+    (Styles::Help, Style::default().fg(colors.help)),
+    (
+      Styles::Background,
+      Style::default().bg(colors.background).fg(colors.default),
+    ),
+    (Styles::JsonKey, Style::default().fg(colors.json_key)),
+    (Styles::JsonString, Style::default().fg(colors.json_string)),
+    (Styles::JsonNumber, Style::default().fg(colors.json_number)),
+    (
+      Styles::JsonLiteral,
+      Style::default().fg(colors.json_literal),
+    ),
+    (
+      Styles::JsonTimestamp,
+      Style::default().fg(colors.json_timestamp),
+    ),
+  ]);
+  if matches!(theme, Theme::Monochrome | Theme::Plain) {
+    for (style, modifier) in [
+      (Styles::Failure, Modifier::BOLD | Modifier::UNDERLINED),
+      (Styles::Warning, Modifier::UNDERLINED),
+      (Styles::Secondary, Modifier::BOLD),
+      (Styles::JsonKey, Modifier::BOLD),
+    ] {
+      styles
+        .entry(style)
+        .and_modify(|s| *s = s.add_modifier(modifier));
+    }
+  }
+  styles
+}
+
+pub fn style_header_text(theme: Theme) -> Style {
+  *theme_styles(theme).get(&Styles::Header).unwrap()
 }
 
 pub fn style_header() -> Style {
   Style::default().bg(COLOR_MAGENTA)
 }
 
-pub fn style_default(light: bool) -> Style {
-  *theme_styles(light).get(&Styles::Default).unwrap()
+pub fn style_default(theme: Theme) -> Style {
+  *theme_styles(theme).get(&Styles::Default).unwrap()
 }
 
-pub fn style_failure(light: bool) -> Style {
-  *theme_styles(light).get(&Styles::Failure).unwrap()
+pub fn style_failure(theme: Theme) -> Style {
+  *theme_styles(theme).get(&Styles::Failure).unwrap()
 }
 
-pub fn style_primary(light: bool) -> Style {
-  *theme_styles(light).get(&Styles::Primary).unwrap()
+pub fn style_success(theme: Theme) -> Style {
+  *theme_styles(theme).get(&Styles::Success).unwrap()
 }
-pub fn style_help(light: bool) -> Style {
-  *theme_styles(light).get(&Styles::Help).unwrap()
+
+pub fn style_warning(theme: Theme) -> Style {
+  *theme_styles(theme).get(&Styles::Warning).unwrap()
 }
 
-pub fn style_secondary(light: bool) -> Style {
-  *theme_styles(light).get(&Styles::Secondary).unwrap()
+pub fn style_primary(theme: Theme) -> Style {
+  *theme_styles(theme).get(&Styles::Primary).unwrap()
+}
+pub fn style_help(theme: Theme) -> Style {
+  *theme_styles(theme).get(&Styles::Help).unwrap()
 }
 
-pub fn style_main_background(light: bool) -> Style {
-  *theme_styles(light).get(&Styles::Background).unwrap()
+pub fn style_secondary(theme: Theme) -> Style {
+  *theme_styles(theme).get(&Styles::Secondary).unwrap()
+}
+
+pub fn style_main_background(theme: Theme) -> Style {
+  *theme_styles(theme).get(&Styles::Background).unwrap()
 }
 
 pub fn style_highlight() -> Style {
   Style::default().add_modifier(Modifier::REVERSED)
 }
 
+/// ASCII border characters of the plain theme
+const ASCII_BORDER: border::Set = border::Set {
+  top_left: "+",
+  top_right: "+",
+  bottom_left: "+",
+  bottom_right: "+",
+  vertical_left: "|",
+  vertical_right: "|",
+  horizontal_top: "-",
+  horizontal_bottom: "-",
+};
+
+/// block with borders on all sides, drawn with ASCII characters in the plain theme
+pub fn bordered_block<'a>(theme: Theme) -> Block<'a> {
+  let block = Block::default().borders(Borders::ALL);
+  match theme {
+    Theme::Plain => block.border_set(ASCII_BORDER),
+    _ => block,
+  }
+}
+
+/// marker of a passed check
+pub fn success_marker(theme: Theme) -> &'static str {
+  match theme {
+    Theme::Plain => "[OK]",
+    _ => "✔",
+  }
+}
+
+/// marker of a failed check
+pub fn failure_marker(theme: Theme) -> &'static str {
+  match theme {
+    Theme::Plain => "[X]",
+    _ => "×",
+  }
+}
+
+/// frame of the spinner shown while loading
+pub fn spinner(theme: Theme, frame: usize) -> &'static str {
+  const FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+  const PLAIN_FRAMES: [&str; 4] = ["|", "/", "-", "\\"];
+  match theme {
+    Theme::Plain => PLAIN_FRAMES[frame % PLAIN_FRAMES.len()],
+    _ => FRAMES[frame % FRAMES.len()],
+  }
+}
+
+/// marker of a warning
+pub fn warning_marker(theme: Theme) -> &'static str {
+  match theme {
+    Theme::Plain => "[!]",
+    _ => "!",
+  }
+}
+
+/// character shown instead of each character of a masked input
+pub fn mask_char(theme: Theme) -> &'static str {
+  match theme {
+    Theme::Plain => "*",
+    _ => "•",
+  }
+}
+
+/// claims with a timestamp value, colored differently from other numbers and strings
+const TIME_CLAIMS: [&str; 5] = ["iat", "nbf", "exp", "auth_time", "updated_at"];
+
+/// color the keys, strings, numbers, literals and timestamps of a line of pretty printed JSON
+pub fn highlight_json_line(line: &str, theme: Theme) -> Line<'_> {
+  let styles = theme_styles(theme);
+  let mut spans = vec![];
+  let mut key: Option<&str> = None;
+  let mut plain = 0;
+  let mut i = 0;
+  while i < line.len() {
+    let rest = &line[i..];
+    let token_len = match rest.as_bytes()[0] {
+      b'"' => string_len(rest),
+      b'-' | b'0'..=b'9' => rest
+        .find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+        .unwrap_or(rest.len()),
+      b't' | b'f' | b'n' => ["true", "false", "null"]
+        .into_iter()
+        .find(|literal| rest.starts_with(literal))
+        .map_or(0, str::len),
+      _ => 0,
+    };
+    if token_len == 0 {
+      i += rest.chars().next().map_or(1, char::len_utf8);
+      continue;
+    }
+    let token = &rest[..token_len];
+    let is_key = token.starts_with('"') && rest[token_len..].trim_start().starts_with(':');
+    let style = match token.as_bytes()[0] {
+      _ if is_key => Styles::JsonKey,
+      _ if key.is_some_and(|key| TIME_CLAIMS.contains(&key)) => Styles::JsonTimestamp,
+      b'"' => Styles::JsonString,
+      b't' | b'f' | b'n' => Styles::JsonLiteral,
+      _ => Styles::JsonNumber,
+    };
+    if is_key {
+      key = Some(token.trim_matches('"'));
+    }
+    if plain < i {
+      spans.push(Span::styled(&line[plain..i], styles[&Styles::Default]));
+    }
+    spans.push(Span::styled(token, styles[&style]));
+    i += token_len;
+    plain = i;
+  }
+  if plain < line.len() {
+    spans.push(Span::styled(&line[plain..], styles[&Styles::Default]));
+  }
+  Line::from(spans)
+}
+
+/// length of the JSON string at the start of the text, including the quotes
+fn string_len(text: &str) -> usize {
+  let mut escaped = false;
+  for (i, c) in text.char_indices().skip(1) {
+    match c {
+      _ if escaped => escaped = false,
+      '\\' => escaped = true,
+      '"' => return i + 1,
+      _ => {}
+    }
+  }
+  text.len()
+}
+
+/// split the spans of the line to highlight the case-insensitive matches of the search
+pub fn highlight_matches<'a>(line: Line<'a>, query: &str) -> Line<'a> {
+  if query.is_empty() {
+    return line;
+  }
+  let query = query.to_ascii_lowercase();
+  let mut spans = vec![];
+  for span in line.spans {
+    let content = span.content.to_string();
+    let mut last = 0;
+    for (start, _) in content.to_ascii_lowercase().match_indices(&query) {
+      let end = start + query.len();
+      if start > last {
+        spans.push(Span::styled(content[last..start].to_string(), span.style));
+      }
+      spans.push(Span::styled(
+        content[start..end].to_string(),
+        span.style.patch(style_highlight()),
+      ));
+      last = end;
+    }
+    match last {
+      0 => spans.push(span),
+      _ if last < content.len() => {
+        spans.push(Span::styled(content[last..].to_string(), span.style))
+      }
+      _ => {}
+    }
+  }
+  Line { spans, ..line }
+}
+
+pub fn highlight_text_matches<'a>(text: Text<'a>, query: &str) -> Text<'a> {
+  Text {
+    lines: text
+      .lines
+      .into_iter()
+      .map(|line| highlight_matches(line, query))
+      .collect(),
+    ..text
+  }
+}
+
 pub fn horizontal_chunks(constraints: Vec<Constraint>, size: Rect) -> Rc<[Rect]> {
   Layout::default()
     .constraints(<Vec<Constraint> as AsRef<[Constraint]>>::as_ref(
@@ -162,17 +501,38 @@ pub fn vertical_chunks_with_margin(
     .split(size)
 }
 
-pub fn layout_block_with_line(title: Line<'_>, light: bool, is_active: bool) -> Block<'_> {
+/// returns a rect centered in the given area, sized in percent of that area
+pub fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+  let vertical = vertical_chunks(
+    vec![
+      Constraint::Percentage((100 - percent_y) / 2),
+      Constraint::Percentage(percent_y),
+      Constraint::Percentage((100 - percent_y) / 2),
+    ],
+    area,
+  );
+  horizontal_chunks(
+    vec![
+      Constraint::Percentage((100 - percent_x) / 2),
+      Constraint::Percentage(percent_x),
+      Constraint::Percentage((100 - percent_x) / 2),
+    ],
+    vertical[1],
+  )[1]
+}
+
+pub fn layout_block_with_line(mut title: Line<'_>, theme: Theme, is_active: bool) -> Block<'_> {
   let style = if is_active {
-    style_secondary(light)
+    style_secondary(theme)
   } else {
-    style_default(light)
+    style_default(theme)
   };
+  // without colors the active block is told apart by a marker in its title
+  if is_active && theme == Theme::Plain {
+    title.spans.insert(0, Span::raw(" *"));
+  }
 
-  Block::default()
-    .borders(Borders::ALL)
-    .title(title)
-    .style(style)
+  bordered_block(theme).title(title).style(style)
 }
 
 pub fn title_with_dual_style<'a>(part_1: String, part_2: String) -> Line<'a> {
@@ -182,24 +542,56 @@ pub fn title_with_dual_style<'a>(part_1: String, part_2: String) -> Line<'a> {
   ])
 }
 
+/// compact layout is used when toggled on for the view or when there isn't enough height
+pub fn use_compact_layout(compact: bool, area: Rect) -> bool {
+  compact || area.height < COMPACT_HEIGHT_THRESHOLD
+}
+
+/// minimal layout showing only the selected block, when there is no room for all the blocks
+pub fn use_minimal_layout(area: Rect) -> bool {
+  area.width < MINIMAL_WIDTH_THRESHOLD || area.height < MINIMAL_HEIGHT_THRESHOLD
+}
+
+/// the two sides of the decoder and encoder views, stacked when the view is narrow. `left` is the
+/// percentage of the first side
+pub fn split_sides(left: u16, area: Rect) -> Rc<[Rect]> {
+  let constraints = vec![
+    Constraint::Percentage(left),
+    Constraint::Percentage(100 - left),
+  ];
+  if area.width < NARROW_WIDTH_THRESHOLD {
+    vertical_chunks(constraints, area)
+  } else {
+    horizontal_chunks(constraints, area)
+  }
+}
+
 pub fn render_input_widget(
   f: &mut Frame<'_>,
   chunk: Rect,
   text_input: &TextInput,
-  light_theme: bool,
-) {
-  let width = chunk.width.max(3) - 3;
-  // keep 2 for borders and 1 for cursor
+  theme: Theme,
+  compact: bool,
+) -> Rect {
+  // keep 2 for borders and 1 for cursor, borders are not drawn in compact mode
+  let border = if compact { 0 } else { 1 };
+  let width = chunk.width.max(2 * border + 1) - (2 * border + 1);
   let scroll = text_input.input.visual_scroll(width as usize);
-  let input = Paragraph::new(text_input.input.value())
+  let block = if compact {
+    Block::default()
+  } else {
+    bordered_block(theme)
+  };
+  let inner = block.inner(chunk);
+  let value = match text_input.masked {
+    true => mask_char(theme).repeat(text_input.input.value().chars().count()),
+    false => text_input.input.value().to_string(),
+  };
+  let input = Paragraph::new(value)
     .wrap(Wrap { trim: false })
-    .style(get_input_style(&text_input.input_mode, light_theme))
+    .style(get_input_style(&text_input.input_mode, theme))
     .scroll((0, scroll as u16))
-    .block(
-      Block::default()
-        .borders(Borders::ALL)
-        .style(get_input_style(&text_input.input_mode, light_theme)),
-    );
+    .block(block.style(get_input_style(&text_input.input_mode, theme)));
 
   f.render_widget(input, chunk);
 
@@ -208,16 +600,25 @@ pub fn render_input_widget(
       // Hide the cursor. `Frame` does this by default, so we don't need to do anything here
     }
 
+    InputMode::Editing if text_input.wraps_in(inner) => {
+      // long values wrap over the lines of the input instead of scrolling
+      let cursor = text_input.input.visual_cursor() as u16;
+      f.set_cursor_position(Position {
+        x: inner.x + cursor % inner.width,
+        y: inner.y + (cursor / inner.width).min(inner.height - 1),
+      })
+    }
     InputMode::Editing => {
       // Make the cursor visible and ask tui-rs to put it at the specified coordinates after rendering
       f.set_cursor_position(Position {
         // Put cursor past the end of the input text
-        x: chunk.x + ((text_input.input.visual_cursor()).max(scroll) - scroll) as u16 + 1,
+        x: chunk.x + ((text_input.input.visual_cursor()).max(scroll) - scroll) as u16 + border,
         // Move one line down, from the border to the input line
-        y: chunk.y + 1,
+        y: chunk.y + border,
       })
     }
   }
+  inner
 }
 
 pub fn get_hint(input_mode: &InputMode, is_active: bool) -> &str {
@@ -231,21 +632,62 @@ pub fn get_hint(input_mode: &InputMode, is_active: bool) -> &str {
   }
 }
 
-pub fn get_input_style(input_mode: &InputMode, light: bool) -> Style {
+pub fn get_input_style(input_mode: &InputMode, theme: Theme) -> Style {
   match input_mode {
-    InputMode::Normal => style_default(light),
-    InputMode::Editing => style_secondary(light),
+    InputMode::Normal => style_default(theme),
+    InputMode::Editing => style_secondary(theme),
   }
 }
 
+/// maximum number of findings shown before the validation pane scrolls off
+pub const MAX_VALIDATION_LINES: u16 = 5;
+
+/// list the findings of the validation checks
+pub fn draw_validation_block(f: &mut Frame<'_>, findings: &[Finding], theme: Theme, area: Rect) {
+  let title_style = if has_errors(findings) {
+    style_failure(theme)
+  } else {
+    style_warning(theme)
+  };
+
+  let block = bordered_block(theme)
+    .title(title_with_dual_style(
+      " Validation ".into(),
+      format!("({} issues) ", findings.len()),
+    ))
+    .style(title_style);
+
+  let lines: Vec<Line<'_>> = findings
+    .iter()
+    .map(|finding| {
+      let (marker, style) = match finding.severity {
+        Severity::Error => (failure_marker(theme), style_failure(theme)),
+        Severity::Warning => (warning_marker(theme), style_warning(theme)),
+      };
+      Line::from(vec![
+        Span::styled(format!("{marker} "), style),
+        Span::styled(finding.message.clone(), style),
+      ])
+    })
+    .collect();
+
+  let paragraph = Paragraph::new(lines)
+    .block(block)
+    .wrap(Wrap { trim: false });
+  f.render_widget(paragraph, area);
+}
+
 pub fn get_selectable_block(
   title: &str,
   is_active: bool,
   input_mode: Option<&InputMode>,
-  light_theme: bool,
+  theme: Theme,
+  compact: bool,
 ) -> Block<'static> {
   //   let is_active = *active_block == block;
-  let title_hint = if let Some(im) = input_mode {
+  let title_hint = if compact {
+    ""
+  } else if let Some(im) = input_mode {
     get_hint(im, is_active)
   } else if is_active {
     "(<c> copy) "
@@ -255,8 +697,81 @@ pub fn get_selectable_block(
 
   let block = layout_block_with_line(
     title_with_dual_style(format!(" {} ", title), title_hint.into()),
-    light_theme,
+    theme,
     is_active,
   );
   block
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_theme_styles() {
+    assert_eq!(style_primary(Theme::Dark).fg, Some(COLOR_CYAN));
+    assert_eq!(style_primary(Theme::Light).fg, Some(COLOR_BLUE));
+    for theme in Theme::ALL {
+      // every theme styles the background and the text on it
+      let background = style_main_background(theme);
+      assert!(background.bg.is_some() && background.fg == style_default(theme).fg);
+    }
+    // monochrome keeps the terminal colors and tells styles apart by modifiers
+    let failure = style_failure(Theme::Monochrome);
+    assert_eq!(failure.fg, Some(Color::Reset));
+    assert!(failure.add_modifier.contains(Modifier::BOLD));
+    assert!(style_secondary(Theme::Monochrome)
+      .add_modifier
+      .contains(Modifier::BOLD));
+  }
+
+  #[test]
+  fn test_spinner() {
+    assert_eq!(spinner(Theme::Dark, 0), "⠋");
+    assert_eq!(spinner(Theme::Dark, 11), "⠙");
+    // plain frames are ASCII
+    assert_eq!(spinner(Theme::Plain, 1), "/");
+    assert_eq!(spinner(Theme::Plain, 7), "\\");
+  }
+
+  #[test]
+  fn test_highlight_json_line() {
+    let styled = |line: &'static str| -> Vec<(String, Option<Color>)> {
+      highlight_json_line(line, Theme::Dark)
+        .spans
+        .into_iter()
+        .map(|span| (span.content.into_owned(), span.style.fg))
+        .collect()
+    };
+    assert_eq!(
+      styled(r#"  "name": "John \"JD\" Doe","#),
+      [
+        ("  ".into(), Some(COLOR_WHITE)),
+        (r#""name""#.into(), Some(COLOR_CYAN)),
+        (": ".into(), Some(COLOR_WHITE)),
+        (r#""John \"JD\" Doe""#.into(), Some(COLOR_GREEN)),
+        (",".into(), Some(COLOR_WHITE)),
+      ]
+    );
+    assert_eq!(
+      styled(r#"  "exp": 1516239022,"#)[3],
+      ("1516239022".into(), Some(COLOR_YELLOW))
+    );
+    assert_eq!(
+      styled(r#"  "exp": "2018-01-18T01:30:22+00:00""#)[3].1,
+      Some(COLOR_YELLOW)
+    );
+    assert_eq!(
+      styled(r#"    -1.5e3, true, null"#)
+        .into_iter()
+        .filter(|(_, fg)| *fg != Some(COLOR_WHITE))
+        .collect::<Vec<_>>(),
+      [
+        ("-1.5e3".into(), Some(COLOR_ORANGE)),
+        ("true".into(), Some(COLOR_MAGENTA)),
+        ("null".into(), Some(COLOR_MAGENTA)),
+      ]
+    );
+    assert_eq!(styled("}"), [("}".into(), Some(COLOR_WHITE))]);
+  }
+}